@@ -0,0 +1,160 @@
+//
+//  DemoScene.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// The built-in scenes of the viewer, each built only through the public interface of the world,
+/// so that they double as examples of it.
+enum DemoScene: CaseIterable {
+    case tumblingCube
+    case stack
+    case chain
+    case ragdoll
+    case vehicle
+    case wreckingBall
+    case newtonCradle
+    case spinningTop
+    case tippeTop
+    case dominos
+    case planks
+    case ropeBridge
+    case marbleRun
+    case conveyorSorter
+    case turret
+    case floatingCrates
+    case robotArm
+    case balanceBot
+    
+    var name: String {
+        switch self {
+        case .tumblingCube: return "Tumbling Cube"
+        case .stack: return "Stack"
+        case .chain: return "Chain"
+        case .ragdoll: return "Ragdoll"
+        case .vehicle: return "Vehicle"
+        case .wreckingBall: return "Wrecking Ball"
+        case .newtonCradle: return "Newton's Cradle"
+        case .spinningTop: return "Spinning Top"
+        case .tippeTop: return "Tippe Top"
+        case .dominos: return "Dominos"
+        case .planks: return "Planks"
+        case .ropeBridge: return "Rope Bridge"
+        case .marbleRun: return "Marble Run"
+        case .conveyorSorter: return "Conveyor Sorter"
+        case .turret: return "Turret"
+        case .floatingCrates: return "Floating Crates"
+        case .robotArm: return "Robot Arm"
+        case .balanceBot: return "Balance Bot"
+        }
+    }
+    
+    /// Adds the scene to a world containing nothing but the ground.
+    func build(in world: World) {
+        switch self {
+        case .tumblingCube:
+            world.addDemoScene()
+        
+        case .stack:
+            for level in 0 ..< 8 {
+                world.addCube(at: Point(0, 0, 0.5 + 1.01 * Double(level)))
+            }
+        
+        case .chain:
+            world.instantiate(.chain(linkCount: 8), at: Frame(position: 8 * .ez))
+        
+        case .ragdoll:
+            buildRagdoll(in: world)
+        
+        case .vehicle:
+            buildVehicle(in: world)
+        
+        case .wreckingBall:
+            Scenarios.wreckingBall(in: world)
+        
+        case .newtonCradle:
+            let config = Scenarios.NewtonCradleConfig()
+            world.restitutionPropagation = config.restitutionPropagation
+            Scenarios.newtonCradle(config, in: world)
+        
+        case .spinningTop:
+            Scenarios.spinningTop(in: world)
+        
+        case .tippeTop:
+            Scenarios.tippeTop(in: world)
+        
+        case .dominos:
+            Scenarios.dominoChain(in: world)
+        
+        case .planks:
+            // A diving board with a cube dropped onto its free end, and a bridge sagging under a ball.
+            world.instantiate(.plank(size: Point(4, 0.8, 0.1), segmentCount: 8, mass: 4, stiffness: 400, clampsStart: true),
+                              at: Frame(position: Point(0, -2, 2)))
+            world.addCube(at: Point(1.6, -2, 4))
+            world.instantiate(.plank(size: Point(6, 1, 0.1), segmentCount: 12, mass: 6, stiffness: 50, clampsStart: true, clampsEnd: true),
+                              at: Frame(position: Point(0, 2, 2)))
+            world.addConvex(.sphere(radius: 0.3), at: Point(-1, 2, 3), mass: 5, color: Color(0.3))
+        
+        case .ropeBridge:
+            Scenarios.ropeBridge(in: world)
+        
+        case .marbleRun:
+            Scenarios.marbleRun(in: world)
+        
+        case .conveyorSorter:
+            Scenarios.conveyorSorter(in: world)
+        
+        case .turret:
+            Scenarios.turret(in: world)
+        
+        case .floatingCrates:
+            Scenarios.floatingCrates(in: world)
+        
+        case .robotArm:
+            Scenarios.robotArm(in: world)
+        
+        case .balanceBot:
+            Scenarios.balanceBot(in: world)
+        }
+    }
+    
+    private func buildRagdoll(in world: World) {
+        let torso = world.addConvex(.box(halfExtents: Point(0.25, 0.15, 0.35)), at: Point(0, 0, 1.6), mass: 3)
+        let head = world.addConvex(.sphere(radius: 0.18), at: Point(0, 0, 2.16))
+        world.addJoint(Joint(.ball, between: torso, and: head, at: Point(0, 0, 1.96)))
+        
+        for side in [-1.0, 1.0] {
+            let upperArm = world.addConvex(.capsule(radius: 0.07, halfHeight: 0.2), at: Point(0.35 * side, 0, 1.65), mass: 0.5)
+            let lowerArm = world.addConvex(.capsule(radius: 0.07, halfHeight: 0.2), at: Point(0.35 * side, 0, 1.09), mass: 0.5)
+            world.addJoint(Joint(.ball, between: torso, and: upperArm, at: Point(0.35 * side, 0, 1.92)))
+            world.addJoint(Joint(.hinge(axes: (.ex, .ex)), between: upperArm, and: lowerArm, at: Point(0.35 * side, 0, 1.37)))
+            
+            let thigh = world.addConvex(.capsule(radius: 0.09, halfHeight: 0.2), at: Point(0.13 * side, 0, 0.96), mass: 0.8)
+            let shin = world.addConvex(.capsule(radius: 0.09, halfHeight: 0.2), at: Point(0.13 * side, 0, 0.38), mass: 0.8)
+            world.addJoint(Joint(.ball, between: torso, and: thigh, at: Point(0.13 * side, 0, 1.25)))
+            world.addJoint(Joint(.hinge(axes: (.ex, .ex)), between: thigh, and: shin, at: Point(0.13 * side, 0, 0.67)))
+        }
+        
+        // A shove, so that it doesn't just stand there.
+        torso.velocity = 2 * .ex
+        torso.angularVelocity = Point(0, 1, 0.5)
+    }
+    
+    private func buildVehicle(in world: World) {
+        let chassis = world.addConvex(.box(halfExtents: Point(1, 0.5, 0.2)), at: Point(0, 0, 1), mass: 4)
+        chassis.velocity = 4 * .ex
+        
+        for x in [-0.8, 0.8] {
+            for y in [-0.55, 0.55] {
+                let wheel = world.addConvex(.sphere(radius: 0.3), at: Point(x, y, 0.3), mass: 0.5, color: Color(0.3))
+                wheel.velocity = chassis.velocity
+                world.addSuspension(Suspension(chassis: chassis, at: Point(x, y, -0.2), axis: -Point.ez, wheel: wheel,
+                                               travel: 0 ... 0.6, restLength: 0.5, stiffness: 200, damping: 15))
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//
+//  FileWatcher.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Calls a handler on the main queue whenever a file changes, including when an editor replaces it while saving.
+class FileWatcher {
+    let url: URL
+    private let handler: () -> Void
+    private var source: DispatchSourceFileSystemObject?
+    /// How long to wait before looking for the file again while it is missing, which grows up to a few seconds.
+    private var retryDelay = FileWatcher.initialRetryDelay
+    private static let initialRetryDelay = 0.1
+    
+    init(url: URL, handler: @escaping () -> Void) {
+        self.url = url
+        self.handler = handler
+        watch()
+    }
+    
+    deinit {
+        source?.cancel()
+    }
+    
+    private func watch() {
+        source?.cancel()
+        source = nil
+        let descriptor = open(url.path, O_EVTONLY)
+        if descriptor < 0 {
+            // The file may still be in the middle of being replaced, or be restored later, so it is looked for
+            // until the watcher goes away, less and less often.
+            DispatchQueue.main.asyncAfter(deadline: .now() + retryDelay) { [weak self] in
+                self?.reattach()
+            }
+            retryDelay = min(2 * retryDelay, 5)
+            return
+        }
+        retryDelay = FileWatcher.initialRetryDelay
+        
+        let source = DispatchSource.makeFileSystemObjectSource(fileDescriptor: descriptor, eventMask: [.write, .delete, .rename], queue: .main)
+        source.setEventHandler { [weak self, unowned source] in
+            guard let self = self else {
+                return
+            }
+            if source.data.contains(.write) {
+                self.handler()
+                return
+            }
+            // Editors often save by replacing the file, after which the descriptor refers to the old one.
+            // The new one may take a moment to appear.
+            DispatchQueue.main.asyncAfter(deadline: .now() + FileWatcher.initialRetryDelay) { [weak self] in
+                self?.reattach()
+            }
+        }
+        source.setCancelHandler {
+            close(descriptor)
+        }
+        source.resume()
+        self.source = source
+    }
+    
+    /// Watches the file again once it was replaced, and reports the change once it is there.
+    private func reattach() {
+        watch()
+        if source != nil {
+            handler()
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//
+//  Gamepad.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 17.04.21.
+//
+
+import GameController
+
+
+/// The state of the first connected game controller, polled once per frame.
+struct GamepadState {
+    var leftStick = simd_double2()
+    var rightStick = simd_double2()
+    var leftTrigger = 0.0
+    var rightTrigger = 0.0
+    /// While held, the left stick drives the selected body instead of the camera.
+    var isDriving = false
+    /// Whether the jump button went down since the last poll.
+    var jump = false
+    /// Whether the select button went down since the last poll.
+    var selectNext = false
+}
+
+
+class Gamepad {
+    /// Stick deflections below this magnitude are treated as zero, since sticks rarely rest exactly centered.
+    var deadZone = 0.15
+    private var wasJumpPressed = false
+    private var wasSelectPressed = false
+    
+    var isConnected: Bool {
+        GCController.controllers().contains { $0.extendedGamepad != nil }
+    }
+    
+    func poll() -> GamepadState? {
+        guard let pad = GCController.controllers().lazy.compactMap({ $0.extendedGamepad }).first else {
+            return nil
+        }
+        
+        var state = GamepadState()
+        state.leftStick = filter(pad.leftThumbstick)
+        state.rightStick = filter(pad.rightThumbstick)
+        state.leftTrigger = Double(pad.leftTrigger.value)
+        state.rightTrigger = Double(pad.rightTrigger.value)
+        state.isDriving = pad.rightShoulder.isPressed
+        
+        state.jump = pad.buttonA.isPressed && !wasJumpPressed
+        state.selectNext = pad.buttonY.isPressed && !wasSelectPressed
+        wasJumpPressed = pad.buttonA.isPressed
+        wasSelectPressed = pad.buttonY.isPressed
+        
+        return state
+    }
+    
+    private func filter(_ stick: GCControllerDirectionPad) -> simd_double2 {
+        let value = simd_double2(Double(stick.xAxis.value), Double(stick.yAxis.value))
+        return simd_length(value) < deadZone ? .zero : value
+    }
+}
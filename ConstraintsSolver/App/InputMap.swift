@@ -0,0 +1,320 @@
+//
+//  InputMap.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 17.04.21.
+//
+
+import Cocoa
+
+
+/// Everything the user can trigger with the keyboard or the mouse.
+enum Action: String, CaseIterable, Codable {
+    case orbit
+    case pan
+    case slide
+    case zoom
+    /// Pans like `pan`, for a mouse button rather than a scroll gesture.
+    case grab
+    /// Zooms like `zoom`, for a scroll wheel rather than a pinch.
+    case scrollZoom
+    case pause
+    case spawn
+    case toggleHud
+    case togglePlots
+    case toggleChaseCamera
+    case toggleMinimap
+    case toggleStereo
+    case toggleMargins
+    case toggleTrails
+    case spawnCharacter
+    case walkForward
+    case walkBackward
+    case walkLeft
+    case walkRight
+    case saveCheckpoint
+    case restoreCheckpoint
+    case nextScene
+    case previousScene
+    case openSceneFile
+    case saveSceneFile
+    case toggleContinuousCollision
+    /// Starts recording, replays the recording once stopped, and simulates again from there.
+    case toggleRecording
+    /// Steps through the recording being played back, or through the collision traces being replayed.
+    case stepPlaybackBackward
+    case stepPlaybackForward
+    /// Starts tracing GJK and EPA, and once stopped, saves the traces of failed pairs and replays them.
+    case toggleCollisionTracing
+    case openCollisionTraces
+    /// Switches to the next combination of solver overlays, see `DebugOverlays`.
+    case cycleDebugOverlays
+    /// Turn solver features off and on again, to tell which of them causes an artifact. There is no toggle for
+    /// speculative contacts, which neither backend generates; fast bodies are swept by `toggleContinuousCollision` instead.
+    case toggleFriction
+    case toggleRestitution
+    /// Only the sequential impulses backend warm starts, so this does nothing while the position based one is active.
+    case toggleWarmStarting
+    /// Switches between the position based and the sequential impulses backend, to compare them on the same scene.
+    case toggleBackend
+}
+
+
+/// A key or mouse gesture, together with the modifier keys which must be held down.
+struct Binding: Hashable {
+    enum Trigger: Hashable {
+        /// A key, identified by the character it produces without modifiers, or by `space`.
+        case key(String)
+        /// Any of the digit keys 1 to 9, which then act as a slot number.
+        case digit
+        case leftDrag
+        case rightDrag
+        case otherDrag
+        case scroll
+        case magnify
+    }
+    
+    struct Modifiers: OptionSet, Hashable {
+        let rawValue: UInt8
+        
+        static let shift = Modifiers(rawValue: 1 << 0)
+        static let control = Modifiers(rawValue: 1 << 1)
+        static let option = Modifiers(rawValue: 1 << 2)
+        static let command = Modifiers(rawValue: 1 << 3)
+        
+        fileprivate static let names: [(String, Modifiers)] = [
+            ("shift", .shift), ("control", .control), ("option", .option), ("command", .command)
+        ]
+        
+        init(rawValue: UInt8) {
+            self.rawValue = rawValue
+        }
+        
+        init(_ flags: NSEvent.ModifierFlags) {
+            self = []
+            if flags.contains(.shift) { insert(.shift) }
+            if flags.contains(.control) { insert(.control) }
+            if flags.contains(.option) { insert(.option) }
+            if flags.contains(.command) { insert(.command) }
+        }
+    }
+    
+    let trigger: Trigger
+    let modifiers: Modifiers
+    
+    init(_ trigger: Trigger, _ modifiers: Modifiers = []) {
+        self.trigger = trigger
+        self.modifiers = modifiers
+    }
+    
+    /// Parses bindings like `p`, `space`, `option+digit`, or `shift+scroll`.
+    init?(string: String) {
+        var components = string.lowercased().split(separator: "+").map(String.init)
+        guard let name = components.popLast() else {
+            return nil
+        }
+        
+        var modifiers: Modifiers = []
+        for component in components {
+            guard let modifier = Modifiers.names.first(where: { $0.0 == component })?.1 else {
+                return nil
+            }
+            modifiers.insert(modifier)
+        }
+        
+        switch name {
+        case "digit": trigger = .digit
+        case "leftdrag": trigger = .leftDrag
+        case "rightdrag": trigger = .rightDrag
+        case "otherdrag": trigger = .otherDrag
+        case "scroll": trigger = .scroll
+        case "magnify": trigger = .magnify
+        case _ where name == "space" || name.count == 1: trigger = .key(name)
+        default: return nil
+        }
+        self.modifiers = modifiers
+    }
+}
+
+
+extension Binding: CustomStringConvertible {
+    var description: String {
+        let name: String
+        switch trigger {
+        case let .key(key): name = key
+        case .digit: name = "digit"
+        case .leftDrag: name = "leftDrag"
+        case .rightDrag: name = "rightDrag"
+        case .otherDrag: name = "otherDrag"
+        case .scroll: name = "scroll"
+        case .magnify: name = "magnify"
+        }
+        return (Modifiers.names.filter { modifiers.contains($0.1) }.map { $0.0 } + [name]).joined(separator: "+")
+    }
+}
+
+
+extension Binding: Codable {
+    init(from decoder: Decoder) throws {
+        let string = try decoder.singleValueContainer().decode(String.self)
+        guard let binding = Binding(string: string) else {
+            throw DecodingError.dataCorrupted(.init(codingPath: decoder.codingPath, debugDescription: "Invalid binding '\(string)'"))
+        }
+        self = binding
+    }
+    
+    func encode(to encoder: Encoder) throws {
+        var container = encoder.singleValueContainer()
+        try container.encode(description)
+    }
+}
+
+
+/// Maps input events to actions.
+/// Bindings can be overridden by a JSON file which maps action names to binding strings, e.g. `{ "pause": "p" }`.
+class InputMap {
+    static let defaultBindings: [Action: Binding] = [
+        .orbit: Binding(.leftDrag),
+        .pan: Binding(.scroll, .shift),
+        .slide: Binding(.scroll, .option),
+        .zoom: Binding(.magnify),
+        .grab: Binding(.otherDrag),
+        .scrollZoom: Binding(.scroll),
+        .pause: Binding(.key("space")),
+        .spawn: Binding(.key("n")),
+        .toggleHud: Binding(.key("h")),
+        .togglePlots: Binding(.key("g")),
+        .toggleChaseCamera: Binding(.key("c")),
+        .toggleMinimap: Binding(.key("m")),
+        .toggleStereo: Binding(.key("v")),
+        .toggleMargins: Binding(.key("b")),
+        .toggleTrails: Binding(.key("t")),
+        .spawnCharacter: Binding(.key("k")),
+        .walkForward: Binding(.key("w")),
+        .walkBackward: Binding(.key("s")),
+        .walkLeft: Binding(.key("a")),
+        .walkRight: Binding(.key("d")),
+        .saveCheckpoint: Binding(.digit, .option),
+        .restoreCheckpoint: Binding(.digit),
+        .nextScene: Binding(.key("]")),
+        .previousScene: Binding(.key("[")),
+        .openSceneFile: Binding(.key("o"), .command),
+        .saveSceneFile: Binding(.key("s"), .command),
+        .toggleContinuousCollision: Binding(.key("x")),
+        .toggleRecording: Binding(.key("r")),
+        .stepPlaybackBackward: Binding(.key(",")),
+        .stepPlaybackForward: Binding(.key(".")),
+        .toggleCollisionTracing: Binding(.key("y")),
+        .openCollisionTraces: Binding(.key("y"), .command),
+        .cycleDebugOverlays: Binding(.key("o")),
+        .toggleFriction: Binding(.key("f")),
+        .toggleRestitution: Binding(.key("e")),
+        .toggleWarmStarting: Binding(.key("i")),
+        .toggleBackend: Binding(.key("j")),
+    ]
+    
+    /// The location of the user's bindings file.
+    static var configurationURL: URL {
+        FileManager.default.urls(for: .applicationSupportDirectory, in: .userDomainMask)[0]
+            .appendingPathComponent("ConstraintsSolver")
+            .appendingPathComponent("InputMap.json")
+    }
+    
+    private(set) var bindings: [Action: Binding]
+    
+    init(bindings: [Action: Binding] = InputMap.defaultBindings) {
+        self.bindings = bindings
+    }
+    
+    /// Loads the default bindings, overridden by those in the given file.
+    convenience init(contentsOf url: URL) throws {
+        self.init()
+        let overrides = try JSONDecoder().decode([String: Binding].self, from: Data(contentsOf: url))
+        for (name, binding) in overrides {
+            guard let action = Action(rawValue: name) else {
+                throw DecodingError.dataCorrupted(.init(codingPath: [], debugDescription: "Unknown action '\(name)'"))
+            }
+            bind(action, to: binding)
+        }
+    }
+    
+    /// Loads the user's bindings if there are any, and falls back to the defaults otherwise.
+    /// Throws if the bindings file exists but can't be read.
+    static func load() throws -> InputMap {
+        guard FileManager.default.fileExists(atPath: configurationURL.path) else {
+            return InputMap()
+        }
+        return try InputMap(contentsOf: configurationURL)
+    }
+    
+    func save(to url: URL) throws {
+        let encoder = JSONEncoder()
+        encoder.outputFormatting = [.prettyPrinted, .sortedKeys]
+        let data = try encoder.encode(Dictionary(uniqueKeysWithValues: bindings.map { ($0.key.rawValue, $0.value) }))
+        try data.write(to: url)
+    }
+    
+    /// Binds an action, replacing both its previous binding and any other action bound to the same input.
+    func bind(_ action: Action, to binding: Binding) {
+        for (other, otherBinding) in bindings where otherBinding == binding {
+            bindings[other] = nil
+        }
+        bindings[action] = binding
+    }
+    
+    func binding(for action: Action) -> Binding? {
+        bindings[action]
+    }
+    
+    /// Finds the action bound to the given event, if any.
+    func action(for event: NSEvent) -> Action? {
+        let modifiers = Binding.Modifiers(event.modifierFlags)
+        
+        for trigger in triggers(of: event) {
+            if let match = bindings.first(where: { $0.value == Binding(trigger, modifiers) }) {
+                return match.key
+            }
+        }
+        return nil
+    }
+    
+    private func triggers(of event: NSEvent) -> [Binding.Trigger] {
+        switch event.type {
+        case .keyDown:
+            guard let characters = event.charactersIgnoringModifiers?.lowercased() else {
+                return []
+            }
+            if characters == " " {
+                return [.key("space")]
+            }
+            if event.digit != nil {
+                return [.key(characters), .digit]
+            }
+            return [.key(characters)]
+        case .leftMouseDragged:
+            return [.leftDrag]
+        case .rightMouseDragged:
+            return [.rightDrag]
+        case .otherMouseDragged:
+            return [.otherDrag]
+        case .scrollWheel:
+            return [.scroll]
+        case .magnify:
+            return [.magnify]
+        default:
+            return []
+        }
+    }
+}
+
+
+extension NSEvent {
+    /// The digit from 1 to 9 of a key event, used to address numbered slots.
+    var digit: Int? {
+        guard type == .keyDown, let characters = charactersIgnoringModifiers,
+              let digit = Int(characters), (1 ... 9).contains(digit) else {
+            return nil
+        }
+        return digit
+    }
+}
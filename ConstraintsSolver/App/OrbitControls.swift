@@ -0,0 +1,102 @@
+//
+//  OrbitControls.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A motion of the camera which is eased in rather than applied at once, following its target with a critically
+/// damped spring. That settles as fast as possible without overshooting.
+fileprivate struct SmoothedMotion {
+    /// How much of the motion is still to be made.
+    private(set) var remaining = 0.0
+    private var velocity = 0.0
+    
+    mutating func add(_ amount: Double) {
+        remaining += amount
+    }
+    
+    /// Makes the part of the motion due within the given time, and returns it.
+    mutating func advance(by dt: Double, smoothingTime: Double) -> Double {
+        if smoothingTime <= 0 {
+            defer { (remaining, velocity) = (0, 0) }
+            return remaining
+        }
+        // The exact solution of the spring, so that long frames don't make it overshoot.
+        let omega = 2 / smoothingTime
+        let offset = -remaining
+        let slope = velocity + omega * offset
+        let decay = exp(-omega * dt)
+        let newOffset = (offset + slope * dt) * decay
+        velocity = (velocity - omega * slope * dt) * decay
+        remaining = -newOffset
+        return newOffset - offset
+    }
+}
+
+
+/// Turns mouse and trackpad input into smooth camera motion: orbiting about the focus point, panning it,
+/// sliding it along the ground, and zooming exponentially, so that each scroll tick zooms by the same ratio.
+/// Input only sets where the camera is headed, and `advance` moves it there a bit every frame.
+struct OrbitControls {
+    /// Radians per point of dragging, and of scrolling, which reports larger distances.
+    var orbitSensitivity = (drag: 0.01, scroll: 0.002)
+    /// Fractions of the distance to the focus per point, so that panning feels the same at any zoom.
+    var panSensitivity = (drag: 0.003, scroll: 0.001)
+    /// The exponent of the zoom factor per point of scrolling.
+    var zoomSensitivity = 0.01
+    /// Roughly the time in seconds the camera takes to catch up with the input. Zero moves it immediately.
+    var smoothingTime = 0.08
+    
+    private var orbiting = (rightwards: SmoothedMotion(), upwards: SmoothedMotion())
+    /// Panned and slid distances in fractions of the distance to the focus.
+    private var panning = (rightwards: SmoothedMotion(), upwards: SmoothedMotion())
+    private var sliding = (rightwards: SmoothedMotion(), forwards: SmoothedMotion())
+    /// The logarithm of the zoom factor.
+    private var zooming = SmoothedMotion()
+    
+    /// Orbits by a drag or scroll, given in points growing rightwards and upwards.
+    mutating func orbit(dx: Double, dy: Double, isScroll: Bool) {
+        let sensitivity = isScroll ? orbitSensitivity.scroll : orbitSensitivity.drag
+        orbiting.rightwards.add(sensitivity * -dx)
+        orbiting.upwards.add(sensitivity * dy)
+    }
+    
+    /// Moves the focus along with a drag or scroll, in the plane of the view.
+    mutating func pan(dx: Double, dy: Double, isScroll: Bool) {
+        let sensitivity = isScroll ? panSensitivity.scroll : panSensitivity.drag
+        panning.rightwards.add(sensitivity * -dx)
+        panning.upwards.add(sensitivity * dy)
+    }
+    
+    /// Moves the focus along with a drag or scroll, parallel to the ground.
+    mutating func slide(dx: Double, dy: Double, isScroll: Bool) {
+        let sensitivity = isScroll ? panSensitivity.scroll : panSensitivity.drag
+        sliding.rightwards.add(sensitivity * -dx)
+        sliding.forwards.add(sensitivity * dy)
+    }
+    
+    /// Zooms in when scrolling up.
+    mutating func zoom(dy: Double) {
+        zooming.add(zoomSensitivity * dy)
+    }
+    
+    /// Zooms in by a factor, such as that of a pinch.
+    mutating func zoom(by factor: Double) {
+        zooming.add(log(factor))
+    }
+    
+    /// Moves the camera by as much of the input as it catches up with during a frame.
+    mutating func advance(_ camera: inout Camera, by dt: Double) {
+        camera.orbit(rightwards: orbiting.rightwards.advance(by: dt, smoothingTime: smoothingTime),
+                     upwards: orbiting.upwards.advance(by: dt, smoothingTime: smoothingTime))
+        camera.pan(rightwards: camera.radius * panning.rightwards.advance(by: dt, smoothingTime: smoothingTime),
+                   upwards: camera.radius * panning.upwards.advance(by: dt, smoothingTime: smoothingTime))
+        camera.slide(righwards: camera.radius * sliding.rightwards.advance(by: dt, smoothingTime: smoothingTime),
+                     forwards: camera.radius * sliding.forwards.advance(by: dt, smoothingTime: smoothingTime))
+        camera.zoom(by: exp(zooming.advance(by: dt, smoothingTime: smoothingTime)))
+    }
+}
@@ -5,6 +5,54 @@ class ViewController: NSViewController, FrameDelegate {
     private var renderer: Renderer!
     private var mtkView: MTKView!
     private var world: World!
+    private var hud: Hud!
+    private var energyPlot: Plot!
+    private var penetrationPlot: Plot!
+    private var stepTimePlot: Plot!
+    private var checkpoints: [Int: Snapshot] = [:]
+    private var inputMap = InputMap()
+    private var isPaused = false
+    private let gamepad = Gamepad()
+    private var selectedRigid: Rigid?
+    /// Force per unit mass with which the gamepad drives the selected body.
+    private let driveAcceleration = 20.0
+    private let jumpSpeed = 5.0
+    private var showsChaseCamera = false
+    private var chaseCamera = Camera()
+    /// Eases the camera into the motions requested with the mouse and the trackpad.
+    private var orbitControls = OrbitControls()
+    private var showsMargins = false
+    private var showsTrails = false
+    /// The overlays drawn by the solver, cycled through `debugOverlayCycle`.
+    private var debugOverlays: DebugOverlays = []
+    private let debugOverlayCycle: [DebugOverlays] = [[], [.contacts], [.constraintErrors], [.boundingBoxes, .sleeping], [.velocities], .all]
+    private var character: CharacterController?
+    /// Horizontal displacement requested for the character until the next frame.
+    private var pendingWalk = Point.null
+    private var scene = DemoScene.tumblingCube
+    /// Steps the world at a fixed rate, whatever the frame rate.
+    private var driver: FixedTimeStepDriver!
+    /// Reloads the world whenever the scene file it was loaded from changes.
+    private var sceneFile: FileWatcher?
+    /// The body being dragged with the mouse, and the distance from the camera it is held at.
+    private var drag: (joint: MouseJoint, distance: Double)?
+    /// Whether fast bodies are swept, which is kept across scenes.
+    private var usesContinuousCollision = false
+    /// Solver features which can be turned off for comparison, which is kept across scenes as well.
+    private var usesFriction = true
+    private var usesRestitution = true
+    private var usesWarmStarting = true
+    private var usesSequentialImpulses = false
+    /// Records the steps of the world until recording is toggled again.
+    private var recorder: Recorder?
+    /// Traces GJK and EPA until tracing is toggled again, which is kept across scenes.
+    private var collisionTracer: CollisionTracer?
+    /// The collision traces being replayed, and the trace and iteration shown.
+    private var collisionReplay: (traces: [CollisionTrace], trace: Int, iteration: Int)?
+    
+    override var acceptsFirstResponder: Bool {
+        true
+    }
     
     override func loadView() {
         mtkView = MTKView(frame: AppDelegate.windowRect)
@@ -17,36 +65,529 @@ class ViewController: NSViewController, FrameDelegate {
         renderer.frameDelegate = self
         renderer.camera.look(at: .null, from: Point(5, 6, 4), up: .ez)
         
-        world = World(renderer: renderer)
+        // The HUD comes first, so that it shows warnings about the rigids of the first scene.
+        hud = Hud(in: mtkView)
+        load(scene)
+        do {
+            inputMap = try InputMap.load()
+        }
+        catch {
+            report("Failed to load input map from \(InputMap.configurationURL.path): \(error.localizedDescription)")
+        }
+        energyPlot = renderer.addPlot(name: "kinetic energy", color: .yellow)
+        penetrationPlot = renderer.addPlot(name: "max penetration", color: .magenta)
+        stepTimePlot = renderer.addPlot(name: "step time", color: .cyan)
+    }
+    
+    /// Replaces the world by a new one showing the given scene.
+    private func load(_ scene: DemoScene) {
+        world?.removeMeshes()
+        hud?.error = nil
+        world = World(renderer: renderer, inertiaBounds: inertiaBounds)
+        scene.build(in: world)
+        self.scene = scene
+        sceneFile = nil
+        didReplaceWorld(caption: scene.name)
+    }
+    
+    /// Replaces the world by the one in a file, keeping the current one if the file can't be loaded.
+    private func load(contentsOf url: URL) {
+        let loaded: World
+        hud?.error = nil
+        do {
+            loaded = try World(contentsOf: url, renderer: renderer, inertiaBounds: inertiaBounds)
+        }
+        catch {
+            report("Failed to load scene from \(url.path): \(error.localizedDescription)")
+            return
+        }
+        world.removeMeshes()
+        world = loaded
+        didReplaceWorld(caption: url.lastPathComponent)
+    }
+    
+    /// Shows an error in the HUD, where it stays until another world is loaded.
+    private func report(_ message: String) {
+        hud?.error = message
+    }
+    
+    /// Bounds for the rigids of new worlds, which report the warnings about them in the HUD, including those
+    /// about the rigids a world is built with.
+    private var inertiaBounds: InertiaBounds {
+        var bounds = InertiaBounds()
+        bounds.warn = { [weak self] warning in
+            self?.report(warning.description)
+        }
+        return bounds
+    }
+    
+    private func didReplaceWorld(caption: String) {
+        world.continuousCollision = usesContinuousCollision ? ContinuousCollision() : nil
+        world.collisionTracer = collisionTracer
+        applySolverToggles()
+        driver = FixedTimeStepDriver(world: world)
+        // Everything referring to the old world is stale now.
+        selectedRigid = nil
+        drag = nil
+        character = nil
+        checkpoints.removeAll()
+        recorder = nil
+        hud?.caption = caption
+    }
+    
+    private func openSceneFile() {
+        let panel = NSOpenPanel()
+        panel.allowedFileTypes = ["json"]
+        guard panel.runModal() == .OK, let url = panel.url else {
+            return
+        }
+        load(contentsOf: url)
+        sceneFile = FileWatcher(url: url) { [weak self] in
+            self?.load(contentsOf: url)
+        }
+    }
+    
+    private func saveSceneFile() {
+        let panel = NSSavePanel()
+        panel.allowedFileTypes = ["json"]
+        guard panel.runModal() == .OK, let url = panel.url else {
+            return
+        }
+        do {
+            try world.save(to: url)
+        }
+        catch {
+            report("Failed to save scene to \(url.path): \(error.localizedDescription)")
+        }
+    }
+    
+    /// Stops tracing collisions, saves the traces kept, and starts replaying them.
+    private func stopCollisionTracing(_ tracer: CollisionTracer) {
+        collisionTracer = nil
+        world.collisionTracer = nil
+        let traces = tracer.traces
+        guard !traces.isEmpty else {
+            report("No collision pair failed while tracing")
+            return
+        }
+        let url = FileManager.default.temporaryDirectory.appendingPathComponent("CollisionTraces.json")
+        do {
+            try tracer.write(to: url)
+            report("Saved \(traces.count) collision traces to \(url.path)")
+        }
+        catch {
+            report("Failed to save collision traces to \(url.path): \(error.localizedDescription)")
+        }
+        collisionReplay = (traces, 0, 0)
+    }
+    
+    private func openCollisionTraces() {
+        let panel = NSOpenPanel()
+        panel.allowedFileTypes = ["json"]
+        guard panel.runModal() == .OK, let url = panel.url else {
+            return
+        }
+        do {
+            let traces = try CollisionTracer.traces(contentsOf: url)
+            collisionReplay = traces.isEmpty ? nil : (traces, 0, 0)
+        }
+        catch {
+            report("Failed to load collision traces from \(url.path): \(error.localizedDescription)")
+        }
+    }
+    
+    /// Moves the collision replay by an iteration, continuing with the neighboring trace at either end of one.
+    private func stepCollisionReplay(by offset: Int) {
+        guard var replay = collisionReplay else {
+            return
+        }
+        replay.iteration += offset
+        if replay.iteration >= replay.traces[replay.trace].iterationCount && replay.trace + 1 < replay.traces.count {
+            replay.trace += 1
+            replay.iteration = 0
+        }
+        else if replay.iteration < 0 && replay.trace > 0 {
+            replay.trace -= 1
+            replay.iteration = replay.traces[replay.trace].iterationCount - 1
+        }
+        replay.iteration = replay.iteration.clamped(to: 0 ... max(replay.traces[replay.trace].iterationCount - 1, 0))
+        collisionReplay = replay
+        let trace = replay.traces[replay.trace]
+        hud?.status = "Trace \(replay.trace + 1)/\(replay.traces.count) (\(trace.outcome.rawValue)): iteration \(replay.iteration + 1)/\(trace.iterationCount)"
     }
     
     func onFrame(dt: Double, t: Double) {
-        world.integrate(dt: dt)
+        if let state = gamepad.poll() {
+            apply(state, dt: dt)
+        }
+        
+        orbitControls.advance(&renderer.camera, by: dt)
+        
+        if !isPaused {
+            world.lod?.focus = renderer.camera.focus
+            driver.advance(by: dt)
+        }
+        
+        if showsChaseCamera, let rigid = selectedRigid ?? world.rigids.first(where: { $0.inverseMass > 0 }) {
+            updateChaseCamera(following: rigid, dt: dt)
+            renderer.auxiliaryCameras = [chaseCamera]
+        }
+        else {
+            renderer.auxiliaryCameras = []
+        }
+        
+        if showsMargins {
+            drawCollisionBoundaries()
+        }
+        
+        if showsTrails {
+            drawTrails()
+        }
+        
+        if !debugOverlays.isEmpty {
+            world.drawDebugOverlays(debugOverlays, into: renderer.lineDebugger)
+        }
+        
+        for spring in world.springs {
+            renderer.lineDebugger.draw(spring)
+        }
+        for suspension in world.suspensions {
+            renderer.lineDebugger.draw(suspension)
+        }
+        
+        if let drag = drag {
+            renderer.lineDebugger.line(from: drag.joint.position, to: drag.joint.target, color: .yellow)
+        }
+        if let playback = world.playback {
+            drawInputs(of: playback.recording.steps[playback.step])
+        }
+        if let replay = collisionReplay, replay.traces[replay.trace].iterationCount > 0 {
+            replay.traces[replay.trace].draw(iteration: replay.iteration, into: renderer.lineDebugger)
+        }
+        
+        if let character = character {
+            character.move(by: pendingWalk, dt: dt, gravity: world.gravity, in: world)
+            pendingWalk = .null
+            renderer.lineDebugger.draw(character)
+        }
+        
+        if renderer.showsMinimap {
+            renderer.minimap.markers = world.rigids.filter { $0.inverseMass > 0 }.map { rigid in
+                MinimapMarker(position: simd_float3(rigid.frame.position),
+                              velocity: simd_float3(rigid.velocity),
+                              color: rigid === selectedRigid ? .yellow : .white)
+            }
+        }
+        
+        let telemetry = world.telemetry
+        energyPlot.push(telemetry.kineticEnergy)
+        penetrationPlot.push(telemetry.maxPenetration)
+        stepTimePlot.push(telemetry.stepDuration)
+        hud.update(dt: dt, telemetry: telemetry, plots: renderer.plots)
+    }
+    
+    /// Passes the solver features on to the world, and lists the ones turned off in the HUD.
+    private func applySolverToggles() {
+        world.friction.isEnabled = usesFriction
+        world.isRestitutionEnabled = usesRestitution
+        world.isWarmStarting = usesWarmStarting
+        world.backend = usesSequentialImpulses ? .sequentialImpulses(iterations: 8) : .positionBased
+        // Warm starting is only listed for the backend which does it.
+        let disabled = [(usesFriction, "friction"), (usesRestitution, "restitution"), (usesWarmStarting || !usesSequentialImpulses, "warm starting")]
+            .filter { !$0.0 }
+            .map { $0.1 }
+        var status = usesSequentialImpulses ? ["Sequential impulses"] : []
+        if !disabled.isEmpty {
+            status.append("Off: " + disabled.joined(separator: ", "))
+        }
+        hud?.status = status.isEmpty ? nil : status.joined(separator: " · ")
+    }
+    
+    /// Draws the boundaries at which contacts are generated, which are inflated by the collision margin.
+    private func drawCollisionBoundaries() {
+        for rigid in world.rigids {
+            if case let .box(box) = rigid.collider {
+                let color: Color = box.margin > 0 ? .cyan : .green
+                renderer.lineDebugger.box(halfExtents: Point(0.5 + box.margin), frame: rigid.frame, color: color)
+            }
+        }
+    }
+    
+    /// Draws the recent trajectory of every dynamic rigid, starting to record it if necessary.
+    private func drawTrails() {
+        for rigid in world.rigids where rigid.inverseMass > 0 {
+            guard let history = rigid.poseHistory else {
+                rigid.recordPoseHistory(capacity: 120)
+                continue
+            }
+            let positions = history.elements.map { $0.position }
+            for (a, b) in zip(positions, positions.dropFirst()) {
+                renderer.lineDebugger.line(from: a, to: b, color: .yellow)
+            }
+        }
+    }
+    
+    /// Draws the drags which were recorded during a step.
+    private func drawInputs(of step: Recording.Step) {
+        for input in step.inputs {
+            if case let .drag(handle, target) = input, handle.index < world.rigids.count {
+                renderer.lineDebugger.line(from: world.rigid(handle).frame.position, to: target, color: .yellow)
+            }
+        }
+    }
+    
+    /// Moves the chase camera smoothly behind the direction of travel of the given rigid.
+    private func updateChaseCamera(following rigid: Rigid, dt: Double) {
+        let distance = 5.0
+        let height = 2.0
+        let horizontalVelocity = Point(rigid.velocity.ex, rigid.velocity.ey, 0)
+        let behind = horizontalVelocity.length > 0.5 ? -horizontalVelocity.normalize : -Point(chaseCamera.forward.ex, chaseCamera.forward.ey, 0).normalize
+        let target = rigid.frame.position + distance * behind + height * .ez
+        
+        let smoothing = 1 - exp(-4 * dt)
+        let smoothed = chaseCamera.position + smoothing * chaseCamera.position.to(target)
+        let position = world.collisionFreeCameraPosition(target: rigid.frame.position, desired: smoothed, filter: QueryFilter(excluding: [rigid]))
+        chaseCamera.look(at: rigid.frame.position, from: position, up: .ez)
+    }
+    
+    private func apply(_ state: GamepadState, dt: Double) {
+        let dynamicRigids = world.rigids.filter { $0.inverseMass > 0 }
+        if state.selectNext || selectedRigid == nil {
+            let index = dynamicRigids.firstIndex { $0 === selectedRigid }.map { $0 + 1 } ?? 0
+            selectedRigid = dynamicRigids.isEmpty ? nil : dynamicRigids[index % dynamicRigids.count]
+        }
+        
+        // The right stick orbits, the triggers zoom.
+        let orbitSpeed = 2.0
+        renderer.camera.orbit(rightwards: -orbitSpeed * dt * state.rightStick.x, upwards: orbitSpeed * dt * state.rightStick.y)
+        renderer.camera.zoom(by: exp(dt * (state.rightTrigger - state.leftTrigger)))
+        
+        if state.isDriving, let rigid = selectedRigid {
+            // Drive relative to the camera, projected onto the ground.
+            let right = Point(renderer.camera.right.ex, renderer.camera.right.ey, 0).normalize
+            let forward = Point(renderer.camera.forward.ex, renderer.camera.forward.ey, 0).normalize
+            let acceleration = driveAcceleration * (state.leftStick.x * right + state.leftStick.y * forward)
+            rigid.applyImpulse((dt / rigid.inverseMass) * acceleration)
+            
+            if state.jump {
+                rigid.applyImpulse((jumpSpeed / rigid.inverseMass) * .ez)
+            }
+        }
+        else {
+            // Fly relative to ground
+            let speed = renderer.camera.radius
+            renderer.camera.slide(righwards: speed * dt * state.leftStick.x, forwards: speed * dt * state.leftStick.y)
+        }
+    }
+    
+    override func keyDown(with event: NSEvent) {
+        dispatch(event) { super.keyDown(with: event) }
+    }
+    
+    /// The ray from the camera through the mouse location of an event.
+    private func ray(at event: NSEvent) -> Ray {
+        let location = mtkView.convert(event.locationInWindow, from: nil)
+        let size = mtkView.bounds.size
+        let viewPoint = (x: Double(2 * location.x / size.width - 1), y: Double(2 * location.y / size.height - 1))
+        return renderer.camera.ray(through: viewPoint, fovY: Double(renderer.fovY), aspectRatio: Double(size.width / size.height))
+    }
+    
+    override func mouseDown(with event: NSEvent) {
+        // Clicking a body selects it, so that the gamepad drives it, and dragging it pulls it along.
+        // Dragging anywhere else still orbits.
+        let ray = self.ray(at: event)
+        if let hit = world.closestHit(along: ray), hit.rigid.inverseMass > 0 {
+            selectedRigid = hit.rigid
+            drag = (world.addMouseJoint(grabbing: hit.rigid, at: hit.point), hit.distance)
+            return
+        }
+        super.mouseDown(with: event)
     }
     
     override func mouseDragged(with event: NSEvent) {
-        // Orbit
-        let sensitivity = 0.01
-        renderer.camera.orbit(rightwards: sensitivity * Double(-event.deltaX), upwards: sensitivity * Double(event.deltaY))
+        if let drag = drag {
+            // The grabbed point is kept at its distance from the camera.
+            drag.joint.target = ray(at: event).at(drag.distance)
+            return
+        }
+        dispatch(event) { super.mouseDragged(with: event) }
+    }
+    
+    override func mouseUp(with event: NSEvent) {
+        if let drag = drag {
+            world.removeMouseJoint(drag.joint)
+            self.drag = nil
+            return
+        }
+        super.mouseUp(with: event)
+    }
+    
+    override func rightMouseDragged(with event: NSEvent) {
+        dispatch(event) { super.rightMouseDragged(with: event) }
+    }
+    
+    override func otherMouseDragged(with event: NSEvent) {
+        dispatch(event) { super.otherMouseDragged(with: event) }
     }
 
     override func scrollWheel(with event: NSEvent) {
-        if event.modifierFlags.contains(.shift) {
-            // Pan
-            let sensitivity = 0.001 * renderer.camera.radius
-            renderer.camera.pan(rightwards: sensitivity * Double(-event.scrollingDeltaX), upwards: sensitivity * Double(event.scrollingDeltaY))
+        dispatch(event) { super.scrollWheel(with: event) }
+    }
+
+    override func magnify(with event: NSEvent) {
+        dispatch(event) { super.magnify(with: event) }
+    }
+    
+    private func dispatch(_ event: NSEvent, otherwise fallback: () -> Void) {
+        if let action = inputMap.action(for: event) {
+            perform(action, with: event)
         }
         else {
-            // Pan relative to ground
-            let sensitivity = 0.001 * renderer.camera.radius
-            let dx = sensitivity * Double(-event.scrollingDeltaX)
-            let dy = sensitivity * Double(event.scrollingDeltaY)
-            renderer.camera.slide(righwards: dx, forwards: dy)
+            fallback()
         }
     }
-
-    override func magnify(with event: NSEvent) {
-        // Zoom
-        renderer.camera.zoom(by: 1 + Double(event.magnification))
+    
+    private func perform(_ action: Action, with event: NSEvent) {
+        // Mouse drags report their motion in deltaX and deltaY, scroll gestures in scrollingDeltaX and scrollingDeltaY.
+        let isScroll = event.type == .scrollWheel
+        let dx = Double(isScroll ? event.scrollingDeltaX : event.deltaX)
+        let dy = Double(isScroll ? event.scrollingDeltaY : event.deltaY)
+        
+        switch action {
+        case .orbit:
+            orbitControls.orbit(dx: dx, dy: dy, isScroll: isScroll)
+        case .pan, .grab:
+            orbitControls.pan(dx: dx, dy: dy, isScroll: isScroll)
+        case .slide:
+            // Pan relative to ground
+            orbitControls.slide(dx: dx, dy: dy, isScroll: isScroll)
+        case .zoom, .scrollZoom:
+            if event.type == .magnify {
+                orbitControls.zoom(by: 1 + Double(event.magnification))
+            }
+            else {
+                orbitControls.zoom(dy: dy)
+            }
+        case .pause:
+            isPaused.toggle()
+        case .spawn:
+            let cube = world.addCube(at: renderer.camera.focus + 4 * .ez)
+            cube.frame.quaternion = Quaternion(by: .random(in: 0 ..< 2 * .pi), around: Point(.random(in: -1 ... 1), .random(in: -1 ... 1), 1))
+            selectedRigid = cube
+        case .toggleHud:
+            hud.isHidden.toggle()
+        case .togglePlots:
+            renderer.showsPlots.toggle()
+        case .toggleChaseCamera:
+            showsChaseCamera.toggle()
+            chaseCamera = renderer.camera
+        case .toggleMinimap:
+            renderer.showsMinimap.toggle()
+        case .toggleStereo:
+            renderer.stereoRig = renderer.stereoRig == nil ? StereoRig() : nil
+        case .toggleMargins:
+            showsMargins.toggle()
+        case .cycleDebugOverlays:
+            let index = debugOverlayCycle.firstIndex(of: debugOverlays) ?? 0
+            debugOverlays = debugOverlayCycle[(index + 1) % debugOverlayCycle.count]
+        case .toggleTrails:
+            showsTrails.toggle()
+            if !showsTrails {
+                world.rigids.forEach { $0.recordPoseHistory(capacity: nil) }
+            }
+        case .spawnCharacter:
+            character = character == nil ? CharacterController(position: renderer.camera.focus) : nil
+        case .walkForward, .walkBackward, .walkLeft, .walkRight:
+            let stride = 0.1
+            let right = Point(renderer.camera.right.ex, renderer.camera.right.ey, 0).normalize
+            let forward = Point(renderer.camera.forward.ex, renderer.camera.forward.ey, 0).normalize
+            let directions: [Action: Point] = [.walkForward: forward, .walkBackward: -forward, .walkLeft: -right, .walkRight: right]
+            pendingWalk = pendingWalk + stride * directions[action]!
+        case .saveCheckpoint:
+            if let slot = event.digit {
+                checkpoints[slot] = world.snapshot()
+            }
+        case .restoreCheckpoint:
+            if let slot = event.digit, let checkpoint = checkpoints[slot] {
+                world.restore(checkpoint)
+            }
+        case .nextScene, .previousScene:
+            let scenes = DemoScene.allCases
+            let offset = action == .nextScene ? 1 : scenes.count - 1
+            load(scenes[(scenes.firstIndex(of: scene)! + offset) % scenes.count])
+        case .openSceneFile:
+            openSceneFile()
+        case .saveSceneFile:
+            saveSceneFile()
+        case .toggleFriction:
+            usesFriction.toggle()
+            applySolverToggles()
+        case .toggleRestitution:
+            usesRestitution.toggle()
+            applySolverToggles()
+        case .toggleWarmStarting:
+            if usesSequentialImpulses {
+                usesWarmStarting.toggle()
+                applySolverToggles()
+            }
+        case .toggleBackend:
+            usesSequentialImpulses.toggle()
+            applySolverToggles()
+        case .toggleContinuousCollision:
+            usesContinuousCollision.toggle()
+            world.continuousCollision = usesContinuousCollision ? ContinuousCollision() : nil
+        case .toggleRecording:
+            if let recorder = recorder {
+                // The recording is replayed in a world rebuilt from where it started.
+                recorder.stop(world)
+                let recording = recorder.recording
+                let replay: World
+                hud?.error = nil
+                do {
+                    replay = try World(scene: recording.scene, renderer: renderer, inertiaBounds: inertiaBounds)
+                }
+                catch {
+                    report("Failed to replay the recording: \(error.localizedDescription)")
+                    return
+                }
+                do {
+                    try replay.play(recording)
+                }
+                catch {
+                    replay.removeMeshes()
+                    report("Failed to replay the recording: \(error.localizedDescription)")
+                    return
+                }
+                world.removeMeshes()
+                world = replay
+                didReplaceWorld(caption: "Replay")
+            }
+            else if world.playback != nil {
+                world.stopPlayback()
+            }
+            else {
+                recorder = Recorder(world, recordsInputs: true)
+            }
+        case .stepPlaybackBackward, .stepPlaybackForward:
+            let offset = action == .stepPlaybackForward ? 1 : -1
+            if let playback = world.playback {
+                world.seek(toStep: playback.step + offset)
+            }
+            else {
+                stepCollisionReplay(by: offset)
+            }
+        case .toggleCollisionTracing:
+            if let tracer = collisionTracer {
+                stopCollisionTracing(tracer)
+            }
+            else {
+                // Replaying stops while tracing anew.
+                collisionReplay = nil
+                collisionTracer = CollisionTracer()
+                world.collisionTracer = collisionTracer
+            }
+        case .openCollisionTraces:
+            openCollisionTraces()
+        }
     }
 }
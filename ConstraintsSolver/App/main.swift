@@ -1,6 +1,122 @@
 import Foundation
 import Cocoa
 
+/// Returns the value following a command line flag.
+func argument(_ flag: String) -> String? {
+    guard let index = CommandLine.arguments.firstIndex(of: flag), index + 1 < CommandLine.arguments.count else {
+        return nil
+    }
+    return CommandLine.arguments[index + 1]
+}
+
+// Usage: ConstraintsSolver --drop-test [--bodies N] [--duration SECONDS] [--seed S]
+if CommandLine.arguments.contains("--drop-test") {
+    var test = DropTest()
+    test.bodyCount = argument("--bodies").flatMap(Int.init) ?? test.bodyCount
+    test.duration = argument("--duration").flatMap(Double.init) ?? test.duration
+    test.seed = argument("--seed").flatMap(UInt64.init) ?? test.seed
+    
+    print("Dropping \(test.bodyCount) bodies for \(test.duration) s (seed \(test.seed))")
+    let report = test.run()
+    print(report)
+    exit(report.settleTime == nil ? 1 : 0)
+}
+
+// Usage: ConstraintsSolver --determinism-check [--seed S]
+// Runs the drop test on one thread and on several, and fails unless the final states agree. The state hash is printed,
+// so that runs of other processes, builds or machines can be compared as well.
+if CommandLine.arguments.contains("--determinism-check") {
+    var test = DropTest()
+    test.seed = argument("--seed").flatMap(UInt64.init) ?? test.seed
+    let configurations: [(String, (World) -> Void)] = [
+        ("single-threaded", { _ in }),
+        ("concurrent", { world in
+            world.isBroadphaseConcurrent = true
+            world.isIslandSolvingConcurrent = true
+        }),
+        ("single-threaded again", { _ in })
+    ]
+    var hashes: [UInt64] = []
+    for (name, configure) in configurations {
+        test.configure = configure
+        let hash = test.run().stateHash
+        hashes.append(hash)
+        print("\(name): " + String(format: "%016llx", hash))
+    }
+    exit(Set(hashes).count == 1 ? 0 : 1)
+}
+
+// Usage: ConstraintsSolver --trace FILE [--bodies N] [--duration SECONDS]
+// Runs the drop test while tracing, and writes a trace to open in chrome://tracing or Perfetto.
+if let path = argument("--trace") {
+    var test = DropTest()
+    test.bodyCount = argument("--bodies").flatMap(Int.init) ?? test.bodyCount
+    test.duration = argument("--duration").flatMap(Double.init) ?? 1
+    let trace = StepTrace()
+    test.configure = { $0.trace = trace }
+    print(test.run())
+    do {
+        try trace.write(to: URL(fileURLWithPath: path))
+        print("Wrote \(trace.eventCount) events to \(path)")
+        exit(0)
+    }
+    catch {
+        print("Failed to write trace to \(path): \(error)")
+        exit(1)
+    }
+}
+
+// Usage: ConstraintsSolver --tunneling-check
+// Fails if any shot of the turret scenario passes through its wall despite continuous collision,
+// or if none does without it, in which case the check proves nothing.
+if CommandLine.arguments.contains("--tunneling-check") {
+    let withoutSweeping = Scenarios.tunnelingCheck(continuousCollision: nil)
+    let withSweeping = Scenarios.tunnelingCheck(continuousCollision: ContinuousCollision())
+    print("Shots through the wall without continuous collision: \(withoutSweeping), with it: \(withSweeping)")
+    if withoutSweeping == 0 {
+        print("No shot tunnels without continuous collision either, so the shots are too slow to test it")
+    }
+    exit(withoutSweeping > 0 && withSweeping == 0 ? 0 : 1)
+}
+
+// Usage: ConstraintsSolver --balance-check [--duration SECONDS]
+// Fails if the balance bot falls, exceeds the torque of its motors, or depends on the frame durations.
+if CommandLine.arguments.contains("--balance-check") {
+    let report = Scenarios.balanceCheck(duration: argument("--duration").flatMap(Double.init) ?? 10)
+    print(report)
+    exit(!report.hasFallen && report.peakTorque <= report.maxTorque && report.isDeterministic ? 0 : 1)
+}
+
+// Usage: ConstraintsSolver --momentum-check [--duration SECONDS] [--sub-steps N]
+// Reports how far the angular momentum of a tumbling box drifts.
+if CommandLine.arguments.contains("--momentum-check") {
+    var config = Scenarios.AngularMomentumConfig()
+    config.duration = argument("--duration").flatMap(Double.init) ?? config.duration
+    config.subStepCount = argument("--sub-steps").flatMap(Int.init) ?? config.subStepCount
+    print(Scenarios.angularMomentumDrift(config))
+    exit(0)
+}
+
+// Usage: ConstraintsSolver --destruction-benchmark [--duration SECONDS]
+// Runs the benchmark for a few solver configurations, one report each.
+if CommandLine.arguments.contains("--destruction-benchmark") {
+    let configurations: [(String, (World) -> Void)] = [
+        ("position based, 50 sub-steps", { _ in }),
+        ("position based, 10 sub-steps", { $0.subStepCount = 10 }),
+        ("sequential impulses, 10 sub-steps, 8 iterations", { world in
+            world.subStepCount = 10
+            world.backend = .sequentialImpulses(iterations: 8)
+        })
+    ]
+    for (name, configure) in configurations {
+        var benchmark = DestructionBenchmark()
+        benchmark.duration = argument("--duration").flatMap(Double.init) ?? benchmark.duration
+        benchmark.configure = configure
+        print("\(name):\n\(benchmark.run())\n")
+    }
+    exit(0)
+}
+
 let delegate = AppDelegate()
 NSApplication.shared.delegate = delegate
 _ = NSApplicationMain(CommandLine.argc, CommandLine.unsafeArgv)
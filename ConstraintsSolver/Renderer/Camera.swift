@@ -111,4 +111,13 @@ public struct Camera {
             forwards * Point(forward.ex, forward.ey, 0).normalize
     }
     
+    /// The ray from the camera through a point of the view, given in coordinates from -1 to 1
+    /// growing rightwards and upwards, e.g. for picking the body under the mouse.
+    func ray(through viewPoint: (x: Double, y: Double), fovY: Double, aspectRatio: Double) -> Ray
+    {
+        let halfHeight = tan(0.5 * fovY)
+        let direction = forward + (viewPoint.y * halfHeight) * up + (viewPoint.x * halfHeight * aspectRatio) * right
+        return Ray(origin: position, direction: direction)
+    }
+    
 }
@@ -0,0 +1,76 @@
+//
+//  Hud.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 16.04.21.
+//
+
+import Cocoa
+
+
+/// A text overlay in the upper left corner of the view, showing frame rate and solver statistics.
+class Hud {
+    private let label: NSTextField
+    private var smoothedFrameTime = 1.0 / 60
+    /// A line above the statistics, such as the name of the current scene.
+    var caption: String?
+    /// A line below the caption, such as the solver features turned off.
+    var status: String?
+    /// The last error, such as a scene file which failed to load, shown in red until it is cleared.
+    var error: String?
+    
+    var isHidden: Bool {
+        get { label.isHidden }
+        set { label.isHidden = newValue }
+    }
+    
+    init(in view: NSView) {
+        label = NSTextField(labelWithString: "")
+        label.font = NSFont.monospacedSystemFont(ofSize: 12, weight: .regular)
+        label.textColor = NSColor(white: 0.8, alpha: 1)
+        label.maximumNumberOfLines = 0
+        label.translatesAutoresizingMaskIntoConstraints = false
+        
+        view.addSubview(label)
+        NSLayoutConstraint.activate([
+            label.leadingAnchor.constraint(equalTo: view.leadingAnchor, constant: 12),
+            label.topAnchor.constraint(equalTo: view.topAnchor, constant: 36)
+        ])
+    }
+    
+    func update(dt: Double, telemetry: Telemetry, plots: [Plot] = []) {
+        // Exponential smoothing, otherwise the frame rate is unreadable.
+        smoothedFrameTime = 0.95 * smoothedFrameTime + 0.05 * dt
+        
+        let text = NSMutableAttributedString(string: ([caption, status].compactMap { $0 } + [
+            String(format: "%6.1f fps", 1 / smoothedFrameTime),
+            String(format: "%6.2f ms step", 1000 * telemetry.stepDuration),
+            String(format: "%6.2f ms broad phase", 1000 * telemetry.broadPhaseDuration),
+            String(format: "%6.2f ms narrow phase", 1000 * telemetry.narrowPhaseDuration),
+            String(format: "%6.2f ms sub-steps", 1000 * telemetry.solverDuration),
+            String(format: "%6d sub-steps", telemetry.subStepCount),
+            String(format: "%6d iterations", telemetry.iterationCount),
+            String(format: "%6d bodies", telemetry.bodyCount),
+            String(format: "%6d contacts", telemetry.contactCount),
+            String(format: "%6d islands", telemetry.islandCount),
+            String(format: "%6d frozen", telemetry.frozenCount),
+            String(format: "%6.1f J kinetic", telemetry.kineticEnergy),
+            String(format: "%6.1f J potential", telemetry.potentialEnergy),
+            String(format: "%6.1f J total", telemetry.kineticEnergy + telemetry.potentialEnergy)
+        ]).joined(separator: "\n"), attributes: [.foregroundColor: label.textColor!, .font: label.font!])
+        
+        if let error = error {
+            text.insert(NSAttributedString(string: error + "\n", attributes: [.foregroundColor: NSColor.systemRed, .font: label.font!]), at: 0)
+        }
+        
+        // The plot legend uses the colors of the plotted lines.
+        for plot in plots {
+            let color = NSColor(red: CGFloat(plot.color.rgb.x), green: CGFloat(plot.color.rgb.y), blue: CGFloat(plot.color.rgb.z), alpha: 1)
+            text.append(NSAttributedString(
+                            string: String(format: "\n%@: %.4g (max %.4g)", plot.name, plot.samples.last ?? 0, plot.maximum),
+                            attributes: [.foregroundColor: color, .font: label.font!]))
+        }
+        
+        label.attributedStringValue = text
+    }
+}
@@ -0,0 +1,165 @@
+//
+//  LineDebugger.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 19.04.21.
+//
+
+import Metal
+
+
+/// Collects colored line segments in world space which are drawn on top of the meshes in every viewport.
+/// Lines are cleared after each frame, so they must be submitted anew every frame.
+class LineDebugger {
+    private var vertices: [Vertex] = []
+    private var buffer: MTLBuffer
+    private let device: MTLDevice
+    
+    init(device: MTLDevice) {
+        self.device = device
+        buffer = device.makeBuffer(length: MemoryLayout<Vertex>.stride * 1024, options: .cpuCacheModeWriteCombined)!
+    }
+    
+    var isEmpty: Bool {
+        vertices.isEmpty
+    }
+    
+    func line(from a: Point, to b: Point, color: Color) {
+        vertices.append(Vertex(position: simd_float3(a), normal: simd_float3(0, 0, 1), color: color.rgb))
+        vertices.append(Vertex(position: simd_float3(b), normal: simd_float3(0, 0, 1), color: color.rgb))
+    }
+    
+    /// Draws the edges of a box with the given half extents, attached to a frame.
+    func box(halfExtents: Point, frame: Frame, color: Color) {
+        let corners = (0 ..< 8).map { i in
+            frame.act(Point(
+                i & 1 == 0 ? -halfExtents.ex : halfExtents.ex,
+                i & 2 == 0 ? -halfExtents.ey : halfExtents.ey,
+                i & 4 == 0 ? -halfExtents.ez : halfExtents.ez))
+        }
+        
+        // Connect corners whose indices differ in exactly one bit.
+        for i in 0 ..< 8 {
+            for bit in [1, 2, 4] where i & bit == 0 {
+                line(from: corners[i], to: corners[i | bit], color: color)
+            }
+        }
+    }
+    
+    /// Draws an arrow from a point along a vector.
+    func arrow(from origin: Point, along vector: Point, color: Color) {
+        let tip = origin + vector
+        line(from: origin, to: tip, color: color)
+        
+        let length = vector.length
+        if length > 1e-9 {
+            let (u, v) = (1 / length * vector).tangents
+            let back = -0.2 * vector
+            line(from: tip, to: tip + back + 0.1 * length * u, color: color)
+            line(from: tip, to: tip + back - 0.1 * length * u, color: color)
+            line(from: tip, to: tip + back + 0.1 * length * v, color: color)
+            line(from: tip, to: tip + back - 0.1 * length * v, color: color)
+        }
+    }
+    
+    /// Draws a coil between two points, whose turns spread out as it is stretched.
+    func coil(from a: Point, to b: Point, turns: Int = 8, radius: Double = 0.1, color: Color) {
+        let axis = a.to(b)
+        if axis.length < 1e-9 {
+            return
+        }
+        let (u, v) = axis.normalize.tangents
+        let segmentsPerTurn = 12
+        let points = (0 ... turns * segmentsPerTurn).map { i -> Point in
+            let t = Double(i) / Double(turns * segmentsPerTurn)
+            let angle = 2 * .pi * Double(turns) * t
+            return a + t * axis + radius * (cos(angle) * u + sin(angle) * v)
+        }
+        for (start, end) in zip(points, points.dropFirst()) {
+            line(from: start, to: end, color: color)
+        }
+    }
+    
+    /// Uploads the lines of this frame. Must be called once per frame before rendering.
+    func upload() {
+        let byteCount = vertices.count * MemoryLayout<Vertex>.stride
+        if byteCount > buffer.length {
+            buffer = device.makeBuffer(length: 2 * byteCount, options: .cpuCacheModeWriteCombined)!
+        }
+        if byteCount > 0 {
+            buffer.contents().copyMemory(from: vertices, byteCount: byteCount)
+        }
+    }
+    
+    func render(into encoder: MTLRenderCommandEncoder, uniforms: inout Uniforms) {
+        if vertices.isEmpty {
+            return
+        }
+        
+        encoder.pushDebugGroup("Draw Debug Lines")
+        uniforms.model = simd_float4x4(1)
+        encoder.setVertexBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+        encoder.setFragmentBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+        encoder.setVertexBuffer(buffer, offset: 0, index: Int(BufferIndexVertices))
+        encoder.drawPrimitives(type: .line, vertexStart: 0, vertexCount: vertices.count)
+        encoder.popDebugGroup()
+    }
+    
+    func clear() {
+        vertices.removeAll(keepingCapacity: true)
+    }
+}
+
+
+extension LineDebugger {
+    /// Draws a spring as a coil between its anchors, which turns red when stretched and blue when compressed.
+    func draw(_ spring: Spring) {
+        let (a, b) = spring.endpoints
+        let stretch = a.distance(to: b) - spring.restLength
+        let color: Color = abs(stretch) < 0.01 ? .white : stretch > 0 ? .red : .blue
+        coil(from: a, to: b, color: color)
+    }
+    
+    /// Draws a suspension as a coil along its strut, which turns yellow when the wheel hits the end of its travel.
+    func draw(_ suspension: Suspension) {
+        let (a, b) = suspension.endpoints
+        coil(from: a, to: b, color: suspension.isAtLimit ? .yellow : .white)
+    }
+    
+    /// Draws the body of a character and the probes of its last move:
+    /// hits are red, misses grey, successful step probes green, and slide planes magenta.
+    func draw(_ character: CharacterController) {
+        let frame = Frame(position: character.position + 0.5 * character.height * .ez)
+        box(halfExtents: Point(character.radius, character.radius, 0.5 * character.height), frame: frame, color: .white)
+        
+        func drawProbe(_ probe: CharacterController.Probe, missColor: Color) {
+            if let hit = probe.hit {
+                line(from: probe.ray.origin, to: hit.point, color: .red)
+                arrow(from: hit.point, along: 0.2 * hit.normal, color: .red)
+            }
+            else {
+                line(from: probe.ray.origin, to: probe.ray.at(probe.ray.maxDistance), color: missColor)
+            }
+        }
+        
+        let info = character.debugInfo
+        if let probe = info.groundProbe {
+            drawProbe(probe, missColor: Color(0.5))
+        }
+        info.obstacleProbes.forEach { drawProbe($0, missColor: Color(0.5)) }
+        info.stepProbes.forEach { drawProbe($0, missColor: .green) }
+        
+        for (point, normal) in info.slidePlanes {
+            let (u, _) = normal.tangents
+            let side = Point(u.ex, u.ey, 0).normalize
+            let corners = [point - 0.5 * side, point + 0.5 * side, point + 0.5 * side + character.height * .ez, point - 0.5 * side + character.height * .ez]
+            for i in corners.indices {
+                line(from: corners[i], to: corners[(i + 1) % corners.count], color: .magenta)
+            }
+            arrow(from: point, along: 0.5 * normal, color: .magenta)
+        }
+    }
+}
+
+
+extension LineDebugger: DebugCanvas {}
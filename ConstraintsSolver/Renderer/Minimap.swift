@@ -0,0 +1,111 @@
+//
+//  Minimap.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 18.04.21.
+//
+
+import Metal
+
+
+/// A body as shown on the minimap.
+struct MinimapMarker {
+    var position: simd_float3
+    var velocity: simd_float3
+    var color: Color
+}
+
+
+/// A top-down orthographic view of the ground around the camera focus,
+/// showing bodies as diamonds and their horizontal velocities as arrows.
+class Minimap {
+    /// Half the side length of the square shown, in world units.
+    var extent: Float = 15
+    /// Length of the velocity arrows per unit speed, in world units.
+    var velocityScale: Float = 0.3
+    var markers: [MinimapMarker] = []
+    private var vertices: [Vertex] = []
+    private var buffer: MTLBuffer
+    private let device: MTLDevice
+    private let frameColor = simd_float3(repeating: 0.5)
+    
+    init(device: MTLDevice) {
+        self.device = device
+        buffer = device.makeBuffer(length: MemoryLayout<Vertex>.stride * 1024, options: .cpuCacheModeWriteCombined)!
+    }
+    
+    /// The camera looking straight down onto the given focus point.
+    func camera(centeredAt focus: Point) -> Camera {
+        var camera = Camera()
+        camera.look(at: Point(focus.ex, focus.ey, 0), from: Point(focus.ex, focus.ey, 50), up: .ey)
+        return camera
+    }
+    
+    var projectionMatrix: simd_float4x4 {
+        orthographicMatrix(left: -extent, right: extent, bottom: -extent, top: extent, near: 0.1, far: 100)
+    }
+    
+    /// Draws the markers as lines using the uniforms of the top-down camera.
+    func render(into encoder: MTLRenderCommandEncoder, uniforms: inout Uniforms) {
+        encoder.pushDebugGroup("Draw Minimap Markers")
+        
+        vertices.removeAll(keepingCapacity: true)
+        let size = 0.02 * extent
+        
+        for marker in markers {
+            let p = marker.position
+            let color = marker.color.rgb
+            
+            let corners = [p + simd_float3(size, 0, 0), p + simd_float3(0, size, 0), p + simd_float3(-size, 0, 0), p + simd_float3(0, -size, 0)]
+            for i in corners.indices {
+                push(corners[i], color)
+                push(corners[(i + 1) % corners.count], color)
+            }
+            
+            let velocity = simd_float3(marker.velocity.x, marker.velocity.y, 0)
+            if simd_length(velocity) > 1e-3 {
+                let tip = p + velocityScale * velocity
+                let back = -simd_normalize(velocity) * size
+                let side = simd_float3(-back.y, back.x, 0)
+                push(p, color)
+                push(tip, color)
+                push(tip, color)
+                push(tip + back + side, color)
+                push(tip, color)
+                push(tip + back - side, color)
+            }
+        }
+        
+        let byteCount = vertices.count * MemoryLayout<Vertex>.stride
+        if byteCount > buffer.length {
+            buffer = device.makeBuffer(length: 2 * byteCount, options: .cpuCacheModeWriteCombined)!
+        }
+        
+        if !vertices.isEmpty {
+            buffer.contents().copyMemory(from: vertices, byteCount: byteCount)
+            uniforms.model = simd_float4x4(1)
+            encoder.setVertexBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+            encoder.setFragmentBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+            encoder.setVertexBuffer(buffer, offset: 0, index: Int(BufferIndexVertices))
+            encoder.drawPrimitives(type: .line, vertexStart: 0, vertexCount: vertices.count)
+        }
+        encoder.popDebugGroup()
+    }
+    
+    private func push(_ position: simd_float3, _ color: simd_float3) {
+        vertices.append(Vertex(position: position, normal: simd_float3(0, 0, 1), color: color))
+    }
+}
+
+
+/// A right-handed orthographic projection mapping depth into [0, 1].
+func orthographicMatrix(left: Float, right: Float, bottom: Float, top: Float, near: Float, far: Float) -> simd_float4x4 {
+    var matrix = simd_float4x4(1)
+    matrix[0][0] = 2 / (right - left)
+    matrix[1][1] = 2 / (top - bottom)
+    matrix[2][2] = 1 / (near - far)
+    matrix[3][0] = -(right + left) / (right - left)
+    matrix[3][1] = -(top + bottom) / (top - bottom)
+    matrix[3][2] = near / (near - far)
+    return matrix
+}
@@ -0,0 +1,74 @@
+//
+//  Plot.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 16.04.21.
+//
+
+import Metal
+
+
+/// A line graph of the most recent samples of some quantity, drawn as a HUD element.
+/// The vertical axis is scaled so that the largest visible sample touches the top edge.
+class Plot {
+    let name: String
+    let color: Color
+    private(set) var samples: RingBuffer<Double>
+    private var vertices: [Vertex] = []
+    private let buffer: MTLBuffer
+    private let baselineColor = simd_float3(repeating: 0.4)
+    
+    init(device: MTLDevice, name: String, color: Color, capacity: Int = 300) {
+        self.name = name
+        self.color = color
+        samples = RingBuffer(capacity: capacity)
+        buffer = device.makeBuffer(length: MemoryLayout<Vertex>.stride * (capacity + 2), options: .cpuCacheModeWriteCombined)!
+    }
+    
+    var maximum: Double {
+        samples.elements.max() ?? 0
+    }
+    
+    func push(_ value: Double) {
+        samples.push(value)
+    }
+    
+    /// Renders the plot into the rectangle spanned by `origin` and `size`, given in pixels with the y-axis pointing downwards.
+    func render(into encoder: MTLRenderCommandEncoder, uniforms: inout Uniforms, origin: simd_float2, size: simd_float2) {
+        encoder.pushDebugGroup("Draw Plot '\(name)'")
+        
+        uniforms.model = simd_float4x4(1)
+        uniforms.model[0, 0] = size.x
+        uniforms.model[1, 1] = size.y
+        uniforms.model[3, 0] = origin.x
+        uniforms.model[3, 1] = origin.y
+        
+        vertices.removeAll(keepingCapacity: true)
+        push(0, 1, baselineColor)
+        push(1, 1, baselineColor)
+        
+        let scale = max(maximum, 1e-9)
+        for (index, sample) in samples.elements.enumerated() {
+            push(Float(index) / Float(samples.capacity - 1), 1 - Float(sample / scale), color.rgb)
+        }
+        
+        buffer.contents().copyMemory(from: vertices, byteCount: vertices.count * MemoryLayout<Vertex>.stride)
+        
+        encoder.setVertexBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+        encoder.setFragmentBytes(&uniforms, length: MemoryLayout<Uniforms>.size, index: Int(BufferIndexUniforms))
+        encoder.setVertexBuffer(buffer, offset: 0, index: Int(BufferIndexVertices))
+        encoder.drawPrimitives(type: .line, vertexStart: 0, vertexCount: 2)
+        if samples.count > 1 {
+            encoder.drawPrimitives(type: .lineStrip, vertexStart: 2, vertexCount: samples.count)
+        }
+        encoder.popDebugGroup()
+    }
+    
+    private func push(_ x: Float, _ y: Float, _ color: simd_float3) {
+        vertices.append(Vertex(
+                            position: simd_float3(x, y, 0),
+                            normal: simd_float3(0, 0, -1),
+                            color: color)
+        )
+    }
+}
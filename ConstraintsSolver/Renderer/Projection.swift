@@ -0,0 +1,82 @@
+//
+//  Projection.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 18.04.21.
+//
+
+import Foundation
+
+
+/// A perspective view volume, given by its extents on the near plane.
+/// The extents may be asymmetric around the view axis, as required for off-axis stereo projection.
+struct Frustum {
+    var left: Float
+    var right: Float
+    var bottom: Float
+    var top: Float
+    var near: Float
+    var far: Float
+    
+    /// A frustum centered around the view axis.
+    init(fovY: Float, aspectRatio: Float, near: Float, far: Float) {
+        let halfHeight = near * tan(0.5 * fovY)
+        self.init(left: -aspectRatio * halfHeight, right: aspectRatio * halfHeight, bottom: -halfHeight, top: halfHeight, near: near, far: far)
+    }
+    
+    init(left: Float, right: Float, bottom: Float, top: Float, near: Float, far: Float) {
+        self.left = left
+        self.right = right
+        self.bottom = bottom
+        self.top = top
+        self.near = near
+        self.far = far
+    }
+    
+    /// Shifts the frustum horizontally on the near plane.
+    func shifted(by offset: Float) -> Frustum {
+        var frustum = self
+        frustum.left += offset
+        frustum.right += offset
+        return frustum
+    }
+    
+    /// A right-handed projection matrix mapping depth into [0, 1].
+    var matrix: simd_float4x4 {
+        var matrix = simd_float4x4(0)
+        matrix[0][0] = 2 * near / (right - left)
+        matrix[1][1] = 2 * near / (top - bottom)
+        matrix[2][0] = (right + left) / (right - left)
+        matrix[2][1] = (top + bottom) / (top - bottom)
+        matrix[2][2] = far / (near - far)
+        matrix[2][3] = -1
+        matrix[3][2] = near * far / (near - far)
+        return matrix
+    }
+}
+
+
+/// Two eyes looking in parallel, whose frusta converge on a plane at a given distance.
+/// Objects on the convergence plane appear at screen depth.
+struct StereoRig {
+    var eyeSeparation: Double = 0.064
+    var convergenceDistance: Double = 5
+    
+    /// The left and right eye views of a camera, rendered side by side.
+    func viewports(of camera: Camera, fovY: Float, aspectRatio: Float, near: Float, far: Float) -> [Viewport] {
+        let frustum = Frustum(fovY: fovY, aspectRatio: aspectRatio, near: near, far: far)
+        
+        return [-1.0, 1.0].enumerated().map { index, side in
+            let eyeOffset = 0.5 * side * eyeSeparation
+            var eye = camera
+            eye.pan(rightwards: eyeOffset, upwards: 0)
+            
+            // Shift the frustum against the eye offset so that both frusta coincide on the convergence plane.
+            let shift = Float(-eyeOffset * Double(near) / convergenceDistance)
+            
+            return Viewport(camera: eye,
+                            region: CGRect(x: 0.5 * CGFloat(index), y: 0, width: 0.5, height: 1),
+                            frustum: frustum.shifted(by: shift))
+        }
+    }
+}
@@ -6,6 +6,15 @@ protocol FrameDelegate {
     func onFrame(dt: Double, t: Double)
 }
 
+/// A camera rendered into a part of the drawable.
+struct Viewport {
+    var camera: Camera
+    /// The covered part of the drawable in normalized coordinates, with the origin in the upper left corner.
+    var region: CGRect
+    /// An explicit view volume, otherwise a symmetric one matching the region is used.
+    var frustum: Frustum? = nil
+}
+
 class Renderer: NSObject, MTKViewDelegate {
     var frameDelegate: FrameDelegate? = .none
     private var startTime = Double(CACurrentMediaTime())
@@ -24,8 +33,20 @@ class Renderer: NSObject, MTKViewDelegate {
     var height: Float = 1.0
     var aspectRatio: Float = 1.0
     var camera = Camera()
+    /// Additional cameras which share the drawable side by side with the main camera, e.g. a chase camera.
+    var auxiliaryCameras: [Camera] = []
+    /// When set, the main camera is rendered as a side-by-side stereo pair instead.
+    var stereoRig: StereoRig?
     
     private var meshBuffers: [(Mesh, MTLBuffer)] = []
+    private(set) var plots: [Plot] = []
+    var showsPlots = true
+    let minimap: Minimap
+    let lineDebugger: LineDebugger
+    var showsMinimap = false
+    let minimapSize: Float = 300
+    let plotSize = simd_float2(360, 80)
+    let plotMargin: Float = 20
     fileprivate let grid: Grid
     fileprivate let axes: Axes
     
@@ -64,6 +85,8 @@ class Renderer: NSObject, MTKViewDelegate {
         
         grid = Grid(device: device, sections: 30)
         axes = Axes(device: device)
+        minimap = Minimap(device: device)
+        lineDebugger = LineDebugger(device: device)
         
         super.init()
     }
@@ -89,9 +112,73 @@ class Renderer: NSObject, MTKViewDelegate {
         encoder.setRenderPipelineState(pipelineState)
         encoder.setDepthStencilState(depthState)
         
+        lineDebugger.upload()
+        for viewport in viewports {
+            render(viewport, into: encoder)
+        }
+        
+        if showsMinimap {
+            renderMinimap(into: encoder)
+        }
+        
+        encoder.pushDebugGroup("Draw HUDs")
+        
         var uniforms = Uniforms()
-        uniforms.view = camera.viewMatrix
-        uniforms.projection = projectionMatrix
+        uniforms.model = simd_float4x4(1)
+        uniforms.projection = simd_float4x4(1)
+        uniforms.view = simd_float4x4(1)
+        uniforms.view[0, 0] = 2 * Float(1 / width)
+        uniforms.view[1, 1] = -2 * Float(1 / height)
+        uniforms.view[3, 0] = -1
+        uniforms.view[3, 1] = 1
+        encoder.setViewport(MTLViewport(originX: 0, originY: 0, width: Double(width), height: Double(height), znear: 0, zfar: 1))
+        encoder.setCullMode(.none)
+        encoder.setDepthStencilState(hudDepthState)
+        axes.render(into: encoder, uniforms: &uniforms, width: width, height: height, viewMatrix: camera.viewMatrix)
+        
+        // Plots are stacked upwards from the lower left corner.
+        for (index, plot) in plots.enumerated() where showsPlots {
+            let origin = simd_float2(plotMargin, height - Float(plots.count - index) * (plotSize.y + plotMargin))
+            plot.render(into: encoder, uniforms: &uniforms, origin: origin, size: plotSize)
+        }
+        
+        encoder.popDebugGroup()
+        
+        
+        encoder.endEncoding()
+        
+        commandBuffer.present(view.currentDrawable!)
+        commandBuffer.commit()
+        
+        lineDebugger.clear()
+    }
+    
+    /// The main camera followed by the auxiliary cameras, splitting the drawable into equally wide columns.
+    var viewports: [Viewport] {
+        if let stereoRig = stereoRig {
+            return stereoRig.viewports(of: camera, fovY: fovY, aspectRatio: 0.5 * aspectRatio, near: zNear, far: zFar)
+        }
+        
+        let cameras = [camera] + auxiliaryCameras
+        let columnWidth = 1 / CGFloat(cameras.count)
+        return cameras.enumerated().map { index, camera in
+            Viewport(camera: camera, region: CGRect(x: CGFloat(index) * columnWidth, y: 0, width: columnWidth, height: 1))
+        }
+    }
+    
+    private func render(_ viewport: Viewport, into encoder: MTLRenderCommandEncoder) {
+        let region = viewport.region
+        encoder.setViewport(MTLViewport(
+                                originX: Double(region.minX) * Double(width),
+                                originY: Double(region.minY) * Double(height),
+                                width: Double(region.width) * Double(width),
+                                height: Double(region.height) * Double(height),
+                                znear: 0,
+                                zfar: 1))
+        
+        var uniforms = Uniforms()
+        uniforms.view = viewport.camera.viewMatrix
+        uniforms.projection = projectionMatrix(for: viewport)
         
         encoder.pushDebugGroup("Draw Meshes")
         for (mesh, buffer) in meshBuffers {
@@ -111,28 +198,29 @@ class Renderer: NSObject, MTKViewDelegate {
         encoder.popDebugGroup()
         
         grid.render(into: encoder, uniforms: &uniforms)
-        
-        encoder.pushDebugGroup("Draw HUDs")
-        
-        let viewMatrix = uniforms.view
-        uniforms.model = simd_float4x4(1)
-        uniforms.projection = simd_float4x4(1)
-        uniforms.view = simd_float4x4(1)
-        uniforms.view[0, 0] = 2 * Float(1 / width)
-        uniforms.view[1, 1] = -2 * Float(1 / height)
-        uniforms.view[3, 0] = -1
-        uniforms.view[3, 1] = 1
+        lineDebugger.render(into: encoder, uniforms: &uniforms)
+    }
+    
+    /// Renders the minimap into a square in the lower right corner.
+    private func renderMinimap(into encoder: MTLRenderCommandEncoder) {
+        encoder.pushDebugGroup("Draw Minimap")
+        encoder.setViewport(MTLViewport(
+                                originX: Double(width - minimapSize - plotMargin),
+                                originY: Double(height - minimapSize - plotMargin),
+                                width: Double(minimapSize),
+                                height: Double(minimapSize),
+                                znear: 0,
+                                zfar: 1))
         encoder.setCullMode(.none)
         encoder.setDepthStencilState(hudDepthState)
-        axes.render(into: encoder, uniforms: &uniforms, width: width, height: height, viewMatrix: viewMatrix)
         
-        encoder.popDebugGroup()
-        
-        
-        encoder.endEncoding()
+        var uniforms = Uniforms()
+        uniforms.view = minimap.camera(centeredAt: camera.focus).viewMatrix
+        uniforms.projection = minimap.projectionMatrix
         
-        commandBuffer.present(view.currentDrawable!)
-        commandBuffer.commit()
+        grid.render(into: encoder, uniforms: &uniforms)
+        minimap.render(into: encoder, uniforms: &uniforms)
+        encoder.popDebugGroup()
     }
     
     func mtkView(_ view: MTKView, drawableSizeWillChange size: CGSize) {
@@ -141,32 +229,35 @@ class Renderer: NSObject, MTKViewDelegate {
         aspectRatio = Float(size.width / size.height)
     }
     
-    private var projectionMatrix: simd_float4x4 {
-        let tanHalfFovy = tan(0.5 * fovY)
-
-        var perspectiveMatrix = simd_float4x4(1)
-        perspectiveMatrix[0][0] = 1 / (aspectRatio * tanHalfFovy)
-        perspectiveMatrix[1][1] = 1 / (tanHalfFovy)
-        perspectiveMatrix[2][2] = zFar / (zNear - zFar)
-        perspectiveMatrix[2][3] = -1
-        perspectiveMatrix[3][2] = -(zFar * zNear) / (zFar - zNear)
-        
-        return perspectiveMatrix
+    func projectionMatrix(for viewport: Viewport) -> simd_float4x4 {
+        let region = viewport.region
+        let frustum = viewport.frustum ?? Frustum(fovY: fovY, aspectRatio: aspectRatio * Float(region.width / region.height), near: zNear, far: zFar)
+        return frustum.matrix
+    }
+    
+    func addPlot(name: String, color: Color) -> Plot {
+        let plot = Plot(device: device, name: name, color: color)
+        plots.append(plot)
+        return plot
     }
     
     func registerMesh(_ newMesh: Mesh) {
         for (mesh, buffer) in meshBuffers {
             if (mesh === newMesh) {
-                if (newMesh.vertices.count != buffer.length / MemoryLayout<Vertex>.stride) {
+                if (newMesh.vertices.count != buffer.length / MemoryLayout<MeshVertex>.stride) {
                     fatalError("Cannot update mesh when the vertex count is different")
                 }
-                buffer.contents().copyMemory(from: newMesh.vertices, byteCount: newMesh.vertices.count * MemoryLayout<Vertex>.stride)
+                buffer.contents().copyMemory(from: newMesh.vertices, byteCount: newMesh.vertices.count * MemoryLayout<MeshVertex>.stride)
             }
         }
         
-        let buffer = device.makeBuffer(bytes: newMesh.vertices, length: newMesh.vertices.count * MemoryLayout<Vertex>.stride, options: .cpuCacheModeWriteCombined)!
+        let buffer = device.makeBuffer(bytes: newMesh.vertices, length: newMesh.vertices.count * MemoryLayout<MeshVertex>.stride, options: .cpuCacheModeWriteCombined)!
         meshBuffers.append((newMesh, buffer))
     }
+    
+    func unregisterMesh(_ mesh: Mesh) {
+        meshBuffers.removeAll { $0.0 === mesh }
+    }
 }
 
 fileprivate class Grid {
@@ -302,3 +393,6 @@ fileprivate class Axes {
         encoder.popDebugGroup()
     }
 }
+
+
+extension Renderer: MeshRegistry {}
@@ -0,0 +1,37 @@
+//
+//  AdaptiveSubSteps.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Scales the number of sub-steps with the activity of a scene, so that calm scenes run cheaply
+/// while violent moments automatically get a finer resolution.
+struct AdaptiveSubSteps {
+    var range = 10 ... 100
+    /// How far a point on the fastest body may move within one sub-step.
+    var maxDisplacement = 0.01
+    /// Penetrations deeper than this during the last step raise the count proportionally.
+    var penetrationTolerance = 0.01
+    
+    func count(for rigids: [Rigid], dt: Double, lastPenetration: Double) -> Int {
+        // No point of a rigid is further from its center than its bounding radius.
+        let maxSpeed = rigids
+            .filter { $0.inverseMass > 0 }
+            .map { $0.velocity.length + min($0.collider.boundingRadius, 1e6) * $0.angularVelocity.length }
+            .max() ?? 0
+        
+        var count = maxSpeed * dt / maxDisplacement
+        if lastPenetration > penetrationTolerance {
+            count = max(count, Double(range.lowerBound) * lastPenetration / penetrationTolerance)
+        }
+        
+        guard count.isFinite else {
+            return range.upperBound
+        }
+        return Int(min(count, Double(range.upperBound)).rounded(.up)).clamped(to: range)
+    }
+}
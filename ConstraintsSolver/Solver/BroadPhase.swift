@@ -0,0 +1,111 @@
+//
+//  BroadPhase.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// An axis-aligned box in global coordinates.
+struct BoundingBox {
+    var lower: Point
+    var upper: Point
+    
+    var center: Point {
+        0.5 * (lower + upper)
+    }
+    
+    func overlaps(_ other: BoundingBox) -> Bool {
+        lower.ex <= other.upper.ex && other.lower.ex <= upper.ex &&
+            lower.ey <= other.upper.ey && other.lower.ey <= upper.ey &&
+            lower.ez <= other.upper.ez && other.lower.ez <= upper.ez
+    }
+    
+    func expanded(by margin: Double) -> BoundingBox {
+        BoundingBox(lower: lower - Point(margin), upper: upper + Point(margin))
+    }
+}
+
+
+extension Rigid {
+    /// The box enclosing the collider in the current frame, which is nil for unbounded colliders.
+    var boundingBox: BoundingBox? {
+        switch collider {
+        case .plane(_), .mesh(_):
+            return nil
+        case let .box(box):
+            return boundingBox(of: [PlacedConvexCollider(box.convex, frame: frame)]).expanded(by: box.margin)
+        case let .convex(convex):
+            return boundingBox(of: [PlacedConvexCollider(convex, frame: frame)])
+        case let .compound(compound):
+            return boundingBox(of: compound.placed(by: frame))
+        }
+    }
+    
+    private func boundingBox(of volumes: [ConvexVolume]) -> BoundingBox {
+        if volumes.isEmpty {
+            return BoundingBox(lower: frame.position, upper: frame.position)
+        }
+        // The extent along each global axis is the furthest support point in that direction.
+        func extent(along axis: Point) -> Double {
+            volumes.map { $0.furthestPoint(in: axis).dot(axis) }.max()!
+        }
+        return BoundingBox(lower: Point(-extent(along: -Point.ex), -extent(along: -Point.ey), -extent(along: -Point.ez)),
+                           upper: Point(extent(along: .ex), extent(along: .ey), extent(along: .ez)))
+    }
+}
+
+
+/// Finds the pairs of rigids close enough to touch before their contacts are generated,
+/// which is otherwise done for every pair.
+enum BroadPhase {
+    /// Every pair is passed on, which only pays off for a handful of rigids.
+    case allPairs
+    /// Passes on the pairs whose bounding boxes overlap, found by sorting the boxes along the axis in which they
+    /// are spread the most. The boxes are enlarged by how far the rigids may move within the time given,
+    /// plus the margin, which covers accelerations and pushes by contacts.
+    case sweepAndPrune(margin: Double)
+    
+    /// For each rigid, the indices of the rigids after it in the list which it may touch within the given time,
+    /// in ascending order. Unbounded rigids like the ground may touch any other.
+    func partners(of rigids: [Rigid], within dt: Double) -> [[Int]] {
+        guard case let .sweepAndPrune(margin) = self else {
+            return rigids.indices.map { Array($0 + 1 ..< rigids.count) }
+        }
+        
+        let boxes = rigids.map { rigid in
+            rigid.boundingBox?.expanded(by: margin + dt * (rigid.velocity.length + rigid.angularVelocity.length * min(rigid.collider.boundingRadius, 1e6)))
+        }
+        var rows = [[Int]](repeating: [], count: rigids.count)
+        
+        for i in rigids.indices where boxes[i] == nil {
+            for j in rigids.indices where j != i && (boxes[j] != nil || i < j) {
+                rows[min(i, j)].append(max(i, j))
+            }
+        }
+        
+        let bounded = rigids.indices.compactMap { i in boxes[i].map { (i, $0) } }
+        let centers = bounded.map { $0.1.center }
+        func spread(_ component: (Point) -> Double) -> Double {
+            let values = centers.map(component)
+            return (values.max() ?? 0) - (values.min() ?? 0)
+        }
+        let components: [(Point) -> Double] = [{ $0.ex }, { $0.ey }, { $0.ez }]
+        let key = components.max { spread($0) < spread($1) }!
+        
+        // Boxes are visited by their lower bound, keeping those which still reach past it.
+        var active: [(Int, BoundingBox)] = []
+        for (i, box) in bounded.sorted(by: { key($0.1.lower) < key($1.1.lower) }) {
+            active.removeAll { key($0.1.upper) < key(box.lower) }
+            for (j, other) in active where box.overlaps(other) {
+                rows[min(i, j)].append(max(i, j))
+            }
+            active.append((i, box))
+        }
+        
+        // Contacts are solved in the order of the rows, which must not depend on the sorting.
+        return rows.map { $0.sorted() }
+    }
+}
@@ -0,0 +1,122 @@
+//
+//  Buoyancy.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Water below a surface plane, which lifts the rigids dipping into it by the weight of the water they displace,
+/// and drags them along with its current. Each rigid is sampled by a grid of points inside its collider, each standing
+/// for an equal share of its volume, so partly submerged rigids are lifted off-center and right themselves.
+/// The surface may be changed between steps, e.g. to make waves.
+final class BuoyancyForce: ForceGenerator {
+    /// The surface, whose normal points out of the water.
+    var surface: Plane
+    /// Kilograms per cubic meter.
+    var density: Double
+    /// The gravity pulling the water down, which lifts the rigids the other way.
+    var gravity: Point
+    /// Force in newtons per meter per second of speed relative to the water, for every cubic meter submerged.
+    var drag: Double
+    /// The velocity of the water, which the drag pulls rigids along with.
+    var current = Point.null
+    /// The number of sample points along each axis of the bounding box of a rigid.
+    var resolution = 4
+    
+    init(surface: Plane, density: Double = 1000, gravity: Point, drag: Double = 500) {
+        self.surface = surface
+        self.density = density
+        self.gravity = gravity
+        self.drag = drag
+    }
+    
+    func apply(to rigids: [Rigid]) {
+        for rigid in rigids where rigid.inverseMass > 0 && !rigid.isSensor {
+            // Rigids clearly above the surface aren't sampled.
+            guard surface.normal.dot(rigid.frame.position) - surface.offset < rigid.collider.boundingRadius,
+                  let samples = samples(of: rigid.collider) else {
+                continue
+            }
+            // Samples are lifted gradually while their cell crosses the surface, so that rigids don't jitter on it.
+            let cellSize = cbrt(samples.volume)
+            for point in samples.points.map(rigid.frame.act) {
+                let depth = surface.offset - surface.normal.dot(point)
+                let submerged = (depth / cellSize + 0.5).clamped(to: 0 ... 1) * samples.volume
+                if submerged == 0 {
+                    continue
+                }
+                let lift = -(density * submerged) * gravity
+                let resistance = -(drag * submerged) * (rigid.pointVelocity(at: point) - current)
+                rigid.applyGeneratedForce(lift + resistance, at: point)
+            }
+        }
+    }
+    
+    /// The sample points in the local frame of a collider, and the volume each of them stands for.
+    private func samples(of collider: Collider) -> (points: [Point], volume: Double)? {
+        guard let volume = collider.volume, let bounds = collider.localBounds else {
+            return nil
+        }
+        let size = bounds.upper - bounds.lower
+        var points: [Point] = []
+        for i in 0 ..< resolution {
+            for j in 0 ..< resolution {
+                for k in 0 ..< resolution {
+                    let cell = (1 / Double(resolution)) * Point(Double(i) + 0.5, Double(j) + 0.5, Double(k) + 0.5)
+                    let point = bounds.lower + size .* cell
+                    if collider.distance(to: point) == 0 {
+                        points.append(point)
+                    }
+                }
+            }
+        }
+        // Thin colliders may fall between the samples, and then float by their center.
+        if points.isEmpty {
+            points = [bounds.center]
+        }
+        return (points, volume / Double(points.count))
+    }
+}
+
+
+extension Collider {
+    /// The volume of a bounded collider, or `nil` for planes and meshes.
+    var volume: Double? {
+        switch self {
+        case .plane(_), .mesh(_):
+            return nil
+        case .box(_):
+            return 1
+        case let .compound(compound):
+            return compound.shapes.reduce(0) { $0 + $1.collider.volume }
+        case let .convex(convex):
+            return convex.volume
+        }
+    }
+    
+    /// The box enclosing a bounded collider in its local frame.
+    fileprivate var localBounds: BoundingBox? {
+        let volumes: [ConvexVolume]
+        switch self {
+        case .plane(_), .mesh(_):
+            return nil
+        case let .box(box):
+            volumes = [PlacedConvexCollider(box.convex, frame: Frame())]
+        case let .compound(compound):
+            volumes = compound.placed(by: Frame())
+        case let .convex(convex):
+            volumes = [PlacedConvexCollider(convex, frame: Frame())]
+        }
+        if volumes.isEmpty {
+            return nil
+        }
+        func extent(along axis: Point) -> Double {
+            volumes.map { $0.furthestPoint(in: axis).dot(axis) }.max()!
+        }
+        return BoundingBox(lower: Point(-extent(along: -Point.ex), -extent(along: -Point.ey), -extent(along: -Point.ez)),
+                           upper: Point(extent(along: .ex), extent(along: .ey), extent(along: .ez)))
+    }
+}
@@ -0,0 +1,36 @@
+//
+//  CameraCollision.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+extension World {
+    /// Sweeps a sphere from the camera target towards the desired camera position and returns how far the camera
+    /// may move away from the target without clipping into geometry.
+    func allowedCameraDistance(from target: Point, towards desired: Point, radius: Double = 0.2, filter: QueryFilter = .none) -> Double {
+        let offset = target.to(desired)
+        let desiredDistance = offset.length
+        if desiredDistance < 1e-9 {
+            return 0
+        }
+        
+        let ray = Ray(origin: target, direction: offset, maxDistance: desiredDistance)
+        var allowed = desiredDistance
+        for rigid in rigids where filter.accepts(rigid) {
+            if let hit = rigid.collider.cast(ray.localized(to: rigid.frame), radius: radius) {
+                allowed = min(allowed, hit.distance)
+            }
+        }
+        return allowed
+    }
+    
+    /// The desired camera position pulled towards the target as far as needed to keep it out of geometry.
+    func collisionFreeCameraPosition(target: Point, desired: Point, radius: Double = 0.2, filter: QueryFilter = .none) -> Point {
+        let distance = allowedCameraDistance(from: target, towards: desired, radius: radius, filter: filter)
+        return target + distance * target.to(desired).normalize
+    }
+}
@@ -0,0 +1,105 @@
+//
+//  CharacterController.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 20.04.21.
+//
+
+import Foundation
+
+
+/// A kinematic character which is moved directly rather than by forces, stepping up small ledges and sliding along walls.
+/// Collisions are approximated by rays probing forwards for obstacles and downwards for ground.
+class CharacterController {
+    /// The point between the feet.
+    var position: Point
+    var radius = 0.3
+    var height = 1.8
+    /// Obstacles up to this height are stepped onto.
+    var stepOffset = 0.3
+    /// Steepest slope, in radians, which can be walked on.
+    var maxSlope = Double.pi / 4
+    var verticalVelocity = 0.0
+    private(set) var isGrounded = false
+    /// What happened during the last move, kept for debug visualization.
+    private(set) var debugInfo = DebugInfo()
+    
+    struct Probe {
+        let ray: Ray
+        let hit: RayHit?
+    }
+    
+    struct DebugInfo {
+        var groundProbe: Probe?
+        var obstacleProbes: [Probe] = []
+        /// Probes above obstacles at step height, which succeeded if they did not hit anything.
+        var stepProbes: [Probe] = []
+        var slidePlanes: [(point: Point, normal: Point)] = []
+    }
+    
+    init(position: Point) {
+        self.position = position
+    }
+    
+    private func isWalkable(_ normal: Point) -> Bool {
+        normal.ez >= cos(maxSlope)
+    }
+    
+    /// Moves horizontally by the given displacement and applies gravity for the given time step.
+    func move(by displacement: Point, dt: Double, gravity: Point, in world: World) {
+        debugInfo = DebugInfo()
+        
+        var remaining = Point(displacement.ex, displacement.ey, 0)
+        for _ in 0 ..< 3 {
+            let length = remaining.length
+            if length < 1e-9 {
+                break
+            }
+            let direction = (1 / length) * remaining
+            
+            let obstacleRay = Ray(origin: position + 0.05 * .ez, direction: direction, maxDistance: length + radius)
+            let obstacle = world.intersections(with: obstacleRay).first
+            debugInfo.obstacleProbes.append(Probe(ray: obstacleRay, hit: obstacle))
+            
+            guard let hit = obstacle, !isWalkable(hit.normal) else {
+                position = position + remaining
+                break
+            }
+            
+            let stepRay = Ray(origin: position + stepOffset * .ez, direction: direction, maxDistance: length + radius)
+            let stepHit = world.intersections(with: stepRay).first
+            debugInfo.stepProbes.append(Probe(ray: stepRay, hit: stepHit))
+            
+            if stepHit == nil {
+                // The ground probe below snaps onto the obstacle.
+                position = position + remaining + stepOffset * .ez
+                break
+            }
+            
+            // Slide along the wall, moving up to it first.
+            let wallNormal = Point(hit.normal.ex, hit.normal.ey, 0).normalize
+            let approach = max(hit.distance - radius, 0)
+            position = position + approach * direction
+            remaining = (length - approach) * direction
+            remaining = remaining - remaining.project(onto: wallNormal)
+            debugInfo.slidePlanes.append((hit.point, wallNormal))
+        }
+        
+        verticalVelocity += dt * gravity.ez
+        let fall = -min(verticalVelocity * dt, 0)
+        
+        let groundRay = Ray(origin: position + stepOffset * .ez, direction: -Point.ez, maxDistance: 2 * stepOffset + fall)
+        let ground = world.intersections(with: groundRay).first { isWalkable($0.normal) }
+        debugInfo.groundProbe = Probe(ray: groundRay, hit: ground)
+        
+        if let ground = ground, ground.point.ez >= position.ez - fall - (isGrounded ? stepOffset : 0) {
+            position = ground.point
+            verticalVelocity = 0
+            isGrounded = true
+        }
+        else {
+            position = position + (dt * verticalVelocity) * .ez
+            isGrounded = false
+        }
+    }
+}
@@ -2,12 +2,18 @@
 enum Collider {
     case plane(Plane)
     case box(BoxCollider)
+    case mesh(TriangleMeshCollider)
+    case compound(CompoundCollider)
+    case convex(ConvexCollider)
 }
 
 struct BoxCollider {
     let points: [Point]
+    /// Contacts are generated this far outside of the box already, which keeps it slightly above surfaces.
+    let margin: Double
     
-    init() {
+    init(margin: Double = 0) {
+        self.margin = margin
         points = [
             .init(-1, -1, -1),
             .init(1, -1, -1),
@@ -24,52 +30,51 @@ struct BoxCollider {
         points.map { frame.act($0) }
     }
     
-    func intersect(attachedTo rigid: Rigid, with p: Plane, attachedTo other: Rigid) -> [Constraint] {
-        var constraints: [Constraint] = []
-        let plane = other.frame.act(p)
-        
-        for position in points.map(rigid.frame.act) {
-            if position.reject(from: plane).dot(plane.normal) >= 0 {
-                continue
-            }
-            
-            let targetPosition = position.project(onto: plane)
-            let correction = position.to(targetPosition)
-            
-            let deltaPosition = rigid.delta(global: position)
-            let deltaTangentialPosition = deltaPosition - deltaPosition.project(onto: correction)
-            
-            constraints.append(PositionalConstraint(
-                rigids: (rigid, other),
-                contacts: (position, targetPosition - 1 * deltaTangentialPosition),
-                distance: 0
-            ))
-        }
-        
-        return constraints
+    func intersect(attachedTo rigid: Rigid, with p: Plane, attachedTo other: Rigid, friction: Friction) -> [Constraint] {
+        intersectPoints(points, margin: margin, attachedTo: rigid, with: p, attachedTo: other, friction: friction)
     }
     
-    func intersect(attachedTo rigid: Rigid, with b: BoxCollider, attachedTo other: Rigid) -> [Constraint] {
-        var constraints: [Constraint] = []
-        
-        let d = intersectBoxBox(box: b.apply(frame: other.frame).map { rigid.frame.inverse.act($0) })
-            .map { a, b in (rigid.frame.act(a), rigid.frame.act(b)) }
-        
-        let g = intersectBoxBox(box: apply(frame: rigid.frame).map { other.frame.inverse.act($0) })
-            .map { a, b in (other.frame.act(a), other.frame.act(b)) }
-        
-        if !d.isEmpty {
-            other.frame.position = other.frame.position + (d[0].0.to(d[0].1))
-            return []
+    /// The unit box as a convex collider, without its margin.
+    var convex: ConvexCollider {
+        .box(halfExtents: Point(0.5))
+    }
+    
+    /// Resolves an overlap with another convex collider, see `intersectConvex`.
+    func intersect(attachedTo rigid: Rigid, with b: ConvexCollider, attachedTo other: Rigid, tracer: CollisionTracer? = nil) -> [Constraint] {
+        intersectConvex(PlacedConvexCollider(convex, frame: rigid.frame), attachedTo: rigid, with: PlacedConvexCollider(b, frame: other.frame), attachedTo: other,
+                        tracer: tracer)
+    }
+}
+
+
+/// Pushes points given in the local frame of a rigid out of a plane attached to another rigid.
+/// Contacts are told apart across sub-steps by the index of the point they stem from.
+func intersectPoints(_ points: [Point], margin: Double, attachedTo rigid: Rigid, with p: Plane, attachedTo other: Rigid, friction: Friction) -> [Constraint] {
+    var constraints: [Constraint] = []
+    let surface = other.frame.act(p)
+    let plane = Plane(normal: surface.normal, offset: surface.offset + margin)
+    
+    for (index, position) in points.map(rigid.frame.act).enumerated() {
+        if position.reject(from: plane).dot(plane.normal) >= 0 {
+            continue
         }
         
-        for (a, b) in d + g {
-            constraints.append(PositionalConstraint(
-                                rigids: (rigid, other),
-                                contacts: (a, b),
-                                distance: 0))
-        }
+        let targetPosition = position.project(onto: plane)
+        let correction = position.to(targetPosition)
         
-        return constraints
+        let deltaPosition = rigid.delta(global: position)
+        let deltaTangentialPosition = deltaPosition - deltaPosition.project(onto: correction)
+        let surfaceDisplacement = friction.surfaceDisplacement - friction.surfaceDisplacement.project(onto: plane.normal)
+        let tangentialCorrection = friction.limit(surfaceDisplacement - deltaTangentialPosition, normal: plane.normal, depth: correction.length, of: rigid)
+        
+        constraints.append(PositionalConstraint(
+            rigids: (rigid, other),
+            contacts: (position, targetPosition + tangentialCorrection),
+            distance: 0,
+            normal: plane.normal,
+            feature: ContactFeature(isReferenceFirst: true, referenceFace: -1, incidentFace: -1, point: index)
+        ))
     }
+    
+    return constraints
 }
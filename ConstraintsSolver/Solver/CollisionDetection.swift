@@ -0,0 +1,80 @@
+//
+//  CollisionDetection.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum CollisionDetection {
+    /// All pairs are searched for contacts in every sub-step, which is the most accurate.
+    case everySubStep
+    /// The pairs which may touch during a step are found once at its beginning, and only their contacts are updated
+    /// in the sub-steps. Much cheaper for many bodies, but fast bodies may miss pairs they only reach within the step.
+    case oncePerStep
+}
+
+
+extension Collider {
+    /// Radius of a sphere around the origin of the collider enclosing it, which is infinite for unbounded colliders.
+    var boundingRadius: Double {
+        switch self {
+        case .plane(_), .mesh(_):
+            return .infinity
+        case let .box(box):
+            return 0.5 * 3.0.squareRoot() + box.margin
+        case let .compound(compound):
+            return compound.boundingRadius
+        case let .convex(convex):
+            return convex.boundingRadius
+        }
+    }
+}
+
+
+/// Whether two rigids may touch within the given time, assuming they keep their velocities.
+func mayTouch(_ rigid: Rigid, _ other: Rigid, within dt: Double) -> Bool {
+    let reach = dt * (rigid.velocity - other.velocity).length +
+        dt * (rigid.angularVelocity.length * min(rigid.collider.boundingRadius, 1e6) + other.angularVelocity.length * min(other.collider.boundingRadius, 1e6))
+    
+    // Measure from the bounded collider to the surface of the other one.
+    func gap(from a: Rigid, to b: Rigid) -> Double {
+        let radius = a.collider.boundingRadius
+        if radius.isInfinite {
+            return .infinity
+        }
+        return b.collider.distance(to: b.frame.inverse.act(a.frame.position)) - radius
+    }
+    
+    return min(gap(from: rigid, to: other), gap(from: other, to: rigid)) <= reach
+}
+
+
+/// The pairs of rigids which may touch within the given time, among the partners of each rigid found by the broad phase.
+/// With `concurrently`, the rows of the pair matrix are checked on several threads, but always merged in row order,
+/// so the result never depends on the number of threads available, which lockstep networking and comparisons
+/// between machines rely on.
+func findCandidatePairs(of rigids: [Rigid], among partners: [[Int]], within dt: Double, concurrently: Bool) -> [IndexPair] {
+    var rows = [[Int]](repeating: [], count: rigids.count)
+    
+    func check(row i: Int) -> [Int] {
+        partners[i].filter { mayTouch(rigids[i], rigids[$0], within: dt) }
+    }
+    
+    if concurrently {
+        rows.withUnsafeMutableBufferPointer { rows in
+            DispatchQueue.concurrentPerform(iterations: rows.count) { i in
+                rows[i] = check(row: i)
+            }
+        }
+    }
+    else {
+        for i in rows.indices {
+            rows[i] = check(row: i)
+        }
+    }
+    
+    return rows.enumerated().flatMap { i, row in row.map { IndexPair(i, $0) } }
+}
@@ -0,0 +1,151 @@
+//
+//  CollisionTrace.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Every iteration GJK and EPA took for a pair of convex volumes, in the coordinates of their Minkowski difference,
+/// so that numerical edge cases of the simplex reduction can be analyzed offline. Traces are saved as JSON,
+/// and `draw(iteration:into:)` replays them through a line debugger one iteration at a time.
+struct CollisionTrace: Codable {
+    struct SimplexIteration: Codable {
+        /// The simplex after adding the latest support point, most recent point first.
+        let simplex: [Point]
+        /// The search direction after reducing the simplex to the feature closest to the origin.
+        let direction: Point
+    }
+    
+    struct PolytopeIteration: Codable {
+        let points: [Point]
+        /// Triangles as indices of three points, wound counter-clockwise when seen from outside.
+        let triangles: [[Int]]
+        /// The outward normal of the triangle closest to the origin, and its distance.
+        let normal: Point
+        let distance: Double
+        /// The support point along the normal, which the polytope is expanded to unless it is hardly any further out.
+        let support: Point
+    }
+    
+    enum Outcome: String, Codable {
+        /// GJK found a separating direction.
+        case separated
+        /// EPA converged to the shortest separating translation.
+        case resolved
+        /// GJK ran out of iterations, e.g. cycling between simplices near the boundary of the difference.
+        case simplexExhausted
+        /// EPA ran out of iterations, which leaves the depth approximate.
+        case polytopeExhausted
+        /// All triangles of the polytope degenerated, so EPA stopped without a normal.
+        case polytopeDegenerated
+    }
+    
+    /// The frames of both rigids, to find the pair in the scene again.
+    let frames: [Frame]
+    var simplices: [SimplexIteration] = []
+    var polytopes: [PolytopeIteration] = []
+    var outcome = Outcome.separated
+    
+    init(frames: [Frame]) {
+        self.frames = frames
+    }
+    
+    /// Whether GJK or EPA gave up rather than reaching an answer.
+    var hasFailed: Bool {
+        switch outcome {
+        case .separated, .resolved:
+            return false
+        case .simplexExhausted, .polytopeExhausted, .polytopeDegenerated:
+            return true
+        }
+    }
+    
+    /// The GJK iterations followed by the EPA iterations.
+    var iterationCount: Int {
+        simplices.count + polytopes.count
+    }
+    
+    /// Draws the state after an iteration around the origin, which the Minkowski difference has to contain for the
+    /// volumes to overlap: the simplex and the search direction for GJK, and the polytope, its nearest triangle
+    /// and the support point found along its normal for EPA.
+    func draw(iteration: Int, into canvas: DebugCanvas) {
+        canvas.box(halfExtents: Point(0.01), frame: .identity, color: .white)
+        if iteration < simplices.count {
+            let step = simplices[iteration]
+            for (index, a) in step.simplex.enumerated() {
+                for b in step.simplex[(index + 1)...] {
+                    canvas.line(from: a, to: b, color: .yellow)
+                }
+            }
+            // The direction vanishes once the simplex touches the origin.
+            if step.direction.length > 1e-12 {
+                canvas.arrow(from: .null, along: step.direction.normalize, color: .cyan)
+            }
+            return
+        }
+        
+        let step = polytopes[iteration - simplices.count]
+        for triangle in step.triangles {
+            let corners = triangle.map { step.points[$0] }
+            for index in corners.indices {
+                canvas.line(from: corners[index], to: corners[(index + 1) % corners.count], color: Color(0.5))
+            }
+        }
+        canvas.arrow(from: .null, along: step.distance * step.normal, color: .red)
+        canvas.box(halfExtents: Point(0.02), frame: Frame(position: step.support), color: .green)
+    }
+}
+
+
+/// Collects the traces of the collision pairs checked with GJK and EPA while set on the world, keeping those of
+/// pairs which failed, or all of them for following a single pair. Pairs are checked on several threads if islands
+/// are solved concurrently, so recording is synchronized.
+///
+///     let tracer = CollisionTracer()
+///     world.collisionTracer = tracer
+///     world.integrate(dt: 1.0 / 60)
+///     try tracer.write(to: URL(fileURLWithPath: "collisions.json"))
+///
+final class CollisionTracer {
+    /// Whether traces of pairs which GJK and EPA handled fine are kept as well.
+    var keepsAll = false
+    /// Traces beyond this many are dropped, so that a tracer left on doesn't fill up the memory.
+    var capacity = 256
+    private let lock = NSLock()
+    private var kept: [CollisionTrace] = []
+    
+    var traces: [CollisionTrace] {
+        lock.lock()
+        defer { lock.unlock() }
+        return kept
+    }
+    
+    func record(_ trace: CollisionTrace) {
+        guard keepsAll || trace.hasFailed else {
+            return
+        }
+        lock.lock()
+        defer { lock.unlock() }
+        if kept.count < capacity {
+            kept.append(trace)
+        }
+    }
+    
+    func clear() {
+        lock.lock()
+        defer { lock.unlock() }
+        kept.removeAll()
+    }
+    
+    func write(to url: URL) throws {
+        try JSONEncoder().encode(traces).write(to: url)
+    }
+    
+    /// Reads traces written by `write(to:)`, to replay them.
+    static func traces(contentsOf url: URL) throws -> [CollisionTrace] {
+        try JSONDecoder().decode([CollisionTrace].self, from: Data(contentsOf: url))
+    }
+}
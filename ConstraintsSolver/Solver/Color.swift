@@ -1,4 +1,5 @@
 import Foundation
+import simd
 
 struct Color {
     let rgb: simd_float3
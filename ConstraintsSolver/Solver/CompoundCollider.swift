@@ -0,0 +1,292 @@
+//
+//  CompoundCollider.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A convex part of a compound collider, given by the triangles of its hull.
+public struct ConvexPiece {
+    let vertices: [Point]
+    let faces: [(Int, Int, Int)]
+    /// The outward facing planes of the faces. The piece is the intersection of the half-spaces behind them.
+    let planes: [Plane]
+    let center: Point
+    /// Radius of the sphere around the center enclosing the piece.
+    let radius: Double
+    
+    init(vertices: [Point], faces: [(Int, Int, Int)]) {
+        self.vertices = vertices
+        self.faces = faces
+        let center = vertices.center
+        self.center = center
+        radius = vertices.map { center.distance(to: $0) }.max() ?? 0
+        planes = faces.map { a, b, c in
+            let plane = Plane((vertices[a], vertices[b], vertices[c]))
+            // Decomposition tools don't agree on winding, so orient the planes away from the center.
+            return center.dot(plane.normal) > plane.offset ? plane.flip : plane
+        }
+    }
+    
+    /// Intersects a ray given in the local frame of the piece, with all faces pushed outwards by `offset`.
+    func intersect(_ ray: Ray, offset: Double = 0) -> (distance: Double, normal: Point)? {
+        var entry = -Double.infinity
+        var exit = Double.infinity
+        var normal = Point.null
+        
+        for plane in planes {
+            let height = ray.origin.dot(plane.normal) - plane.offset - offset
+            let alignment = ray.direction.dot(plane.normal)
+            
+            if abs(alignment) < 1e-12 {
+                if height > 0 {
+                    return nil
+                }
+                continue
+            }
+            
+            let distance = -height / alignment
+            if alignment < 0 {
+                if distance > entry {
+                    entry = distance
+                    normal = plane.normal
+                }
+            }
+            else {
+                exit = min(exit, distance)
+            }
+        }
+        
+        if entry > exit || exit < 0 || entry > ray.maxDistance {
+            return nil
+        }
+        return entry < 0 ? (0, -ray.direction) : (entry, normal)
+    }
+    
+    /// The faces wound counter-clockwise when seen from outside, regardless of the winding in the source file.
+    var outwardFaces: [(Int, Int, Int)] {
+        zip(faces, planes).map { face, plane in
+            let (a, b, c) = face
+            let normal = vertices[a].to(vertices[b]).cross(vertices[a].to(vertices[c]))
+            return normal.dot(plane.normal) > 0 ? (a, b, c) : (a, c, b)
+        }
+    }
+    
+    /// A lower bound of the distance of a point to the piece, which is exact near the faces.
+    func distance(to point: Point) -> Double {
+        max(planes.map { point.dot($0.normal) - $0.offset }.max() ?? 0, 0)
+    }
+}
+
+
+/// A convex shape placed in the local frame of a rigid as part of a compound collider.
+struct CompoundShape {
+    let collider: ConvexCollider
+    /// The frame of the shape relative to the rigid.
+    let frame: Frame
+    /// Mass per volume relative to the other shapes, e.g. for a table top heavier than its legs.
+    var density = 1.0
+    
+    /// The center of mass of the shape in the frame of the rigid.
+    var center: Point {
+        frame.act(collider.centerOfMass)
+    }
+}
+
+
+/// A collider made of several convex shapes at arbitrary offsets. The shapes are either primitives, for props like
+/// L-shapes or tables which are easier to build from boxes and capsules than to decompose, or the hulls of an offline
+/// convex decomposition of a concave prop. Every shape collides exactly. Rigids express it in the frame of their
+/// center of mass and principal axes.
+struct CompoundCollider {
+    let shapes: [CompoundShape]
+    
+    init(shapes: [CompoundShape]) {
+        self.shapes = shapes
+    }
+    
+    /// A compound of convex pieces given in the local frame, each of them a hull of its own.
+    init(pieces: [ConvexPiece]) {
+        shapes = pieces.map { CompoundShape(collider: .hull($0), frame: Frame()) }
+    }
+    
+    /// The shapes placed in the world by the frame of their rigid.
+    func placed(by frame: Frame) -> [PlacedConvexCollider] {
+        shapes.map { PlacedConvexCollider($0.collider, frame: frame.act($0.frame)) }
+    }
+    
+    /// The center of mass in the local frame, and the inertia tensor about it for the given mass.
+    /// The mass is shared by the volumes and densities of the shapes. Each shape contributes its own tensor,
+    /// rotated into the local frame and moved to the center by the parallel axis theorem.
+    func massProperties(mass: Double) -> (center: Point, inertia: InertiaTensor) {
+        let weights = shapes.map { $0.density * $0.collider.volume }
+        let totalWeight = weights.reduce(0, +)
+        if totalWeight < 1e-12 {
+            return (.null, ConvexCollider.box(halfExtents: Point(0.5)).tensor(mass: mass))
+        }
+        
+        let center = zip(shapes, weights).reduce(Point.null) { $0 + ($1.1 / totalWeight) * $1.0.center }
+        var inertia = InertiaTensor.zero
+        for (shape, weight) in zip(shapes, weights) {
+            let shapeMass = mass * weight / totalWeight
+            inertia = inertia + shape.collider.tensor(mass: shapeMass).rotated(by: shape.frame.quaternion) +
+                InertiaTensor(pointMass: shapeMass, at: shape.center - center)
+        }
+        return (center, inertia)
+    }
+    
+    var boundingRadius: Double {
+        shapes.map { $0.frame.position.length + $0.collider.boundingRadius }.max() ?? 0
+    }
+    
+    /// The contact points of all shapes in the local frame, for a surface facing the collider from the given local direction.
+    func contactPoints(towards direction: Point) -> [Point] {
+        shapes.flatMap { shape in
+            shape.collider.contactPoints(towards: shape.frame.quaternion.inverse.act(on: direction)).map(shape.frame.act)
+        }
+    }
+    
+    var surfacePoints: [Point] {
+        shapes.flatMap { $0.collider.surfacePoints.map($0.frame.act) }
+    }
+    
+    /// The polyhedral shapes as convex pieces in the local frame, or `nil` if any shape is round.
+    var pieces: [ConvexPiece]? {
+        var pieces: [ConvexPiece] = []
+        for shape in shapes {
+            guard let piece = shape.collider.piece else {
+                return nil
+            }
+            pieces.append(piece.transformed(by: shape.frame))
+        }
+        return pieces
+    }
+    
+    func intersect(_ ray: Ray, offset: Double = 0) -> (distance: Double, normal: Point)? {
+        shapes.compactMap { shape in
+            shape.collider.intersect(ray.localized(to: shape.frame), offset: offset).map { hit in
+                (hit.distance, shape.frame.quaternion.act(on: hit.normal))
+            }
+        }.min { $0.distance < $1.distance }
+    }
+    
+    func distance(to point: Point) -> Double {
+        shapes.map { $0.collider.distance(to: $0.frame.inverse.act(point)) }.min() ?? .infinity
+    }
+}
+
+
+extension CompoundCollider {
+    /// An L-shaped bar of square cross-section lying in the xy-plane, whose legs run along +x and +y from the corner.
+    static func lShape(legLength: Double, thickness: Double) -> CompoundCollider {
+        let half = 0.5 * thickness
+        let halfLeg = 0.5 * (legLength - thickness)
+        return CompoundCollider(shapes: [
+            CompoundShape(collider: .box(halfExtents: Point(half + halfLeg, half, half)), frame: Frame(position: Point(halfLeg, 0, 0))),
+            CompoundShape(collider: .box(halfExtents: Point(half, halfLeg, half)), frame: Frame(position: Point(0, half + halfLeg, 0)))
+        ])
+    }
+    
+    /// A table on four round legs, whose size includes the legs. Its feet stand on the xy-plane around the origin.
+    static func table(size: Point, topThickness: Double = 0.05, legRadius: Double = 0.04) -> CompoundCollider {
+        let legHalfHeight = 0.5 * (size.ez - topThickness) - legRadius
+        var shapes = [CompoundShape(collider: .box(halfExtents: Point(0.5 * size.ex, 0.5 * size.ey, 0.5 * topThickness)),
+                                    frame: Frame(position: Point(0, 0, size.ez - 0.5 * topThickness)))]
+        for x in [-1.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                let position = Point(x * (0.5 * size.ex - legRadius), y * (0.5 * size.ey - legRadius), legRadius + legHalfHeight)
+                shapes.append(CompoundShape(collider: .capsule(radius: legRadius, halfHeight: legHalfHeight), frame: Frame(position: position)))
+            }
+        }
+        return CompoundCollider(shapes: shapes)
+    }
+}
+
+
+enum ConvexDecompositionError: Error {
+    case malformedLine(number: Int, line: String)
+    case noPieces
+}
+
+
+extension CompoundCollider {
+    /// Reads convex pieces from Wavefront OBJ text as written by V-HACD and similar tools.
+    /// Each `o` or `g` statement starts a new piece, polygons are fanned into triangles,
+    /// and everything but vertex positions and faces is ignored.
+    init(obj: String) throws {
+        var positions: [Point] = []
+        var pieces: [ConvexPiece] = []
+        var faces: [(Int, Int, Int)] = []
+        
+        func finishPiece() {
+            if faces.isEmpty {
+                return
+            }
+            // Compact the globally indexed vertices to the ones used by this piece.
+            var localIndices: [Int: Int] = [:]
+            var vertices: [Point] = []
+            func local(_ index: Int) -> Int {
+                if let existing = localIndices[index] {
+                    return existing
+                }
+                localIndices[index] = vertices.count
+                vertices.append(positions[index])
+                return vertices.count - 1
+            }
+            pieces.append(ConvexPiece(vertices: vertices, faces: faces.map { a, b, c in (local(a), local(b), local(c)) }))
+            faces = []
+        }
+        
+        for (number, line) in obj.components(separatedBy: .newlines).enumerated() {
+            let tokens = line.split(whereSeparator: { $0 == " " || $0 == "\t" }).map(String.init)
+            let malformed = ConvexDecompositionError.malformedLine(number: number + 1, line: line)
+            
+            switch tokens.first ?? "" {
+            case "v":
+                let coordinates = tokens.dropFirst().prefix(3).compactMap(Double.init)
+                guard coordinates.count == 3 else {
+                    throw malformed
+                }
+                positions.append(Point(coordinates[0], coordinates[1], coordinates[2]))
+            
+            case "f":
+                // Indices are one-based, or relative to the end if negative, and may be followed by texture and normal indices.
+                let indices = try tokens.dropFirst().map { token -> Int in
+                    guard let index = token.split(separator: "/", omittingEmptySubsequences: false).first.flatMap({ Int($0) }), index != 0 else {
+                        throw malformed
+                    }
+                    let resolved = index < 0 ? positions.count + index : index - 1
+                    guard positions.indices.contains(resolved) else {
+                        throw malformed
+                    }
+                    return resolved
+                }
+                guard indices.count >= 3 else {
+                    throw malformed
+                }
+                for i in 1 ..< indices.count - 1 {
+                    faces.append((indices[0], indices[i], indices[i + 1]))
+                }
+            
+            case "o", "g":
+                finishPiece()
+            
+            default:
+                continue
+            }
+        }
+        finishPiece()
+        
+        if pieces.isEmpty {
+            throw ConvexDecompositionError.noPieces
+        }
+        self.init(pieces: pieces)
+    }
+    
+    init(contentsOf url: URL) throws {
+        try self.init(obj: String(contentsOf: url, encoding: .utf8))
+    }
+}
@@ -20,6 +20,10 @@ struct PositionalConstraint: Constraint {
     let rigids: (Rigid, Rigid)
     let contacts: (Point, Point)
     let distance: Double
+    /// The surface normal of a contact, which differs from the direction once friction shifts the target.
+    var normal: Point? = nil
+    /// Identifies a contact across sub-steps, for contacts between convex volumes and of points against planes and meshes.
+    var feature: ContactFeature? = nil
     
     var difference: Point {
         contacts.0.to(contacts.1)
@@ -39,12 +43,12 @@ struct PositionalConstraint: Constraint {
     
     var inverseResistance: Double {
         let angularImpulseDual: (Point, Point) = (
-            rigids.0.frame.quaternion.inverse.act(on: (contacts.0 - rigids.0.frame.position).cross(direction)),
-            rigids.1.frame.quaternion.act(on: (contacts.1 - rigids.1.frame.position).cross(direction))
+            (contacts.0 - rigids.0.frame.position).cross(direction),
+            (contacts.1 - rigids.1.frame.position).cross(direction)
         )
         return rigids.0.inverseMass + rigids.1.inverseMass +
-            (rigids.0.inverseInertia .* angularImpulseDual.0).dot(angularImpulseDual.0) +
-            (rigids.1.inverseInertia .* angularImpulseDual.1).dot(angularImpulseDual.1)
+            rigids.0.applyInverseInertia(angularImpulseDual.0).dot(angularImpulseDual.0) +
+            rigids.1.applyInverseInertia(angularImpulseDual.1).dot(angularImpulseDual.1)
     }
     
     func act(factor: Double) {
@@ -53,3 +57,30 @@ struct PositionalConstraint: Constraint {
         rigids.1.applyLinearImpulse(-impulse, at: contacts.1)
     }
 }
+
+
+/// Rotates two rigids against each other about an axis until the angle between them reaches a target.
+struct AngularConstraint: Constraint {
+    let rigids: (Rigid, Rigid)
+    /// The global axis, about which the second rigid is rotated by the angle relative to the first one.
+    let axis: Point
+    let angle: Double
+    let targetAngle: Double
+    
+    var measure: Double {
+        angle
+    }
+    
+    var targetMeasure: Double {
+        targetAngle
+    }
+    
+    var inverseResistance: Double {
+        rigids.0.applyInverseInertia(axis).dot(axis) + rigids.1.applyInverseInertia(axis).dot(axis)
+    }
+    
+    func act(factor: Double) {
+        rigids.0.applyAngularImpulse(factor * axis)
+        rigids.1.applyAngularImpulse(-factor * axis)
+    }
+}
@@ -0,0 +1,226 @@
+//
+//  ContactManifold.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Identifies a contact of two rigids by the features of their surfaces it stems from. These stay the same while
+/// the rigids rest on each other, so that the contact can be recognized from one sub-step to the next.
+struct ContactFeature: Hashable {
+    /// Whether the face the contact was clipped against belongs to the first rigid of the contact.
+    let isReferenceFirst: Bool
+    /// The face clipped against, or -1 if the contact was found without clipping.
+    let referenceFace: Int
+    /// The face which was clipped, or -1 if the contact was found without clipping.
+    let incidentFace: Int
+    /// The vertex of the clipped face the contact lies at, or the crossing of an edge with a side of the reference face.
+    /// Contacts found without clipping are numbered by the point they stem from instead.
+    let point: Int
+}
+
+
+/// A face of a polyhedral volume, with its corners wound counter-clockwise when seen from outside.
+struct ContactFace {
+    /// Numbers the face among the faces of its volume.
+    let id: Int
+    let normal: Point
+    let vertices: [(position: Point, id: Int)]
+    
+    func transformed(by frame: Frame) -> ContactFace {
+        ContactFace(id: id, normal: frame.quaternion.act(on: normal), vertices: vertices.map { (frame.act($0.position), $0.id) })
+    }
+}
+
+
+extension ConvexCollider {
+    /// The face in the local frame whose normal is closest to the given direction, or `nil` for round shapes.
+    func face(towards direction: Point) -> ContactFace? {
+        switch self {
+        case .sphere(_), .capsule(_, _):
+            return nil
+        case let .box(halfExtents):
+            // Faces are numbered by their axis and side, and corners by their coordinates as bits, x being the lowest.
+            let magnitudes = [abs(direction.ex), abs(direction.ey), abs(direction.ez)]
+            let axis = magnitudes.indices.max { magnitudes[$0] < magnitudes[$1] }!
+            let side = [direction.ex, direction.ey, direction.ez][axis] < 0 ? 0 : 1
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3)
+            // Going round the corners in this order is counter-clockwise about the positive axis.
+            let corners = [(0, 0), (1, 0), (1, 1), (0, 1)]
+            let vertices = (side == 1 ? corners : corners.reversed()).map { a, b -> (position: Point, id: Int) in
+                var bits = [0, 0, 0]
+                bits[axis] = side
+                bits[u] = a
+                bits[v] = b
+                let position = Point(Double(2 * bits[0] - 1), Double(2 * bits[1] - 1), Double(2 * bits[2] - 1)) .* halfExtents
+                return (position, bits[0] | bits[1] << 1 | bits[2] << 2)
+            }
+            var normal = [0.0, 0.0, 0.0]
+            normal[axis] = side == 1 ? 1 : -1
+            return ContactFace(id: 2 * axis + side, normal: Point(normal[0], normal[1], normal[2]), vertices: vertices)
+        case let .hull(piece):
+            guard let best = piece.planes.indices.max(by: { piece.planes[$0].normal.dot(direction) < piece.planes[$1].normal.dot(direction) }) else {
+                return nil
+            }
+            let plane = piece.planes[best]
+            // Faces are triangulated, so the face is numbered by the first of its coplanar triangles.
+            let id = piece.planes.firstIndex { $0.normal.dot(plane.normal) > 1 - 1e-9 } ?? best
+            let onFace = piece.vertices.indices.filter { abs(piece.vertices[$0].dot(plane.normal) - plane.offset) < 1e-6 * max(piece.radius, 1) }
+            let center = onFace.map { piece.vertices[$0] }.center
+            let (u, v) = plane.normal.tangents
+            let sorted = onFace.sorted { a, b in
+                let (p, q) = (center.to(piece.vertices[a]), center.to(piece.vertices[b]))
+                return atan2(p.dot(v), p.dot(u)) < atan2(q.dot(v), q.dot(u))
+            }
+            return ContactFace(id: id, normal: plane.normal, vertices: sorted.map { (piece.vertices[$0], $0) })
+        }
+    }
+}
+
+
+/// A point of a clipped face below the reference face.
+fileprivate struct ClippedPoint {
+    let incident: Point
+    /// The point projected onto the reference face.
+    let reference: Point
+    let depth: Double
+    let id: Int
+}
+
+
+/// Generates the contacts of two overlapping polyhedral volumes by clipping the face of one of them against the face
+/// of the other one, each facing the other volume along the normal found by EPA. The face more parallel to the contact
+/// is the reference face, and the corners of the other, incident face are clipped against its sides and pushed out
+/// of its plane. This gives up to four contacts covering the whole touching area, which keeps stacked boxes from rocking.
+/// Returns `nil` for round volumes, and for edges crossing each other, where neither face is parallel to the contact.
+func clipFaces(_ volume: ConvexVolume, attachedTo rigid: Rigid, with otherVolume: ConvexVolume, attachedTo other: Rigid, normal: Point) -> [Constraint]? {
+    guard let face = volume.face(towards: normal), let otherFace = otherVolume.face(towards: -normal) else {
+        return nil
+    }
+    let alignments = (face.normal.dot(normal), -otherFace.normal.dot(normal))
+    if max(alignments.0, alignments.1) < 0.7 {
+        return nil
+    }
+    // The first face is preferred when both are about equally parallel, so that the choice doesn't flicker.
+    let isReferenceFirst = alignments.0 + 0.01 >= alignments.1
+    let (reference, incident) = isReferenceFirst ? (face, otherFace) : (otherFace, face)
+    
+    var polygon = incident.vertices
+    for side in reference.vertices.indices {
+        let start = reference.vertices[side].position
+        let end = reference.vertices[(side + 1) % reference.vertices.count].position
+        polygon = clip(polygon, at: start, outward: start.to(end).cross(reference.normal), side: side)
+        if polygon.isEmpty {
+            return nil
+        }
+    }
+    
+    let base = reference.vertices[0].position
+    let points: [ClippedPoint] = polygon.compactMap { position, id in
+        let height = base.to(position).dot(reference.normal)
+        return height < 0 ? ClippedPoint(incident: position, reference: position - height * reference.normal, depth: -height, id: id) : nil
+    }
+    if points.isEmpty {
+        return nil
+    }
+    
+    // The reference face pushes the first rigid against its normal if it belongs to it, and along it otherwise.
+    return reduced(points, normal: reference.normal).map { point in
+        let feature = ContactFeature(isReferenceFirst: isReferenceFirst, referenceFace: reference.id, incidentFace: incident.id, point: point.id)
+        return PositionalConstraint(rigids: (rigid, other),
+                                    contacts: isReferenceFirst ? (point.reference, point.incident) : (point.incident, point.reference),
+                                    distance: 0,
+                                    normal: isReferenceFirst ? -reference.normal : reference.normal,
+                                    feature: feature)
+    }
+}
+
+
+/// Cuts off the part of a polygon outside of a side of the reference face, in the manner of Sutherland and Hodgman.
+/// New corners are numbered by the edge they lie on and the side, such that they keep their number in the next sub-step.
+fileprivate func clip(_ polygon: [(position: Point, id: Int)], at start: Point, outward: Point, side: Int) -> [(position: Point, id: Int)] {
+    var clipped: [(position: Point, id: Int)] = []
+    for (index, current) in polygon.enumerated() {
+        let next = polygon[(index + 1) % polygon.count]
+        let heights = (start.to(current.position).dot(outward), start.to(next.position).dot(outward))
+        if heights.0 <= 0 {
+            clipped.append(current)
+        }
+        if (heights.0 <= 0) != (heights.1 <= 0) {
+            let t = heights.0 / (heights.0 - heights.1)
+            // The number doesn't depend on the direction the edge is passed in, and is negative unlike the ones of corners.
+            let (low, high) = (min(current.id, next.id), max(current.id, next.id))
+            let id = -1 - ((low &* 2_654_435_761) ^ (high &* 40_503) ^ ((side + 1) &* 97_531)) & Int(Int32.max)
+            clipped.append((current.position + t * current.position.to(next.position), id))
+        }
+    }
+    return clipped
+}
+
+
+/// Keeps the deepest point and up to three more spanning the largest area, since four points hold a face as steadily
+/// as any more. The points keep their order.
+fileprivate func reduced(_ points: [ClippedPoint], normal: Point) -> [ClippedPoint] {
+    if points.count <= 4 {
+        return points
+    }
+    func area(_ a: Int, _ b: Int, _ c: Int) -> Double {
+        points[a].incident.to(points[b].incident).cross(points[a].incident.to(points[c].incident)).dot(normal)
+    }
+    
+    let deepest = points.indices.max { points[$0].depth < points[$1].depth }!
+    let furthest = points.indices.max { points[deepest].incident.distance(to: points[$0].incident) < points[deepest].incident.distance(to: points[$1].incident) }!
+    let widest = points.indices.max { abs(area(deepest, furthest, $0)) < abs(area(deepest, furthest, $1)) }!
+    var kept = [deepest, furthest, widest]
+    
+    // The last point lies furthest outside of the triangle, beyond whichever of its edges.
+    let triangle = area(deepest, furthest, widest) > 0 ? (deepest, furthest, widest) : (deepest, widest, furthest)
+    func outside(_ index: Int) -> Double {
+        -min(area(triangle.0, triangle.1, index), area(triangle.1, triangle.2, index), area(triangle.2, triangle.0, index))
+    }
+    if let last = points.indices.filter({ !kept.contains($0) }).max(by: { outside($0) < outside($1) }), outside(last) > 0 {
+        kept.append(last)
+    }
+    return Set(kept).sorted().map { points[$0] }
+}
+
+
+/// The contacts of a pair of rigids during a sub-step, which are remembered until the next one. Contacts found again
+/// at the same features then know how long they have persisted, and start from the impulses they ended with.
+struct ContactManifold {
+    struct Contact {
+        let feature: ContactFeature?
+        /// The contact points on both rigids in global coordinates.
+        let positions: (Point, Point)
+        /// The direction the first rigid is pushed into.
+        let normal: Point
+        /// Sub-steps in a row in which the contact was found at the same features before this one.
+        let age: Int
+        /// The impulses the sequential impulses backend applied along the normal and the surface.
+        var normalImpulse = 0.0
+        var tangentImpulse = Point.null
+    }
+    
+    let rigids: (Rigid, Rigid)
+    var contacts: [Contact]
+    
+    /// Collects the contacts of a pair of rigids, matching them with the ones of the last sub-step by their features.
+    /// Returns `nil` if there are none.
+    init?(_ constraints: [PositionalConstraint], previous: ContactManifold?) {
+        guard let first = constraints.first else {
+            return nil
+        }
+        rigids = first.rigids
+        contacts = constraints.map { constraint in
+            let age = constraint.feature.flatMap { previous?.contact(at: $0) }.map { $0.age + 1 } ?? 0
+            return Contact(feature: constraint.feature, positions: constraint.contacts, normal: constraint.normal ?? constraint.direction, age: age)
+        }
+    }
+    
+    func contact(at feature: ContactFeature) -> Contact? {
+        contacts.first { $0.feature == feature }
+    }
+}
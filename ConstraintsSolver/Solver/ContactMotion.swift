@@ -0,0 +1,52 @@
+//
+//  ContactMotion.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// How the rigids of a persistent contact move against each other, e.g. to play a scrape sound while sliding,
+/// or to kick up dust while rolling.
+enum ContactMotion: Equatable {
+    case resting
+    /// The surfaces slip against each other at the contact.
+    case sliding
+    /// The surfaces turn against each other without slipping, like a wheel on the road.
+    case rolling
+}
+
+
+/// Tells apart how the contacts which persisted over a sub-step move, by the relative velocity along the surface.
+struct ContactMotionClassifier {
+    /// Speed in meters per second at which the surfaces slip against each other at a contact, above which it slides.
+    var slidingSpeed = 0.05
+    /// Angular speed in radians per second at which the rigids turn against each other about an axis along the surface,
+    /// above which a contact which doesn't slide rolls.
+    var rollingSpeed = 0.5
+    
+    /// Classifies the contacts of a pair of rigids, or returns `nil` if none of them persisted from the sub-step before,
+    /// as when the rigids just hit each other.
+    func classify(_ manifold: ContactManifold) -> ContactMotion? {
+        let persistent = manifold.contacts.filter { $0.age > 0 }
+        guard let normal = persistent.first?.normal else {
+            return nil
+        }
+        let (a, b) = manifold.rigids
+        // The fastest contact decides, since a box tipping over onto an edge still scrapes along it.
+        let slip = persistent.map { contact -> Double in
+            let velocity = b.pointVelocity(at: contact.positions.1) - a.pointVelocity(at: contact.positions.0)
+            return (velocity - velocity.project(onto: contact.normal)).length
+        }.max()!
+        if slip > slidingSpeed {
+            return .sliding
+        }
+        let spin = b.angularVelocity - a.angularVelocity
+        if (spin - spin.project(onto: normal)).length > rollingSpeed {
+            return .rolling
+        }
+        return .resting
+    }
+}
@@ -0,0 +1,99 @@
+//
+//  ContactSound.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// How hard a pair of rigids hit or rubbed each other during a step, as tracked by the solver for contact sounds.
+/// The first rigid of the pair is the one with the lower index.
+struct ContactImpulse {
+    /// The sum of the impulses of all contacts of the pair during the step in newton seconds.
+    var impulse = 0.0
+    /// The velocity of the second rigid relative to the first one at a contact point, just before the contact was
+    /// solved in the sub-step in which they moved fastest against each other.
+    var relativeVelocity = Point.null
+    /// The direction the first rigid was pushed into at that contact point.
+    var normal = Point.null
+    
+    /// Notes the relative velocity at a contact of the pair if it is the fastest so far. The contact is flipped
+    /// if its first rigid is the second one of the pair.
+    mutating func note(_ contact: PositionalConstraint, isFlipped: Bool) {
+        let velocity = contact.rigids.1.pointVelocity(at: contact.contacts.1) - contact.rigids.0.pointVelocity(at: contact.contacts.0)
+        if velocity.length > relativeVelocity.length {
+            let normal = contact.normal ?? contact.direction
+            (relativeVelocity, self.normal) = isFlipped ? (-velocity, -normal) : (velocity, normal)
+        }
+    }
+    
+    mutating func merge(_ other: ContactImpulse) {
+        impulse += other.impulse
+        if other.relativeVelocity.length > relativeVelocity.length {
+            relativeVelocity = other.relativeVelocity
+            normal = other.normal
+        }
+    }
+}
+
+
+/// A contact of a step hard enough to be heard, with what an audio engine needs to pick and shape an impact
+/// or a scrape sound.
+struct ContactSound {
+    let rigids: (RigidHandle, RigidHandle)
+    /// The materials of both rigids, which usually select the sample.
+    let materials: (Material?, Material?)
+    /// The user tags of both rigids, see `Rigid.userTag`.
+    let userTags: (Int?, Int?)
+    /// The sum of the contact impulses between the rigids during the step in newton seconds, which usually sets the volume.
+    let impulse: Double
+    /// The velocity of the second rigid relative to the first one at a contact point. Its part along the normal
+    /// is the speed of an impact, and the rest the one of a scrape.
+    let relativeVelocity: Point
+    /// The direction the first rigid is pushed into.
+    let normal: Point
+    /// How the rigids moved against each other at the end of the step, telling a scrape from rolling,
+    /// or `nil` if they just hit each other.
+    let motion: ContactMotion?
+}
+
+
+/// Delivers the contacts of every step which are hard enough to a handler on a queue of its choice. Each pair of rigids
+/// is reported at most once per interval, so that thousands of resting or scraping contacts don't flood the handler.
+class ContactSoundChannel {
+    let queue: DispatchQueue
+    /// Contacts with a smaller impulse in newton seconds are not reported.
+    var minimumImpulse: Double
+    /// Simulated seconds which pass at least between two reports of the same pair.
+    var minimumInterval: Double
+    private let handler: ([ContactSound], StepStamp) -> Void
+    /// When each pair was last reported, for the pairs reported within the interval.
+    private var reportTimes: [RigidPair: Double] = [:]
+    
+    init(queue: DispatchQueue, minimumImpulse: Double, minimumInterval: Double, handler: @escaping ([ContactSound], StepStamp) -> Void) {
+        self.queue = queue
+        self.minimumImpulse = minimumImpulse
+        self.minimumInterval = minimumInterval
+        self.handler = handler
+    }
+    
+    /// Sends the contacts which are hard enough and whose pair wasn't reported too recently.
+    func send(_ contacts: [(pair: RigidPair, sound: ContactSound)], stamp: StepStamp) {
+        let sounds = contacts.filter { pair, sound in
+            sound.impulse >= minimumImpulse && stamp.time - (reportTimes[pair] ?? -.infinity) >= minimumInterval
+        }
+        // Pairs last reported before the interval are forgotten, so that the times don't pile up.
+        reportTimes = reportTimes.filter { stamp.time - $0.value < minimumInterval }
+        for (pair, _) in sounds {
+            reportTimes[pair] = stamp.time
+        }
+        
+        if !sounds.isEmpty {
+            queue.async { [handler] in
+                handler(sounds.map { $0.sound }, stamp)
+            }
+        }
+    }
+}
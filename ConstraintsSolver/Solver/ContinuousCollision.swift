@@ -0,0 +1,79 @@
+//
+//  ContinuousCollision.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Keeps fast rigids from passing through thin ones within a single sub-step, which contacts can't catch since
+/// they only see the overlaps at the end of it. The sphere inscribed in a fast rigid is swept from its previous
+/// position to its new one, and the rigid is moved back to where the sphere first hits something, just touching it,
+/// so that the contacts take over from there. Only the translation of the center is swept, not the rotation,
+/// so the ends of thin rigids spinning fast enough still tunnel. Only used by the position based backend.
+struct ContinuousCollision {
+    /// Rigids moving less than this fraction of their inscribed radius within a sub-step aren't swept,
+    /// since contacts catch them anyway.
+    var motionThreshold = 0.5
+    /// How far the swept sphere is left overlapping what it hit, as a fraction of its radius,
+    /// so that a contact is generated right away.
+    var overlap = 0.1
+    
+    /// Moves a rigid back to its first hit along its motion during the sub-step, returning whether it hit anything.
+    /// Rigids which don't collide with it, like those jointed to it, are passed through.
+    @discardableResult
+    func sweep(_ rigid: Rigid, against others: [Rigid], except disabledPairs: Set<RigidPair> = []) -> Bool {
+        guard let radius = rigid.inscribedRadius else {
+            return false
+        }
+        let start = rigid.pastFrame.position
+        let motion = start.to(rigid.frame.position)
+        if motion.length < motionThreshold * radius {
+            return false
+        }
+        
+        let ray = Ray(origin: start, direction: motion, maxDistance: motion.length)
+        var firstHit = motion.length
+        for other in others where other !== rigid && !other.isSensor && !disabledPairs.contains(RigidPair(rigid, other)) {
+            guard let hit = other.collider.cast(ray.localized(to: other.frame), radius: radius) else {
+                continue
+            }
+            // Rigids which are already touched or moved away from are left to the contacts.
+            if hit.distance > 0 && other.frame.quaternion.act(on: hit.normal).dot(ray.direction) < 0 {
+                firstHit = min(firstHit, hit.distance)
+            }
+        }
+        if firstHit >= motion.length {
+            return false
+        }
+        
+        rigid.frame.position = ray.at(min(firstHit + overlap * radius, motion.length))
+        return true
+    }
+}
+
+
+extension Rigid {
+    /// The radius of a sphere around the center which fits into the collider, if there is a simple one.
+    var inscribedRadius: Double? {
+        switch collider {
+        case .box(_):
+            return 0.5
+        case let .convex(convex):
+            switch convex {
+            case let .sphere(radius), let .capsule(radius, _):
+                return radius
+            case let .box(halfExtents):
+                return min(halfExtents.ex, halfExtents.ey, halfExtents.ez)
+            case .hull(_):
+                // The smallest support along the axes, which is at least the inscribed radius, but close enough for sweeping.
+                let directions = [Point.ex, Point.ey, Point.ez, -Point.ex, -Point.ey, -Point.ez]
+                return directions.map { convex.support($0).dot($0) }.min()
+            }
+        case .plane(_), .mesh(_), .compound(_):
+            return nil
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//
+//  ConvexCollider.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A convex shape of any size, given in a local frame whose origin is its center of mass.
+/// Its support function lets GJK and EPA collide it with any other convex shape.
+public enum ConvexCollider {
+    case sphere(radius: Double)
+    /// A box centered at the origin.
+    case box(halfExtents: Point)
+    /// A cylinder along the local z-axis capped by hemispheres. The half height is the one of the cylinder alone.
+    case capsule(radius: Double, halfHeight: Double)
+    /// A convex polyhedron, usually built by `hull(of:)`.
+    case hull(ConvexPiece)
+    
+    /// The convex hull of a point cloud, moved such that its center of mass is at the origin.
+    /// Points inside the hull are dropped. Returns `nil` if the points don't enclose any volume.
+    /// Every triple of points is tried as a face, so this is meant for tens of points rather than thousands.
+    public static func hull(of points: [Point]) -> ConvexCollider? {
+        var faces: [(Int, Int, Int)] = []
+        for i in points.indices {
+            for j in i + 1 ..< points.count {
+                for k in j + 1 ..< points.count {
+                    let normal = points[i].to(points[j]).cross(points[i].to(points[k]))
+                    if normal.length < 1e-12 {
+                        continue
+                    }
+                    let heights = points.map { points[i].to($0).dot(normal.normalize) }
+                    // Faces are wound counter-clockwise when seen from outside.
+                    if heights.allSatisfy({ $0 <= 1e-9 }) {
+                        faces.append((i, j, k))
+                    }
+                    else if heights.allSatisfy({ $0 >= -1e-9 }) {
+                        faces.append((i, k, j))
+                    }
+                }
+            }
+        }
+        
+        // Drop the points not on the hull and renumber the others.
+        let used = Set(faces.flatMap { [$0.0, $0.1, $0.2] }).sorted()
+        let renumbered = Dictionary(uniqueKeysWithValues: used.enumerated().map { ($1, $0) })
+        let piece = ConvexPiece(vertices: used.map { points[$0] },
+                                faces: faces.map { (renumbered[$0.0]!, renumbered[$0.1]!, renumbered[$0.2]!) })
+        
+        let properties = VolumeProperties(of: [piece])
+        if properties.volume < 1e-12 {
+            return nil
+        }
+        return .hull(ConvexPiece(vertices: piece.vertices.map { $0 - properties.center }, faces: piece.faces))
+    }
+    
+    /// The point of the shape furthest in the given direction, in the local frame.
+    func support(_ direction: Point) -> Point {
+        switch self {
+        case let .sphere(radius):
+            return direction.length < 1e-12 ? radius * Point.ez : radius * direction.normalize
+        case let .box(halfExtents):
+            return Point(direction.ex < 0 ? -halfExtents.ex : halfExtents.ex,
+                         direction.ey < 0 ? -halfExtents.ey : halfExtents.ey,
+                         direction.ez < 0 ? -halfExtents.ez : halfExtents.ez)
+        case let .capsule(radius, halfHeight):
+            let cap = (direction.ez < 0 ? -halfHeight : halfHeight) * Point.ez
+            return direction.length < 1e-12 ? cap : cap + radius * direction.normalize
+        case let .hull(piece):
+            return piece.vertices.max { $0.dot(direction) < $1.dot(direction) }!
+        }
+    }
+    
+    var boundingRadius: Double {
+        switch self {
+        case let .sphere(radius):
+            return radius
+        case let .box(halfExtents):
+            return halfExtents.length
+        case let .capsule(radius, halfHeight):
+            return radius + halfHeight
+        case let .hull(piece):
+            return piece.vertices.map { $0.length }.max() ?? 0
+        }
+    }
+    
+    /// The corners of a polyhedral shape, or `nil` for a round one.
+    var vertices: [Point]? {
+        switch self {
+        case .sphere(_), .capsule(_, _):
+            return nil
+        case .box(_):
+            return piece?.vertices
+        case let .hull(piece):
+            return piece.vertices
+        }
+    }
+    
+    /// The shape as a convex piece, if it is polyhedral.
+    var piece: ConvexPiece? {
+        switch self {
+        case .sphere(_), .capsule(_, _):
+            return nil
+        case let .box(halfExtents):
+            let unit = BoxCollider().piece
+            return ConvexPiece(vertices: unit.vertices.map { (2 * halfExtents) .* $0 }, faces: unit.faces)
+        case let .hull(piece):
+            return piece
+        }
+    }
+    
+    /// The points in the local frame which may touch a surface facing the shape from the given direction,
+    /// that is the corners of polyhedra and the deepest points of round shapes.
+    func contactPoints(towards direction: Point) -> [Point] {
+        if let vertices = vertices {
+            return vertices
+        }
+        if case let .capsule(radius, halfHeight) = self, direction.length > 1e-12 {
+            // Both caps may touch, so that capsules can lie flat.
+            return [halfHeight * Point.ez + radius * direction.normalize, -halfHeight * Point.ez + radius * direction.normalize]
+        }
+        return [support(direction)]
+    }
+    
+    /// Points on the surface in the local frame which are tested against triangle meshes, whose normals differ from
+    /// triangle to triangle. Round shapes are sampled in 26 directions, so they are slightly faceted on meshes.
+    var surfacePoints: [Point] {
+        if let vertices = vertices {
+            return vertices
+        }
+        var directions: [Point] = []
+        for x in -1 ... 1 {
+            for y in -1 ... 1 {
+                for z in -1 ... 1 where x != 0 || y != 0 || z != 0 {
+                    directions.append(Point(Double(x), Double(y), Double(z)))
+                }
+            }
+        }
+        return directions.flatMap(contactPoints(towards:))
+    }
+    
+    /// Whether a point in the local frame lies strictly inside the shape.
+    func contains(_ point: Point) -> Bool {
+        switch self {
+        case let .sphere(radius):
+            return point.length < radius
+        case let .box(halfExtents):
+            return abs(point.ex) < halfExtents.ex && abs(point.ey) < halfExtents.ey && abs(point.ez) < halfExtents.ez
+        case let .capsule(radius, halfHeight):
+            return point.distance(to: Point(0, 0, point.ez.clamped(to: -halfHeight ... halfHeight))) < radius
+        case let .hull(piece):
+            return piece.planes.allSatisfy { point.dot($0.normal) < $0.offset }
+        }
+    }
+    
+    /// Distance of a point in the local frame to the surface, or zero if inside.
+    func distance(to point: Point) -> Double {
+        switch self {
+        case let .sphere(radius):
+            return max(point.length - radius, 0)
+        case let .box(halfExtents):
+            let outside = Point(max(abs(point.ex) - halfExtents.ex, 0), max(abs(point.ey) - halfExtents.ey, 0), max(abs(point.ez) - halfExtents.ez, 0))
+            return outside.length
+        case let .capsule(radius, halfHeight):
+            return max(point.distance(to: Point(0, 0, point.ez.clamped(to: -halfHeight ... halfHeight))) - radius, 0)
+        case let .hull(piece):
+            return piece.distance(to: point)
+        }
+    }
+    
+    /// The diagonal of the inertia tensor about the origin for the given mass.
+    func inertia(mass: Double) -> Point {
+        switch self {
+        case let .sphere(radius):
+            return Point(0.4 * mass * radius.sq)
+        case let .box(halfExtents):
+            let extent = 2 * halfExtents
+            return 1 / 12 * mass * Point(
+                extent.ey * extent.ey + extent.ez * extent.ez,
+                extent.ex * extent.ex + extent.ez * extent.ez,
+                extent.ex * extent.ex + extent.ey * extent.ey)
+        case let .capsule(radius, halfHeight):
+            // Split the mass between the cylinder and the hemispheres by volume, and move the hemispheres to their caps.
+            let height = 2 * halfHeight
+            let cylinderVolume = Double.pi * radius.sq * height
+            let sphereVolume = 4 / 3 * Double.pi * radius.sq * radius
+            let cylinderMass = mass * cylinderVolume / (cylinderVolume + sphereVolume)
+            let sphereMass = mass - cylinderMass
+            let axial = cylinderMass * radius.sq / 2 + sphereMass * 0.4 * radius.sq
+            let lateral = cylinderMass * (radius.sq / 4 + height.sq / 12) +
+                sphereMass * (0.4 * radius.sq + halfHeight.sq + 0.375 * height * radius)
+            return Point(lateral, lateral, axial)
+        case let .hull(piece):
+            return VolumeProperties(of: [piece]).inertia(mass: mass)
+        }
+    }
+    
+    var volume: Double {
+        switch self {
+        case let .sphere(radius):
+            return 4 / 3 * Double.pi * radius.sq * radius
+        case let .box(halfExtents):
+            return 8 * halfExtents.ex * halfExtents.ey * halfExtents.ez
+        case let .capsule(radius, halfHeight):
+            return Double.pi * radius.sq * (2 * halfHeight + 4 / 3 * radius)
+        case let .hull(piece):
+            return VolumeProperties(of: [piece]).volume
+        }
+    }
+    
+    /// Intersects a ray given in the local frame, with the surface pushed outwards by `offset`.
+    /// Boxes and hulls are inflated along their faces, which slightly overestimates their rounded corners.
+    func intersect(_ ray: Ray, offset: Double = 0) -> (distance: Double, normal: Point)? {
+        switch self {
+        case let .sphere(radius):
+            return intersectSphere(ray, center: .null, radius: radius + offset)
+        case let .box(halfExtents):
+            return Collider.intersectBox(ray, halfExtents: halfExtents + Point(offset))
+        case let .capsule(radius, halfHeight):
+            let radius = radius + offset
+            let o = ray.origin
+            let d = ray.direction
+            if o.distance(to: Point(0, 0, o.ez.clamped(to: -halfHeight ... halfHeight))) < radius {
+                return (0, -d)
+            }
+            
+            var hits = [halfHeight * Point.ez, -halfHeight * Point.ez].compactMap { intersectSphere(ray, center: $0, radius: radius) }
+            // The side of the cylinder, solved in the plane perpendicular to its axis.
+            let a = d.ex.sq + d.ey.sq
+            let b = o.ex * d.ex + o.ey * d.ey
+            let discriminant = b.sq - a * (o.ex.sq + o.ey.sq - radius.sq)
+            if a > 1e-12 && discriminant >= 0 {
+                let distance = (-b - discriminant.squareRoot()) / a
+                let hit = ray.at(distance)
+                if distance >= 0 && distance <= ray.maxDistance && abs(hit.ez) <= halfHeight {
+                    hits.append((distance, Point(hit.ex, hit.ey, 0).normalize))
+                }
+            }
+            return hits.min { $0.distance < $1.distance }
+        case let .hull(piece):
+            return piece.intersect(ray, offset: offset)
+        }
+    }
+    
+    private func intersectSphere(_ ray: Ray, center: Point, radius: Double) -> (distance: Double, normal: Point)? {
+        let offset = ray.origin - center
+        let b = offset.dot(ray.direction)
+        let c = offset.dot(offset) - radius.sq
+        if c < 0 {
+            return (0, -ray.direction)
+        }
+        let discriminant = b.sq - c
+        if discriminant < 0 {
+            return nil
+        }
+        let distance = -b - discriminant.squareRoot()
+        if distance < 0 || distance > ray.maxDistance {
+            return nil
+        }
+        return (distance, (1 / radius) * (ray.at(distance) - center))
+    }
+    
+    /// Vertices and outward wound triangles of a round shape, tessellated by latitude and longitude.
+    func tessellation(segments: Int = 24) -> (vertices: [Point], triangles: [(Int, Int, Int)])? {
+        let radius: Double
+        let halfHeight: Double
+        switch self {
+        case let .sphere(sphereRadius):
+            radius = sphereRadius
+            halfHeight = 0
+        case let .capsule(capsuleRadius, capsuleHalfHeight):
+            radius = capsuleRadius
+            halfHeight = capsuleHalfHeight
+        case .box(_), .hull(_):
+            return nil
+        }
+        
+        // Rings from the top pole down, where the upper hemisphere ends and the lower one starts at the equator.
+        var vertices: [Point] = []
+        let rings = segments / 2
+        for ring in 0 ... rings + 1 {
+            let isUpper = ring <= rings / 2
+            let latitude = Double.pi / 2 - Double.pi * Double(isUpper ? ring : ring - 1) / Double(rings)
+            let cap = (isUpper ? halfHeight : -halfHeight) * Point.ez
+            for segment in 0 ..< segments {
+                let longitude = 2 * Double.pi * Double(segment) / Double(segments)
+                vertices.append(cap + radius * Point(cos(latitude) * cos(longitude), cos(latitude) * sin(longitude), sin(latitude)))
+            }
+        }
+        
+        var triangles: [(Int, Int, Int)] = []
+        for ring in 0 ... rings {
+            for segment in 0 ..< segments {
+                let next = (segment + 1) % segments
+                let (a, b) = (ring * segments + segment, (ring + 1) * segments + segment)
+                let (c, d) = ((ring + 1) * segments + next, ring * segments + next)
+                triangles += [(a, b, c), (a, c, d)]
+            }
+        }
+        return (vertices, triangles)
+    }
+}
+
+
+/// A convex collider placed in the world by the frame of its rigid.
+struct PlacedConvexCollider: ConvexVolume {
+    let collider: ConvexCollider
+    let frame: Frame
+    
+    init(_ collider: ConvexCollider, frame: Frame) {
+        self.collider = collider
+        self.frame = frame
+    }
+    
+    func furthestPoint(in direction: Point) -> Point {
+        frame.act(collider.support(frame.quaternion.inverse.act(on: direction)))
+    }
+    
+    func contains(_ point: Point) -> Bool {
+        collider.contains(frame.inverse.act(point))
+    }
+    
+    func contactPoints(towards direction: Point) -> [Point] {
+        collider.contactPoints(towards: frame.quaternion.inverse.act(on: direction)).map(frame.act)
+    }
+    
+    func face(towards direction: Point) -> ContactFace? {
+        collider.face(towards: frame.quaternion.inverse.act(on: direction))?.transformed(by: frame)
+    }
+}
+
+
+/// Resolves an overlap of two convex volumes along the shortest separating translation, as found by GJK and EPA.
+/// Polyhedra touching with a face get the contacts found by clipping their faces, see `clipFaces`. Otherwise,
+/// contact points of either volume inside the other one are pushed out along it, and volumes overlapping without
+/// any such point, like crossing edges, get a single contact at the deepest point.
+/// The iterations of GJK and EPA are traced if a tracer is given.
+func intersectConvex(_ volume: ConvexVolume, attachedTo rigid: Rigid, with otherVolume: ConvexVolume, attachedTo other: Rigid,
+                     tracer: CollisionTracer? = nil) -> [Constraint] {
+    let support = MinkowskiDifference(volume, otherVolume)
+    var trace = tracer.map { _ in CollisionTrace(frames: [rigid.frame, other.frame]) }
+    defer {
+        if let trace = trace {
+            tracer?.record(trace)
+        }
+    }
+    guard let tetrahedron = gjk(support, trace: &trace) else {
+        return []
+    }
+    // The normal points from the first volume towards the other one.
+    let (normal, depth) = epa(tetrahedron: tetrahedron, support: support, trace: &trace)
+    if depth < 1e-9 {
+        return []
+    }
+    if let contacts = clipFaces(volume, attachedTo: rigid, with: otherVolume, attachedTo: other, normal: normal) {
+        return contacts
+    }
+    
+    let extents = (volume.furthestPoint(in: normal).dot(normal), otherVolume.furthestPoint(in: -normal).dot(normal))
+    // Contacts are told apart by the point they stem from, counting the points of the other volume after the ones of the first.
+    var contacts: [(positions: (Point, Point), point: Int)] = []
+    // No point needs to move further than the volume as a whole.
+    let points = (volume.contactPoints(towards: normal), otherVolume.contactPoints(towards: -normal))
+    for (index, point) in points.1.enumerated() where volume.contains(point) {
+        let penetration = min(extents.0 - point.dot(normal), depth)
+        if penetration > 0 {
+            contacts.append(((point + penetration * normal, point), points.0.count + index))
+        }
+    }
+    for (index, point) in points.0.enumerated() where otherVolume.contains(point) {
+        let penetration = min(point.dot(normal) - extents.1, depth)
+        if penetration > 0 {
+            contacts.append(((point, point - penetration * normal), index))
+        }
+    }
+    if contacts.isEmpty {
+        let deepest = otherVolume.furthestPoint(in: -normal)
+        contacts.append(((deepest + depth * normal, deepest), -1))
+    }
+    
+    return contacts.map { positions, point in
+        PositionalConstraint(rigids: (rigid, other), contacts: positions, distance: 0, normal: -normal,
+                             feature: ContactFeature(isReferenceFirst: true, referenceFace: -1, incidentFace: -1, point: point))
+    }
+}
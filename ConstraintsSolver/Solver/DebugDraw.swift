@@ -0,0 +1,84 @@
+//
+//  DebugDraw.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Receives the lines the solver draws to show what it is doing. The solver only knows line debuggers through this,
+/// like it only knows renderers through `MeshRegistry`.
+protocol DebugCanvas: AnyObject {
+    func line(from a: Point, to b: Point, color: Color)
+    func arrow(from origin: Point, along vector: Point, color: Color)
+    func box(halfExtents: Point, frame: Frame, color: Color)
+}
+
+
+/// The kinds of state the solver can draw on top of the scene, which can be combined freely.
+struct DebugOverlays: OptionSet {
+    let rawValue: Int
+    
+    /// The contact points of the last sub-step on both rigids, with the normal along which the first one is pushed.
+    static let contacts = DebugOverlays(rawValue: 1 << 0)
+    /// How far each joint is from being satisfied, from where the second anchor should be to where it is.
+    static let constraintErrors = DebugOverlays(rawValue: 1 << 1)
+    static let boundingBoxes = DebugOverlays(rawValue: 1 << 2)
+    /// The linear velocity and the angular velocity of every moving rigid, showing how far it gets in a fraction of a second.
+    static let velocities = DebugOverlays(rawValue: 1 << 3)
+    /// Colors the bounding boxes of sleeping and frozen rigids, and draws them even if bounding boxes are off.
+    static let sleeping = DebugOverlays(rawValue: 1 << 4)
+    
+    static let all: DebugOverlays = [.contacts, .constraintErrors, .boundingBoxes, .velocities, .sleeping]
+}
+
+
+extension Solver {
+    /// The time over which velocity arrows extrapolate, which keeps them short enough to read at walking speeds.
+    private static let velocityArrowTime = 0.25
+    
+    /// Draws the requested overlays for the state the last step left the rigids and joints in.
+    func draw(_ overlays: DebugOverlays, rigids: [Rigid], joints: [Joint], into canvas: DebugCanvas) {
+        if overlays.contains(.contacts) {
+            for manifold in manifolds.values {
+                for contact in manifold.contacts {
+                    // A persistent contact is drawn in a different color than a new one, to tell jittering contacts apart.
+                    let color: Color = contact.age > 0 ? .green : .yellow
+                    canvas.line(from: contact.positions.0, to: contact.positions.1, color: .red)
+                    canvas.arrow(from: contact.positions.0, along: 0.2 * contact.normal, color: color)
+                }
+            }
+        }
+        
+        if overlays.contains(.constraintErrors) {
+            for joint in joints {
+                for constraint in joint.constraints() {
+                    let target = constraint.contacts.0 + constraint.distance * constraint.direction
+                    canvas.line(from: target, to: constraint.contacts.1, color: Color(1, 0.5, 0))
+                }
+            }
+        }
+        
+        for rigid in rigids {
+            guard let box = rigid.boundingBox else {
+                continue
+            }
+            let color: Color? = overlays.contains(.sleeping) && rigid.isSleeping ? .blue
+                : overlays.contains(.sleeping) && rigid.isFrozen ? Color(0.5)
+                : overlays.contains(.boundingBoxes) ? .white
+                : nil
+            if let color = color {
+                canvas.box(halfExtents: 0.5 * box.lower.to(box.upper), frame: Frame(position: box.center), color: color)
+            }
+        }
+        
+        if overlays.contains(.velocities) {
+            for rigid in rigids where rigid.inverseMass > 0 && !rigid.isFrozen {
+                canvas.arrow(from: rigid.frame.position, along: Solver.velocityArrowTime * rigid.velocity, color: .cyan)
+                canvas.arrow(from: rigid.frame.position, along: Solver.velocityArrowTime * rigid.angularVelocity, color: .magenta)
+            }
+        }
+    }
+}
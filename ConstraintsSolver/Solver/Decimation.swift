@@ -0,0 +1,138 @@
+//
+//  Decimation.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Upper bounds for the size of a collision proxy built from a render mesh.
+struct DecimationBudget {
+    var maxTriangles: Int?
+    var maxVertices: Int?
+    
+    init(maxTriangles: Int? = nil, maxVertices: Int? = nil) {
+        self.maxTriangles = maxTriangles
+        self.maxVertices = maxVertices
+    }
+    
+    func admits(vertexCount: Int, triangleCount: Int) -> Bool {
+        vertexCount <= maxVertices ?? .max && triangleCount <= maxTriangles ?? .max
+    }
+}
+
+
+/// Simplifies indexed triangles by merging all vertices within cells of a grid into their average,
+/// growing the cells until the budget is met. Triangles collapsing to lines or points are dropped.
+/// This is cruder than edge collapses, but fast and good enough for collision proxies.
+func decimate(vertices: [Point], triangles: [(Int, Int, Int)], budget: DecimationBudget) -> (vertices: [Point], triangles: [(Int, Int, Int)]) {
+    if budget.admits(vertexCount: vertices.count, triangleCount: triangles.count) || vertices.isEmpty {
+        return (vertices, triangles)
+    }
+    
+    let lower = vertices.dropFirst().reduce(vertices[0]) { Point(min($0.ex, $1.ex), min($0.ey, $1.ey), min($0.ez, $1.ez)) }
+    let upper = vertices.dropFirst().reduce(vertices[0]) { Point(max($0.ex, $1.ex), max($0.ey, $1.ey), max($0.ez, $1.ez)) }
+    var cellSize = max(lower.distance(to: upper), 1e-9) / 256
+    var result = (vertices: vertices, triangles: triangles)
+    
+    for _ in 0 ..< 64 {
+        result = cluster(vertices: vertices, triangles: triangles, origin: lower, cellSize: cellSize)
+        if budget.admits(vertexCount: result.vertices.count, triangleCount: result.triangles.count) {
+            break
+        }
+        cellSize *= 1.25
+    }
+    
+    return result
+}
+
+
+/// Merges the vertices within each cell of a grid, which also welds duplicated vertices if the cells are tiny.
+func cluster(vertices: [Point], triangles: [(Int, Int, Int)], origin: Point, cellSize: Double) -> (vertices: [Point], triangles: [(Int, Int, Int)]) {
+    var cellIndices: [SIMD3<Int>: Int] = [:]
+    var sums: [Point] = []
+    var counts: [Int] = []
+    
+    let remap: [Int] = vertices.map { vertex in
+        let offset = origin.to(vertex)
+        let cell = SIMD3<Int>(Int((offset.ex / cellSize).rounded(.down)), Int((offset.ey / cellSize).rounded(.down)), Int((offset.ez / cellSize).rounded(.down)))
+        if let index = cellIndices[cell] {
+            sums[index] = sums[index] + vertex
+            counts[index] += 1
+            return index
+        }
+        cellIndices[cell] = sums.count
+        sums.append(vertex)
+        counts.append(1)
+        return sums.count - 1
+    }
+    
+    var seen = Set<[Int]>()
+    var clustered: [(Int, Int, Int)] = []
+    for (a, b, c) in triangles {
+        let corners = (remap[a], remap[b], remap[c])
+        if corners.0 == corners.1 || corners.1 == corners.2 || corners.2 == corners.0 {
+            continue
+        }
+        // Rotate the smallest index to the front, so that duplicates are found regardless of their starting corner.
+        let rotated = [corners.0, corners.1, corners.2]
+        let start = rotated.firstIndex(of: rotated.min()!)!
+        if seen.insert((0 ..< 3).map { rotated[(start + $0) % 3] }).inserted {
+            clustered.append(corners)
+        }
+    }
+    
+    // Drop vertices no longer used by any triangle.
+    var used: [Int: Int] = [:]
+    var compacted: [Point] = []
+    func compact(_ index: Int) -> Int {
+        if let existing = used[index] {
+            return existing
+        }
+        used[index] = compacted.count
+        compacted.append((1 / Double(counts[index])) * sums[index])
+        return compacted.count - 1
+    }
+    let compactedTriangles = clustered.map { a, b, c in (compact(a), compact(b), compact(c)) }
+    
+    return (compacted, compactedTriangles)
+}
+
+
+extension TriangleMeshCollider {
+    /// Builds a collider from a render mesh, which is simplified to the budget if given.
+    init(mesh: Mesh, budget: DecimationBudget? = nil, sidedness: TriangleSidedness = .oneSided, correctsInternalEdges: Bool = true) {
+        let positions = mesh.vertices.map { Point(Double($0.position.x), Double($0.position.y), Double($0.position.z)) }
+        let soup = (0 ..< positions.count / 3).map { (3 * $0, 3 * $0 + 1, 3 * $0 + 2) }
+        
+        // Render meshes repeat vertices per triangle, so weld them first.
+        let lower = positions.reduce(Point(Double.infinity)) { Point(min($0.ex, $1.ex), min($0.ey, $1.ey), min($0.ez, $1.ez)) }
+        var indexed = cluster(vertices: positions, triangles: soup, origin: lower, cellSize: 1e-6)
+        if let budget = budget {
+            indexed = decimate(vertices: indexed.vertices, triangles: indexed.triangles, budget: budget)
+        }
+        
+        self.init(vertices: indexed.vertices, triangles: indexed.triangles, sidedness: sidedness, correctsInternalEdges: correctsInternalEdges)
+    }
+}
+
+
+extension CompoundCollider {
+    /// Simplifies every hull to the budget, which keeps decompositions of high-poly props cheap to collide.
+    /// Primitive shapes are kept as they are.
+    func decimated(to budget: DecimationBudget) -> CompoundCollider {
+        CompoundCollider(shapes: shapes.map { shape in
+            guard case let .hull(piece) = shape.collider else {
+                return shape
+            }
+            let simplified = decimate(vertices: piece.vertices, triangles: piece.faces, budget: budget)
+            if simplified.triangles.isEmpty {
+                return shape
+            }
+            return CompoundShape(collider: .hull(ConvexPiece(vertices: simplified.vertices, faces: simplified.triangles)),
+                                 frame: shape.frame, density: shape.density)
+        })
+    }
+}
@@ -0,0 +1,99 @@
+//
+//  DestructionBenchmark.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Knocks a wall of boxes over with a heavy ball and scores the outcome, so that solver configurations can be compared
+/// by numbers rather than by eye. Everything but the configuration is fixed, and it runs headlessly like the drop test.
+struct DestructionBenchmark {
+    /// The wall is laid in a running bond, with every other course shifted by half a box.
+    var columns = 8
+    var courses = 8
+    var boxSize = 0.5
+    var boxMass = 1.0
+    var ballRadius = 0.6
+    var ballMass = 200.0
+    var ballSpeed = 12.0
+    /// Simulated time in seconds.
+    var duration = 6.0
+    var timeStep = 1.0 / 60
+    /// Boxes which ended up further than this from where they were laid count as displaced.
+    var displacementThreshold = 0.25
+    /// Sets up the solver of the world before the run, e.g. its backend or sub-step count.
+    var configure: (World) -> Void = { _ in }
+    
+    struct Report {
+        var displacedCount: Int
+        var boxCount: Int
+        /// The largest penetration during any step.
+        var maxPenetration: Double
+        /// Simulated time in seconds.
+        var simulatedTime: Double
+        /// Wall-clock time spent in the solver.
+        var solverTime: Double
+    }
+    
+    /// Creates the world without running it, e.g. to show the benchmark in the viewer.
+    func makeWorld(renderer: MeshRegistry? = nil) -> (world: World, boxes: [RigidHandle], ball: RigidHandle) {
+        let world = World(renderer: renderer)
+        configure(world)
+        
+        var boxes: [RigidHandle] = []
+        let spacing = 1.01 * boxSize
+        for course in 0 ..< courses {
+            let shift = course % 2 == 0 ? 0 : 0.5 * spacing
+            for column in 0 ..< columns {
+                let y = (Double(column) - 0.5 * Double(columns - 1)) * spacing + shift
+                let box = world.addConvex(.box(halfExtents: Point(0.5 * boxSize)), at: Point(0, y, (Double(course) + 0.5) * spacing), mass: boxMass)
+                boxes.append(world.handle(of: box)!)
+            }
+        }
+        
+        // The ball is thrown at the middle of the wall from a few meters away.
+        let height = 0.5 * Double(courses) * spacing
+        let ball = world.addConvex(.sphere(radius: ballRadius), at: Point(-4, 0, height), mass: ballMass, color: Color(0.3))
+        ball.velocity = ballSpeed * .ex
+        
+        return (world, boxes, world.handle(of: ball)!)
+    }
+    
+    func run() -> Report {
+        let (world, boxes, _) = makeWorld()
+        let laid = boxes.map { world.rigid($0).frame.position }
+        let stepCount = Int((duration / timeStep).rounded())
+        var maxPenetration = 0.0
+        var solverTime = 0.0
+        
+        for _ in 0 ..< max(stepCount, 1) {
+            world.integrate(dt: timeStep)
+            solverTime += world.telemetry.stepDuration
+            maxPenetration = max(maxPenetration, world.telemetry.maxPenetration)
+        }
+        
+        let displaced = zip(boxes, laid).filter { box, position in
+            world.rigid(box).frame.position.distance(to: position) > displacementThreshold
+        }
+        return Report(displacedCount: displaced.count,
+                      boxCount: boxes.count,
+                      maxPenetration: maxPenetration,
+                      simulatedTime: Double(max(stepCount, 1)) * timeStep,
+                      solverTime: solverTime)
+    }
+}
+
+
+extension DestructionBenchmark.Report: CustomStringConvertible {
+    var description: String {
+        [
+            "displaced boxes:   \(displacedCount) of \(boxCount)",
+            String(format: "max penetration:   %.5f", maxPenetration),
+            String(format: "simulated time:    %.2f s", simulatedTime),
+            String(format: "solver time:       %.2f s", solverTime)
+        ].joined(separator: "\n")
+    }
+}
@@ -0,0 +1,117 @@
+//
+//  DropTest.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 18.04.21.
+//
+
+import Foundation
+
+
+/// A deterministic pseudo-random number generator (SplitMix64), so that scenarios are reproducible given a seed.
+struct SeededGenerator: RandomNumberGenerator {
+    private var state: UInt64
+    
+    init(seed: UInt64) {
+        state = seed
+    }
+    
+    mutating func next() -> UInt64 {
+        state &+= 0x9E3779B97F4A7C15
+        var z = state
+        z = (z ^ (z >> 30)) &* 0xBF58476D1CE4E5B9
+        z = (z ^ (z >> 27)) &* 0x94D049BB133111EB
+        return z ^ (z >> 31)
+    }
+}
+
+
+/// Drops randomly placed and oriented cubes into an arena and reports how well the solver brings them to rest.
+/// Runs headlessly, which makes it a quick health check for solver changes.
+struct DropTest {
+    var bodyCount = 20
+    /// Simulated time in seconds.
+    var duration = 10.0
+    var timeStep = 1.0 / 60
+    var seed: UInt64 = 1
+    var arenaExtent = 4.0
+    /// The scene counts as settled once the kinetic energy per body stays below this threshold.
+    var restingEnergy = 1e-3
+    /// Sets up the solver of the world before the run, e.g. to solve concurrently.
+    var configure: (World) -> Void = { _ in }
+    
+    struct Report {
+        /// Simulated time after which the scene stayed at rest, or `nil` if it never settled.
+        var settleTime: Double?
+        var finalPenetration: Double
+        var finalKineticEnergy: Double
+        /// Wall-clock time spent in the solver.
+        var solverTime: Double
+        /// The checksum of the final state, which runs with the same seed agree on.
+        var stateHash: UInt64
+    }
+    
+    /// Creates the world without running it, e.g. to show the scenario in the viewer.
+    func makeWorld(renderer: MeshRegistry? = nil) -> World {
+        var generator = SeededGenerator(seed: seed)
+        let world = World(renderer: renderer)
+        configure(world)
+        world.addArena(extent: arenaExtent, wallHeight: 1)
+        
+        for index in 0 ..< bodyCount {
+            let spread = arenaExtent - 1
+            let position = Point(
+                .random(in: -spread ... spread, using: &generator),
+                .random(in: -spread ... spread, using: &generator),
+                2 + 1.5 * Double(index))
+            let axis = Point(
+                .random(in: -1 ... 1, using: &generator),
+                .random(in: -1 ... 1, using: &generator),
+                .random(in: 0.1 ... 1, using: &generator))
+            
+            let cube = world.addCube(at: position)
+            cube.frame.quaternion = Quaternion(by: .random(in: 0 ..< 2 * .pi, using: &generator), around: axis.normalize)
+        }
+        
+        return world
+    }
+    
+    func run() -> Report {
+        let world = makeWorld()
+        let stepCount = Int((duration / timeStep).rounded())
+        var settleTime: Double?
+        var solverTime = 0.0
+        
+        for step in 1 ... max(stepCount, 1) {
+            world.integrate(dt: timeStep)
+            let telemetry = world.telemetry
+            solverTime += telemetry.stepDuration
+            
+            if telemetry.kineticEnergy < restingEnergy * Double(bodyCount) {
+                settleTime = settleTime ?? Double(step) * timeStep
+            }
+            else {
+                settleTime = nil
+            }
+        }
+        
+        return Report(settleTime: settleTime,
+                      finalPenetration: world.telemetry.maxPenetration,
+                      finalKineticEnergy: world.telemetry.kineticEnergy,
+                      solverTime: solverTime,
+                      stateHash: world.stateHash)
+    }
+}
+
+
+extension DropTest.Report: CustomStringConvertible {
+    var description: String {
+        [
+            "settle time:       " + (settleTime.map { String(format: "%.2f s", $0) } ?? "not settled"),
+            String(format: "final penetration: %.5f", finalPenetration),
+            String(format: "final energy:      %.5f", finalKineticEnergy),
+            String(format: "solver time:       %.2f s", solverTime),
+            String(format: "state hash:        %016llx", stateHash)
+        ].joined(separator: "\n")
+    }
+}
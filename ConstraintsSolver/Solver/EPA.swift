@@ -7,99 +7,103 @@
 
 import Foundation
 
-//fileprivate struct ExpandingPolytope {
-//    var points: [simd_double3] = []
-//    var triangles: [(Int, Int, Int)]
-//
-//    init(from tetrahedron: Tetrahedron) {
-//        points = [tetrahedron.0, tetrahedron.1, tetrahedron.2, tetrahedron.3]
-//        triangles = [(0, 2, 1), (0, 1, 3), (1, 2, 3), (2, 0, 3)]
-//    }
-//
-//    func nearestOrthogonalProjection() -> simd_double3 {
-//        var minimalSquaredDistance = Double.infinity
-//        var nearestOrthogonalProjection = simd_double3.zero
-//
-//        for triangle in triangles {
-//            let points = (self.points[triangle.0], self.points[triangle.1], self.points[triangle.2])
-//            let normal = (points.1 - points.0).cross(points.2 - points.0)
-//            let offset = points.0
-//            let orthogonalProjection = (normal.dot(-offset)) * normal
-//            let squaredDistance = length_squared(orthogonalProjection)
-//
-//            if squaredDistance < minimalSquaredDistance {
-//                minimalSquaredDistance = squaredDistance
-//                nearestOrthogonalProjection = orthogonalProjection
-//            }
-//        }
-//
-//        return nearestOrthogonalProjection
-//    }
-//
-//    func triangleEdges(of triangle: (Int, Int, Int)) -> [(Int, Int)] {
-//        return [(triangle.0, triangle.1), (triangle.1, triangle.2), (triangle.2, triangle.0)]
-//    }
-//
-//    /// Expands the polytope to the given point, while mainting convexity.
-//    mutating func expand(to x: simd_double3) {
-//        var seamEdges: [(Int, Int)] = []
-//        var vanishingEdges: [Bool] = []
-//        var vanishingTriangles: [Bool] = .init(repeating: false, count: triangles.count)
-//
-//        for (index, triangle) in triangles.enumerated() {
-//            let points = (self.points[triangle.0], self.points[triangle.1], self.points[triangle.2])
-//            let normal = (points.1 - points.0).cross(points.2 - points.0)
-//
-//            if normal.dot(x) > 0 {
-//                vanishingTriangles[index] = true
-//
-//                for edge in triangleEdges(of: triangle) {
-//                    let reversedEdge = (edge.1, edge.0)
-//                    var isVanishing = false
-//                    for (index, seamEdge) in seamEdges.enumerated() {
-//                        if seamEdge == reversedEdge {
-//                            vanishingEdges[index] = true
-//                            isVanishing = true
-//                            break
-//                        }
-//                    }
-//                    if !isVanishing {
-//                        seamEdges.append(edge)
-//                        vanishingEdges.append(false)
-//                    }
-//                }
-//            }
-//        }
-//
-//        for i in 0..<triangles.count {
-//            if vanishingTriangles[i] {
-//                triangles.remove(at: i) // TODO: invalidates next indices
-//            }
-//        }
-//
-//        for (index, seamEdge) in seamEdges.enumerated() {
-//            if vanishingEdges[index] {
-//                continue
-//            }
-//
-//            points.append(x)
-//            triangles.append((seamEdge.0, seamEdge.1, points.count - 1))
-//        }
-//    }
-//}
-//
-//func epa(tetrahedron: Tetrahedron, support: MinkowskiDifference) -> simd_double3 {
-//    var polytope = ExpandingPolytope(from: tetrahedron)
-//
-//    while true {
-//        let projection = polytope.nearestOrthogonalProjection()
-//        let actualExtent = support[in: projection]
-//
-//        if length_squared(projection - actualExtent) < 0.01 {
-//            return projection
-//        }
-//        else {
-//            polytope.expand(to: projection)
-//        }
-//    }
-//}
+
+fileprivate struct ExpandingPolytope {
+    var points: [Point]
+    /// Triangles wound counter-clockwise when seen from outside.
+    var triangles: [(Int, Int, Int)]
+    
+    init(from tetrahedron: Tetrahedron) {
+        points = [tetrahedron.0, tetrahedron.1, tetrahedron.2, tetrahedron.3]
+        triangles = [(0, 2, 1), (0, 1, 3), (1, 2, 3), (2, 0, 3)]
+        
+        // Flip all triangles if the tetrahedron has the opposite orientation.
+        let center = points.center
+        let (a, b, c) = triangles[0]
+        if normal(of: (a, b, c)).dot(points[a] - center) < 0 {
+            triangles = triangles.map { ($0.0, $0.2, $0.1) }
+        }
+    }
+    
+    func normal(of triangle: (Int, Int, Int)) -> Point {
+        (points[triangle.1] - points[triangle.0]).cross(points[triangle.2] - points[triangle.0]).normalize
+    }
+    
+    /// The triangle closest to the origin, with its outward normal and distance.
+    func nearestTriangle() -> (normal: Point, distance: Double)? {
+        var nearest: (normal: Point, distance: Double)?
+        for triangle in triangles {
+            let normal = self.normal(of: triangle)
+            // Skip triangles which degenerated to lines.
+            if !normal.length.isFinite || normal.length < 0.5 {
+                continue
+            }
+            let distance = normal.dot(points[triangle.0])
+            if distance < nearest?.distance ?? .infinity {
+                nearest = (normal, distance)
+            }
+        }
+        return nearest
+    }
+    
+    /// Expands the polytope to the given point, while maintaining convexity.
+    mutating func expand(to x: Point) {
+        var horizon: [(Int, Int)] = []
+        var remaining: [(Int, Int, Int)] = []
+        
+        for triangle in triangles {
+            if normal(of: triangle).dot(x - points[triangle.0]) > 0 {
+                // Edges shared by two visible triangles are interior, the others form the horizon.
+                for edge in [(triangle.0, triangle.1), (triangle.1, triangle.2), (triangle.2, triangle.0)] {
+                    if let index = horizon.firstIndex(where: { $0 == (edge.1, edge.0) }) {
+                        horizon.remove(at: index)
+                    }
+                    else {
+                        horizon.append(edge)
+                    }
+                }
+            }
+            else {
+                remaining.append(triangle)
+            }
+        }
+        
+        points.append(x)
+        triangles = remaining + horizon.map { ($0.0, $0.1, points.count - 1) }
+    }
+}
+
+
+/// Finds the shortest translation of the second volume which separates it from the first one, given a tetrahedron
+/// of their Minkowski difference containing the origin as found by GJK. Returns the direction and the length.
+func epa(tetrahedron: Tetrahedron, support: MinkowskiDifference) -> (normal: Point, depth: Double) {
+    var trace: CollisionTrace?
+    return epa(tetrahedron: tetrahedron, support: support, trace: &trace)
+}
+
+
+/// Runs EPA, adding every iteration and its outcome to the trace if there is one.
+func epa(tetrahedron: Tetrahedron, support: MinkowskiDifference, trace: inout CollisionTrace?) -> (normal: Point, depth: Double) {
+    var polytope = ExpandingPolytope(from: tetrahedron)
+    var result = (normal: Point.ex, depth: 0.0)
+    
+    for _ in 0 ..< 32 {
+        guard let nearest = polytope.nearestTriangle() else {
+            trace?.outcome = .polytopeDegenerated
+            return result
+        }
+        result = (nearest.normal, max(nearest.distance, 0))
+        
+        let extent = support[in: nearest.normal]
+        trace?.polytopes.append(CollisionTrace.PolytopeIteration(points: polytope.points, triangles: polytope.triangles.map { [$0.0, $0.1, $0.2] },
+                                                                 normal: nearest.normal, distance: nearest.distance, support: extent))
+        if extent.dot(nearest.normal) - nearest.distance < 1e-6 {
+            trace?.outcome = .resolved
+            return result
+        }
+        polytope.expand(to: extent)
+    }
+    
+    trace?.outcome = .polytopeExhausted
+    return result
+}
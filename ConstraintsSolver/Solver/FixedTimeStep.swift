@@ -0,0 +1,68 @@
+//
+//  FixedTimeStep.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// How a world is stepped by a `FixedTimeStepDriver`.
+struct SolverConfig {
+    /// Overrides the sub-step count of the world if set.
+    var subStepCount: Int?
+    /// Simulated seconds per step.
+    var fixedTimeStep = 1.0 / 60
+    /// Steps taken at most per frame, so that a slow frame doesn't make the next one even slower by simulating more.
+    /// Time beyond that is dropped, and the simulation runs slower than real time meanwhile.
+    var maxStepsPerFrame = 4
+}
+
+
+/// Advances a world in steps of a fixed duration by the time passed between frames, and carries the remainder over
+/// to the next frame. The simulation then gives the same results at any frame rate, which a step per frame doesn't.
+class FixedTimeStepDriver {
+    let world: World
+    var config: SolverConfig {
+        didSet {
+            apply()
+        }
+    }
+    /// Frame time which was not simulated yet, since it is shorter than a step.
+    private(set) var accumulatedTime = 0.0
+    
+    init(world: World, config: SolverConfig = SolverConfig()) {
+        self.world = world
+        self.config = config
+        apply()
+    }
+    
+    private func apply() {
+        if let subStepCount = config.subStepCount {
+            world.subStepCount = subStepCount
+        }
+    }
+    
+    /// How far the world lags behind the frame time, as a fraction of a step.
+    var lag: Double {
+        accumulatedTime / config.fixedTimeStep
+    }
+    
+    /// Takes as many steps as fit into the time passed, and returns their number.
+    @discardableResult
+    func advance(by frameDuration: Double) -> Int {
+        accumulatedTime += frameDuration
+        var stepCount = 0
+        while accumulatedTime >= config.fixedTimeStep {
+            if stepCount == config.maxStepsPerFrame {
+                accumulatedTime = 0
+                break
+            }
+            world.integrate(dt: config.fixedTimeStep)
+            accumulatedTime -= config.fixedTimeStep
+            stepCount += 1
+        }
+        return stepCount
+    }
+}
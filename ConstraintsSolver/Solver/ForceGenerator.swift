@@ -0,0 +1,71 @@
+//
+//  ForceGenerator.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Computes forces which change from step to step, unlike the constant external force of a rigid.
+/// Generators run at the beginning of every step and add to the generated force of the rigids,
+/// which is reset to zero before.
+protocol ForceGenerator {
+    func apply(to rigids: [Rigid])
+}
+
+
+/// How the force between two magnets decreases with their distance.
+enum MagnetFalloff {
+    /// Full strength anywhere within the radius.
+    case constant
+    /// Decreasing linearly to zero at the radius.
+    case linear
+    /// Decreasing with the inverse square of the distance, like gravity, and cut off at the radius.
+    /// The strength is the one at a distance of one meter.
+    case inverseSquare
+}
+
+
+/// Attracts rigids belonging to any of the groups to each other if they are within a radius, pulling their
+/// centers together with equal and opposite forces. Suited for magnet puzzles, or orbits with the inverse square falloff.
+struct MagnetForce: ForceGenerator {
+    var groups: CollisionGroups
+    var radius: Double
+    /// Force in newtons, see the falloff.
+    var strength: Double
+    var falloff = MagnetFalloff.inverseSquare
+    /// Distances are taken as at least this long, so that touching magnets don't receive huge forces.
+    var minDistance = 0.1
+    
+    func force(at distance: Double) -> Double {
+        if distance > radius {
+            return 0
+        }
+        switch falloff {
+        case .constant:
+            return strength
+        case .linear:
+            return strength * (1 - distance / radius)
+        case .inverseSquare:
+            return strength / max(distance, minDistance).sq
+        }
+    }
+    
+    func apply(to rigids: [Rigid]) {
+        let magnets = rigids.filter { !$0.groups.isDisjoint(with: groups) }
+        for (index, magnet) in magnets.enumerated() {
+            for other in magnets[(index + 1)...] where magnet.inverseMass > 0 || other.inverseMass > 0 {
+                let offset = magnet.frame.position.to(other.frame.position)
+                let distance = offset.length
+                if distance < 1e-9 {
+                    continue
+                }
+                let pull = (force(at: distance) / distance) * offset
+                magnet.generatedForce = magnet.generatedForce + pull
+                other.generatedForce = other.generatedForce - pull
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//
+//  Fracture.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Breaks a rigid into precomputed fragments as soon as a contact pushes it harder than a threshold.
+/// Only the position based backend measures contact impulses. The first fragment takes over the handle of the rigid
+/// and the others are appended, so the handles of other rigids stay valid.
+struct Fracture {
+    /// Contact impulse in newton seconds above which the rigid breaks.
+    var impulseThreshold: Double
+    /// The convex pieces of every fragment, in the local frame of the rigid.
+    var fragments: [[ConvexPiece]]
+    
+    init(impulseThreshold: Double, fragments: [[ConvexPiece]]) {
+        self.impulseThreshold = impulseThreshold
+        self.fragments = fragments
+    }
+    
+    /// Precomputes fragments by cutting a collider with all the planes given in its local frame,
+    /// which yields up to two to the power of the number of planes fragments.
+    init?(impulseThreshold: Double, cutting collider: Collider, by planes: [Plane]) {
+        guard var fragments = collider.convexPieces.map({ [$0] }) else {
+            return nil
+        }
+        for plane in planes {
+            fragments = fragments.flatMap { pieces in
+                [pieces.compactMap { $0.clipped(behind: plane) }, pieces.compactMap { $0.clipped(behind: plane.flip) }]
+            }.filter { !$0.isEmpty }
+        }
+        self.init(impulseThreshold: impulseThreshold, fragments: fragments)
+    }
+}
+
+
+extension Collider {
+    /// The convex pieces the collider is made of, if it is bounded.
+    var convexPieces: [ConvexPiece]? {
+        switch self {
+        case let .box(box):
+            return [box.piece]
+        case let .compound(compound):
+            return compound.pieces
+        case let .convex(convex):
+            return convex.piece.map { [$0] }
+        case .plane(_), .mesh(_):
+            return nil
+        }
+    }
+}
@@ -6,15 +6,16 @@
 //
 
 import Foundation
+import simd
 
 
-struct Frame {
-    var position: Point
-    var quaternion: Quaternion
+public struct Frame {
+    public var position: Point
+    public var quaternion: Quaternion
     
-    static let identity = Frame(position: .null, quaternion: .identity)
+    public static let identity = Frame(position: .null, quaternion: .identity)
     
-    init(position: Point = .null, quaternion: Quaternion = .identity) {
+    public init(position: Point = .null, quaternion: Quaternion = .identity) {
         self.position = position
         self.quaternion = quaternion
     }
@@ -33,16 +34,21 @@ struct Frame {
             simd_float4(translation, 1))
     }
     
-    var inverse: Frame {
+    public var inverse: Frame {
         let inverseOrientation = quaternion.inverse
         return Frame(position: inverseOrientation.act(on: -position),
                      quaternion: inverseOrientation)
     }
     
-    func act(_ x: Point) -> Point {
+    public func act(_ x: Point) -> Point {
         quaternion.act(on: x) + position
     }
     
+    /// Expresses a frame given relative to this one globally.
+    func act(_ frame: Frame) -> Frame {
+        Frame(position: act(frame.position), quaternion: quaternion * frame.quaternion)
+    }
+    
     func act(_ plane: Plane) -> Plane {
         let support = (plane.support + position).project(onto: plane.normal)
         let offset = support.dot(plane.normal) > 0 ? support.length : -support.length
@@ -64,3 +70,5 @@ struct Frame {
         position = position + translation
     }
 }
+
+extension Frame: Codable {}
@@ -0,0 +1,148 @@
+//
+//  Friction.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 19.04.21.
+//
+
+import Foundation
+
+
+/// How the tangential correction of a contact is bounded.
+enum FrictionModel: String, Codable {
+    /// Undoes the tangential motion of every contact entirely, so nothing ever slides. The coefficients, materials
+    /// and anisotropic friction only take effect with the other models. The sequential impulses backend always
+    /// applies the coefficients.
+    case stick
+    /// Bounds the length of the tangential correction, which treats all tangential directions alike.
+    case cone
+    /// Bounds the two tangential components independently.
+    /// Cheaper, but diagonal motion is held back by up to √2 times as much.
+    case box
+}
+
+
+/// Friction coefficients which differ between two tangential directions of a rigid, like for skis or treads.
+struct AnisotropicFriction: Codable {
+    /// The direction in the local frame of the rigid along which the primary coefficient applies.
+    var primaryAxis: Point
+    var primaryCoefficient: Double
+    /// Applies orthogonally to the primary axis within the contact plane.
+    var secondaryCoefficient: Double
+}
+
+
+struct Friction: Codable {
+    var model = FrictionModel.stick
+    /// Whether contacts are held back tangentially at all, including by anisotropic and rolling friction.
+    /// Turning it off tells friction artifacts apart from others.
+    var isEnabled = true
+    /// Ratio of the largest tangential correction of a sliding contact to its penetration depth.
+    var coefficient = 1.0
+    /// Ratio up to which a contact sticks, which means that its tangential motion is undone entirely.
+    /// Contacts moving further slide, and are only held back by the kinetic coefficient.
+    /// The same as the kinetic coefficient if `nil`, which makes contacts slide as soon as they stop sticking.
+    var staticCoefficient: Double?
+    /// How far the touched surface moves during the sub-step, which sticking contacts follow. See `Rigid.surfaceVelocity`.
+    var surfaceDisplacement = Point.null
+    
+    /// Only the configuration is saved, but neither the debugging switch nor what is set per contact.
+    private enum CodingKeys: String, CodingKey {
+        case model, coefficient, staticCoefficient
+    }
+    
+    /// Bounds the tangential correction of a contact with the given normal and penetration depth.
+    /// An anisotropic rigid overrides the coefficients, in which case the cone becomes elliptic,
+    /// and its static coefficients exceed the kinetic ones by the same ratio as the isotropic ones.
+    func limit(_ correction: Point, normal: Point, depth: Double, of rigid: Rigid? = nil) -> Point {
+        if !isEnabled {
+            return .null
+        }
+        if model == .stick {
+            return correction
+        }
+        var (u, v) = normal.tangents
+        var coefficients = (coefficient, coefficient)
+        let stickingRatio = coefficient > 0 ? max(staticCoefficient ?? coefficient, coefficient) / coefficient : 1
+        
+        if let rigid = rigid, let anisotropy = rigid.anisotropicFriction {
+            let axis = rigid.frame.quaternion.act(on: anisotropy.primaryAxis)
+            let tangentialAxis = axis - axis.project(onto: normal)
+            // An axis along the normal has no preferred tangential direction, so the rigid is isotropic there.
+            if tangentialAxis.length > 1e-9 {
+                u = tangentialAxis.normalize
+                v = normal.cross(u)
+                coefficients = (anisotropy.primaryCoefficient, anisotropy.secondaryCoefficient)
+            }
+        }
+        
+        let tangential = (correction.dot(u), correction.dot(v))
+        let sticking = clamp(tangential, to: (stickingRatio * coefficients.0 * depth, stickingRatio * coefficients.1 * depth))
+        if sticking == tangential {
+            return tangential.0 * u + tangential.1 * v
+        }
+        let sliding = clamp(tangential, to: (coefficients.0 * depth, coefficients.1 * depth))
+        return sliding.0 * u + sliding.1 * v
+    }
+    
+    private func clamp(_ correction: (Double, Double), to bounds: (Double, Double)) -> (Double, Double) {
+        var x = bounds.0 > 0 ? correction.0 : 0
+        var y = bounds.1 > 0 ? correction.1 : 0
+        
+        switch model {
+        case .stick:
+            // Sticking contacts are never clamped.
+            break
+        case .cone:
+            let excess = (bounds.0 > 0 ? (x / bounds.0).sq : 0) + (bounds.1 > 0 ? (y / bounds.1).sq : 0)
+            if excess > 1 {
+                x /= excess.squareRoot()
+                y /= excess.squareRoot()
+            }
+        case .box:
+            x = min(max(x, -bounds.0), bounds.0)
+            y = min(max(y, -bounds.1), bounds.1)
+        }
+        
+        return (x, y)
+    }
+}
+
+
+/// A contact remembered during the position pass of a sub-step, which afterwards brakes the rigids rolling over each
+/// other. Spinning about the normal is left alone, since that is held back by the tangential friction already.
+struct RollingContact {
+    let rigids: (Rigid, Rigid)
+    let normal: Point
+    /// The largest angular impulse the contact may apply, which is its normal impulse times the rolling friction.
+    let maxImpulse: Double
+    
+    /// Remembers a contact given the correction of its last solve, unless it doesn't resist rolling.
+    init?(_ contact: PositionalConstraint, lagrangeFactor: Double, rollingFriction: Double, dt: Double) {
+        if rollingFriction <= 0 || lagrangeFactor == 0 {
+            return nil
+        }
+        rigids = contact.rigids
+        normal = contact.normal ?? contact.direction
+        // The positional correction spread over the sub-step is the normal impulse.
+        maxImpulse = rollingFriction * abs(lagrangeFactor) / dt
+    }
+    
+    func apply() {
+        let spin = rigids.0.angularVelocity - rigids.1.angularVelocity
+        let rolling = spin - spin.project(onto: normal)
+        if rolling.length < 1e-12 {
+            return
+        }
+        let axis = rolling.normalize
+        let inverseInertia = rigids.0.applyInverseInertia(axis).dot(axis) + rigids.1.applyInverseInertia(axis).dot(axis)
+        if inverseInertia < 1e-12 {
+            return
+        }
+        
+        // Brakes the rolling up to a stop, but never reverses it.
+        let impulse = min(rolling.length / inverseInertia, maxImpulse) * axis
+        rigids.0.angularVelocity = rigids.0.angularVelocity - rigids.0.applyInverseInertia(impulse)
+        rigids.1.angularVelocity = rigids.1.angularVelocity + rigids.1.applyInverseInertia(impulse)
+    }
+}
@@ -7,155 +7,193 @@
 
 import Foundation
 
-//struct MinkowskiDifference {
-//    let convexVolumes: (ConvexVolume, ConvexVolume)
-//
-//    init(_ a: ConvexVolume, _ b: ConvexVolume) {
-//        convexVolumes = (a, b)
-//    }
-//
-//    /// Returns the point within the Minkowski difference which is furthest away from the origin in the given direction.
-//    subscript (in direction: simd_double3) -> simd_double3 {
-//        convexVolumes.0.furthestPoint(in: direction) - convexVolumes.1.furthestPoint(in: -direction)
-//    }
-//}
-//
-//protocol ConvexVolume {
-//    func furthestPoint(in direction: simd_double3) -> simd_double3
-//}
-//
-///// 0-, 1-, or 2-simplices which arise during the iterations of the GJK algorithm.
-//fileprivate enum IntermediateSimplex {
-//    case point(simd_double3)
-//    case line(simd_double3, simd_double3)
-//    case triangle(simd_double3, simd_double3, simd_double3)
-//}
-//
-//typealias Tetrahedron = (simd_double3, simd_double3, simd_double3, simd_double3)
-//
-///// The simplex after an iteration is either still an intermediate one, or the final tetrahedron which contains the origin.
-//fileprivate enum NextSimplex {
-//    case intermediate(IntermediateSimplex)
-//    case containingTetrahedron(Tetrahedron)
-//}
-//
-//func gjk(a: ConvexVolume, b: ConvexVolume) -> Bool {
-//    let support = MinkowskiDifference(a, b)
-//
-//    let initialPoint = support[in: simd_double3.random(in: 0...1)]
-//    var simplex = IntermediateSimplex.point(initialPoint)
-//    var searchDirection = -initialPoint
-//
-//    while true {
-//        let nextPoint = support[in: searchDirection]
-//
-//        if dot(nextPoint, searchDirection) <= 0 {
-//            // No collision possible anymore.
-//            return false
-//        }
-//
-//        switch nextSimplex(simplex: simplex, point: nextPoint, direction: &searchDirection) {
-//        case let .intermediate(nextSimplex):
-//            simplex = nextSimplex
-//        case .containingTetrahedron:
-//            return true
-//        }
-//    }
-//}
-//
-//fileprivate func nextSimplex(simplex: IntermediateSimplex, point a: simd_double3, direction: inout simd_double3) -> NextSimplex {
-//    switch simplex {
-//    case let .point(b):
-//        return .intermediate(processLine(a, b, direction: &direction))
-//    case let .line(b, c):
-//        return .intermediate(processTriangle(a, b, c, direction: &direction))
-//    case let .triangle(b, c, d):
-//        if let simplex = processTetrahedron(a, b, c, d, direction: &direction) {
-//            return .intermediate(simplex)
-//        }
-//        else {
-//            return .containingTetrahedron(Tetrahedron(a, b, c, d))
-//        }
-//    }
-//}
-//
-//extension simd_double3 {
-//    func cross(_ x: simd_double3) -> simd_double3 {
-//        simd.cross(self, x)
-//    }
-//
-//    func dot(_ x: simd_double3) -> Double {
-//        simd.dot(self, x)
-//    }
-//}
-//
-//fileprivate func sameDirection(_ a: simd_double3, _ b: simd_double3) -> Bool {
-//    dot(a, b) > 0
-//}
-//
-//fileprivate func processLine(_ a: simd_double3, _ b: simd_double3, direction: inout simd_double3) -> IntermediateSimplex {
-//    let ao = -a
-//    let ab = b - a
-//    if sameDirection(cross(a, b), ao) {
-//        direction = ab.cross(ao).cross(ab)
-//        return .line(a, b)
-//    }
-//    else {
-//        direction = ao
-//        return .point(a)
-//    }
-//}
-//
-//fileprivate func processTriangle(_ a: simd_double3, _ b: simd_double3, _ c: simd_double3, direction: inout simd_double3) -> IntermediateSimplex {
-//    let ao = -a
-//    let ab = b - a
-//    let ac = c - a
-//    let abc = ab.cross(ac)
-//
-//    if sameDirection(abc.cross(ac), ao) {
-//        if sameDirection(ac, ao) {
-//            direction = ac.cross(ao).cross(ac)
-//            return .line(a, c)
-//        }
-//        else {
-//            return processLine(a, b, direction: &direction)
-//        }
-//    }
-//    else {
-//        if sameDirection(ab.cross(abc), ao) {
-//            return processLine(a, b, direction: &direction)
-//        }
-//        else if sameDirection(abc, ao) {
-//            direction = abc
-//            return .triangle(a, b, c)
-//        }
-//        else {
-//            direction = -abc
-//            return .triangle(a, c, b)
-//        }
-//    }
-//}
-//
-//fileprivate func processTetrahedron(_ a: simd_double3, _ b: simd_double3, _ c: simd_double3, _ d: simd_double3, direction: inout simd_double3) -> IntermediateSimplex? {
-//    let ab = b - a
-//    let ac = c - a
-//    let ad = d - a
-//    let ao = -a
-//
-//    let abc = ab.cross(ac)
-//    let acd = ac.cross(ad)
-//    let adb = ad.cross(ab)
-//
-//    if sameDirection(abc, ao) {
-//        return processTriangle(a, b, c, direction: &direction)
-//    }
-//    else if sameDirection(acd, ao) {
-//        return processTriangle(a, c, d, direction: &direction)
-//    }
-//    else if sameDirection(adb, ao) {
-//        return processTriangle(a, d, b, direction: &direction)
-//    }
-//    else {
-//        return .none
-//    }
-//}
+
+protocol ConvexVolume {
+    func furthestPoint(in direction: Point) -> Point
+    /// Whether a point lies strictly inside the volume.
+    func contains(_ point: Point) -> Bool
+    /// The points which may lie inside another volume touching this one from the given direction.
+    func contactPoints(towards direction: Point) -> [Point]
+    /// The face whose normal is closest to the given direction, or `nil` if the volume has no faces.
+    func face(towards direction: Point) -> ContactFace?
+}
+
+
+extension ConvexVolume {
+    func contains(_ point: Point) -> Bool {
+        gjk(MinkowskiDifference(self, ConvexHull(points: [point]))) != nil
+    }
+    
+    func contactPoints(towards direction: Point) -> [Point] {
+        [furthestPoint(in: direction)]
+    }
+    
+    func face(towards direction: Point) -> ContactFace? {
+        nil
+    }
+}
+
+
+/// The convex hull of a set of points, such as the corners of a box in global coordinates.
+struct ConvexHull: ConvexVolume {
+    let points: [Point]
+    
+    func furthestPoint(in direction: Point) -> Point {
+        points.max { $0.dot(direction) < $1.dot(direction) }!
+    }
+    
+    func contactPoints(towards direction: Point) -> [Point] {
+        points
+    }
+}
+
+
+struct MinkowskiDifference {
+    let convexVolumes: (ConvexVolume, ConvexVolume)
+    
+    init(_ a: ConvexVolume, _ b: ConvexVolume) {
+        convexVolumes = (a, b)
+    }
+    
+    /// Returns the point within the Minkowski difference which is furthest away from the origin in the given direction.
+    subscript (in direction: Point) -> Point {
+        convexVolumes.0.furthestPoint(in: direction) - convexVolumes.1.furthestPoint(in: -direction)
+    }
+}
+
+
+typealias Tetrahedron = (Point, Point, Point, Point)
+
+
+/// Searches a tetrahedron within the Minkowski difference which contains the origin, which exists if and only if
+/// the volumes overlap. Volumes which merely touch are not considered overlapping.
+func gjk(_ support: MinkowskiDifference) -> Tetrahedron? {
+    var trace: CollisionTrace?
+    return gjk(support, trace: &trace)
+}
+
+
+/// Runs GJK, adding every iteration and its outcome to the trace if there is one.
+func gjk(_ support: MinkowskiDifference, trace: inout CollisionTrace?) -> Tetrahedron? {
+    trace?.outcome = .separated
+    // The simplex is kept with the most recently added point first.
+    var simplex = [support[in: .ex]]
+    var direction = -simplex[0]
+    
+    // Bound the iterations, since rounding can make the search cycle between simplices near the boundary.
+    for _ in 0 ..< 64 {
+        if direction.length < 1e-12 {
+            return nil
+        }
+        
+        let point = support[in: direction]
+        if point.dot(direction) <= 0 {
+            // No collision possible anymore.
+            return nil
+        }
+        
+        simplex.insert(point, at: 0)
+        let added = simplex
+        let encloses = enclose(&simplex, direction: &direction)
+        trace?.simplices.append(CollisionTrace.SimplexIteration(simplex: added, direction: direction))
+        if encloses {
+            return (simplex[0], simplex[1], simplex[2], simplex[3])
+        }
+    }
+    
+    trace?.outcome = .simplexExhausted
+    return nil
+}
+
+
+/// Reduces the simplex to the feature closest to the origin and updates the search direction towards the origin,
+/// or returns whether the simplex is a tetrahedron containing the origin.
+fileprivate func enclose(_ simplex: inout [Point], direction: inout Point) -> Bool {
+    switch simplex.count {
+    case 2:
+        simplex = processLine(simplex[0], simplex[1], direction: &direction)
+    case 3:
+        simplex = processTriangle(simplex[0], simplex[1], simplex[2], direction: &direction)
+    default:
+        guard let reduced = processTetrahedron(simplex[0], simplex[1], simplex[2], simplex[3], direction: &direction) else {
+            return true
+        }
+        simplex = reduced
+    }
+    return false
+}
+
+
+fileprivate func sameDirection(_ a: Point, _ b: Point) -> Bool {
+    a.dot(b) > 0
+}
+
+
+fileprivate func processLine(_ a: Point, _ b: Point, direction: inout Point) -> [Point] {
+    let ao = -a
+    let ab = b - a
+    if sameDirection(ab, ao) {
+        direction = ab.cross(ao).cross(ab)
+        return [a, b]
+    }
+    else {
+        direction = ao
+        return [a]
+    }
+}
+
+
+fileprivate func processTriangle(_ a: Point, _ b: Point, _ c: Point, direction: inout Point) -> [Point] {
+    let ao = -a
+    let ab = b - a
+    let ac = c - a
+    let abc = ab.cross(ac)
+    
+    if sameDirection(abc.cross(ac), ao) {
+        if sameDirection(ac, ao) {
+            direction = ac.cross(ao).cross(ac)
+            return [a, c]
+        }
+        else {
+            return processLine(a, b, direction: &direction)
+        }
+    }
+    else {
+        if sameDirection(ab.cross(abc), ao) {
+            return processLine(a, b, direction: &direction)
+        }
+        else if sameDirection(abc, ao) {
+            direction = abc
+            return [a, b, c]
+        }
+        else {
+            direction = -abc
+            return [a, c, b]
+        }
+    }
+}
+
+
+fileprivate func processTetrahedron(_ a: Point, _ b: Point, _ c: Point, _ d: Point, direction: inout Point) -> [Point]? {
+    let ab = b - a
+    let ac = c - a
+    let ad = d - a
+    let ao = -a
+    
+    let abc = ab.cross(ac)
+    let acd = ac.cross(ad)
+    let adb = ad.cross(ab)
+    
+    if sameDirection(abc, ao) {
+        return processTriangle(a, b, c, direction: &direction)
+    }
+    else if sameDirection(acd, ao) {
+        return processTriangle(a, c, d, direction: &direction)
+    }
+    else if sameDirection(adb, ao) {
+        return processTriangle(a, d, b, direction: &direction)
+    }
+    else {
+        return nil
+    }
+}
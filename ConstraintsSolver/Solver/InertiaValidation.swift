@@ -0,0 +1,103 @@
+//
+//  InertiaValidation.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum InertiaError: LocalizedError {
+    case invalidMass(Double)
+    case invalidInertia(Point)
+    /// No rigid body has a principal moment larger than the sum of the other two.
+    case violatesTriangleInequality(Point)
+    
+    var errorDescription: String? {
+        switch self {
+        case let .invalidMass(mass):
+            return "The mass \(mass) is not a positive number. Static rigids have no mass rather than a zero one."
+        case let .invalidInertia(inertia):
+            return "The principal moments of inertia \(inertia) must all be positive numbers."
+        case let .violatesTriangleInequality(inertia):
+            return "The principal moments of inertia \(inertia) are not physical, since one of them exceeds the sum of the others."
+        }
+    }
+}
+
+
+/// Moments of inertia which are usable, but likely to make the rigid behave badly.
+enum InertiaWarning: CustomStringConvertible {
+    /// The largest principal moment exceeds the smallest one by more than the bounds allow.
+    case anisotropic(Point)
+    
+    var description: String {
+        switch self {
+        case let .anisotropic(inertia):
+            return "The principal moments of inertia \(inertia) are very anisotropic, which may make the rigid spin unstably."
+        }
+    }
+}
+
+
+/// Limits for the principal moments of inertia of new rigids. Invalid ones are rejected right away,
+/// instead of showing up as NaNs much later, and tiny or huge ones are clamped to keep the solver stable.
+struct InertiaBounds {
+    var range = 1e-6 ... 1e6
+    /// Rigids whose largest moment exceeds the smallest one by a larger factor cause a warning,
+    /// since they tend to spin up about their weakest axis.
+    var maxAnisotropy = 1e3
+    /// Called with the warnings about moments which are accepted nonetheless, e.g. to show them in the viewer.
+    var warn: (InertiaWarning) -> Void = { _ in }
+    
+    /// Returns the moments clamped to the range, or throws if they are unusable.
+    func validate(mass: Double, inertia: Point) throws -> Point {
+        guard mass.isFinite && mass > 0 else {
+            throw InertiaError.invalidMass(mass)
+        }
+        let moments = [inertia.ex, inertia.ey, inertia.ez]
+        guard moments.allSatisfy({ $0.isFinite && $0 > 0 }) else {
+            throw InertiaError.invalidInertia(inertia)
+        }
+        // Allow for rounding in moments computed for flat shapes, which meet the inequality exactly.
+        let sum = moments.reduce(0, +)
+        guard moments.allSatisfy({ $0 <= (sum - $0) * (1 + 1e-9) }) else {
+            throw InertiaError.violatesTriangleInequality(inertia)
+        }
+        
+        let clamped = moments.map { $0.clamped(to: range) }
+        if clamped.max()! > maxAnisotropy * clamped.min()! {
+            warn(.anisotropic(inertia))
+        }
+        return Point(clamped[0], clamped[1], clamped[2])
+    }
+}
+
+
+extension Rigid {
+    /// Creates a dynamic rigid with the mass properties of its collider, which are validated and clamped by the bounds.
+    convenience init(collider: Collider, mass: Double, bounds: InertiaBounds) throws {
+        let (centerOfMass, inertia) = Rigid.massProperties(of: collider, mass: mass)
+        try self.init(collider: collider, mass: mass, inertia: inertia, centerOfMass: centerOfMass, bounds: bounds)
+    }
+    
+    /// Creates a dynamic rigid with the given principal moments of inertia about the axes of its local frame,
+    /// which are validated and clamped by the bounds.
+    convenience init(collider: Collider, mass: Double, inertia: Point, bounds: InertiaBounds = InertiaBounds()) throws {
+        let inertia = try bounds.validate(mass: mass, inertia: inertia)
+        self.init(collider: collider, inverseMass: 1 / mass, inverseInertia: Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez))
+    }
+    
+    /// Creates a dynamic rigid with a full inertia tensor about a center of mass, both given in the frame the collider
+    /// is given in. The tensor is diagonalized, and the collider is expressed in the frame of the center of mass
+    /// and the principal axes, which becomes the principal frame of the rigid. Its body frame starts out at the origin.
+    convenience init(collider: Collider, mass: Double, inertia: InertiaTensor, centerOfMass: Point, bounds: InertiaBounds = InertiaBounds()) throws {
+        let principal = inertia.principalAxes
+        let principalFrame = Frame(position: centerOfMass, quaternion: principal.rotation)
+        try self.init(collider: collider.expressed(in: principalFrame), mass: mass, inertia: principal.moments, bounds: bounds)
+        self.principalFrame = principalFrame
+        frame = principalFrame
+        pastFrame = frame
+    }
+}
@@ -8,36 +8,535 @@
 import Foundation
 
 class Solver {
-    let subStepCount: Int
+    var subStepCount: Int
+    /// Overrides the fixed sub-step count depending on the activity of the scene if set.
+    var adaptiveSubSteps: AdaptiveSubSteps?
+    var friction = Friction()
+    let materials = MaterialTable()
+    var lod: SimulationLod?
+    /// Volumes in which rigids are simulated with a scaled time step.
+    var timeDilationVolumes: [TimeDilationVolume] = []
+    var backend = SolverBackend.positionBased
+    var collisionDetection = CollisionDetection.everySubStep
+    /// Skips the pairs of rigids which are too far apart to touch before generating their contacts.
+    var broadPhase = BroadPhase.sweepAndPrune(margin: 0.05)
+    /// Searches the pairs for collision detection once per step on several threads. The result is the same for
+    /// any number of threads, and contacts are still solved in a fixed order, so the simulation stays reproducible.
+    var isBroadphaseConcurrent = false
+    /// Solves the islands of the position based backend on several threads. Each island only writes its own rigids
+    /// and joints, and what it finds is merged in the order of the islands, so the result is the same as on one thread.
+    /// Islands are solved one after the other while continuous collision is enabled, since sweeps look at all rigids.
+    var isIslandSolvingConcurrent = false
+    /// Gives islands which did not converge in the last step extra iterations if set.
+    var iterationBudget: IslandIterationBudget?
+    /// Gives light rigids touching much heavier ones extra iterations if set. Ignored by the sequential impulses backend.
+    var massRatioMitigation: MassRatioMitigation?
+    /// Puts resting islands to sleep if set.
+    var sleeping: Sleeping?
+    var restitutionPropagation = RestitutionPropagation.simultaneous
+    /// Whether contacts bounce as far as the restitution of their rigids asks for, or never.
+    var isRestitutionEnabled = true
+    /// Whether persistent contacts start from the impulses they ended the last sub-step with. Only the sequential
+    /// impulses backend warm starts, which converges worse without it, but also can't overshoot from stale impulses.
+    var isWarmStarting = true
+    /// Integrates the gyroscopic torque of each rigid, which spinning tops need. It is integrated explicitly,
+    /// so fast spins need short sub-steps.
+    var isGyroscopic = false
+    /// How much contacts give under load, in meters per newton. Stiffer contacts pass pushes on more crisply,
+    /// like along a chain of dominos, but jitter more in stacks.
+    var contactCompliance = 1e-6
+    /// Keeps fast rigids from tunneling through thin ones if set.
+    var continuousCollision: ContinuousCollision?
+    /// Iterations per rigid for the next step, as distributed by the budget.
+    private var iterationCounts: [Int] = []
+    /// Pairs of rigids which never collide, checked before generating contacts.
+    var disabledPairs = Set<RigidPair>()
+    private(set) var telemetry = Telemetry()
+    /// Records the phases of every step if set.
+    var trace: StepTrace?
+    /// Records the iterations of GJK and EPA for the convex pairs checked, if set.
+    var collisionTracer: CollisionTracer?
+    /// The largest contact impulse each rigid received during the last step. Only tracked by the position based backend,
+    /// and only if any rigid is fracturable.
+    private(set) var peakContactImpulses: [Double] = []
+    /// Sums up the impulses of every touching pair of rigids during each step, for contact sounds.
+    var tracksContactImpulses = false
+    /// The impulses and speeds of the pairs of rigids touching during the last step, by their indices. Only tracked
+    /// by the position based backend, and only if enabled.
+    private(set) var contactImpulses: [IndexPair: ContactImpulse] = [:]
+    /// Indices of the rigids which were in contact during the last sub-step.
+    private(set) var touchingPairs = Set<IndexPair>()
+    /// The contacts of every pair of rigids touching during the last sub-step, kept to recognize them in the next one.
+    private(set) var manifolds: [RigidPair: ContactManifold] = [:]
+    /// The duration of the sub-step being solved, by which moving surfaces are displaced.
+    private var subStepDuration = 0.0
     
     init(subStepCount: Int) {
         self.subStepCount = subStepCount
     }
     
-    func integrate(_ rigids: [Rigid], by dt: Double) {
+    func integrate(_ rigids: [Rigid], joints: [Joint] = [], mouseJoints: [MouseJoint] = [], by dt: Double) {
+        let startTime = DispatchTime.now()
+        let subStepCount = adaptiveSubSteps?.count(for: rigids, dt: dt, lastPenetration: telemetry.maxPenetration) ?? self.subStepCount
         let subdt = dt / Double(subStepCount)
-        let compliance = 1e-6 / subdt.sq
+        subStepDuration = subdt
+        let compliance = contactCompliance / subdt.sq
+        contactImpulses.removeAll(keepingCapacity: true)
+        var contactCount = 0
+        var contactPairs: [(Int, Int)] = []
+        var sensorPairs: [(Int, Int)] = []
+        var subStepManifolds: [RigidPair: ContactManifold] = [:]
+        var maxPenetration = 0.0
+        var broadPhaseDuration = 0.0
+        var narrowPhaseDuration = 0.0
+        var iterationCount = 0
+        // Islands solved concurrently write the strides and residuals of their own rigids, which the elements of an array
+        // can't be from several threads at once.
+        let strides = UnsafeMutableBufferPointer<Int>.allocate(capacity: rigids.count)
+        let residuals = UnsafeMutableBufferPointer<Double>.allocate(capacity: rigids.count)
+        defer {
+            strides.deallocate()
+            residuals.deallocate()
+        }
+        for (i, rigid) in rigids.enumerated() {
+            strides[i] = rigid.isSleeping ? 0 : lod?.stride(for: rigid) ?? 1
+        }
+        residuals.initialize(repeating: 0)
+        // Rigids with a scaled time are integrated over scaled sub-steps, so their velocities are in their own time.
+        // Contacts are still solved on positions, so a push moves a slowed rigid as far as any other.
+        // Rigids whose time stands still are not integrated at all, and keep their velocities even when pushed.
+        let timeScales = rigids.map(timeScale(for:))
+        if case .sequentialImpulses = backend {
+            strides.initialize(repeating: 1)
+        }
+        
+        func isAwakeAndDynamic(_ i: Int) -> Bool {
+            strides[i] > 0 && rigids[i].inverseMass > 0
+        }
+        
+        let iterations = iterationBudget != nil && iterationCounts.count == rigids.count ? iterationCounts : rigids.map { _ in 1 }
+        
+        let indices = Dictionary(uniqueKeysWithValues: rigids.enumerated().map { (ObjectIdentifier($1), $0) })
+        let tracksImpulses = rigids.contains { $0.fracture != nil }
+        var peakImpulses = [Double](repeating: 0, count: tracksImpulses ? rigids.count : 0)
+        
+        // Contacts of bouncy rigids get their normal velocity reflected after the positions of each sub-step are solved.
+        let bounces = isRestitutionEnabled && rigids.contains { $0.restitution > 0 }
+        var previousVelocities: [(Point, Point)] = []
+        var bouncingContacts: [RestitutionContact] = []
+        
+        // Contacts of rigids resisting rolling brake their angular velocities after the positions of each sub-step.
+        let resistsRolling = friction.isEnabled && rigids.contains { ($0.material?.rollingFriction ?? 0) > 0 }
+        var rollingContacts: [RollingContact] = []
+        
+        func bounce(of constraint: Constraint) -> RestitutionContact? {
+            guard let contact = constraint as? PositionalConstraint,
+                  let a = indices[ObjectIdentifier(contact.rigids.0)], let b = indices[ObjectIdentifier(contact.rigids.1)] else {
+                return nil
+            }
+            // Rigids with a larger stride derive their velocities over several sub-steps, which would undo the bounce.
+            for k in [a, b] where strides[k] != 1 && rigids[k].inverseMass > 0 {
+                return nil
+            }
+            var requiresImpact = true
+            if case .sequential = restitutionPropagation {
+                requiresImpact = false
+            }
+            return RestitutionContact(contact, previousVelocities: (previousVelocities[a], previousVelocities[b]), dt: subdt, requiresImpact: requiresImpact)
+        }
         
-        for _ in 0 ..< subStepCount {
-            for i in rigids.indices {
+        // Corrects a constraint and returns its violation before the correction.
+        @discardableResult
+        func solve(_ constraint: Constraint, remembersRolling: Bool = false, into outcome: inout IslandOutcome) -> Double {
+            let difference = constraint.measure - constraint.targetMeasure
+            var lagrangeFactor = difference / (constraint.inverseResistance + compliance)
+            if let contact = constraint as? PositionalConstraint,
+               let softness = SoftContact.combined(contact.rigids.0.softContact, contact.rigids.1.softContact) {
+                lagrangeFactor = softness.lagrangeFactor(for: contact, depth: difference, compliance: compliance, dt: subdt)
+            }
+            constraint.act(factor: lagrangeFactor)
+            if remembersRolling, let contact = constraint as? PositionalConstraint,
+               let rolling = RollingContact(contact, lagrangeFactor: lagrangeFactor,
+                                            rollingFriction: materials.rollingFriction(between: contact.rigids.0.material, and: contact.rigids.1.material),
+                                            dt: subdt) {
+                outcome.rollingContacts.append(rolling)
+            }
+            if tracksContactImpulses, let contact = constraint as? PositionalConstraint,
+               let a = indices[ObjectIdentifier(contact.rigids.0)], let b = indices[ObjectIdentifier(contact.rigids.1)] {
+                outcome.contactImpulses[IndexPair(a, b), default: ContactImpulse()].impulse += abs(lagrangeFactor) / subdt
+            }
+            if tracksImpulses, let contact = constraint as? PositionalConstraint {
+                // The Lagrange multiplier is a positional impulse, which is applied within the sub-step.
+                let impulse = abs(lagrangeFactor) / subdt
+                for rigid in [contact.rigids.0, contact.rigids.1] {
+                    if let index = indices[ObjectIdentifier(rigid)] {
+                        outcome.peakImpulses[index] = max(outcome.peakImpulses[index] ?? 0, impulse)
+                    }
+                }
+            }
+            return difference
+        }
+        
+        // Generates the contacts of a pair of rigids, timing the narrow phase.
+        func contacts(of rigid: Rigid, and other: Rigid, into outcome: inout IslandOutcome) -> [Constraint] {
+            let start = DispatchTime.now()
+            let constraints = generateConstraints(for: rigid, and: other)
+            outcome.narrowPhaseDuration += seconds(since: start)
+            return constraints
+        }
+        
+        func merge(_ outcome: IslandOutcome) {
+            contactCount += outcome.contactCount
+            contactPairs += outcome.contactPairs
+            sensorPairs += outcome.sensorPairs
+            for (pair, manifold) in outcome.manifolds {
+                subStepManifolds[pair] = manifold
+            }
+            maxPenetration = max(maxPenetration, outcome.maxPenetration)
+            bouncingContacts += outcome.bouncingContacts
+            rollingContacts += outcome.rollingContacts
+            narrowPhaseDuration += outcome.narrowPhaseDuration
+            iterationCount += outcome.iterationCount
+            for (index, impulse) in outcome.peakImpulses {
+                peakImpulses[index] = max(peakImpulses[index], impulse)
+            }
+            for (pair, impulse) in outcome.contactImpulses {
+                contactImpulses[pair, default: ContactImpulse()].merge(impulse)
+            }
+            // Static rigids are only integrated at the start of a sub-step, so they needn't be woken up any earlier.
+            for k in outcome.touchedStatics {
+                strides[k] = max(strides[k], 1)
+            }
+        }
+        
+        // Each joint is solved together with the contacts of the first of its dynamic rigids, which belongs to its island.
+        var ownedJoints = [[Joint]](repeating: [], count: rigids.count)
+        var jointPairs: [(Int, Int)] = []
+        for joint in joints {
+            joint.accumulatedImpulse = 0
+            joint.force = 0
+            let (a, b) = (indices[ObjectIdentifier(joint.rigids.0)], indices[ObjectIdentifier(joint.rigids.1)])
+            // Joints attached to the world only have one rigid in the list. Joints without a dynamic rigid can't move
+            // anything, and static rigids belong to every island they touch, so such a joint would be solved once
+            // per island, possibly on several threads at once.
+            guard let owner = [a, b].compactMap({ $0 }).filter({ rigids[$0].inverseMass > 0 }).min() else {
+                continue
+            }
+            ownedJoints[owner].append(joint)
+            if let a = a, let b = b {
+                jointPairs.append((a, b))
+            }
+        }
+        
+        var draggingJoints = [[MouseJoint]](repeating: [], count: rigids.count)
+        for joint in mouseJoints {
+            if let index = indices[ObjectIdentifier(joint.rigid)] {
+                draggingJoints[index].append(joint)
+                // Dragged rigids stay awake.
+                joint.rigid.wake()
+                strides[index] = max(strides[index], 1)
+            }
+        }
+        
+        // Rigids with a larger stride move over several sub-steps at once, so the boxes cover the whole step.
+        // Rigids in different islands can't touch each other before the next step then.
+        let broadPhaseStart = DispatchTime.now()
+        let stepPartners = broadPhase.partners(of: rigids, within: dt)
+        var candidatePairs: Set<IndexPair>?
+        if collisionDetection == .oncePerStep {
+            candidatePairs = Set(findCandidatePairs(of: rigids, among: stepPartners, within: dt, concurrently: isBroadphaseConcurrent))
+        }
+        broadPhaseDuration += seconds(since: broadPhaseStart)
+        trace?.span("Broad phase", category: "solver", from: broadPhaseStart)
+        let solverIslands = SolverIslands(of: rigids, partners: stepPartners, jointPairs: jointPairs)
+        
+        // Solves the rigids of an island during a sub-step, writing nothing but its own rigids and joints, and its outcome.
+        func solveIsland(_ island: Int, subStep: Int) -> IslandOutcome {
+            let islandStart = DispatchTime.now()
+            var outcome = IslandOutcome()
+            defer {
+                trace?.span("Island", category: "solver", from: islandStart,
+                            args: ["island": Double(island), "rigids": Double(solverIslands.members[island].count), "contacts": Double(outcome.contactCount)])
+            }
+            for i in solverIslands.members[island] {
                 let rigid = rigids[i]
-                rigid.integrateAttitude(by: subdt)
+                // Rigids with a larger stride take one long step covering the following sub-steps.
+                let isStepping = rigid.inverseMass > 0 && strides[i] > 0 && subStep % strides[i] == 0 && timeScales[i] > 0
+                let stepdt = Double(min(strides[i], subStepCount - subStep)) * subdt * timeScales[i]
+                if isStepping {
+                    rigid.integrateAttitude(by: stepdt, isGyroscopic: isGyroscopic)
+                    if let continuousCollision = continuousCollision, rigid.inverseMass > 0 {
+                        continuousCollision.sweep(rigid, against: rigids, except: disabledPairs)
+                    }
+                }
                 
                 var constraints: [Constraint] = []
-                for j in i + 1 ..< rigids.count {
+                var partners: [Int] = []
+                for j in stepPartners[i] where isAwakeAndDynamic(i) || isAwakeAndDynamic(j) {
                     let other = rigids[j]
-                    constraints += generateConstraints(for: rigid, and: other)
+                    // Static rigids are visited once per island touching them, for its own rigids only.
+                    if rigid.inverseMass == 0 && solverIslands.islandOfRigid[j] != island {
+                        continue
+                    }
+                    if candidatePairs?.contains(IndexPair(i, j)) == false {
+                        continue
+                    }
+                    if !disabledPairs.isEmpty && disabledPairs.contains(RigidPair(rigid, other)) {
+                        continue
+                    }
+                    let pairConstraints = contacts(of: rigid, and: other, into: &outcome)
+                    // Sensors only note what overlaps them.
+                    if rigid.isSensor || other.isSensor {
+                        if !pairConstraints.isEmpty {
+                            outcome.sensorPairs.append((i, j))
+                        }
+                        continue
+                    }
+                    if !pairConstraints.isEmpty {
+                        outcome.contactPairs.append((i, j))
+                        partners.append(j)
+                        let pair = RigidPair(rigid, other)
+                        outcome.manifolds.append((pair, ContactManifold(pairConstraints.compactMap { $0 as? PositionalConstraint }, previous: manifolds[pair])))
+                        if tracksContactImpulses, let contact = pairConstraints.first as? PositionalConstraint {
+                            // The velocities are noted before any contact of the pair is solved.
+                            let lower = i < j ? rigid : other
+                            outcome.contactImpulses[IndexPair(i, j), default: ContactImpulse()].note(contact, isFlipped: contact.rigids.0 !== lower)
+                        }
+                        // Touching an active body wakes frozen ones up for the rest of the step.
+                        for k in [i, j] {
+                            if rigids[k].inverseMass > 0 {
+                                strides[k] = max(strides[k], 1)
+                            }
+                            else {
+                                outcome.touchedStatics.append(k)
+                            }
+                        }
+                    }
+                    constraints += pairConstraints
+                }
+                outcome.contactCount += constraints.count
+                if !constraints.isEmpty {
+                    outcome.iterationCount += max(iterations[i], 1)
                 }
                 
                 for constraint in constraints {
-                    let difference = constraint.measure - constraint.targetMeasure
-                    let lagrangeFactor = difference / (constraint.inverseResistance + compliance)
-                    constraint.act(factor: lagrangeFactor)
+                    if bounces, let contact = bounce(of: constraint) {
+                        outcome.bouncingContacts.append(contact)
+                    }
+                    let difference = solve(constraint, remembersRolling: resistsRolling, into: &outcome)
+                    outcome.maxPenetration = max(outcome.maxPenetration, difference)
+                    // Static rigids belong to no island, so their residuals don't count.
+                    if rigid.inverseMass > 0 {
+                        residuals[i] = max(residuals[i], difference)
+                    }
+                }
+                
+                // Further iterations need fresh contacts, since the previous ones refer to the old positions.
+                for _ in 1 ..< max(iterations[i], 1) {
+                    for j in partners {
+                        for constraint in contacts(of: rigid, and: rigids[j], into: &outcome) {
+                            solve(constraint, into: &outcome)
+                        }
+                    }
+                }
+                
+                for joint in ownedJoints[i] {
+                    // The force of the sub-step follows from all multipliers of the joint together.
+                    var subStepFactor = 0.0
+                    for constraint in joint.constraints() as [Constraint] + joint.angularConstraints() {
+                        let lagrangeFactor = (constraint.measure - constraint.targetMeasure) / (constraint.inverseResistance + joint.compliance / subdt.sq + compliance)
+                        constraint.act(factor: lagrangeFactor)
+                        joint.accumulatedImpulse += abs(lagrangeFactor)
+                        subStepFactor += abs(lagrangeFactor)
+                    }
+                    joint.force = max(joint.force, subStepFactor / subdt.sq)
+                }
+                
+                for joint in draggingJoints[i] {
+                    if let constraint = joint.constraint() {
+                        var lagrangeFactor = constraint.measure / (constraint.inverseResistance + joint.compliance / subdt.sq + compliance)
+                        if let maxForce = joint.maxForce {
+                            lagrangeFactor = lagrangeFactor.clamped(to: -maxForce * subdt.sq ... maxForce * subdt.sq)
+                        }
+                        constraint.act(factor: lagrangeFactor)
+                    }
                 }
                 
-                rigid.deriveVelocity(for: subdt)
+                if isStepping {
+                    rigid.deriveVelocity(for: stepdt)
+                }
+            }
+            return outcome
+        }
+        
+        let solverStart = DispatchTime.now()
+        for subStep in 0 ..< subStepCount {
+            let subStepStart = DispatchTime.now()
+            defer {
+                trace?.span("Sub-step", category: "solver", from: subStepStart, args: ["sub-step": Double(subStep)])
+                trace?.counter("Contacts", ["contacts": Double(contactCount), "pairs": Double(contactPairs.count)])
+            }
+            contactCount = 0
+            contactPairs.removeAll(keepingCapacity: true)
+            sensorPairs.removeAll(keepingCapacity: true)
+            subStepManifolds.removeAll(keepingCapacity: true)
+            for i in residuals.indices {
+                residuals[i] = 0
+            }
+            
+            if case let .sequentialImpulses(backendIterations) = backend {
+                let broadPhaseStart = DispatchTime.now()
+                let partners = broadPhase.partners(of: rigids, within: dt)
+                broadPhaseDuration += seconds(since: broadPhaseStart)
+                let result = stepSequentialImpulses(rigids, joints: joints, by: subdt, timeScales: timeScales, iterations: backendIterations,
+                                                    rigidIterations: iterations, partners: partners, candidatePairs: candidatePairs)
+                contactPairs = result.pairs
+                sensorPairs = result.sensorPairs
+                manifolds = result.manifolds
+                contactCount = result.contactCount
+                maxPenetration = max(maxPenetration, result.maxPenetration)
+                for (i, residual) in result.residuals.enumerated() {
+                    residuals[i] = residual
+                }
+                continue
+            }
+            
+            if bounces {
+                previousVelocities = rigids.map { ($0.velocity, $0.angularVelocity) }
+                bouncingContacts.removeAll(keepingCapacity: true)
+            }
+            rollingContacts.removeAll(keepingCapacity: true)
+            
+            // Static and kinematic rigids aren't moved by contacts, so they move ahead of the islands sharing them.
+            for i in rigids.indices where rigids[i].inverseMass == 0 && strides[i] > 0 && subStep % strides[i] == 0 && timeScales[i] > 0 {
+                let stepdt = Double(min(strides[i], subStepCount - subStep)) * subdt * timeScales[i]
+                rigids[i].integrateAttitude(by: stepdt, isGyroscopic: isGyroscopic)
+                rigids[i].deriveVelocity(for: stepdt)
+            }
+            
+            // Islands without any awake dynamic rigid are skipped as a whole.
+            let awakeIslands = solverIslands.members.indices.filter { solverIslands.members[$0].contains(where: isAwakeAndDynamic) }
+            var outcomes = [IslandOutcome](repeating: IslandOutcome(), count: awakeIslands.count)
+            if isIslandSolvingConcurrent && continuousCollision == nil {
+                outcomes.withUnsafeMutableBufferPointer { outcomes in
+                    DispatchQueue.concurrentPerform(iterations: outcomes.count) { k in
+                        outcomes[k] = solveIsland(awakeIslands[k], subStep: subStep)
+                    }
+                }
+            }
+            else {
+                for k in outcomes.indices {
+                    outcomes[k] = solveIsland(awakeIslands[k], subStep: subStep)
+                }
+            }
+            for outcome in outcomes {
+                merge(outcome)
+            }
+            
+            manifolds = subStepManifolds
+            
+            if let mitigation = massRatioMitigation {
+                let pairs = mitigation.schedule(contactPairs, of: rigids)
+                var outcome = IslandOutcome()
+                for _ in 0 ..< (pairs.isEmpty ? 0 : mitigation.extraIterations) {
+                    for (i, j) in pairs {
+                        for constraint in contacts(of: rigids[i], and: rigids[j], into: &outcome) {
+                            solve(constraint, into: &outcome)
+                        }
+                    }
+                }
+                merge(outcome)
+                // The velocities need to include the extra corrections. Rigids with larger strides keep theirs,
+                // since their past frame is older than this sub-step.
+                let corrected = Set(pairs.flatMap { [$0.0, $0.1] })
+                for k in corrected.sorted() where strides[k] == 1 && timeScales[k] > 0 && rigids[k].inverseMass > 0 {
+                    rigids[k].deriveVelocity(for: subdt * timeScales[k])
+                }
+            }
+            
+            // Rigids with a larger stride derive their velocities over several sub-steps, which would undo the braking.
+            for contact in rollingContacts {
+                let isStriding = [contact.rigids.0, contact.rigids.1].contains { rigid in
+                    rigid.inverseMass > 0 && indices[ObjectIdentifier(rigid)].map { strides[$0] != 1 } == true
+                }
+                if !isStriding {
+                    contact.apply()
+                }
+            }
+            
+            switch restitutionPropagation {
+            case .simultaneous:
+                for contact in bouncingContacts {
+                    contact.apply()
+                }
+            case let .sequential(passes):
+                propagateBounces(through: bouncingContacts, passes: passes)
             }
         }
+        
+        let solverDuration = seconds(since: solverStart)
+        
+        touchingPairs = Set((contactPairs + sensorPairs).map { IndexPair($0.0, $0.1) })
+        peakContactImpulses = peakImpulses
+        
+        if let budget = iterationBudget {
+            let islandOfRigid = islands(of: rigids, linkedBy: contactPairs + jointPairs)
+            var islandResiduals: [Int: Double] = [:]
+            for (island, residual) in zip(islandOfRigid, residuals) {
+                if let island = island {
+                    islandResiduals[island] = max(islandResiduals[island] ?? 0, residual)
+                }
+            }
+            iterationCounts = budget.iterations(islands: islandOfRigid, residuals: islandResiduals)
+        }
+        
+        for (rigid, stride) in zip(rigids, strides) {
+            rigid.isFrozen = stride == 0
+            // Sleeping rigids which were touched during the step are awake now.
+            if rigid.isSleeping && stride > 0 {
+                rigid.wake()
+            }
+            rigid.recordPose()
+        }
+        
+        if let sleeping = sleeping, case .positionBased = backend {
+            sleeping.update(rigids, islands: islands(of: rigids, linkedBy: contactPairs + jointPairs), dt: dt)
+        }
+        
+        telemetry.stepDuration = seconds(since: startTime)
+        trace?.span("Integrate", category: "solver", from: startTime, args: ["sub-steps": Double(subStepCount), "bodies": Double(rigids.count)])
+        telemetry.broadPhaseDuration = broadPhaseDuration
+        telemetry.narrowPhaseDuration = narrowPhaseDuration
+        telemetry.solverDuration = solverDuration
+        telemetry.iterationCount = iterationCount
+        telemetry.bodyCount = rigids.count
+        telemetry.subStepCount = subStepCount
+        telemetry.contactCount = contactCount
+        telemetry.islandCount = countIslands(of: rigids, linkedBy: contactPairs + jointPairs)
+        telemetry.kineticEnergy = rigids.reduce(0) { $0 + $1.kineticEnergy }
+        telemetry.maxPenetration = maxPenetration
+        telemetry.frozenCount = strides.filter { $0 == 0 }.count
+    }
+    
+    /// Drops the state kept for a rigid removed from the list, and moves the one of the rigids after it down by one,
+    /// so that the next step doesn't attribute it to the wrong rigids.
+    func removeState(ofRigidAt index: Int) {
+        func shifted(_ pair: IndexPair) -> IndexPair? {
+            if pair.first == index || pair.second == index {
+                return nil
+            }
+            return IndexPair(pair.first > index ? pair.first - 1 : pair.first, pair.second > index ? pair.second - 1 : pair.second)
+        }
+        if index < iterationCounts.count {
+            iterationCounts.remove(at: index)
+        }
+        if index < peakContactImpulses.count {
+            peakContactImpulses.remove(at: index)
+        }
+        touchingPairs = Set(touchingPairs.compactMap(shifted))
+        contactImpulses = Dictionary(uniqueKeysWithValues: contactImpulses.compactMap { pair, impulse in
+            shifted(pair).map { ($0, impulse) }
+        })
     }
     
     func intersect(for rigid: Rigid, and other: Rigid) -> [Constraint]? {
@@ -45,15 +544,79 @@ class Solver {
         case let .box(box):
             switch other.collider {
             case let .plane(plane):
-                return box.intersect(attachedTo: rigid, with: plane, attachedTo: other)
+                return box.intersect(attachedTo: rigid, with: plane, attachedTo: other, friction: contactFriction(for: rigid, and: other))
+            case let .box(otherBox):
+                return box.intersect(attachedTo: rigid, with: otherBox.convex, attachedTo: other, tracer: collisionTracer)
+            case let .mesh(mesh):
+                return box.intersect(attachedTo: rigid, with: mesh, attachedTo: other, friction: contactFriction(for: rigid, and: other))
+            case let .convex(convex):
+                return box.intersect(attachedTo: rigid, with: convex, attachedTo: other, tracer: collisionTracer)
+            case .compound(_):
+                return nil
+            }
+        case let .convex(convex):
+            let volume = PlacedConvexCollider(convex, frame: rigid.frame)
+            switch other.collider {
+            case let .plane(plane):
+                let normal = rigid.frame.quaternion.inverse.act(on: other.frame.quaternion.act(on: plane.normal))
+                return intersectPoints(convex.contactPoints(towards: -normal), margin: 0, attachedTo: rigid, with: plane, attachedTo: other, friction: contactFriction(for: rigid, and: other))
+            case let .mesh(mesh):
+                return intersectPoints(convex.surfacePoints, margin: 0, attachedTo: rigid, with: mesh, attachedTo: other, friction: contactFriction(for: rigid, and: other))
+            case let .box(box):
+                return intersectConvex(volume, attachedTo: rigid, with: PlacedConvexCollider(box.convex, frame: other.frame), attachedTo: other,
+                                       tracer: collisionTracer)
+            case let .convex(otherConvex):
+                return intersectConvex(volume, attachedTo: rigid, with: PlacedConvexCollider(otherConvex, frame: other.frame), attachedTo: other,
+                                       tracer: collisionTracer)
+            case .compound(_):
+                return nil
+            }
+        case let .compound(compound):
+            // Every shape is intersected on its own, each with every shape of the other rigid.
+            let volumes = compound.placed(by: rigid.frame)
+            let otherVolumes: [ConvexVolume]
+            switch other.collider {
+            case let .plane(plane):
+                let normal = rigid.frame.quaternion.inverse.act(on: other.frame.quaternion.act(on: plane.normal))
+                return intersectPoints(compound.contactPoints(towards: -normal), margin: 0, attachedTo: rigid, with: plane, attachedTo: other, friction: contactFriction(for: rigid, and: other))
+            case let .mesh(mesh):
+                return intersectPoints(compound.surfacePoints, margin: 0, attachedTo: rigid, with: mesh, attachedTo: other, friction: contactFriction(for: rigid, and: other))
             case let .box(box):
-                return box.intersect(attachedTo: rigid, with: box, attachedTo: other)
+                otherVolumes = [PlacedConvexCollider(box.convex, frame: other.frame)]
+            case let .convex(convex):
+                otherVolumes = [PlacedConvexCollider(convex, frame: other.frame)]
+            case let .compound(otherCompound):
+                otherVolumes = otherCompound.placed(by: other.frame)
+            }
+            return volumes.flatMap { volume in
+                otherVolumes.flatMap { intersectConvex(volume, attachedTo: rigid, with: $0, attachedTo: other, tracer: collisionTracer) }
             }
-        case .plane(_):
+        case .plane(_), .mesh(_):
             return nil
         }
     }
     
+    func pairFriction(for rigid: Rigid, and other: Rigid) -> Friction {
+        var pairFriction = friction
+        pairFriction.coefficient = materials.friction(between: rigid.material, and: other.material, default: friction.coefficient)
+        pairFriction.staticCoefficient = materials.staticFriction(between: rigid.material, and: other.material,
+                                                                  default: friction.staticCoefficient ?? friction.coefficient)
+        return pairFriction
+    }
+    
+    /// The friction bounding the tangential corrections of contacts, which the velocity backend applies itself instead.
+    /// The rigid touches a plane or mesh of the other one, whose surface may move.
+    private func contactFriction(for rigid: Rigid, and other: Rigid) -> Friction {
+        var contactFriction = pairFriction(for: rigid, and: other)
+        if case .sequentialImpulses = backend {
+            contactFriction.coefficient = 0
+        }
+        else if let surfaceVelocity = other.surfaceVelocity {
+            contactFriction.surfaceDisplacement = subStepDuration * other.frame.quaternion.act(on: surfaceVelocity)
+        }
+        return contactFriction
+    }
+    
     func generateConstraints(for rigid: Rigid, and other: Rigid) -> [Constraint] {
         if let constraints = intersect(for: rigid, and: other) {
             return constraints
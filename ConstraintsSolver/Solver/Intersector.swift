@@ -15,81 +15,6 @@ extension Array where Element == Point {
 }
 
 
-func intersectBoxBox(box: [Point]) -> [(Point, Point)] {
-//    if box.center!.length > sqrt(2) {
-//        return []
-//    }
-    
-    var constraints: [(Point, Point)] = []
-
-    let distance = box.center.length
-    let correction = 2 - distance
-    
-    if correction < 0 {
-        return []
-    }
-    else {
-        let direction = box.center.normalize
-        let a = 1 * direction
-        let b = box.center - 1 * direction
-        return [(b, a)]
-    }
-    
-    // p is inside the box
-//    let a = Point(abs(c.x), abs(c.y), abs(c.z))
-//    let direction: Point
-
-//    // search for smallest vector correcting the penetrating vertex
-//    if a.x > a.y && a.x > a.z {
-//        direction = c.x > 0 ? .ex : -.ex
-//    }
-//    else if a.y > a.x && a.y > a.z {
-//        direction = c.y > 0 ? .ey : -.ey
-//    }
-//    else if a.z > a.y && a.z > a.x {
-//        direction = c.z > 0 ? .ez : -.ez
-//    }
-//    else {
-//        direction = .null
-//    }
-    
-    for p in box {
-        if abs(p.ex) >= 0.5 || abs(p.ey) >= 0.5 || abs(p.ez) >= 0.5 {
-            continue
-        }
-
-        // p is inside the box
-        let a = Point(abs(p.ex), abs(p.ey), abs(p.ez))
-        let correction: Point
-
-        // search for smallest vector correcting the penetrating vertex
-        if a.ex > a.ey && a.ex > a.ez {
-            correction = Point(p.ex > 0 ? 0.5 : -0.5, p.ey, p.ez)
-        }
-        else if a.ey > a.ex && a.ey > a.ez {
-            correction = Point(p.ex, p.ey > 0 ? 0.5 : -0.5, p.ez)
-        }
-        else if a.ez > a.ey && a.ez > a.ex {
-            correction = Point(p.ex, p.ey, p.ez > 0 ? 0.5 : -0.5)
-        }
-        else {
-            correction = .null
-        }
-        
-        constraints.append((p, correction))
-
-//        let constraint = PositionalConstraint(
-//            rigids: rigids,
-//            contacts: (p, p + correction),
-//            targetDistance: 0,
-//            compliance: 0)
-//        constraints.append(constraint)
-    }
-    
-    return constraints
-}
-
-
 //struct MinkowskiDifference {
 //    var points: [Point]
 //    var center: Point
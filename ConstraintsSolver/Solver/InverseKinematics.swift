@@ -0,0 +1,72 @@
+//
+//  InverseKinematics.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A chain of hinges from a base to a tip, for finding the hinge angles which bring the tip to a target.
+/// Everything is given in the frame of the base, in the pose in which all angles are zero.
+/// An angle turns everything after its hinge about its axis, counter-clockwise when seen from the tip of the axis,
+/// which is how joints measure their hinge angle if the rigid closer to the base is the first one.
+struct HingeChain {
+    struct Hinge {
+        /// A point on the axis.
+        var origin: Point
+        var axis: Point
+        var limits: ClosedRange<Double>?
+    }
+    
+    var hinges: [Hinge]
+    var tip: Point
+    
+    /// The frames by which each hinge moves the parts after it, from the base to the tip.
+    private func frames(for angles: [Double]) -> [Frame] {
+        var frame = Frame.identity
+        return zip(hinges, angles).map { hinge, angle in
+            let rotation = Quaternion(by: angle, around: hinge.axis.normalize)
+            frame = frame.act(Frame(position: hinge.origin - rotation.act(on: hinge.origin), quaternion: rotation))
+            return frame
+        }
+    }
+    
+    /// Where the tip is for the given angles.
+    func tipPosition(for angles: [Double]) -> Point {
+        frames(for: angles).last.map { $0.act(tip) } ?? tip
+    }
+    
+    /// Finds angles bringing the tip as close to the target as the limits allow, starting from the given ones,
+    /// by cyclic coordinate descent: each hinge in turn, from the tip to the base, is turned such that the tip points
+    /// at the target as seen from the hinge. Small corrections suffice when tracking a moving target from the current angles.
+    func solve(reaching target: Point, from angles: [Double], iterations: Int = 10, tolerance: Double = 1e-3) -> [Double] {
+        var angles = angles
+        for _ in 0 ..< iterations {
+            for index in hinges.indices.reversed() {
+                // The hinge as moved by the hinges before it.
+                let placement = index > 0 ? frames(for: angles)[index - 1] : .identity
+                let origin = placement.act(hinges[index].origin)
+                let axis = placement.quaternion.act(on: hinges[index].axis.normalize)
+                
+                let toTip = origin.to(tipPosition(for: angles))
+                let toTarget = origin.to(target)
+                let (u, v) = (toTip - toTip.project(onto: axis), toTarget - toTarget.project(onto: axis))
+                if u.length < 1e-9 || v.length < 1e-9 {
+                    continue
+                }
+                var angle = angles[index] + atan2(axis.dot(u.cross(v)), u.dot(v))
+                angle -= 2 * .pi * (angle / (2 * .pi)).rounded()
+                if let limits = hinges[index].limits {
+                    angle = angle.clamped(to: limits)
+                }
+                angles[index] = angle
+            }
+            if tipPosition(for: angles).distance(to: target) < tolerance {
+                break
+            }
+        }
+        return angles
+    }
+}
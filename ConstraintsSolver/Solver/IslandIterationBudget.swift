@@ -0,0 +1,36 @@
+//
+//  IslandIterationBudget.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Shares a fixed number of extra contact iterations among the islands which had the largest remaining
+/// penetration in the last step, such as tall stacks, while converged islands are solved only once per sub-step.
+struct IslandIterationBudget {
+    /// Extra iterations per sub-step distributed among all islands.
+    var extraIterations = 8
+    /// Islands whose deepest penetration stayed below this get no extra iterations.
+    var tolerance = 1e-3
+    var maxIterationsPerIsland = 8
+    
+    /// The number of iterations for each rigid, given the island of each rigid and the deepest penetration of each island.
+    func iterations(islands: [Int?], residuals: [Int: Double]) -> [Int] {
+        let excess = residuals.filter { $0.value > tolerance }
+        // Summed in the order of the islands, since the order of a dictionary differs from process to process.
+        let totalExcess = excess.keys.sorted().reduce(0) { $0 + excess[$1]! }
+        if totalExcess == 0 {
+            return islands.map { _ in 1 }
+        }
+        
+        let islandIterations = excess.mapValues { residual in
+            min(1 + Int(Double(extraIterations) * residual / totalExcess), maxIterationsPerIsland)
+        }
+        return islands.map { island in
+            island.flatMap { islandIterations[$0] } ?? 1
+        }
+    }
+}
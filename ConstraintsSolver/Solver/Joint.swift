@@ -0,0 +1,189 @@
+//
+//  Joint.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum JointKind {
+    /// Holds the anchors together, leaving all rotations free, like a shoulder.
+    case ball
+    /// Keeps the distance between the anchors within the limits, like a rope or a rod.
+    case distance(ClosedRange<Double>)
+    /// Allows rotations about an axis only, like a knee, by also holding together points on the axes
+    /// given in the local frames of both rigids.
+    case hinge(axes: (Point, Point))
+    /// Allows no relative motion at all, keeping the orientation of the first rigid relative to the second one,
+    /// which is given as the rotation from the local frame of the first rigid to the one of the second.
+    case fixed(relativeOrientation: Quaternion)
+    /// Keeps the anchor of the second rigid on a line through the anchor of the first one, along an axis given in
+    /// the local frame of the first rigid, and within the travel along it. Rotations stay free, like a wheel on a strut.
+    case line(axis: Point, travel: ClosedRange<Double>)
+}
+
+
+/// Drives a hinge towards a relative angular speed, with a limited torque.
+struct JointMotor: Codable {
+    /// Angular speed of the second rigid relative to the first one about the hinge axis, in radians per second.
+    var targetSpeed: Double
+    /// Torque in newton meters.
+    var maxTorque: Double
+}
+
+
+/// Connects two rigids at anchors given in their local frames.
+class Joint {
+    let rigids: (Rigid, Rigid)
+    let anchors: (Point, Point)
+    let kind: JointKind
+    /// Zero for a rigid joint, and larger for softer ones.
+    var compliance = 0.0
+    /// Sum of the impulses the joint applied during the last step, which tells how much it is loaded.
+    var accumulatedImpulse = 0.0
+    /// The largest force the constraints of the joint exerted together in any sub-step of the last step, in newtons.
+    var force = 0.0
+    /// The world removes the joint after a step in which its force exceeded this, if set.
+    var breakingForce: Double?
+    /// Bounds of the hinge angle in radians, within -π ... π. Only hinges have an angle.
+    var angleLimits: ClosedRange<Double>?
+    var motor: JointMotor?
+    /// The torque the motor applied during the last step, in newton meters, which is at most its maximum.
+    private(set) var motorTorque = 0.0
+    /// The angle between the reference directions of both rigids which counts as zero,
+    /// which is the one at the creation of the joint.
+    var referenceAngle = 0.0
+    
+    init(_ kind: JointKind, between a: Rigid, at anchorA: Point, and b: Rigid, at anchorB: Point) {
+        self.kind = kind
+        rigids = (a, b)
+        anchors = (anchorA, anchorB)
+        referenceAngle = unreferencedAngle
+    }
+    
+    /// Creates a joint whose anchors are at the same global point on both rigids.
+    convenience init(_ kind: JointKind, between a: Rigid, and b: Rigid, at anchor: Point) {
+        self.init(kind, between: a, at: a.frame.inverse.act(anchor), and: b, at: b.frame.inverse.act(anchor))
+    }
+    
+    /// Creates a joint attaching an anchor of a rigid to a fixed global point, like the pivot of a pendulum.
+    convenience init(_ kind: JointKind, attaching rigid: Rigid, at anchor: Point, toWorldAt point: Point) {
+        self.init(kind, between: rigid, at: anchor, and: Joint.world, at: point)
+    }
+    
+    /// A static rigid at the origin standing for the world itself, which is not part of any world.
+    /// Joints attached to it hold their other rigid in place, and are solved with that rigid.
+    static let world = Rigid(collider: .compound(CompoundCollider(pieces: [])), mass: nil)
+    
+    /// The global axis of a hinge, as attached to the first rigid.
+    var hingeAxis: Point? {
+        guard case let .hinge(axes) = kind else {
+            return nil
+        }
+        return rigids.0.frame.quaternion.act(on: axes.0.normalize)
+    }
+    
+    /// The rotation of the second rigid relative to the first one about the hinge axis, in -π ... π.
+    var angle: Double {
+        let raw = unreferencedAngle - referenceAngle
+        return raw - 2 * .pi * (raw / (2 * .pi)).rounded()
+    }
+    
+    /// The angle between directions perpendicular to the axes of both rigids.
+    private var unreferencedAngle: Double {
+        guard case let .hinge(axes) = kind, let axis = hingeAxis else {
+            return 0
+        }
+        let references = (rigids.0.frame.quaternion.act(on: axes.0.normalize.tangents.0),
+                          rigids.1.frame.quaternion.act(on: axes.1.normalize.tangents.0))
+        let projected = references.1 - references.1.project(onto: axis)
+        return atan2(axis.dot(references.0.cross(projected)), references.0.dot(projected))
+    }
+    
+    /// The constraints needed to keep the hinge angle within its limits.
+    func angularConstraints() -> [AngularConstraint] {
+        guard let limits = angleLimits, let axis = hingeAxis else {
+            return []
+        }
+        let angle = self.angle
+        if limits.contains(angle) {
+            return []
+        }
+        return [AngularConstraint(rigids: rigids, axis: axis, angle: angle, targetAngle: angle.clamped(to: limits))]
+    }
+    
+    /// Adds the torque of the motor for a step of the given duration to both rigids.
+    func applyMotorTorque(for dt: Double) {
+        motorTorque = 0
+        guard let motor = motor, let axis = hingeAxis, dt > 0 else {
+            return
+        }
+        let inverseInertia = rigids.0.applyInverseInertia(axis).dot(axis) + rigids.1.applyInverseInertia(axis).dot(axis)
+        if inverseInertia < 1e-12 {
+            return
+        }
+        let speed = (rigids.1.angularVelocity - rigids.0.angularVelocity).dot(axis)
+        // The torque reaching the target speed within the step, as far as the motor is strong enough.
+        let torque = ((motor.targetSpeed - speed) / (inverseInertia * dt)).clamped(to: -motor.maxTorque ... motor.maxTorque)
+        rigids.0.generatedTorque = rigids.0.generatedTorque - torque * axis
+        rigids.1.generatedTorque = rigids.1.generatedTorque + torque * axis
+        motorTorque = torque
+    }
+    
+    /// Whether the motor applied its maximum torque during the last step, which means it fell short of its target speed.
+    var isMotorSaturated: Bool {
+        guard let motor = motor else {
+            return false
+        }
+        return abs(motorTorque) >= motor.maxTorque
+    }
+    
+    /// Sets the target speed of the motor in proportion to the angle left to a target angle, up to a maximum,
+    /// which turns the motor into a servo holding the hinge at that angle.
+    func steerMotor(towards targetAngle: Double, gain: Double, maxSpeed: Double) {
+        var remaining = targetAngle - angle
+        remaining -= 2 * .pi * (remaining / (2 * .pi)).rounded()
+        motor?.targetSpeed = (gain * remaining).clamped(to: -maxSpeed ... maxSpeed)
+    }
+    
+    /// The constraints needed to restore the joint in the current frames of its rigids.
+    func constraints() -> [PositionalConstraint] {
+        let contacts = (rigids.0.frame.act(anchors.0), rigids.1.frame.act(anchors.1))
+        let distance = contacts.0.distance(to: contacts.1)
+        
+        switch kind {
+        case .ball:
+            return distance > 1e-12 ? [PositionalConstraint(rigids: rigids, contacts: contacts, distance: 0)] : []
+        
+        case let .distance(limits):
+            if limits.contains(distance) || distance < 1e-12 {
+                return []
+            }
+            return [PositionalConstraint(rigids: rigids, contacts: contacts, distance: distance.clamped(to: limits))]
+        
+        case let .hinge(axes):
+            let axisContacts = (rigids.0.frame.act(anchors.0 + axes.0.normalize), rigids.1.frame.act(anchors.1 + axes.1.normalize))
+            return [contacts, axisContacts]
+                .filter { $0.0.distance(to: $0.1) > 1e-12 }
+                .map { PositionalConstraint(rigids: rigids, contacts: $0, distance: 0) }
+        
+        case let .fixed(relativeOrientation):
+            // Holding together the anchors and two points along perpendicular axes also fixes the orientation.
+            let axisContacts = [Point.ex, Point.ey].map { axis in
+                (rigids.0.frame.act(anchors.0 + axis), rigids.1.frame.act(anchors.1 + relativeOrientation.act(on: axis)))
+            }
+            return ([contacts] + axisContacts)
+                .filter { $0.0.distance(to: $0.1) > 1e-12 }
+                .map { PositionalConstraint(rigids: rigids, contacts: $0, distance: 0) }
+        
+        case let .line(axis, travel):
+            let direction = rigids.0.frame.quaternion.act(on: axis.normalize)
+            let offset = contacts.0.to(contacts.1).dot(direction).clamped(to: travel)
+            // The closest allowed point moves along with the first rigid.
+            let target = contacts.0 + offset * direction
+            return target.distance(to: contacts.1) > 1e-12 ? [PositionalConstraint(rigids: rigids, contacts: (target, contacts.1), distance: 0)] : []
+        }
+    }
+}
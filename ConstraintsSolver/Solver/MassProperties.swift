@@ -0,0 +1,180 @@
+//
+//  MassProperties.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A symmetric inertia tensor, given by its diagonal and the entries off it.
+struct InertiaTensor {
+    var diagonal: Point
+    /// The entries at `(y, z)`, `(x, z)` and `(x, y)`, which are the negated products of inertia.
+    var offDiagonal: Point
+    
+    static let zero = InertiaTensor(diagonal: .null)
+    
+    init(diagonal: Point, offDiagonal: Point = .null) {
+        self.diagonal = diagonal
+        self.offDiagonal = offDiagonal
+    }
+    
+    /// The tensor of a symmetric linear map, given by applying it.
+    init(applying map: (Point) -> Point) {
+        let (x, y, z) = (map(.ex), map(.ey), map(.ez))
+        diagonal = Point(x.ex, y.ey, z.ez)
+        offDiagonal = Point(z.ey, z.ex, y.ex)
+    }
+    
+    /// The inertia of a point mass at an offset, which moves a tensor about the center of mass to the point
+    /// the offset is taken from when added to it, by the parallel axis theorem.
+    init(pointMass mass: Double, at offset: Point) {
+        self.init(applying: { mass * (offset.dot(offset) * $0 - offset.dot($0) * offset) })
+    }
+    
+    func apply(_ v: Point) -> Point {
+        Point(diagonal.ex * v.ex + offDiagonal.ez * v.ey + offDiagonal.ey * v.ez,
+              offDiagonal.ez * v.ex + diagonal.ey * v.ey + offDiagonal.ex * v.ez,
+              offDiagonal.ey * v.ex + offDiagonal.ex * v.ey + diagonal.ez * v.ez)
+    }
+    
+    /// The tensor of the body rotated by the quaternion.
+    func rotated(by rotation: Quaternion) -> InertiaTensor {
+        InertiaTensor(applying: { rotation.act(on: apply(rotation.inverse.act(on: $0))) })
+    }
+    
+    static func +(lhs: InertiaTensor, rhs: InertiaTensor) -> InertiaTensor {
+        InertiaTensor(diagonal: lhs.diagonal + rhs.diagonal, offDiagonal: lhs.offDiagonal + rhs.offDiagonal)
+    }
+    
+    /// The principal moments, and the rotation from the principal axes to the axes the tensor is given in.
+    /// Found by Jacobi rotations, each zeroing the largest entry off the diagonal by rotating about the remaining axis.
+    var principalAxes: (moments: Point, rotation: Quaternion) {
+        let axes = [Point.ex, Point.ey, Point.ez]
+        let trace = diagonal.ex + diagonal.ey + diagonal.ez
+        var rotation = Quaternion.identity
+        for _ in 0 ..< 32 {
+            let local = rotated(by: rotation.inverse)
+            let off = local.offDiagonal
+            if off.length <= 1e-12 * trace {
+                break
+            }
+            // The entries with the indices of their row, column and remaining axis, which are in cyclic order.
+            let entries = [(off.ez, 0, 1, 2), (off.ex, 1, 2, 0), (off.ey, 2, 0, 1)]
+            let (entry, p, q, k) = entries.max { abs($0.0) < abs($1.0) }!
+            let moments = [local.diagonal.ex, local.diagonal.ey, local.diagonal.ez]
+            rotation = rotation * Quaternion(by: 0.5 * atan2(2 * entry, moments[p] - moments[q]), around: axes[k])
+        }
+        return (rotated(by: rotation.inverse).diagonal, rotation)
+    }
+}
+
+
+/// The mass, center of mass and principal moments of inertia of a collider, computed from its shape.
+struct MassProperties {
+    let mass: Double
+    /// The principal moments of inertia about the center of mass.
+    let inertia: Point
+    /// The center of mass and the principal axes relative to the frame the collider is given in. Rigids need
+    /// their origin at the center of mass and their axes along the principal ones, so this is their frame
+    /// when the collider is placed at the origin.
+    let frame: Frame
+}
+
+
+extension ConvexCollider {
+    /// The center of mass in the local frame, which is the origin for all but hulls given with any other origin.
+    var centerOfMass: Point {
+        if case let .hull(piece) = self {
+            return VolumeProperties(of: [piece]).center
+        }
+        return .null
+    }
+    
+    /// The inertia tensor about the center of mass for the given mass.
+    func tensor(mass: Double) -> InertiaTensor {
+        if case let .hull(piece) = self {
+            return VolumeProperties(of: [piece]).tensor(mass: mass)
+        }
+        return InertiaTensor(diagonal: inertia(mass: mass))
+    }
+}
+
+
+extension Collider {
+    /// The mass properties of the collider for a uniform density in kilograms per cubic meter. Polyhedra are integrated
+    /// exactly over the tetrahedra spanned by their faces, and the shapes of compounds are weighted by their own density
+    /// relative to this one. Returns `nil` for unbounded colliders and those without volume.
+    func massProperties(density: Double) -> MassProperties? {
+        let mass: Double
+        let center: Point
+        let tensor: InertiaTensor
+        switch self {
+        case .plane(_), .mesh(_):
+            return nil
+        case .box(_):
+            mass = density
+            center = .null
+            tensor = ConvexCollider.box(halfExtents: Point(0.5)).tensor(mass: mass)
+        case let .compound(compound):
+            mass = density * compound.shapes.reduce(0) { $0 + $1.density * $1.collider.volume }
+            let properties = compound.massProperties(mass: mass)
+            center = properties.center
+            tensor = properties.inertia
+        case let .convex(convex):
+            mass = density * convex.volume
+            center = convex.centerOfMass
+            tensor = convex.tensor(mass: mass)
+        }
+        guard mass > 0 else {
+            return nil
+        }
+        
+        let principal = tensor.principalAxes
+        return MassProperties(mass: mass, inertia: principal.moments, frame: Frame(position: center, quaternion: principal.rotation))
+    }
+    
+    /// The collider expressed in a frame given relative to its own. Shapes which are always centered on their origin
+    /// become a compound of the shape placed in that frame, unless the frame is the identity. Static colliders stay as they are.
+    func expressed(in frame: Frame) -> Collider {
+        if frame.position.length < 1e-12 && abs(frame.quaternion.scalar) > 1 - 1e-12 {
+            return self
+        }
+        let inverse = frame.inverse
+        switch self {
+        case .plane(_), .mesh(_):
+            return self
+        case let .box(box):
+            return .compound(CompoundCollider(shapes: [CompoundShape(collider: box.convex, frame: inverse)]))
+        case let .compound(compound):
+            return .compound(CompoundCollider(shapes: compound.shapes.map { shape in
+                CompoundShape(collider: shape.collider, frame: inverse.act(shape.frame), density: shape.density)
+            }))
+        case let .convex(.hull(piece)):
+            return .convex(.hull(piece.transformed(by: inverse)))
+        case let .convex(convex):
+            return .compound(CompoundCollider(shapes: [CompoundShape(collider: convex, frame: inverse)]))
+        }
+    }
+}
+
+
+extension Rigid {
+    /// Creates a dynamic rigid whose mass and inertia follow from the shape of the collider and a uniform density,
+    /// or returns `nil` if the collider has no volume. The collider is expressed in the frame of the center of mass
+    /// and the principal axes, which becomes the principal frame of the rigid. Its body frame starts out at the origin,
+    /// and is where the collider as given is placed.
+    convenience init?(collider: Collider, density: Double) {
+        guard let properties = collider.massProperties(density: density) else {
+            return nil
+        }
+        let inertia = properties.inertia
+        self.init(collider: collider.expressed(in: properties.frame), inverseMass: 1 / properties.mass,
+                  inverseInertia: Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez))
+        principalFrame = properties.frame
+        frame = properties.frame
+        pastFrame = frame
+    }
+}
@@ -0,0 +1,43 @@
+//
+//  MassRatioMitigation.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Keeps heavy rigids from squashing light ones into their supports.
+/// Gauss-Seidel iterations converge slowly for large mass ratios, since each contact of the light rigid
+/// undoes much of the others. Light rigids touching much heavier ones therefore get extra iterations,
+/// in which their contacts with the heaviest partners, like the ground, are solved last and have the final say.
+struct MassRatioMitigation {
+    /// Pairs of dynamic rigids whose masses differ by a larger factor are flagged.
+    var threshold = 10.0
+    var extraIterations = 4
+    
+    func isFlagged(_ rigid: Rigid, _ other: Rigid) -> Bool {
+        // Static rigids are supports, and cannot be squashed.
+        if rigid.inverseMass == 0 || other.inverseMass == 0 {
+            return false
+        }
+        return max(rigid.inverseMass, other.inverseMass) > threshold * min(rigid.inverseMass, other.inverseMass)
+    }
+    
+    /// The touching pairs to solve again, which are all those involving the lighter rigid of a flagged pair,
+    /// ordered by the inverse mass of the heavier rigid in the pair, decreasingly.
+    func schedule(_ pairs: [(Int, Int)], of rigids: [Rigid]) -> [(Int, Int)] {
+        var lightRigids = Set<Int>()
+        for (i, j) in pairs where isFlagged(rigids[i], rigids[j]) {
+            lightRigids.insert(rigids[i].inverseMass > rigids[j].inverseMass ? i : j)
+        }
+        if lightRigids.isEmpty {
+            return []
+        }
+        
+        return pairs
+            .filter { lightRigids.contains($0.0) || lightRigids.contains($0.1) }
+            .sorted { min(rigids[$0.0].inverseMass, rigids[$0.1].inverseMass) > min(rigids[$1.0].inverseMass, rigids[$1.1].inverseMass) }
+    }
+}
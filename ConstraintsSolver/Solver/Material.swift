@@ -0,0 +1,78 @@
+//
+//  Material.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 19.04.21.
+//
+
+import Foundation
+
+
+/// Surface properties of a rigid, identified by name for lookups in a `MaterialTable`.
+struct Material: Codable {
+    let name: String
+    var friction: Double
+    /// The static friction coefficient, which is the same as the kinetic one if `nil`.
+    var staticFriction: Double?
+    /// The lever arm in meters by which the normal force of a contact resists rolling, which slows down balls
+    /// and wheels rolling on it. None if `nil`. Only the position based backend applies it.
+    var rollingFriction: Double?
+    
+    static let rubber = Material(name: "rubber", friction: 1.2, staticFriction: 1.5)
+    static let ice = Material(name: "ice", friction: 0.05)
+    static let wood = Material(name: "wood", friction: 0.5, staticFriction: 0.6)
+    static let metal = Material(name: "metal", friction: 0.4)
+}
+
+
+/// Contact coefficients for specific combinations of materials, overriding the ones combined from both materials.
+/// That is how physics feel is usually tuned: rubber on ice is slippery even though rubber alone is grippy.
+class MaterialTable {
+    /// The coefficients of a pair of materials.
+    struct Override: Codable {
+        var friction: Double
+        /// The static coefficient, which is combined from the static coefficients of the materials if `nil`.
+        var staticFriction: Double?
+    }
+    
+    private var frictions: [Set<String>: Override] = [:]
+    
+    func setFriction(_ friction: Double, staticFriction: Double? = nil, between a: String, and b: String) {
+        frictions[[a, b]] = Override(friction: friction, staticFriction: staticFriction)
+    }
+    
+    func removeFriction(between a: String, and b: String) {
+        frictions[[a, b]] = nil
+    }
+    
+    /// Every pair with its coefficients, by the names of its materials in order, and sorted by them.
+    /// A material paired with itself only has its name once.
+    var pairs: [(materials: [String], coefficients: Override)] {
+        frictions.map { ($0.key.sorted(), $0.value) }.sorted { $0.materials.lexicographicallyPrecedes($1.materials) }
+    }
+    
+    /// The friction coefficient for a contact between two rigids.
+    /// Without an entry for the pair, the geometric mean of both materials is used,
+    /// where rigids without material contribute the given default.
+    func friction(between a: Material?, and b: Material?, default defaultFriction: Double) -> Double {
+        if let a = a, let b = b, let override = frictions[[a.name, b.name]] {
+            return override.friction
+        }
+        return ((a?.friction ?? defaultFriction) * (b?.friction ?? defaultFriction)).squareRoot()
+    }
+    
+    /// The static friction coefficient for a contact between two rigids, combined like the kinetic one.
+    /// An entry for the pair without a static coefficient only overrides the kinetic one.
+    func staticFriction(between a: Material?, and b: Material?, default defaultFriction: Double) -> Double {
+        if let a = a, let b = b, let friction = frictions[[a.name, b.name]]?.staticFriction {
+            return friction
+        }
+        let (staticA, staticB) = (a.map { $0.staticFriction ?? $0.friction }, b.map { $0.staticFriction ?? $0.friction })
+        return ((staticA ?? defaultFriction) * (staticB ?? defaultFriction)).squareRoot()
+    }
+    
+    /// The rolling friction for a contact between two rigids, which rolls as badly as the worse rolling one of them.
+    func rollingFriction(between a: Material?, and b: Material?) -> Double {
+        max(a?.rollingFriction ?? 0, b?.rollingFriction ?? 0)
+    }
+}
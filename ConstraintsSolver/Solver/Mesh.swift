@@ -1,8 +1,16 @@
 import Foundation
+import simd
+
+/// A corner of a mesh triangle, laid out like `Vertex` in the shaders, so that renderers can upload meshes as they are.
+struct MeshVertex {
+    var position: simd_float3
+    var normal: simd_float3
+    var color: simd_float3
+}
 
 class Mesh {
     let name: String
-    var vertices: [Vertex] = []
+    var vertices: [MeshVertex] = []
     var transform = simd_float4x4(1)
     
     init(name: String) {
@@ -32,12 +40,12 @@ class Mesh {
     static func makeTriangle(name: String, colors: (Color, Color, Color)) -> Mesh {
         let builder = Mesh(name: name).builder()
         
-        builder.push(Vertex(position: simd_float3(-1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.0.rgb))
-        builder.push(Vertex(position: simd_float3(1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.1.rgb))
-        builder.push(Vertex(position: simd_float3(0, 0, 1), normal: simd_float3(0, -1, 0), color: colors.2.rgb))
-        builder.push(Vertex(position: simd_float3(-1, 0, -1), normal: simd_float3(0, 1, 0), color: colors.0.rgb))
-        builder.push(Vertex(position: simd_float3(0, 0, 1), normal: simd_float3(0, -1, 0), color: colors.2.rgb))
-        builder.push(Vertex(position: simd_float3(1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.1.rgb))
+        builder.push(MeshVertex(position: simd_float3(-1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.0.rgb))
+        builder.push(MeshVertex(position: simd_float3(1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.1.rgb))
+        builder.push(MeshVertex(position: simd_float3(0, 0, 1), normal: simd_float3(0, -1, 0), color: colors.2.rgb))
+        builder.push(MeshVertex(position: simd_float3(-1, 0, -1), normal: simd_float3(0, 1, 0), color: colors.0.rgb))
+        builder.push(MeshVertex(position: simd_float3(0, 0, 1), normal: simd_float3(0, -1, 0), color: colors.2.rgb))
+        builder.push(MeshVertex(position: simd_float3(1, 0, -1), normal: simd_float3(0, -1, 0), color: colors.1.rgb))
         
         return builder.mesh
     }
@@ -51,6 +59,33 @@ class Mesh {
         return builder.mesh
     }
     
+    /// Makes a double-sided vertical rectangle standing on the line from `a` to `b`.
+    static func makeWall(name: String, from a: simd_float3, to b: simd_float3, height: Float, color: Color) -> Mesh {
+        let builder = Mesh(name: name).builder()
+        let up = simd_float3(0, 0, height)
+        
+        builder.push(a, b, b + up, color: color)
+        builder.push(a, b + up, a + up, color: color)
+        builder.push(a, b + up, b, color: color)
+        builder.push(a, a + up, b + up, color: color)
+        
+        return builder.mesh
+    }
+    
+    /// Makes a mesh from indexed triangles, which are also pushed in reverse winding if double-sided.
+    static func makeTriangles(name: String, vertices: [simd_float3], triangles: [(Int, Int, Int)], isDoubleSided: Bool, color: Color) -> Mesh {
+        let builder = Mesh(name: name).builder()
+        
+        for (a, b, c) in triangles {
+            builder.push(vertices[a], vertices[b], vertices[c], color: color)
+            if isDoubleSided {
+                builder.push(vertices[a], vertices[c], vertices[b], color: color)
+            }
+        }
+        
+        return builder.mesh
+    }
+    
     static func makeCube(name: String, color: Color) -> Mesh {
         let builder = Mesh(name: name).builder()
         
@@ -83,14 +118,14 @@ fileprivate class MeshBuilder {
         self.mesh = mesh
     }
     
-    func push(_ vertex: Vertex) {
+    func push(_ vertex: MeshVertex) {
         mesh.vertices.append(vertex)
     }
     
     func push(_ a: simd_float3, _ b: simd_float3, _ c: simd_float3, color: Color) {
         let normal = normalize(cross(b - a, c - a))
-        push(Vertex(position: a, normal: normal, color: color.rgb))
-        push(Vertex(position: b, normal: normal, color: color.rgb))
-        push(Vertex(position: c, normal: normal, color: color.rgb))
+        push(MeshVertex(position: a, normal: normal, color: color.rgb))
+        push(MeshVertex(position: b, normal: normal, color: color.rgb))
+        push(MeshVertex(position: c, normal: normal, color: color.rgb))
     }
 }
@@ -0,0 +1,50 @@
+//
+//  MouseJoint.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Pulls an anchor of a rigid towards a target point which can be moved freely, like a hand dragging the rigid
+/// with the mouse. It is solved with the joints of the rigid, but only by the position based backend.
+class MouseJoint {
+    let rigid: Rigid
+    /// The grabbed point in the local frame of the rigid.
+    let anchor: Point
+    /// The global point the anchor is pulled towards.
+    var target: Point
+    /// Meters per newton of pull, which keeps heavy rigids from being yanked around instantly.
+    var compliance = 1e-3
+    /// The pull is limited to this many newtons, if set, which must not be negative.
+    var maxForce: Double? {
+        didSet {
+            precondition(maxForce.map { $0 >= 0 } ?? true, "The maximum force of a mouse joint must not be negative")
+        }
+    }
+    
+    /// Grabs a rigid at a global point, which is also the initial target.
+    init(grabbing rigid: Rigid, at point: Point, maxForce: Double? = nil) {
+        precondition(maxForce.map { $0 >= 0 } ?? true, "The maximum force of a mouse joint must not be negative")
+        self.rigid = rigid
+        anchor = rigid.frame.inverse.act(point)
+        target = point
+        self.maxForce = maxForce
+    }
+    
+    /// The global position of the anchor.
+    var position: Point {
+        rigid.frame.act(anchor)
+    }
+    
+    /// The constraint pulling the anchor onto the target, which is held by the world.
+    func constraint() -> PositionalConstraint? {
+        let position = self.position
+        if position.distance(to: target) < 1e-12 {
+            return nil
+        }
+        return PositionalConstraint(rigids: (rigid, Joint.world), contacts: (position, target), distance: 0)
+    }
+}
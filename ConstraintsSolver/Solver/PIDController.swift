@@ -0,0 +1,45 @@
+//
+//  PIDController.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A proportional-integral-derivative controller, which turns the error of a measured quantity into a correction,
+/// once per step. The integral is bounded, so that it doesn't wind up while the correction can't be followed.
+struct PIDController {
+    var proportionalGain: Double
+    var integralGain: Double
+    var derivativeGain: Double
+    /// Bound of the integrated error, in units of the error times seconds.
+    var integralLimit: Double
+    private(set) var integral = 0.0
+    private var previousError: Double?
+    
+    init(proportionalGain: Double, integralGain: Double = 0, derivativeGain: Double = 0, integralLimit: Double = .infinity) {
+        self.proportionalGain = proportionalGain
+        self.integralGain = integralGain
+        self.derivativeGain = derivativeGain
+        self.integralLimit = integralLimit
+    }
+    
+    /// The correction for the error measured after a step of the given duration.
+    /// The first update has no derivative, since there is no previous error to compare with.
+    mutating func update(error: Double, dt: Double) -> Double {
+        guard dt > 0 else {
+            return proportionalGain * error
+        }
+        integral = (integral + error * dt).clamped(to: -integralLimit ... integralLimit)
+        let derivative = previousError.map { (error - $0) / dt } ?? 0
+        previousError = error
+        return proportionalGain * error + integralGain * integral + derivativeGain * derivative
+    }
+    
+    mutating func reset() {
+        integral = 0
+        previousError = nil
+    }
+}
@@ -0,0 +1,97 @@
+//
+//  PhysicsEvent.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// The surface of one of the rigids of a contact, as gameplay tells them apart.
+struct ContactSurface: Equatable {
+    /// The name of the material of the rigid, if it has one.
+    let material: String?
+    let userTag: Int?
+    
+    init(of rigid: Rigid) {
+        material = rigid.material?.name
+        userTag = rigid.userTag
+    }
+}
+
+
+/// Where and between what two rigids started touching, so that gameplay can tell that metal hit wood at a point
+/// without looking the rigids up.
+struct ContactInfo: Equatable {
+    /// The surfaces of both rigids, in the order of their handles.
+    let surfaces: (ContactSurface, ContactSurface)
+    /// A point halfway between both surfaces, or `nil` for sensors and pairs without contact points.
+    let point: Point?
+    /// The direction the first rigid is pushed into, or `nil` along with the point.
+    let normal: Point?
+    
+    static func == (lhs: ContactInfo, rhs: ContactInfo) -> Bool {
+        lhs.surfaces == rhs.surfaces && lhs.point == rhs.point && lhs.normal == rhs.normal
+    }
+}
+
+
+enum PhysicsEvent: Equatable {
+    case contactBegan(RigidHandle, RigidHandle, ContactInfo)
+    case contactEnded(RigidHandle, RigidHandle)
+    /// A contact which persisted started resting, sliding or rolling, as told by the classifier of the world.
+    case contactMotionChanged(RigidHandle, RigidHandle, ContactMotion)
+    /// The simulation level of detail stopped simulating the rigid.
+    case froze(RigidHandle)
+    case woke(RigidHandle)
+    /// The rigid broke into fragments, the first of which took over its handle.
+    case fractured(RigidHandle, fragments: [RigidHandle])
+    /// A joint was removed since it exceeded its breaking force. Rigids without a handle stand for the world.
+    case jointBroke(RigidHandle?, RigidHandle?)
+}
+
+
+/// The step at whose end events occurred, which lets gameplay logic and logs refer to physics steps
+/// rather than to rendered frames.
+struct StepStamp: Equatable {
+    /// Number of steps taken before, starting at zero.
+    let step: Int
+    /// Simulated time in seconds at the end of the step.
+    let time: Double
+}
+
+
+/// Delivers the events of every step to a handler on a queue of its choice, so that they can be consumed
+/// on another thread without polling the world.
+class EventChannel {
+    let queue: DispatchQueue
+    private let handler: ([PhysicsEvent], StepStamp) -> Void
+    
+    init(queue: DispatchQueue, handler: @escaping ([PhysicsEvent], StepStamp) -> Void) {
+        self.queue = queue
+        self.handler = handler
+    }
+    
+    func send(_ events: [PhysicsEvent], stamp: StepStamp) {
+        queue.async { [handler] in
+            handler(events, stamp)
+        }
+    }
+}
+
+
+/// Two rigid indices in ascending order.
+struct IndexPair: Hashable, Comparable {
+    let first: Int
+    let second: Int
+    
+    init(_ a: Int, _ b: Int) {
+        first = min(a, b)
+        second = max(a, b)
+    }
+    
+    static func < (lhs: IndexPair, rhs: IndexPair) -> Bool {
+        (lhs.first, lhs.second) < (rhs.first, rhs.second)
+    }
+}
@@ -6,6 +6,7 @@
 //
 
 import Foundation
+import simd
 
 
 infix operator .*: MultiplicationPrecedence
@@ -19,41 +20,41 @@ extension Double {
 
 
 /// A point in 3-D Euclidean frame.
-struct Point {
-    var ex: Double
-    var ey: Double
-    var ez: Double
+public struct Point {
+    public var ex: Double
+    public var ey: Double
+    public var ez: Double
     
-    static let null = Point(0, 0, 0)
-    static let ex = Point(1, 0, 0)
-    static let ey = Point(0, 1, 0)
-    static let ez = Point(0, 0, 1)
+    public static let null = Point(0, 0, 0)
+    public static let ex = Point(1, 0, 0)
+    public static let ey = Point(0, 1, 0)
+    public static let ez = Point(0, 0, 1)
     
-    init(_ scalar: Double) {
+    public init(_ scalar: Double) {
         ex = scalar
         ey = scalar
         ez = scalar
     }
     
-    init(_ ex: Double, _ ey: Double, _ ez: Double) {
+    public init(_ ex: Double, _ ey: Double, _ ez: Double) {
         self.ex = ex
         self.ey = ey
         self.ez = ez
     }
     
-    static func +(rhs: Point, lhs: Point) -> Point {
+    public static func +(rhs: Point, lhs: Point) -> Point {
         Point(rhs.ex + lhs.ex, rhs.ey + lhs.ey, rhs.ez + lhs.ez)
     }
     
-    static func -(rhs: Point, lhs: Point) -> Point {
+    public static func -(rhs: Point, lhs: Point) -> Point {
         Point(rhs.ex - lhs.ex, rhs.ey - lhs.ey, rhs.ez - lhs.ez)
     }
     
-    static prefix func -(lhs: Point) -> Point {
+    public static prefix func -(lhs: Point) -> Point {
         Point(-lhs.ex, -lhs.ey, -lhs.ez)
     }
     
-    static func *(scalar: Double, lhs: Point) -> Point {
+    public static func *(scalar: Double, lhs: Point) -> Point {
         Point(scalar * lhs.ex, scalar * lhs.ey, scalar * lhs.ez)
     }
     
@@ -80,23 +81,23 @@ struct Point {
         (1 / dt) * (self - past)
     }
     
-    var normalize: Point {
+    public var normalize: Point {
         (1 / length) * self
     }
     
-    var length: Double {
+    public var length: Double {
         (ex.sq + ey.sq + ez.sq).squareRoot()
     }
     
-    func distance(to rhs: Point) -> Double {
+    public func distance(to rhs: Point) -> Double {
         (rhs - self).length
     }
     
-    func dot(_ rhs: Point) -> Double {
+    public func dot(_ rhs: Point) -> Double {
         ex * rhs.ex + ey * rhs.ey + ez * rhs.ez
     }
     
-    func cross(_ rhs: Point) -> Point {
+    public func cross(_ rhs: Point) -> Point {
         Point(
             ey * rhs.ez - ez * rhs.ey,
             ez * rhs.ex - ex * rhs.ez,
@@ -120,6 +121,13 @@ struct Point {
         project(onto: plane).to(self)
     }
     
+    /// Two unit vectors orthogonal to this unit vector and to each other.
+    var tangents: (Point, Point) {
+        let helper = abs(ex) < 0.9 ? Point.ex : Point.ey
+        let u = cross(helper).normalize
+        return (u, cross(u))
+    }
+    
     func rotate(by angle: Double, around axis: Point) -> Point {
         let c = cos(angle)
         let s = sin(angle)
@@ -149,7 +157,23 @@ struct Point {
 }
 
 extension Point: CustomDebugStringConvertible {
-    var debugDescription: String {
+    public var debugDescription: String {
         "(\(ex), \(ey), \(ey))"
     }
 }
+
+extension Point: Codable {}
+
+extension Point: Equatable {}
+
+extension simd_float3 {
+    init(_ point: Point) {
+        self.init(Float(point.ex), Float(point.ey), Float(point.ez))
+    }
+}
+
+extension Comparable {
+    func clamped(to range: ClosedRange<Self>) -> Self {
+        min(max(self, range.lowerBound), range.upperBound)
+    }
+}
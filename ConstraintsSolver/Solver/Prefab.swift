@@ -0,0 +1,162 @@
+//
+//  Prefab.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A reusable assembly of rigids and joints with poses relative to the assembly, such as a ragdoll or a vehicle,
+/// which can be instantiated many times with `World.instantiate(_:at:)`.
+struct Prefab: Codable {
+    struct Body: Codable {
+        var collider: Collider
+        /// Static if `nil`.
+        var mass: Double?
+        /// Relative to the frame the prefab is instantiated at.
+        var frame: Frame
+        var material: Material?
+        var groups = CollisionGroups.default.rawValue
+    }
+    
+    struct JointDescription: Codable {
+        var kind: JointKind
+        /// Indices of the two bodies.
+        var rigids: [Int]
+        /// Attachment points in the local frames of the two bodies.
+        var anchors: [Point]
+        var compliance = 0.0
+        var disablesCollision = true
+    }
+    
+    var bodies: [Body] = []
+    var joints: [JointDescription] = []
+    
+    /// Adds a body and returns its index, by which joints refer to it.
+    @discardableResult
+    mutating func addBody(_ collider: Collider, mass: Double?, at frame: Frame) -> Int {
+        bodies.append(Body(collider: collider, mass: mass, frame: frame))
+        return bodies.count - 1
+    }
+    
+    /// Adds a joint whose anchors are at the same point on both bodies, given relative to the prefab.
+    mutating func addJoint(_ kind: JointKind, between a: Int, and b: Int, at anchor: Point, compliance: Double = 0, disablesCollision: Bool = true) {
+        joints.append(JointDescription(kind: kind,
+                                       rigids: [a, b],
+                                       anchors: [bodies[a].frame.inverse.act(anchor), bodies[b].frame.inverse.act(anchor)],
+                                       compliance: compliance,
+                                       disablesCollision: disablesCollision))
+    }
+    
+    func encoded() throws -> Data {
+        let encoder = JSONEncoder()
+        encoder.outputFormatting = [.prettyPrinted, .sortedKeys]
+        return try encoder.encode(self)
+    }
+    
+    init() {}
+    
+    init(data: Data) throws {
+        self = try JSONDecoder().decode(Prefab.self, from: data)
+    }
+    
+    /// A chain of cubes hanging from a static anchor cube by ball joints, which starts off horizontally.
+    static func chain(linkCount: Int) -> Prefab {
+        var prefab = Prefab()
+        var previous = prefab.addBody(.box(BoxCollider()), mass: nil, at: Frame())
+        for link in 1 ... linkCount {
+            let current = prefab.addBody(.box(BoxCollider()), mass: 1, at: Frame(position: 1.5 * Double(link) * .ex))
+            prefab.addJoint(.ball, between: previous, and: current, at: (1.5 * Double(link) - 0.75) * .ex)
+            previous = current
+        }
+        return prefab
+    }
+    
+    /// A plank along the x axis made of box segments held together by compliant fixed joints, which bends under load
+    /// like a diving board or a rope bridge. The stiffness is roughly the torque in newton meters per radian of bend
+    /// between neighbouring segments, since the joints hold points one meter away from their anchors.
+    /// Clamped ends are static.
+    static func plank(size: Point, segmentCount: Int, mass: Double, stiffness: Double, clampsStart: Bool = false, clampsEnd: Bool = false) -> Prefab {
+        var prefab = Prefab()
+        let length = size.ex / Double(segmentCount)
+        let segment = ConvexCollider.box(halfExtents: 0.5 * Point(length, size.ey, size.ez))
+        var previous: Int?
+        for i in 0 ..< segmentCount {
+            let x = (Double(i) + 0.5) * length - 0.5 * size.ex
+            let isClamped = clampsStart && i == 0 || clampsEnd && i == segmentCount - 1
+            let current = prefab.addBody(.convex(segment), mass: isClamped ? nil : mass / Double(segmentCount), at: Frame(position: x * .ex))
+            if let previous = previous {
+                prefab.addJoint(.fixed(relativeOrientation: .identity), between: previous, and: current, at: (x - 0.5 * length) * .ex,
+                                compliance: 1 / stiffness)
+            }
+            previous = current
+        }
+        return prefab
+    }
+    
+    /// A static box without a lid, whose floor is centered at the origin with its top face at height zero.
+    /// The size is measured inside the walls.
+    static func openBox(size: Point, wallThickness: Double = 0.1) -> Prefab {
+        var prefab = Prefab()
+        let t = wallThickness
+        prefab.addStatic(halfExtents: 0.5 * Point(size.ex + 2 * t, size.ey + 2 * t, t), at: -0.5 * t * Point.ez)
+        for side in [-1.0, 1.0] {
+            prefab.addStatic(halfExtents: 0.5 * Point(t, size.ey + 2 * t, size.ez), at: Point(side * 0.5 * (size.ex + t), 0, 0.5 * size.ez))
+            prefab.addStatic(halfExtents: 0.5 * Point(size.ex, t, size.ez), at: Point(0, side * 0.5 * (size.ey + t), 0.5 * size.ez))
+        }
+        return prefab
+    }
+    
+    /// A static funnel around the z axis made of flat walls, whose lower opening is centered at the origin.
+    /// The radii are those of the circles touching the walls at the openings.
+    static func funnel(topRadius: Double, bottomRadius: Double, height: Double, sideCount: Int = 8, wallThickness: Double = 0.05) -> Prefab {
+        var prefab = Prefab()
+        let tilt = atan2(topRadius - bottomRadius, height)
+        // The walls meet at the corners of regular polygons around the openings.
+        let (bottomCorner, topCorner) = (bottomRadius / cos(Double.pi / Double(sideCount)), topRadius / cos(Double.pi / Double(sideCount)))
+        for side in 0 ..< sideCount {
+            let heading = 2 * Double.pi * Double(side) / Double(sideCount)
+            let outwards = Point(cos(tilt) * cos(heading), cos(tilt) * sin(heading), -sin(tilt))
+            var corners: [Point] = []
+            for edge in [-0.5, 0.5] {
+                let corner = Point(cos(heading + edge * 2 * Double.pi / Double(sideCount)), sin(heading + edge * 2 * Double.pi / Double(sideCount)), 0)
+                corners += [bottomCorner * corner, topCorner * corner + height * Point.ez]
+            }
+            corners += corners.map { $0 + wallThickness * outwards }
+            guard case let .hull(piece)? = ConvexCollider.hull(of: corners) else {
+                continue
+            }
+            // The hull is centered at its center of mass, which is where the wall goes instead. All corners are kept.
+            prefab.addBody(.convex(.hull(piece)), mass: nil, at: Frame(position: corners[0] - piece.vertices[0]))
+        }
+        return prefab
+    }
+    
+    /// A static ramp descending along the x axis from its upper end at the origin, with rails along both sides
+    /// unless their height is zero. The top face of the ramp starts at height zero.
+    static func ramp(length: Double, width: Double, slope: Double, railHeight: Double = 0.1, thickness: Double = 0.05) -> Prefab {
+        var prefab = Prefab()
+        let orientation = Quaternion(by: slope, around: .ey)
+        let direction = orientation.act(on: .ex)
+        let up = orientation.act(on: .ez)
+        prefab.addStatic(halfExtents: 0.5 * Point(length, width, thickness),
+                         at: Frame(position: 0.5 * length * direction - 0.5 * thickness * up, quaternion: orientation))
+        if railHeight > 0 {
+            for side in [-1.0, 1.0] {
+                let center = 0.5 * length * direction + 0.5 * railHeight * up + side * 0.5 * (width + thickness) * Point.ey
+                prefab.addStatic(halfExtents: 0.5 * Point(length, thickness, railHeight), at: Frame(position: center, quaternion: orientation))
+            }
+        }
+        return prefab
+    }
+    
+    private mutating func addStatic(halfExtents: Point, at position: Point) {
+        addStatic(halfExtents: halfExtents, at: Frame(position: position))
+    }
+    
+    private mutating func addStatic(halfExtents: Point, at frame: Frame) {
+        addBody(.convex(.box(halfExtents: halfExtents)), mass: nil, at: frame)
+    }
+}
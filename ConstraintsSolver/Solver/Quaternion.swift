@@ -6,19 +6,20 @@
 //
 
 import Foundation
+import simd
 
 
 infix operator ^+: AdditionPrecedence
 
 
 /// A unit quaternion.
-struct Quaternion {
+public struct Quaternion {
     private var coordinates: simd_quatd
     
-    static let identity = Quaternion(coordinates: simd_quatd(ix: 0, iy: 0, iz: 0, r: 1))
+    public static let identity = Quaternion(coordinates: simd_quatd(ix: 0, iy: 0, iz: 0, r: 1))
     
     /// Axis-angle constructor.
-    init(by angle: Double, around axis: Point) {
+    public init(by angle: Double, around axis: Point) {
         coordinates = simd_quatd(angle: angle, axis: simd_double3(axis.ex, axis.ey, axis.ez))
     }
     
@@ -27,6 +28,11 @@ struct Quaternion {
         coordinates = simd_quatd(ix: bivector.ex, iy: bivector.ey, iz: bivector.ez, r: 0)
     }
     
+    /// Constructs a quaternion from its components, normalizing it.
+    init(scalar: Double, bivector: Point) {
+        coordinates = simd_quatd(ix: bivector.ex, iy: bivector.ey, iz: bivector.ez, r: scalar).normalized
+    }
+    
     private init(coordinates: simd_quatd) {
         self.coordinates = coordinates
     }
@@ -48,7 +54,7 @@ struct Quaternion {
         ))
     }
     
-    static func *(lhs: Quaternion, rhs: Quaternion) -> Quaternion {
+    public static func *(lhs: Quaternion, rhs: Quaternion) -> Quaternion {
         Quaternion(coordinates: lhs.coordinates * rhs.coordinates)
     }
     
@@ -61,11 +67,16 @@ struct Quaternion {
         Quaternion(coordinates: (lhs.coordinates + rhs.coordinates).normalized)
     }
     
-    var inverse: Quaternion {
+    public var inverse: Quaternion {
         Quaternion(coordinates: coordinates.conjugate)
     }
     
-    func act(on v: Point) -> Point {
+    /// Spherically interpolates towards another quaternion, where `t` of 0 yields `self` and 1 yields `other`.
+    func slerp(to other: Quaternion, by t: Double) -> Quaternion {
+        Quaternion(coordinates: simd_slerp(coordinates, other.coordinates, t))
+    }
+    
+    public func act(on v: Point) -> Point {
         let rotated = coordinates.act(simd_double3(v.ex, v.ey, v.ez))
         return Point(rotated.x, rotated.y, rotated.z)
     }
@@ -84,3 +95,18 @@ struct Quaternion {
         return velocity
     }
 }
+
+
+extension Quaternion: Codable {
+    /// Encodes the components as they are, so that decoding restores the quaternion bit by bit.
+    public func encode(to encoder: Encoder) throws {
+        var container = encoder.unkeyedContainer()
+        try container.encode(contentsOf: [coordinates.imag.x, coordinates.imag.y, coordinates.imag.z, coordinates.real])
+    }
+    
+    public init(from decoder: Decoder) throws {
+        var container = try decoder.unkeyedContainer()
+        let components = try (0 ..< 4).map { _ in try container.decode(Double.self) }
+        coordinates = simd_quatd(ix: components[0], iy: components[1], iz: components[2], r: components[3])
+    }
+}
@@ -0,0 +1,56 @@
+//
+//  QueryFilter.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 20.04.21.
+//
+
+import Foundation
+
+
+/// Up to 32 groups a rigid can belong to, e.g. to tell level geometry, props, and characters apart in queries.
+public struct CollisionGroups: OptionSet {
+    public let rawValue: UInt32
+    
+    public static let `default` = CollisionGroups(rawValue: 1 << 0)
+    public static let all = CollisionGroups(rawValue: .max)
+    
+    public init(rawValue: UInt32) {
+        self.rawValue = rawValue
+    }
+    
+    /// The group with the given index from 0 to 31.
+    public static func group(_ index: Int) -> CollisionGroups {
+        precondition((0 ..< 32).contains(index), "Collision group index out of range")
+        return CollisionGroups(rawValue: 1 << UInt32(index))
+    }
+}
+
+
+/// Decides which rigids a query considers. Almost every gameplay query needs to exclude something,
+/// such as the body of the caster itself.
+public struct QueryFilter {
+    /// Only rigids belonging to at least one of these groups are considered.
+    public var groups = CollisionGroups.all
+    var excluded: [Rigid] = []
+    /// Rigids for which this returns false are skipped.
+    var predicate: ((Rigid) -> Bool)? = nil
+    
+    public static let none = QueryFilter()
+    
+    public init(groups: CollisionGroups = .all) {
+        self.groups = groups
+    }
+    
+    init(groups: CollisionGroups = .all, excluding excluded: [Rigid], where predicate: ((Rigid) -> Bool)? = nil) {
+        self.groups = groups
+        self.excluded = excluded
+        self.predicate = predicate
+    }
+    
+    func accepts(_ rigid: Rigid) -> Bool {
+        !rigid.groups.isDisjoint(with: groups) &&
+            !excluded.contains { $0 === rigid } &&
+            predicate?(rigid) ?? true
+    }
+}
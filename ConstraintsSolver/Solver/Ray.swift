@@ -0,0 +1,143 @@
+//
+//  Ray.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 20.04.21.
+//
+
+import Foundation
+
+
+struct Ray {
+    let origin: Point
+    /// A unit vector.
+    let direction: Point
+    /// Hits further away than this are ignored.
+    var maxDistance: Double
+    
+    init(origin: Point, direction: Point, maxDistance: Double = .infinity) {
+        self.origin = origin
+        self.direction = direction.normalize
+        self.maxDistance = maxDistance
+    }
+    
+    func at(_ distance: Double) -> Point {
+        origin + distance * direction
+    }
+    
+    /// Expresses this ray in the local coordinates of a frame.
+    func localized(to frame: Frame) -> Ray {
+        let inverse = frame.inverse
+        return Ray(origin: inverse.act(origin), direction: inverse.quaternion.act(on: direction), maxDistance: maxDistance)
+    }
+}
+
+
+struct RayHit {
+    let rigid: Rigid
+    let distance: Double
+    let point: Point
+    /// The surface normal at the hit point, facing the ray.
+    let normal: Point
+}
+
+
+extension Collider {
+    /// Intersects a ray given in the local frame of the collider, returning the distance and the local surface normal.
+    func intersect(_ ray: Ray) -> (distance: Double, normal: Point)? {
+        switch self {
+        case let .plane(plane):
+            let alignment = plane.normal.dot(ray.direction)
+            if abs(alignment) < 1e-12 {
+                return nil
+            }
+            let distance = (plane.offset - plane.normal.dot(ray.origin)) / alignment
+            if distance < 0 || distance > ray.maxDistance {
+                return nil
+            }
+            return (distance, alignment < 0 ? plane.normal : -plane.normal)
+            
+        case .box:
+            return Collider.intersectBox(ray, halfExtents: Point(0.5))
+            
+        case let .mesh(mesh):
+            return mesh.intersect(ray)
+            
+        case let .compound(compound):
+            return compound.intersect(ray)
+            
+        case let .convex(convex):
+            return convex.intersect(ray)
+        }
+    }
+    
+    /// Intersects a sphere moving along a ray given in the local frame of the collider.
+    /// Boxes and hulls are inflated by the radius, which slightly overestimates their rounded corners.
+    func cast(_ ray: Ray, radius: Double) -> (distance: Double, normal: Point)? {
+        switch self {
+        case let .plane(plane):
+            let distance = plane.normal.dot(ray.origin) - plane.offset
+            if distance < radius {
+                return (0, plane.normal)
+            }
+            return Collider.plane(Plane(normal: plane.normal, offset: plane.offset + radius)).intersect(ray)
+            
+        case .box:
+            return Collider.intersectBox(ray, halfExtents: Point(0.5 + radius))
+            
+        case let .mesh(mesh):
+            return mesh.intersect(ray, offset: radius)
+            
+        case let .compound(compound):
+            return compound.intersect(ray, offset: radius)
+            
+        case let .convex(convex):
+            return convex.intersect(ray, offset: radius)
+        }
+    }
+    
+    /// Intersects a ray with a box centered at the origin.
+    static func intersectBox(_ ray: Ray, halfExtents: Point) -> (distance: Double, normal: Point)? {
+        // Slab test against the box centered at the origin.
+        var entry = -Double.infinity
+        var exit = Double.infinity
+        var normal = Point.null
+        
+        for axis in [Point.ex, Point.ey, Point.ez] {
+            let halfExtent = halfExtents.dot(axis)
+            let origin = ray.origin.dot(axis)
+            let direction = ray.direction.dot(axis)
+            
+            if abs(direction) < 1e-12 {
+                if abs(origin) > halfExtent {
+                    return nil
+                }
+                continue
+            }
+            
+            let near = ((direction > 0 ? -halfExtent : halfExtent) - origin) / direction
+            let far = ((direction > 0 ? halfExtent : -halfExtent) - origin) / direction
+            if near > entry {
+                entry = near
+                normal = direction > 0 ? -axis : axis
+            }
+            exit = min(exit, far)
+        }
+        
+        if entry > exit || exit < 0 || entry > ray.maxDistance {
+            return nil
+        }
+        // Rays starting inside the box hit it immediately.
+        return entry < 0 ? (0, -ray.direction) : (entry, normal)
+    }
+}
+
+
+extension Rigid {
+    func intersect(_ ray: Ray) -> RayHit? {
+        guard let hit = collider.intersect(ray.localized(to: frame)) else {
+            return nil
+        }
+        return RayHit(rigid: self, distance: hit.distance, point: ray.at(hit.distance), normal: frame.quaternion.act(on: hit.normal))
+    }
+}
@@ -0,0 +1,233 @@
+//
+//  Recording.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// The states of the rigids of a world at the end of every step, along with the scene they started from, so that
+/// a run can be captured once and watched again step by step, see `World.play(_:)`. States are quantized by a codec
+/// and encoded relative to the previous step, which keeps recordings compact.
+struct Recording {
+    struct Step {
+        /// Simulated time in seconds at the end of the step.
+        let time: Double
+        /// The states of the rigids in the order of the world.
+        let states: [QuantizedState]
+        /// What drove the world from outside during the step, if inputs were recorded.
+        let inputs: [Input]
+    }
+    
+    enum PlaybackError: Error {
+        /// The world has another number of rigids than the recording started with, so their states can't be matched up.
+        case rigidCountMismatch(recorded: Int, world: Int)
+    }
+    
+    enum Input {
+        /// The external force of a rigid was changed to the given one.
+        case externalForce(RigidHandle, Point)
+        /// A rigid was dragged towards a global target, e.g. with the mouse.
+        case drag(RigidHandle, target: Point)
+    }
+    
+    /// The world at the start of the recording.
+    let scene: Scene
+    let codec: StateCodec
+    /// The state at the start of the recording, followed by one step after another.
+    var steps: [Step] = []
+    
+    init(scene: Scene, codec: StateCodec) {
+        self.scene = scene
+        self.codec = codec
+    }
+    
+    /// Encodes the recording into a binary format. The scene is stored as JSON, and each step as the number of rigids
+    /// and their states encoded by the codec, followed by the inputs.
+    func encoded() throws -> Data {
+        var data = Data("CSREC2".utf8)
+        write(codec.positionResolution, to: &data)
+        write(codec.velocityResolution, to: &data)
+        let sceneData = try scene.encoded()
+        writeVarint(UInt64(sceneData.count), to: &data)
+        data.append(sceneData)
+        
+        writeVarint(UInt64(steps.count), to: &data)
+        var previous: [QuantizedState]?
+        for step in steps {
+            write(step.time, to: &data)
+            // Rigids may have been added or removed since the previous step, which needs the states in full.
+            let baseline = previous?.count == step.states.count ? previous : nil
+            writeVarint(UInt64(step.states.count), to: &data)
+            data.append(baseline == nil ? 0 : 1)
+            let states = codec.encode(step.states, relativeTo: baseline)
+            writeVarint(UInt64(states.count), to: &data)
+            data.append(states)
+            previous = step.states
+            
+            writeVarint(UInt64(step.inputs.count), to: &data)
+            for input in step.inputs {
+                switch input {
+                case let .externalForce(handle, force):
+                    data.append(0)
+                    writeVarint(UInt64(handle.index), to: &data)
+                    write(force, to: &data)
+                case let .drag(handle, target):
+                    data.append(1)
+                    writeVarint(UInt64(handle.index), to: &data)
+                    write(target, to: &data)
+                }
+            }
+        }
+        return data
+    }
+    
+    init(data: Data) throws {
+        var offset = data.startIndex
+        guard data.count >= 6, data.prefix(6) == Data("CSREC2".utf8) else {
+            throw StateCodingError.malformed
+        }
+        offset += 6
+        codec = StateCodec(positionResolution: try readDouble(from: data, at: &offset),
+                           velocityResolution: try readDouble(from: data, at: &offset))
+        scene = try Scene(data: try readBlock(from: data, at: &offset))
+        
+        let stepCount = try readCount(from: data, at: &offset)
+        var previous: [QuantizedState]?
+        for _ in 0 ..< stepCount {
+            let time = try readDouble(from: data, at: &offset)
+            let rigidCount = try readCount(from: data, at: &offset)
+            guard offset < data.endIndex else {
+                throw StateCodingError.truncated
+            }
+            guard data[offset] <= 1 else {
+                throw StateCodingError.malformed
+            }
+            let isRelative = data[offset] == 1
+            offset += 1
+            let states = try codec.decode(try readBlock(from: data, at: &offset), relativeTo: isRelative ? previous : nil)
+            guard states.count == rigidCount else {
+                throw StateCodingError.malformed
+            }
+            previous = states
+            
+            var inputs: [Input] = []
+            for _ in 0 ..< (try readCount(from: data, at: &offset)) {
+                guard offset < data.endIndex else {
+                    throw StateCodingError.truncated
+                }
+                let kind = data[offset]
+                offset += 1
+                let handle = RigidHandle(index: try readCount(from: data, at: &offset))
+                let point = Point(try readDouble(from: data, at: &offset), try readDouble(from: data, at: &offset), try readDouble(from: data, at: &offset))
+                switch kind {
+                case 0: inputs.append(.externalForce(handle, point))
+                case 1: inputs.append(.drag(handle, target: point))
+                default: throw StateCodingError.malformed
+                }
+            }
+            steps.append(Step(time: time, states: states, inputs: inputs))
+        }
+    }
+}
+
+
+/// Records the steps of a world from the moment it is created, by a stage running at the end of every step.
+final class Recorder {
+    static let stageName = "Record"
+    private(set) var recording: Recording
+    /// Whether external forces and drags are recorded along with the states.
+    let recordsInputs: Bool
+    /// The external forces as of the last step, so that only changes are recorded.
+    private var externalForces: [Point]
+    
+    init(_ world: World, codec: StateCodec = StateCodec(), recordsInputs: Bool = false) {
+        recording = Recording(scene: world.scene(), codec: codec)
+        self.recordsInputs = recordsInputs
+        externalForces = world.rigids.map { $0.externalForce }
+        recording.steps.append(Recording.Step(time: world.time, states: world.quantizedStates(using: codec), inputs: []))
+        world.pipeline.insert(StepStage(name: Recorder.stageName) { [weak self] world, _ in
+            self?.capture(world)
+        }, after: World.advanceTime.name)
+    }
+    
+    /// Stops recording the steps of the world.
+    func stop(_ world: World) {
+        world.pipeline.removeAll { $0.name == Recorder.stageName }
+    }
+    
+    private func capture(_ world: World) {
+        var inputs: [Recording.Input] = []
+        if recordsInputs {
+            for (index, rigid) in world.rigids.enumerated() where index >= externalForces.count || rigid.externalForce != externalForces[index] {
+                inputs.append(.externalForce(RigidHandle(index: index), rigid.externalForce))
+            }
+            for joint in world.mouseJoints {
+                if let handle = world.handle(of: joint.rigid) {
+                    inputs.append(.drag(handle, target: joint.target))
+                }
+            }
+            externalForces = world.rigids.map { $0.externalForce }
+        }
+        recording.steps.append(Recording.Step(time: world.time, states: world.quantizedStates(using: recording.codec), inputs: inputs))
+    }
+}
+
+
+/// A recording being played back by a world, and the step it shows.
+struct Playback {
+    let recording: Recording
+    /// The number of steps recorded with as many rigids as the world has. Welding, splitting and fracture change
+    /// the rigids, after which the states no longer line up with those of the world, so playback ends before.
+    let stepCount: Int
+    var step = 0
+}
+
+
+private func write(_ value: Double, to data: inout Data) {
+    withUnsafeBytes(of: value.bitPattern.littleEndian) { data.append(contentsOf: $0) }
+}
+
+
+private func write(_ point: Point, to data: inout Data) {
+    write(point.ex, to: &data)
+    write(point.ey, to: &data)
+    write(point.ez, to: &data)
+}
+
+
+private func readDouble(from data: Data, at offset: inout Data.Index) throws -> Double {
+    guard data.endIndex - offset >= 8 else {
+        throw StateCodingError.truncated
+    }
+    var bits: UInt64 = 0
+    for byte in 0 ..< 8 {
+        bits |= UInt64(data[offset + byte]) << (8 * byte)
+    }
+    offset += 8
+    return Double(bitPattern: bits)
+}
+
+
+/// Reads a count or an index, which is malformed if it doesn't fit an `Int`.
+private func readCount(from data: Data, at offset: inout Data.Index) throws -> Int {
+    guard let count = Int(exactly: try readVarint(from: data, at: &offset)) else {
+        throw StateCodingError.malformed
+    }
+    return count
+}
+
+
+/// Reads a block of bytes preceded by its length.
+private func readBlock(from data: Data, at offset: inout Data.Index) throws -> Data {
+    let count = try readCount(from: data, at: &offset)
+    guard data.endIndex - offset >= count else {
+        throw StateCodingError.truncated
+    }
+    defer {
+        offset += count
+    }
+    return data.subdata(in: offset ..< offset + count)
+}
@@ -0,0 +1,76 @@
+//
+//  RemoteBody.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 21.04.21.
+//
+
+import Foundation
+
+
+/// A body simulated elsewhere, e.g. by another player, whose poses arrive as timestamped snapshots.
+/// Locally it is kinematic: it pushes dynamic bodies around but is not affected by them.
+/// It is shown slightly in the past, so that there usually are two snapshots to interpolate between.
+class RemoteBody {
+    struct Snapshot {
+        let time: Double
+        let frame: Frame
+        let velocity: Point
+        let angularVelocity: Point
+    }
+    
+    let rigid: Rigid
+    /// How far in the past the body is shown.
+    var interpolationDelay = 0.1
+    /// How long the last snapshot is extrapolated when no newer one arrives, before the body stops.
+    var maxExtrapolation = 0.25
+    private var snapshots: [Snapshot] = []
+    
+    init(rigid: Rigid) {
+        precondition(rigid.inverseMass == 0, "Remote bodies must be kinematic")
+        self.rigid = rigid
+        rigid.isKinematic = true
+    }
+    
+    /// Adds a snapshot, which may arrive out of order.
+    func receive(_ snapshot: Snapshot) {
+        let index = snapshots.firstIndex { $0.time > snapshot.time } ?? snapshots.endIndex
+        snapshots.insert(snapshot, at: index)
+    }
+    
+    /// The interpolated or extrapolated frame to show at the given local time.
+    func frame(at time: Double) -> Frame? {
+        let shownTime = time - interpolationDelay
+        
+        // Snapshots older than the one preceding the shown time are not needed anymore.
+        if let previous = snapshots.lastIndex(where: { $0.time <= shownTime }), previous > 0 {
+            snapshots.removeFirst(previous)
+        }
+        
+        guard let first = snapshots.first else {
+            return nil
+        }
+        if first.time > shownTime {
+            return first.frame
+        }
+        
+        if snapshots.count > 1 {
+            let next = snapshots[1]
+            let t = (shownTime - first.time) / (next.time - first.time)
+            return Frame(position: first.frame.position + t * (next.frame.position - first.frame.position),
+                         quaternion: first.frame.quaternion.slerp(to: next.frame.quaternion, by: t))
+        }
+        
+        let dt = min(shownTime - first.time, maxExtrapolation)
+        return first.frame.integrate(by: dt, linearVelocity: first.velocity, angularVelocity: first.angularVelocity)
+    }
+    
+    /// Sets the velocities of the rigid such that integrating over `dt` moves it to its frame at the given time.
+    /// Moving by velocity rather than teleporting gives dynamic bodies in contact the proper friction.
+    func drive(towardsTime time: Double, dt: Double) {
+        guard let target = frame(at: time) else {
+            return
+        }
+        (rigid.velocity, rigid.angularVelocity) = target.derive(for: dt, rigid.frame)
+    }
+}
@@ -0,0 +1,198 @@
+//
+//  Restitution.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// How the bouncing contacts of a sub-step are resolved after its positions. Only used by the position based backend,
+/// since the sequential impulses backend propagates bounces through its iterations anyway.
+enum RestitutionPropagation {
+    /// Each contact reflects its own approach speed from before the sub-step. Cheap, and fine for rigids bouncing
+    /// off each other or the ground, but a shock running into a row of touching rigids is spread among all of them,
+    /// since the positions are solved for the whole row at once.
+    case simultaneous
+    /// Rigids connected to an impact through contacts get the changes of their velocities along those contacts
+    /// during the sub-step taken back, and the contacts are then resolved one after the other, each with the velocities left by the previous ones,
+    /// until nothing approaches anymore or the passes are used up. A shock then travels through a row of touching
+    /// rigids as in a Newton's cradle. A row of n rigids needs up to n passes.
+    case sequential(passes: Int)
+}
+
+
+/// A contact remembered during the position pass of a sub-step, whose normal velocity is reflected afterwards.
+/// The position pass alone stops approaching rigids dead, since the velocities are derived from the corrected positions.
+struct RestitutionContact {
+    let rigids: (Rigid, Rigid)
+    /// The contact point in the local frames of both rigids.
+    let anchors: (Point, Point)
+    /// The direction the first rigid is pushed into.
+    let normal: Point
+    /// The normal speed of the first rigid relative to the second one before the sub-step, which is negative for impacts.
+    let approachSpeed: Double
+    let restitution: Double
+    /// Approach speeds below this don't bounce.
+    let restingSpeed: Double
+    /// The linear and angular velocities of both rigids before the sub-step.
+    let previousVelocities: ((Point, Point), (Point, Point))
+    
+    /// Whether the rigids approached each other fast enough to bounce before the sub-step.
+    var isImpact: Bool {
+        approachSpeed <= -restingSpeed
+    }
+    
+    /// Remembers a contact given the velocities of both rigids at the beginning of the sub-step,
+    /// unless the rigids don't bounce, or approach too slowly. Slow contacts are kept if no impact is required,
+    /// since a shock may still reach them.
+    init?(_ contact: PositionalConstraint, previousVelocities: ((Point, Point), (Point, Point)), dt: Double, requiresImpact: Bool = true) {
+        let rigids = contact.rigids
+        restitution = rigids.0.restitution(against: rigids.1)
+        if restitution <= 0 || contact.measure < 1e-12 {
+            return nil
+        }
+        
+        let point = contact.contacts.0
+        normal = contact.normal ?? contact.direction
+        let velocities = (
+            previousVelocities.0.0 + previousVelocities.0.1.cross(point - rigids.0.frame.position),
+            previousVelocities.1.0 + previousVelocities.1.1.cross(point - rigids.1.frame.position)
+        )
+        approachSpeed = (velocities.0 - velocities.1).dot(normal)
+        restingSpeed = max(rigids.0.restingSpeed(for: dt), rigids.1.restingSpeed(for: dt))
+        if requiresImpact && approachSpeed > -restingSpeed {
+            return nil
+        }
+        
+        self.rigids = rigids
+        self.previousVelocities = previousVelocities
+        anchors = (rigids.0.frame.inverse.act(point), rigids.1.frame.inverse.act(point))
+    }
+    
+    fileprivate var points: (Point, Point) {
+        (rigids.0.frame.act(anchors.0), rigids.1.frame.act(anchors.1))
+    }
+    
+    /// The current normal speed of the first rigid relative to the second one.
+    var normalSpeed: Double {
+        let points = self.points
+        return (rigids.0.pointVelocity(at: points.0) - rigids.1.pointVelocity(at: points.1)).dot(normal)
+    }
+    
+    /// Sets the normal speed to the approach speed reflected and scaled by the restitution.
+    func apply() {
+        setNormalSpeed(-restitution * approachSpeed)
+    }
+    
+    /// Reflects the current normal speed if the rigids approach each other fast enough, returning whether they did.
+    func propagate() -> Bool {
+        let speed = normalSpeed
+        if speed > -restingSpeed {
+            return false
+        }
+        setNormalSpeed(-restitution * speed)
+        return true
+    }
+    
+    private func setNormalSpeed(_ target: Double) {
+        let points = self.points
+        let arms = (points.0 - rigids.0.frame.position, points.1 - rigids.1.frame.position)
+        let inverseMass = rigids.0.inverseMass + rigids.1.inverseMass +
+            rigids.0.applyInverseInertia(arms.0.cross(normal)).cross(arms.0).dot(normal) +
+            rigids.1.applyInverseInertia(arms.1.cross(normal)).cross(arms.1).dot(normal)
+        if inverseMass < 1e-12 {
+            return
+        }
+        
+        let impulse = ((target - normalSpeed) / inverseMass) * normal
+        rigids.0.applyImpulse(impulse, at: points.0)
+        rigids.1.applyImpulse(-impulse, at: points.1)
+    }
+}
+
+
+/// Resolves the contacts of a sub-step one after the other, see `RestitutionPropagation.sequential`.
+func propagateBounces(through contacts: [RestitutionContact], passes: Int) {
+    // Rigids touching an impact directly or through other dynamic rigids are part of the shock.
+    // Static rigids don't pass it on, or everything on the ground would be.
+    var shaken = Set<ObjectIdentifier>()
+    for contact in contacts where contact.isImpact {
+        shaken.insert(ObjectIdentifier(contact.rigids.0))
+        shaken.insert(ObjectIdentifier(contact.rigids.1))
+    }
+    if shaken.isEmpty {
+        return
+    }
+    var isGrowing = true
+    while isGrowing {
+        isGrowing = false
+        for contact in contacts {
+            let (a, b) = contact.rigids
+            let keys = (ObjectIdentifier(a), ObjectIdentifier(b))
+            if shaken.contains(keys.0) && a.inverseMass > 0 && !shaken.contains(keys.1) ||
+                shaken.contains(keys.1) && b.inverseMass > 0 && !shaken.contains(keys.0) {
+                shaken.insert(keys.0)
+                shaken.insert(keys.1)
+                isGrowing = true
+            }
+        }
+    }
+    
+    // The velocities derived from the positions already spread the shock, so it starts over from the ones before.
+    // Only the changes the contacts can cause are taken back, which keeps those of joints and friction.
+    let involved = contacts.filter { shaken.contains(ObjectIdentifier($0.rigids.0)) && shaken.contains(ObjectIdentifier($0.rigids.1)) }
+    var directions: [ObjectIdentifier: (rigid: Rigid, previous: (Point, Point), linear: [Point], angular: [Point])] = [:]
+    for contact in involved {
+        let points = contact.points
+        for (rigid, point, previous) in [(contact.rigids.0, points.0, contact.previousVelocities.0),
+                                         (contact.rigids.1, points.1, contact.previousVelocities.1)] where rigid.inverseMass > 0 {
+            var entry = directions[ObjectIdentifier(rigid)] ?? (rigid, previous, [], [])
+            entry.linear.append(contact.normal)
+            entry.angular.append(rigid.applyInverseInertia((point - rigid.frame.position).cross(contact.normal)))
+            directions[ObjectIdentifier(rigid)] = entry
+        }
+    }
+    for (rigid, previous, linear, angular) in directions.values {
+        rigid.velocity = rigid.velocity - projection(of: rigid.velocity - previous.0, onto: linear)
+        rigid.angularVelocity = rigid.angularVelocity - projection(of: rigid.angularVelocity - previous.1, onto: angular)
+    }
+    
+    for _ in 0 ..< passes {
+        var hasBounced = false
+        for contact in involved where contact.propagate() {
+            hasBounced = true
+        }
+        if !hasBounced {
+            break
+        }
+    }
+}
+
+
+/// The part of a vector within the span of the given directions.
+fileprivate func projection(of vector: Point, onto directions: [Point]) -> Point {
+    var basis: [Point] = []
+    for direction in directions {
+        let orthogonal = basis.reduce(direction) { $0 - $0.project(onto: $1) }
+        if orthogonal.length > 1e-9 {
+            basis.append(orthogonal.normalize)
+        }
+    }
+    return basis.reduce(.null) { $0 + vector.dot($1) * $1 }
+}
+
+
+extension Rigid {
+    /// The restitution of a contact between two rigids, which bounces as much as the bouncier one of them.
+    func restitution(against other: Rigid) -> Double {
+        max(restitution, other.restitution)
+    }
+    
+    /// Contacts approaching slower than this don't bounce, since a sub-step of acceleration alone may cause such
+    /// a speed. Resting rigids would keep jittering otherwise.
+    func restingSpeed(for dt: Double) -> Double {
+        2 * dt * inverseMass * (externalForce + generatedForce).length
+    }
+}
@@ -12,44 +12,208 @@ class Rigid {
     let inverseMass: Double
     let inverseInertia: Point
     var externalForce: Point = .null
+    /// The force of the force generators of the world during the current step, in addition to the external force.
+    var generatedForce: Point = .null
+    /// The torque of the force generators of the world during the current step, in global coordinates.
+    var generatedTorque: Point = .null
     var velocity: Point = .null
     var angularVelocity: Point = .null
+    /// The frame of the center of mass along the principal axes of inertia, which is the one the solver moves,
+    /// and the one the collider is given in.
     var frame: Frame = .identity
     var pastFrame: Frame = .identity
+    /// The frame of the center of mass and the principal axes relative to the frame the rigid was modelled in,
+    /// for rigids whose mass properties were computed from an off-center or rotated tensor.
+    var principalFrame: Frame = .identity
+    /// Whether this massless rigid is moved by velocities set from outside rather than being static.
+    var isKinematic = false
+    /// Whether the simulation level of detail froze this rigid during the last step, or it sleeps.
+    var isFrozen = false
+    /// Whether this rigid was put to sleep, see `Sleeping`. Setting velocities directly doesn't wake it up,
+    /// but applying an impulse or calling `wake()` does.
+    var isSleeping = false
+    /// How long this rigid stayed slower than the sleeping thresholds.
+    var restingTime = 0.0
+    var material: Material?
+    /// The ratio of the normal speed after a contact to the one before, from zero for no bounce to one for
+    /// a perfectly elastic one. A contact bounces as much as the bouncier one of its rigids.
+    var restitution = 0.0
+    /// Whether this rigid only detects the rigids overlapping it rather than pushing them, like a light barrier.
+    /// Overlaps are reported as contact events, and by `World.rigids(touching:)`.
+    var isSensor = false
+    /// The velocity of the surface relative to the rigid in its local frame, like that of a conveyor belt.
+    /// The friction of contacts with its plane or mesh drags touching rigids along. Only the position based
+    /// backend applies it.
+    var surfaceVelocity: Point?
+    /// Makes the contacts of this rigid give if set, e.g. for a compliant ground.
+    var softContact: SoftContact?
+    /// A number of the game's choice which contact events and sounds pass along, e.g. to look up the sound of a surface
+    /// or to tell a player from an enemy.
+    var userTag: Int?
+    /// The groups this rigid belongs to, which queries can filter by.
+    var groups = CollisionGroups.default
+    /// The frames at the end of the most recent integration steps, oldest first, if enabled by `recordPoseHistory`.
+    /// Useful for motion blur, lag compensation, and trajectory trails.
+    private(set) var poseHistory: RingBuffer<Frame>?
+    /// Overrides the friction coefficient of the solver with direction dependent ones.
+    var anisotropicFriction: AnisotropicFriction?
+    /// Upper bound of the angular speed in radians per second, which keeps thin bodies from spinning absurdly fast.
+    var maxAngularSpeed: Double?
+    var spinDamping: SpinDamping?
+    /// How fast time runs for this rigid, e.g. for a slow-motion shot of a single object. Zero stops it in place.
+    /// It is combined with the time dilation volumes the rigid is in. Contacts between rigids with different
+    /// time scales are resolved on positions as usual, so each rigid is pushed out as far as it would be otherwise,
+    /// and only the velocities resulting from the push are in the time of each rigid.
+    var timeScale = 1.0
+    /// Breaks the rigid into fragments when it is hit hard enough, if set.
+    var fracture: Fracture?
     
-    init(collider: Collider, mass: Double?) {
+    /// Creates a dynamic rigid with the mass properties of its collider if it has a mass, or a static one otherwise.
+    /// Masses and shapes giving unusable moments of inertia are programming errors here,
+    /// whereas `init(collider:mass:bounds:)` throws them.
+    convenience init(collider: Collider, mass: Double?) {
         if let mass = mass {
-            self.inverseMass = 1 / mass
-            let extent = Point(1)
-            let inertia = 1 / 12 * mass * Point(
-                extent.ey * extent.ey + extent.ez * extent.ez,
-                extent.ex * extent.ex + extent.ez * extent.ez,
-                extent.ex * extent.ex + extent.ey * extent.ey)
-            self.inverseInertia = Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez)
+            let (centerOfMass, tensor) = Rigid.massProperties(of: collider, mass: mass)
+            let principal = tensor.principalAxes
+            let principalFrame = Frame(position: centerOfMass, quaternion: principal.rotation)
+            let inertia: Point
+            do {
+                inertia = try InertiaBounds().validate(mass: mass, inertia: principal.moments)
+            }
+            catch {
+                preconditionFailure(error.localizedDescription)
+            }
+            self.init(collider: collider.expressed(in: principalFrame), inverseMass: 1 / mass,
+                      inverseInertia: Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez))
+            self.principalFrame = principalFrame
+            frame = principalFrame
+            pastFrame = frame
         }
         else {
-            self.inverseMass = 0
-            self.inverseInertia = .null
+            self.init(collider: collider, inverseMass: 0, inverseInertia: .null)
         }
+    }
+    
+    init(collider: Collider, inverseMass: Double, inverseInertia: Point) {
         self.collider = collider
+        self.inverseMass = inverseMass
+        self.inverseInertia = inverseInertia
+    }
+    
+    /// The center of mass and the inertia tensor about it of a collider with the given mass.
+    static func massProperties(of collider: Collider, mass: Double) -> (center: Point, inertia: InertiaTensor) {
+        switch collider {
+        case let .compound(compound):
+            // Compounds are integrated over their shapes.
+            return compound.massProperties(mass: mass)
+        case let .convex(shape):
+            return (.null, InertiaTensor(diagonal: shape.inertia(mass: mass)))
+        default:
+            // Colliders without a size of their own get the inertia of the unit cube.
+            return (.null, InertiaTensor(diagonal: ConvexCollider.box(halfExtents: Point(0.5)).inertia(mass: mass)))
+        }
+    }
+    
+    /// The frame the rigid was modelled in, which moves along with the frame of its center of mass.
+    var bodyFrame: Frame {
+        get {
+            frame.act(principalFrame.inverse)
+        }
+        set {
+            frame = newValue.act(principalFrame)
+        }
+    }
+    
+    /// The principal moments of inertia. Axes whose inverse moment is zero, such as locked ones, get a zero moment
+    /// rather than an infinite one, since the rigid can't turn about them anyway.
+    var inertia: Point {
+        func moment(_ inverse: Double) -> Double {
+            inverse == 0 ? 0 : 1 / inverse
+        }
+        return Point(moment(inverseInertia.ex), moment(inverseInertia.ey), moment(inverseInertia.ez))
+    }
+    
+    var kineticEnergy: Double {
+        if inverseMass == 0 {
+            return 0
+        }
+        let localAngularVelocity = frame.quaternion.inverse.act(on: angularVelocity)
+        return 0.5 / inverseMass * velocity.dot(velocity) +
+            0.5 * (inertia .* localAngularVelocity).dot(localAngularVelocity)
+    }
+    
+    /// The angular momentum about the center of mass in global coordinates, which stays constant without any torque.
+    var angularMomentum: Point {
+        if inverseMass == 0 {
+            return .null
+        }
+        let localAngularVelocity = frame.quaternion.inverse.act(on: angularVelocity)
+        return frame.quaternion.act(on: inertia .* localAngularVelocity)
     }
     
-    func integrateAttitude(by dt: Double) {
-        velocity = velocity + dt * inverseMass * externalForce
+    /// The torque turning the angular velocity of a spinning rigid as its principal axes turn with it,
+    /// in global coordinates. It makes tops precess and thin bodies flip over when spun about their middle axis.
+    var gyroscopicTorque: Point {
+        if inverseMass == 0 {
+            return .null
+        }
+        let localAngularVelocity = frame.quaternion.inverse.act(on: angularVelocity)
+        return frame.quaternion.act(on: -localAngularVelocity.cross(inertia .* localAngularVelocity))
+    }
+    
+    func integrateAttitude(by dt: Double, isGyroscopic: Bool = false) {
+        let torque = isGyroscopic ? generatedTorque + gyroscopicTorque : generatedTorque
+        velocity = velocity + dt * inverseMass * (externalForce + generatedForce)
+        angularVelocity = angularVelocity + dt * applyInverseInertia(torque)
         pastFrame = frame
         frame = frame.integrate(by: dt, linearVelocity: velocity, angularVelocity: angularVelocity)
     }
     
     func deriveVelocity(for dt: Double) {
         (velocity, angularVelocity) = frame.derive(for: dt, pastFrame)
+        limitSpin(for: dt)
+    }
+    
+    /// Starts recording the frames of the last `capacity` integration steps, or stops recording if `nil`.
+    func recordPoseHistory(capacity: Int?) {
+        poseHistory = capacity.map { RingBuffer(capacity: $0) }
+    }
+    
+    func recordPose() {
+        poseHistory?.push(frame)
+    }
+    
+    /// Applies a linear impulse at the center of mass, changing the velocity only.
+    func applyImpulse(_ impulse: Point) {
+        wake()
+        velocity = velocity + inverseMass * impulse
+    }
+    
+    /// Wakes this rigid up if it sleeps, and restarts the time it needs to rest before falling asleep again.
+    func wake() {
+        isSleeping = false
+        restingTime = 0
     }
     
     /// Applies a linear impulse in a given direction and magnitude at a given location.
     /// Results in changes in both position and quaternion.
     func applyLinearImpulse(_ impulse: Point, at vertex: Point) {
+        // Static rigids aren't even written, since islands solved concurrently share them.
+        if inverseMass == 0 {
+            return
+        }
         frame.translate(by: inverseMass * impulse)
         
-        let rotation = 0.5 * Quaternion(bivector: inverseInertia .* (vertex - frame.position).cross(impulse)) * frame.quaternion
+        let rotation = 0.5 * Quaternion(bivector: applyInverseInertia((vertex - frame.position).cross(impulse))) * frame.quaternion
+        frame.quaternion = frame.quaternion ^+ rotation
+    }
+    
+    /// Applies an angular impulse in global coordinates, changing the quaternion only.
+    func applyAngularImpulse(_ impulse: Point) {
+        if inverseMass == 0 {
+            return
+        }
+        let rotation = 0.5 * Quaternion(bivector: applyInverseInertia(impulse)) * frame.quaternion
         frame.quaternion = frame.quaternion ^+ rotation
     }
     
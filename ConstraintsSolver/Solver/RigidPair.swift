@@ -0,0 +1,25 @@
+//
+//  RigidPair.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Two rigids in no particular order.
+struct RigidPair: Hashable {
+    private let first: ObjectIdentifier
+    private let second: ObjectIdentifier
+    
+    init(_ a: Rigid, _ b: Rigid) {
+        let (a, b) = (ObjectIdentifier(a), ObjectIdentifier(b))
+        first = min(a, b)
+        second = max(a, b)
+    }
+    
+    func contains(_ rigid: Rigid) -> Bool {
+        first == ObjectIdentifier(rigid) || second == ObjectIdentifier(rigid)
+    }
+}
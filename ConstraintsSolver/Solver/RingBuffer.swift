@@ -0,0 +1,59 @@
+//
+//  RingBuffer.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 16.04.21.
+//
+
+import Foundation
+
+
+/// A buffer of fixed capacity which overwrites its oldest element once it is full.
+struct RingBuffer<Element> {
+    let capacity: Int
+    private var storage: [Element] = []
+    private var head = 0
+    
+    init(capacity: Int) {
+        precondition(capacity > 0, "Ring buffer capacity must be positive")
+        self.capacity = capacity
+        storage.reserveCapacity(capacity)
+    }
+    
+    var count: Int {
+        storage.count
+    }
+    
+    var isEmpty: Bool {
+        storage.isEmpty
+    }
+    
+    var isFull: Bool {
+        storage.count == capacity
+    }
+    
+    /// The elements ordered from oldest to newest.
+    var elements: [Element] {
+        Array(storage[head...] + storage[..<head])
+    }
+    
+    /// The most recently pushed element.
+    var last: Element? {
+        storage.isEmpty ? nil : storage[(head + storage.count - 1) % storage.count]
+    }
+    
+    mutating func push(_ element: Element) {
+        if storage.count < capacity {
+            storage.append(element)
+        }
+        else {
+            storage[head] = element
+            head = (head + 1) % capacity
+        }
+    }
+    
+    mutating func removeAll() {
+        storage.removeAll(keepingCapacity: true)
+        head = 0
+    }
+}
@@ -0,0 +1,1073 @@
+//
+//  Scenarios.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Parametric scenes exercising joints, contacts and restitution. Each returns the handles of what it created,
+/// so that callers can watch or drive the outcome, like whether the tower falls or the last ball swings out.
+enum Scenarios {
+    struct WreckingBallConfig {
+        var pivot = Point(-1, 0, 9)
+        var cableLength = 8.0
+        var ballRadius = 0.8
+        var ballMass = 20.0
+        var ballRestitution = 0.0
+        /// Angle of the cable from the vertical at the start, where π / 2 starts the ball level with the pivot.
+        var releaseAngle = Double.pi / 2
+        /// The tower of unit cubes standing on the ground next to the lowest point of the ball.
+        var towerColumns = 2
+        var towerLevels = 6
+    }
+    
+    struct WreckingBall {
+        let ball: RigidHandle
+        let cable: Joint
+        let tower: [RigidHandle]
+    }
+    
+    /// Adds a ball on a cable, swinging down from the -x side into a tower of cubes.
+    @discardableResult
+    static func wreckingBall(_ config: WreckingBallConfig = WreckingBallConfig(), in world: World) -> WreckingBall {
+        var tower: [RigidHandle] = []
+        for column in 0 ..< config.towerColumns {
+            for level in 0 ..< config.towerLevels {
+                let y = 1.01 * (Double(column) - 0.5 * Double(config.towerColumns - 1))
+                let cube = world.addCube(at: Point(config.pivot.ex + 1.5, config.pivot.ey + y, 0.5 + 1.01 * Double(level)))
+                tower.append(world.handle(of: cube)!)
+            }
+        }
+        
+        let position = config.pivot - config.cableLength * Point(sin(config.releaseAngle), 0, cos(config.releaseAngle))
+        let ball = world.addConvex(.sphere(radius: config.ballRadius), at: position, mass: config.ballMass, color: Color(0.3))
+        ball.restitution = config.ballRestitution
+        let cable = world.addJoint(Joint(.distance(config.cableLength ... config.cableLength),
+                                         attaching: ball, at: .null, toWorldAt: config.pivot))
+        return WreckingBall(ball: world.handle(of: ball)!, cable: cable, tower: tower)
+    }
+    
+    struct NewtonCradleConfig {
+        /// The top of the frame, in the middle of the row of balls.
+        var center = Point(0, 0, 3)
+        var ballCount = 5
+        var ballRadius = 0.25
+        var ballMass = 1.0
+        var restitution = 1.0
+        /// Space between neighbouring balls at rest.
+        var gap = 1e-3
+        /// The depth of the balls below the frame.
+        var stringLength = 2.0
+        /// Each ball hangs on two strings running to both sides of the frame, which keeps it swinging along the row.
+        var stringSpread = 0.5
+        /// The number of balls raised on the -x end of the row, and their angle from the vertical.
+        var raisedCount = 1
+        var raisedAngle = Double.pi / 4
+        
+        /// The restitution propagation to set on the world holding the cradle, without which the raised balls
+        /// push the whole row along.
+        var restitutionPropagation: RestitutionPropagation {
+            .sequential(passes: 2 * ballCount)
+        }
+    }
+    
+    struct NewtonCradle {
+        /// The balls from the -x end of the row to the +x end.
+        let balls: [RigidHandle]
+        /// Both strings of every ball in the same order.
+        let strings: [(Joint, Joint)]
+    }
+    
+    /// Adds a row of touching balls along the x axis, of which the first ones are raised and let go.
+    /// If it works, as many balls swing out on the other end, and the ones in the middle stay put.
+    /// This takes the restitution propagation of the config, which is left to the caller building the world.
+    @discardableResult
+    static func newtonCradle(_ config: NewtonCradleConfig = NewtonCradleConfig(), in world: World) -> NewtonCradle {
+        let spacing = 2 * config.ballRadius + config.gap
+        let length = (config.stringLength.sq + config.stringSpread.sq).squareRoot()
+        var balls: [RigidHandle] = []
+        var strings: [(Joint, Joint)] = []
+        for i in 0 ..< config.ballCount {
+            let top = config.center + (Double(i) - 0.5 * Double(config.ballCount - 1)) * spacing * Point.ex
+            let angle = i < config.raisedCount ? config.raisedAngle : 0
+            // Raising swings the ball about the line through both ends of its strings, which keeps their lengths.
+            let position = top - config.stringLength * Point(sin(angle), 0, cos(angle))
+            let ball = world.addConvex(.sphere(radius: config.ballRadius), at: position, mass: config.ballMass, color: Color(0.7))
+            ball.restitution = config.restitution
+            
+            let pivots = (top - config.stringSpread * Point.ey, top + config.stringSpread * Point.ey)
+            strings.append((world.addJoint(Joint(.distance(length ... length), attaching: ball, at: .null, toWorldAt: pivots.0)),
+                            world.addJoint(Joint(.distance(length ... length), attaching: ball, at: .null, toWorldAt: pivots.1))))
+            balls.append(world.handle(of: ball)!)
+        }
+        return NewtonCradle(balls: balls, strings: strings)
+    }
+    
+    struct SpinningTopConfig {
+        /// Where the tip touches the ground.
+        var position = Point.null
+        var radius = 0.25
+        /// The distance from the rim down to the tip.
+        var height = 0.3
+        var mass = 0.5
+        /// Angle of the axis from the vertical at the start.
+        var tilt = 0.2
+        /// Angular speed about the axis in radians per second.
+        var spin = 60.0
+        var subStepCount = 100
+    }
+    
+    /// Adds a top standing on its tip, spinning about its tilted axis, which should precess around the vertical
+    /// instead of falling over. Turns on the gyroscopic torque of the world, without which it just topples.
+    @discardableResult
+    static func spinningTop(_ config: SpinningTopConfig = SpinningTopConfig(), in world: World) -> RigidHandle {
+        let segments = 16
+        var points = [-config.height * Point.ez]
+        for i in 0 ..< segments {
+            let angle = 2 * Double.pi * Double(i) / Double(segments)
+            let direction = Point(cos(angle), sin(angle), 0)
+            points.append(config.radius * direction)
+            points.append(0.8 * config.radius * direction + 0.2 * config.height * Point.ez)
+        }
+        return addSpinning(ConvexCollider.hull(of: points)!, standingAt: config.position, tilt: config.tilt, spin: config.spin,
+                           mass: config.mass, subStepCount: config.subStepCount, in: world)
+    }
+    
+    struct TippeTopConfig {
+        /// Where the ball touches the ground.
+        var position = Point.null
+        var radius = 0.2
+        /// Height of the flat top above the center of the ball, in multiples of the radius. The lower it is cut,
+        /// the further the center of mass lies below the center of the ball.
+        var cut = 0.4
+        var mass = 0.2
+        var tilt = 0.1
+        var spin = 80.0
+        var subStepCount = 100
+    }
+    
+    /// Adds a ball cut flat on top, spinning on its round side. Since its center of mass lies below the center of
+    /// the ball, friction at the contact should flip it over onto its flat side while it keeps spinning.
+    @discardableResult
+    static func tippeTop(_ config: TippeTopConfig = TippeTopConfig(), in world: World) -> RigidHandle {
+        // It only flips over if its contact can slide.
+        world.friction.model = .cone
+        let (longitudes, latitudes) = (12, 8)
+        let cutAngle = asin(config.cut)
+        var points = [-config.radius * Point.ez]
+        for j in 1 ... latitudes {
+            // From the bottom up to the rim of the cut.
+            let latitude = -Double.pi / 2 + (cutAngle + Double.pi / 2) * Double(j) / Double(latitudes)
+            for i in 0 ..< longitudes {
+                let longitude = 2 * Double.pi * (Double(i) + 0.5 * Double(j % 2)) / Double(longitudes)
+                points.append(config.radius * Point(cos(latitude) * cos(longitude), cos(latitude) * sin(longitude), sin(latitude)))
+            }
+        }
+        return addSpinning(ConvexCollider.hull(of: points)!, standingAt: config.position, tilt: config.tilt, spin: config.spin,
+                           mass: config.mass, subStepCount: config.subStepCount, in: world)
+    }
+    
+    /// Adds a body tilted about the x axis such that its lowest point touches the ground at the given position,
+    /// spinning about its local z axis.
+    private static func addSpinning(_ collider: ConvexCollider, standingAt position: Point, tilt: Double, spin: Double,
+                                    mass: Double, subStepCount: Int, in world: World) -> RigidHandle {
+        world.isGyroscopic = true
+        world.subStepCount = max(world.subStepCount, subStepCount)
+        
+        let quaternion = Quaternion(by: tilt, around: .ex)
+        let lowest = quaternion.act(on: collider.support(quaternion.inverse.act(on: -Point.ez)))
+        let rigid = world.addConvex(collider, at: position - lowest, mass: mass, color: Color(0.8, 0.3, 0.2))
+        rigid.frame.quaternion = quaternion
+        rigid.angularVelocity = spin * quaternion.act(on: .ez)
+        return world.handle(of: rigid)!
+    }
+    
+    struct AngularMomentumConfig {
+        /// The box spun, whose moments of inertia differ about all of its axes.
+        var halfExtents = Point(0.5, 0.3, 0.1)
+        var mass = 1.0
+        /// Angular velocity at the start in the local frame. Spinning mostly about the middle axis makes the box flip
+        /// over and over, which is the hardest case for an integrator.
+        var angularVelocity = Point(0.05, 10, 0.05)
+        /// Simulated time in seconds.
+        var duration = 10.0
+        var timeStep = 1.0 / 60
+        var subStepCount = 50
+        /// Without the gyroscopic torque, the angular velocity stays constant instead of the momentum.
+        var isGyroscopic = true
+    }
+    
+    struct AngularMomentumReport {
+        var initialMomentum: Point
+        var finalMomentum: Point
+        /// The deviation of the momentum from the initial one after each step, relative to its magnitude.
+        var drift: [Double]
+        /// The relative change of the kinetic energy over the whole run.
+        var energyDrift: Double
+        
+        var maxDrift: Double {
+            drift.max() ?? 0
+        }
+        
+        var finalDrift: Double {
+            drift.last ?? 0
+        }
+    }
+    
+    /// Spins a box in a world without gravity, far away from the ground, and measures how well its angular momentum
+    /// is conserved, which tells the quality of the integrator. Runs headlessly and needs nothing set up.
+    static func angularMomentumDrift(_ config: AngularMomentumConfig = AngularMomentumConfig()) -> AngularMomentumReport {
+        let world = World()
+        world.gravity = .null
+        world.isGyroscopic = config.isGyroscopic
+        world.subStepCount = config.subStepCount
+        let rigid = world.addConvex(.box(halfExtents: config.halfExtents), at: Point(0, 0, 100), mass: config.mass)
+        rigid.angularVelocity = rigid.frame.quaternion.act(on: config.angularVelocity)
+        
+        let initialMomentum = rigid.angularMomentum
+        let initialEnergy = rigid.kineticEnergy
+        var drift: [Double] = []
+        for _ in 0 ..< max(Int((config.duration / config.timeStep).rounded()), 1) {
+            world.integrate(dt: config.timeStep)
+            drift.append(rigid.angularMomentum.distance(to: initialMomentum) / max(initialMomentum.length, 1e-12))
+        }
+        return AngularMomentumReport(initialMomentum: initialMomentum,
+                                     finalMomentum: rigid.angularMomentum,
+                                     drift: drift,
+                                     energyDrift: (rigid.kineticEnergy - initialEnergy) / max(initialEnergy, 1e-12))
+    }
+    
+    struct DominoChainConfig {
+        /// Where the first domino stands, and the direction the chain runs in from there.
+        var start = Point(-4, -3, 0)
+        var heading = Point.ex
+        var count = 40
+        /// Thickness along the chain, width and height of each domino.
+        var size = Point(0.1, 0.5, 1)
+        /// Distance between the centers of neighbouring dominos along the chain.
+        var spacing = 0.5
+        /// The change of heading per meter along the chain, where zero is a straight line and positive values turn left.
+        var curvature = 0.15
+        var mass = 0.1
+        /// Friction between dominos, which should be low enough for a falling domino to slide down the next one.
+        var friction = 0.3
+        /// Friction between dominos and the ground, which should be high enough for their feet not to slip.
+        var groundFriction = 0.9
+        /// Angular speed in radians per second the first domino is tipped over with.
+        var push = 2.0
+    }
+    
+    struct DominoChain {
+        /// The dominos from the first one to the last one.
+        let dominos: [RigidHandle]
+        
+        /// The number of dominos leaning more than 45° away from the vertical, which is all of them after a clean run.
+        func toppledCount(in world: World) -> Int {
+            dominos.filter { world.rigid($0).frame.quaternion.act(on: .ez).ez < 0.5.squareRoot() }.count
+        }
+    }
+    
+    /// Adds a chain of dominos standing along a curve, of which the first one is tipped over.
+    @discardableResult
+    static func dominoChain(_ config: DominoChainConfig = DominoChainConfig(), in world: World) -> DominoChain {
+        // The friction coefficients apply to these pairs of materials only, so other rigids aren't affected.
+        // They only take effect once contacts may slide.
+        world.friction.model = .cone
+        let material = Material(name: "domino", friction: config.friction)
+        if world.ground.material == nil {
+            world.ground.material = Material(name: "ground", friction: world.friction.coefficient)
+        }
+        world.materials.setFriction(config.friction, between: material.name, and: material.name)
+        world.materials.setFriction(config.groundFriction, between: material.name, and: world.ground.material!.name)
+        
+        var dominos: [RigidHandle] = []
+        var position = config.start
+        var heading = atan2(config.heading.ey, config.heading.ex)
+        for _ in 0 ..< config.count {
+            let rigid = world.addConvex(.box(halfExtents: 0.5 * config.size), at: position + 0.5 * config.size.ez * Point.ez,
+                                        mass: config.mass, color: Color(0.9, 0.85, 0.7))
+            rigid.frame.quaternion = Quaternion(by: heading, around: .ez)
+            rigid.material = material
+            dominos.append(world.handle(of: rigid)!)
+            
+            heading += config.spacing * config.curvature
+            position = position + config.spacing * Point(cos(heading), sin(heading), 0)
+        }
+        
+        if let first = dominos.first.map({ world.rigid($0) }) {
+            first.angularVelocity = config.push * Point.ez.cross(first.frame.quaternion.act(on: .ex))
+        }
+        return DominoChain(dominos: dominos)
+    }
+    
+    struct RopeBridgeConfig {
+        /// Where the ropes of the first plank are tied to the world, between both edges of the bridge.
+        var start = Point(-4, 0, 3)
+        var span = 8.0
+        var plankCount = 10
+        var plankSize = Point(0.6, 1.2, 0.08)
+        var plankMass = 0.5
+        /// Rope length between neighbouring planks in addition to the gap between them.
+        var slack = 0.05
+        /// The ropes snap once they pull harder than this, in newtons.
+        var breakingForce = 250.0
+        /// A box dropped onto the middle of the bridge from the given height above it, if any.
+        var loadMass: Double? = 30
+        var loadHeight = 2.0
+    }
+    
+    struct RopeBridge {
+        let planks: [RigidHandle]
+        /// The ropes on both sides, from the start to the end of the bridge.
+        let ropes: [(Joint, Joint)]
+        let load: RigidHandle?
+        
+        /// Whether any rope has snapped.
+        func hasCollapsed(in world: World) -> Bool {
+            ropes.contains { pair in
+                [pair.0, pair.1].contains { rope in !world.joints.contains { $0 === rope } }
+            }
+        }
+    }
+    
+    /// Adds planks along the x axis held together by breakable ropes on both sides, and ropes tied to the world
+    /// at both ends, which collapse under the load.
+    @discardableResult
+    static func ropeBridge(_ config: RopeBridgeConfig = RopeBridgeConfig(), in world: World) -> RopeBridge {
+        let spacing = config.span / Double(config.plankCount)
+        let ropeLength = spacing - config.plankSize.ex + config.slack
+        let (halfLength, halfWidth) = (0.5 * config.plankSize.ex, 0.5 * config.plankSize.ey)
+        
+        // Ties the end of one plank to the start of the next one along both edges, where the world stands in
+        // for the missing plank at either end of the bridge.
+        func addRopes(from previous: Rigid?, to next: Rigid?, at position: Point) -> (Joint, Joint) {
+            func addRope(at y: Double) -> Joint {
+                let (end, start) = (Point(halfLength, y, 0), Point(-halfLength, y, 0))
+                let rope: Joint
+                if let previous = previous, let next = next {
+                    rope = Joint(.distance(0 ... ropeLength), between: previous, at: end, and: next, at: start)
+                }
+                else if let previous = previous {
+                    rope = Joint(.distance(0 ... ropeLength), attaching: previous, at: end, toWorldAt: position + y * Point.ey)
+                }
+                else {
+                    rope = Joint(.distance(0 ... ropeLength), attaching: next!, at: start, toWorldAt: position + y * Point.ey)
+                }
+                rope.breakingForce = config.breakingForce
+                return world.addJoint(rope)
+            }
+            return (addRope(at: -halfWidth), addRope(at: halfWidth))
+        }
+        
+        var planks: [Rigid] = []
+        var ropes: [(Joint, Joint)] = []
+        for i in 0 ..< config.plankCount {
+            let plank = world.addConvex(.box(halfExtents: 0.5 * config.plankSize), at: config.start + (Double(i) + 0.5) * spacing * Point.ex,
+                                        mass: config.plankMass, color: Color(0.6, 0.45, 0.3))
+            ropes.append(addRopes(from: planks.last, to: plank, at: config.start))
+            planks.append(plank)
+        }
+        ropes.append(addRopes(from: planks.last, to: nil, at: config.start + config.span * Point.ex))
+        
+        let load = config.loadMass.map { mass in
+            world.addConvex(.box(halfExtents: Point(0.3)), at: config.start + Point(0.5 * config.span, 0, config.loadHeight), mass: mass)
+        }
+        return RopeBridge(planks: planks.map { world.handle(of: $0)! }, ropes: ropes, load: load.flatMap { world.handle(of: $0) })
+    }
+    
+    struct MarbleRunConfig {
+        /// The lower opening of the funnel the marbles are dropped into.
+        var start = Point(0, 0, 4)
+        var marbleCount = 12
+        var marbleRadius = 0.12
+        var marbleMass = 0.1
+        /// The lever arm in meters by which contacts of the marbles resist rolling, see `Material.rollingFriction`.
+        var rollingFriction = 0.005
+        /// The angle by which both ramps descend.
+        var slope = 0.2
+    }
+    
+    struct MarbleRun {
+        let marbles: [RigidHandle]
+        /// The inside of the box catching the marbles at the end of the run.
+        let collector: BoundingBox
+        
+        /// The number of marbles which have arrived in the box.
+        func collectedCount(in world: World) -> Int {
+            marbles.filter { handle in
+                let position = world.rigid(handle).frame.position
+                return collector.overlaps(BoundingBox(lower: position, upper: position))
+            }.count
+        }
+    }
+    
+    /// Adds marbles dropping through a funnel onto a ramp along the x axis, which drops them onto a second one along
+    /// the y axis with high rails, which in turn drops them into a box, where they roll to a stop.
+    @discardableResult
+    static func marbleRun(_ config: MarbleRunConfig = MarbleRunConfig(), in world: World) -> MarbleRun {
+        let start = config.start
+        world.instantiate(.funnel(topRadius: 1, bottomRadius: 2 * config.marbleRadius, height: 1), at: Frame(position: start))
+        
+        let length = 4.5
+        let drop = length * sin(config.slope)
+        world.instantiate(.ramp(length: length, width: 0.8, slope: config.slope), at: Frame(position: start + Point(-0.5, 0, -0.3)))
+        let turn = start + Point(-0.5 + length * cos(config.slope) + 0.3, -0.5, -0.7 - drop)
+        world.instantiate(.ramp(length: length, width: 0.9, slope: config.slope, railHeight: 0.3),
+                          at: Frame(position: turn, quaternion: Quaternion(by: Double.pi / 2, around: .ez)))
+        
+        let boxSize = Point(1.6, 1.8, 0.6)
+        let boxCenter = Point(turn.ex, turn.ey + length * cos(config.slope) + 1.1, 0)
+        world.instantiate(.openBox(size: boxSize), at: Frame(position: boxCenter))
+        let collector = BoundingBox(lower: boxCenter - 0.5 * Point(boxSize.ex, boxSize.ey, 0), upper: boxCenter + 0.5 * boxSize)
+        
+        // The marbles start on a spiral above the funnel, so that they arrive one after the other.
+        // Their material only takes effect once contacts may slide.
+        world.friction.model = .cone
+        let material = Material(name: "marble", friction: 0.4, rollingFriction: config.rollingFriction)
+        var marbles: [RigidHandle] = []
+        for i in 0 ..< config.marbleCount {
+            let angle = 1.3 * Double(i)
+            let position = start + Point(0.6 * cos(angle), 0.6 * sin(angle), 1.5 + 0.4 * Double(i))
+            let marble = world.addConvex(.sphere(radius: config.marbleRadius), at: position, mass: config.marbleMass,
+                                         color: Color(0.2 + 0.3 * Float(i % 3), 0.4, 0.8 - 0.3 * Float(i % 3)))
+            marble.material = material
+            marbles.append(world.handle(of: marble)!)
+        }
+        return MarbleRun(marbles: marbles, collector: collector)
+    }
+    
+    struct ConveyorSorterConfig {
+        /// The start of the belt, which runs along the x axis.
+        var start = Point(-7, 0, 1)
+        var beltLength = 12.0
+        var beltWidth = 1.2
+        var beltSpeed = 1.5
+        /// Alternately tall and short boxes, of which the pusher sorts out the tall ones.
+        var boxCount = 6
+        var boxSpacing = 1.2
+        var shortHeight = 0.4
+        var tallHeight = 0.8
+        /// The distance along the belt of the light barrier, which spans the belt above the short boxes.
+        var sensorPosition = 8.0
+        var pusherSpeed = 3.0
+    }
+    
+    /// Pushes the boxes noticed by a sensor off the side of the belt, by driving a kinematic pusher once they
+    /// have travelled from the sensor to it.
+    final class ConveyorSorter {
+        let belt: RigidHandle
+        let sensor: RigidHandle
+        let pusher: RigidHandle
+        /// All boxes, of which the ones at even indices are tall.
+        let boxes: [RigidHandle]
+        /// The insides of the bins the pushed boxes and those reaching the end of the belt fall into.
+        let sideBin: BoundingBox
+        let endBin: BoundingBox
+        /// The time from noticing a box until pushing it.
+        private let delay: Double
+        /// How far the pusher moves across the belt, from its position beside it.
+        private let stroke: ClosedRange<Double>
+        private let speed: Double
+        /// The times at which the pusher is due for the noticed boxes, earliest first.
+        private var dueTimes: [Double] = []
+        private var noticed = Set<RigidHandle>()
+        private var isExtending = false
+        
+        fileprivate init(belt: RigidHandle, sensor: RigidHandle, pusher: RigidHandle, boxes: [RigidHandle], sideBin: BoundingBox, endBin: BoundingBox,
+                         delay: Double, stroke: ClosedRange<Double>, speed: Double) {
+            self.belt = belt
+            self.sensor = sensor
+            self.pusher = pusher
+            self.boxes = boxes
+            self.sideBin = sideBin
+            self.endBin = endBin
+            self.delay = delay
+            self.stroke = stroke
+            self.speed = speed
+        }
+        
+        /// Notices boxes entering the sensor, and drives the pusher out and back when one of them arrives in front of it.
+        fileprivate func update(in world: World) {
+            for box in world.rigids(touching: sensor) where !noticed.contains(box) {
+                noticed.insert(box)
+                dueTimes.append(world.time + delay)
+            }
+            
+            let rigid = world.rigid(pusher)
+            let extent = rigid.frame.position.ey
+            if isExtending && extent >= stroke.upperBound {
+                isExtending = false
+            }
+            else if !isExtending, let due = dueTimes.first, world.time >= due {
+                dueTimes.removeFirst()
+                isExtending = true
+            }
+            
+            if isExtending {
+                rigid.velocity = speed * .ey
+            }
+            else {
+                rigid.velocity = extent > stroke.lowerBound ? -speed * .ey : .null
+            }
+        }
+        
+        /// The number of tall boxes in the side bin plus the short ones in the end bin, which is all of them
+        /// once the belt is empty.
+        func sortedCount(in world: World) -> Int {
+            boxes.enumerated().filter { index, box in
+                let position = world.rigid(box).frame.position
+                let bin = index % 2 == 0 ? sideBin : endBin
+                return bin.overlaps(BoundingBox(lower: position, upper: position))
+            }.count
+        }
+    }
+    
+    /// Adds a conveyor belt carrying boxes past a light barrier, and a pusher sorting out the boxes tall enough
+    /// to cross the barrier into a bin beside the belt. The others fall into a bin at the end of the belt.
+    /// The sorter is driven by a stage of the world's pipeline.
+    @discardableResult
+    static func conveyorSorter(_ config: ConveyorSorterConfig = ConveyorSorterConfig(), in world: World) -> ConveyorSorter {
+        let start = config.start
+        let halfWidth = 0.5 * config.beltWidth
+        let corners = [Point(0, -halfWidth, 0), Point(config.beltLength, -halfWidth, 0), Point(config.beltLength, halfWidth, 0), Point(0, halfWidth, 0)]
+        let belt = world.addStaticMesh(TriangleMeshCollider(vertices: corners.map { start + $0 }, triangles: [(0, 1, 2), (0, 2, 3)]),
+                                       color: Color(0.25))
+        belt.surfaceVelocity = config.beltSpeed * .ex
+        
+        let boxLength = 0.4
+        var boxes: [RigidHandle] = []
+        for i in 0 ..< config.boxCount {
+            let height = i % 2 == 0 ? config.tallHeight : config.shortHeight
+            let position = start + Point(0.5 + Double(i) * config.boxSpacing, 0, 0.5 * height)
+            let box = world.addConvex(.box(halfExtents: 0.5 * Point(boxLength, boxLength, height)), at: position, mass: 1,
+                                      color: i % 2 == 0 ? Color(0.8, 0.5, 0.2) : Color(0.3, 0.6, 0.8))
+            // Already moving with the belt, or the tall ones would topple over when it catches them.
+            box.velocity = config.beltSpeed * .ex
+            boxes.append(world.handle(of: box)!)
+        }
+        
+        let barrier = start + Point(config.sensorPosition, 0, 0.5 * (config.shortHeight + config.tallHeight))
+        let sensor = addStatic(.box(halfExtents: Point(0.05, halfWidth + 0.2, 0.05)), at: barrier, in: world)
+        sensor.isSensor = true
+        
+        // The pusher waits beside the belt past the sensor. It is long, since the box moves on while being pushed.
+        let pusherSize = Point(1.5, 0.2, 0.5)
+        let restPosition = start.ey - halfWidth - 0.5 * pusherSize.ey - 0.05
+        let pusherPosition = Point(barrier.ex + 0.8, restPosition, start.ez + 0.05 + 0.5 * pusherSize.ez)
+        let pusher = addStatic(.box(halfExtents: 0.5 * pusherSize), at: pusherPosition, in: world)
+        pusher.isKinematic = true
+        // A box is noticed as soon as its front reaches the sensor, and the pusher starts while its middle
+        // is still a bit short of the pusher's.
+        let delay = (pusherPosition.ex - barrier.ex + 0.5 * boxLength - 0.3) / config.beltSpeed
+        let stroke = restPosition ... restPosition + config.beltWidth + 0.1
+        
+        let binSize = Point(1.8, 1.8, 0.5)
+        let sideBinCenter = Point(pusherPosition.ex + 0.5, start.ey + halfWidth + 1, 0)
+        world.instantiate(.openBox(size: binSize), at: Frame(position: sideBinCenter))
+        let endBinCenter = Point(start.ex + config.beltLength + 1, start.ey, 0)
+        world.instantiate(.openBox(size: binSize), at: Frame(position: endBinCenter))
+        func inside(at center: Point) -> BoundingBox {
+            BoundingBox(lower: center - 0.5 * Point(binSize.ex, binSize.ey, 0), upper: center + 0.5 * binSize)
+        }
+        
+        let sorter = ConveyorSorter(belt: world.handle(of: belt)!, sensor: world.handle(of: sensor)!, pusher: world.handle(of: pusher)!,
+                                    boxes: boxes, sideBin: inside(at: sideBinCenter), endBin: inside(at: endBinCenter),
+                                    delay: delay, stroke: stroke, speed: config.pusherSpeed)
+        world.pipeline.insert(StepStage(name: "Sort") { world, _ in
+            sorter.update(in: world)
+        }, before: World.solve.name)
+        return sorter
+    }
+    
+    struct TurretConfig {
+        var muzzle = Point(-6, 0, 1.5)
+        /// The thin wall the turret fires at, standing on the ground across the x axis at this distance from the muzzle.
+        var wallDistance = 6.0
+        var wallThickness = 0.02
+        var wallSize = (width: 4.0, height: 3.0)
+        var projectileRadius = 0.03
+        var projectileMass = 0.05
+        /// Fast enough to cross the wall within a sub-step of the default solver.
+        var muzzleSpeed = 600.0
+        var shotCount = 12
+        var interval = 0.25
+        /// The shots are spread over a cone of this half angle around the x axis.
+        var spread = 0.15
+    }
+    
+    /// Fires small fast spheres at a thin wall, which they pass through unless continuous collision is enabled.
+    final class Turret {
+        let wall: RigidHandle
+        private(set) var projectiles: [RigidHandle] = []
+        private let config: TurretConfig
+        private var nextShotTime: Double
+        
+        fileprivate init(wall: RigidHandle, config: TurretConfig, startTime: Double) {
+            self.wall = wall
+            self.config = config
+            nextShotTime = startTime
+        }
+        
+        /// Whether all shots have been fired.
+        var isDone: Bool {
+            projectiles.count == config.shotCount
+        }
+        
+        fileprivate func fire(in world: World) {
+            guard !isDone, world.time >= nextShotTime else {
+                return
+            }
+            nextShotTime += config.interval
+            
+            // The shots go around the cone in a spiral, so that they hit different spots.
+            let index = Double(projectiles.count)
+            let angle = config.spread * (index + 1) / Double(config.shotCount)
+            let direction = Quaternion(by: 2.4 * index, around: .ex).act(on: Point(cos(angle), sin(angle), 0))
+            let projectile = world.addConvex(.sphere(radius: config.projectileRadius), at: config.muzzle, mass: config.projectileMass,
+                                             color: Color(0.9, 0.2, 0.2))
+            projectile.velocity = config.muzzleSpeed * direction
+            projectiles.append(world.handle(of: projectile)!)
+        }
+        
+        /// The number of projectiles which ended up behind the wall.
+        func tunneledCount(in world: World) -> Int {
+            let back = config.muzzle.ex + config.wallDistance + 0.5 * config.wallThickness
+            return projectiles.filter { world.rigid($0).frame.position.ex > back }.count
+        }
+    }
+    
+    /// Adds a turret firing at a thin wall, driven by a stage of the world's pipeline. Comparing the tunneled count
+    /// with continuous collision on and off shows whether it works, see `tunnelingCheck`.
+    @discardableResult
+    static func turret(_ config: TurretConfig = TurretConfig(), in world: World) -> Turret {
+        let halfExtents = 0.5 * Point(config.wallThickness, config.wallSize.width, config.wallSize.height)
+        let wall = addStatic(.box(halfExtents: halfExtents),
+                             at: Point(config.muzzle.ex + config.wallDistance, config.muzzle.ey, halfExtents.ez), in: world)
+        let turret = Turret(wall: world.handle(of: wall)!, config: config, startTime: world.time)
+        world.pipeline.insert(StepStage(name: "Fire") { world, _ in
+            turret.fire(in: world)
+        }, before: World.solve.name)
+        return turret
+    }
+    
+    /// Fires all shots of a turret headlessly, and returns how many of them passed through the wall.
+    static func tunnelingCheck(_ config: TurretConfig = TurretConfig(), continuousCollision: ContinuousCollision?, dt: Double = 1.0 / 60) -> Int {
+        let world = World()
+        world.continuousCollision = continuousCollision
+        let turret = Scenarios.turret(config, in: world)
+        while !turret.isDone {
+            world.integrate(dt: dt)
+        }
+        // The last shot needs to reach the wall.
+        for _ in 0 ..< Int((config.wallDistance / config.muzzleSpeed / dt).rounded(.up)) + 2 {
+            world.integrate(dt: dt)
+        }
+        return turret.tunneledCount(in: world)
+    }
+    
+    struct FloatingCratesConfig {
+        /// The center of the pool floor, which is level with the ground.
+        var poolCenter = Point.null
+        var poolSize = Point(8, 8, 2)
+        var waterDepth = 1.4
+        /// How far the surface rises and falls with the waves.
+        var waveAmplitude = 0.15
+        /// The largest slope of the surface, which rocks back and forth about the y axis.
+        var waveSlope = 0.08
+        var wavePeriod = 3.0
+        var drag = 400.0
+        var crateSize = 0.8
+        /// The densities of the crates in kilograms per cubic meter, one crate each. All of them float in water.
+        var crateDensities = [200.0, 350, 500, 650, 800, 950]
+    }
+    
+    /// Crates floating in a pool whose surface moves with the waves, driven by a stage of the world's pipeline.
+    final class FloatingCrates {
+        let water: BuoyancyForce
+        /// A sensor showing the surface of the water.
+        let surface: RigidHandle
+        let crates: [RigidHandle]
+        private let config: FloatingCratesConfig
+        
+        fileprivate init(water: BuoyancyForce, surface: RigidHandle, crates: [RigidHandle], config: FloatingCratesConfig) {
+            self.water = water
+            self.surface = surface
+            self.crates = crates
+            self.config = config
+        }
+        
+        /// Moves the surface to where the waves are at the current time.
+        fileprivate func update(in world: World) {
+            let phase = 2 * Double.pi * world.time / config.wavePeriod
+            // The surface is steepest while passing the rest level, like a travelling wave.
+            let tilt = Quaternion(by: atan(config.waveSlope * cos(phase)), around: .ey)
+            let center = config.poolCenter + (config.waterDepth + config.waveAmplitude * sin(phase)) * Point.ez
+            let normal = tilt.act(on: .ez)
+            water.surface = Plane(normal: normal, offset: normal.dot(center))
+            
+            let rigid = world.rigid(surface)
+            rigid.frame = Frame(position: center, quaternion: tilt)
+            rigid.pastFrame = rigid.frame
+        }
+        
+        /// The number of crates bobbing at the surface, rather than lying on the floor or lying around outside the pool.
+        func floatingCount(in world: World) -> Int {
+            crates.filter { crate in
+                let position = world.rigid(crate).frame.position
+                return abs(water.surface.normal.dot(position) - water.surface.offset) < config.crateSize
+            }.count
+        }
+    }
+    
+    /// Adds a pool of water with crates of different densities dropped into it, which float the deeper the denser they are.
+    /// The water is a force generator lifting the crates by buoyancy and slowing them by drag.
+    @discardableResult
+    static func floatingCrates(_ config: FloatingCratesConfig = FloatingCratesConfig(), in world: World) -> FloatingCrates {
+        world.instantiate(.openBox(size: config.poolSize), at: Frame(position: config.poolCenter))
+        
+        let half = 0.5 * config.poolSize
+        let corners = [Point(-half.ex, -half.ey, 0), Point(half.ex, -half.ey, 0), Point(half.ex, half.ey, 0), Point(-half.ex, half.ey, 0)]
+        let sheet = world.addStaticMesh(TriangleMeshCollider(vertices: corners, triangles: [(0, 1, 2), (0, 2, 3)], sidedness: .doubleSided),
+                                        color: Color(0.2, 0.4, 0.7))
+        sheet.isSensor = true
+        sheet.isKinematic = true
+        
+        let levelSurface = config.poolCenter + config.waterDepth * Point.ez
+        let water = BuoyancyForce(surface: Plane(normal: .ez, offset: levelSurface.ez), gravity: world.gravity, drag: config.drag)
+        world.forceGenerators.append(water)
+        
+        // The crates are dropped in two rows, tilted so that they need to right themselves.
+        var crates: [RigidHandle] = []
+        let columns = (config.crateDensities.count + 1) / 2
+        for (index, density) in config.crateDensities.enumerated() {
+            let (row, column) = (index / columns, index % columns)
+            let offset = Point((Double(column) - 0.5 * Double(columns - 1)) * 2 * config.crateSize, (Double(row) - 0.5) * 3 * config.crateSize, 1.5)
+            let mass = density * config.crateSize.sq * config.crateSize
+            let shade = Float(density / 1000)
+            let crate = world.addConvex(.box(halfExtents: Point(0.5 * config.crateSize)), at: levelSurface + offset, mass: mass,
+                                        color: Color(0.9 - 0.5 * shade, 0.7 - 0.4 * shade, 0.4 - 0.3 * shade))
+            crate.frame.quaternion = Quaternion(by: 0.3 * Double(index + 1), around: Point(1, 1, 0).normalize)
+            crates.append(world.handle(of: crate)!)
+        }
+        
+        let floating = FloatingCrates(water: water, surface: world.handle(of: sheet)!, crates: crates, config: config)
+        world.pipeline.insert(StepStage(name: "Waves") { world, _ in
+            floating.update(in: world)
+        }, before: World.applyForces.name)
+        return floating
+    }
+    
+    struct RobotArmConfig {
+        /// The foot of the pedestal the arm is mounted on.
+        var base = Point.null
+        var upperArmLength = 1.2
+        var forearmLength = 1.0
+        var maxTorque = 300.0
+        /// Radians per second of motor speed for every radian left to the angle found by the inverse kinematics.
+        var gain = 8.0
+        var maxSpeed = 3.0
+        /// The target goes round a figure eight about this point relative to the base, taking the period for each round.
+        var targetCenter = Point(1.2, 0, 1.2)
+        var targetRadius = 0.6
+        var targetPeriod = 6.0
+    }
+    
+    /// An arm of three links turning about a vertical, a shoulder and an elbow hinge, whose motors are steered towards
+    /// the angles found by inverse kinematics for a moving target. Driven by a stage of the world's pipeline.
+    final class RobotArm {
+        /// The turret, the upper arm and the forearm.
+        let links: [RigidHandle]
+        /// The hinges of the links, from the base to the tip.
+        let joints: [Joint]
+        /// A sensor marking the target.
+        let marker: RigidHandle
+        let chain: HingeChain
+        /// The tip in the local frame of the forearm.
+        private let tip: Point
+        private let config: RobotArmConfig
+        
+        fileprivate init(links: [RigidHandle], joints: [Joint], marker: RigidHandle, chain: HingeChain, tip: Point, config: RobotArmConfig) {
+            self.links = links
+            self.joints = joints
+            self.marker = marker
+            self.chain = chain
+            self.tip = tip
+            self.config = config
+        }
+        
+        func target(at time: Double) -> Point {
+            let phase = 2 * Double.pi * time / config.targetPeriod
+            return config.base + config.targetCenter + config.targetRadius * Point(0.4 * cos(phase), sin(phase), 0.5 * sin(2 * phase))
+        }
+        
+        /// Moves the marker to the target, and steers the motors towards the angles reaching it.
+        fileprivate func update(in world: World) {
+            let target = self.target(at: world.time)
+            let rigid = world.rigid(marker)
+            rigid.frame.position = target
+            rigid.pastFrame = rigid.frame
+            
+            let angles = chain.solve(reaching: target, from: joints.map { $0.angle })
+            for (joint, angle) in zip(joints, angles) {
+                joint.steerMotor(towards: angle, gain: config.gain, maxSpeed: config.maxSpeed)
+            }
+        }
+        
+        /// How far the tip of the arm is from the target.
+        func tipDistance(in world: World) -> Double {
+            world.rigid(links[2]).frame.act(tip).distance(to: target(at: world.time))
+        }
+    }
+    
+    /// Adds a robot arm on a pedestal reaching for a target going round a figure eight, see `RobotArm`.
+    @discardableResult
+    static func robotArm(_ config: RobotArmConfig = RobotArmConfig(), in world: World) -> RobotArm {
+        let base = config.base
+        let pedestal = addStatic(.box(halfExtents: Point(0.3, 0.3, 0.25)), at: base + Point(0, 0, 0.25), in: world)
+        
+        // The arm starts out stretched along the x axis, which is where all angles are zero.
+        let shoulder = base + Point(0, 0, 0.9)
+        let elbow = shoulder + config.upperArmLength * Point.ex
+        let tip = elbow + config.forearmLength * Point.ex
+        let turret = world.addConvex(.box(halfExtents: Point(0.15)), at: base + Point(0, 0, 0.7), mass: 2, color: Color(0.3))
+        let upperArm = world.addConvex(.box(halfExtents: Point(0.5 * config.upperArmLength, 0.08, 0.08)), at: 0.5 * (shoulder + elbow),
+                                       mass: 1.5, color: Color(0.9, 0.6, 0.1))
+        let forearm = world.addConvex(.box(halfExtents: Point(0.5 * config.forearmLength, 0.06, 0.06)), at: 0.5 * (elbow + tip),
+                                      mass: 1, color: Color(0.9, 0.6, 0.1))
+        
+        let hinges = [
+            HingeChain.Hinge(origin: base + Point(0, 0, 0.5), axis: .ez, limits: nil),
+            HingeChain.Hinge(origin: shoulder, axis: .ey, limits: -1.5 ... 1.5),
+            HingeChain.Hinge(origin: elbow, axis: .ey, limits: -2.5 ... 2.5)
+        ]
+        let joints = zip([pedestal, turret, upperArm], [turret, upperArm, forearm]).enumerated().map { index, rigids -> Joint in
+            let hinge = hinges[index]
+            let joint = Joint(.hinge(axes: (hinge.axis, hinge.axis)), between: rigids.0, and: rigids.1, at: hinge.origin)
+            joint.angleLimits = hinge.limits
+            joint.motor = JointMotor(targetSpeed: 0, maxTorque: config.maxTorque)
+            return world.addJoint(joint)
+        }
+        
+        let marker = addStatic(.sphere(radius: 0.08), at: base + config.targetCenter, in: world)
+        marker.isSensor = true
+        marker.isKinematic = true
+        
+        let arm = RobotArm(links: [turret, upperArm, forearm].map { world.handle(of: $0)! }, joints: joints, marker: world.handle(of: marker)!,
+                           chain: HingeChain(hinges: hinges, tip: tip), tip: forearm.frame.inverse.act(tip), config: config)
+        world.pipeline.insert(StepStage(name: "Reach") { world, _ in
+            arm.update(in: world)
+        }, before: World.applyForces.name)
+        return arm
+    }
+    
+    struct BalanceBotConfig {
+        /// Where the bot stands on the ground. It balances along the x axis.
+        var position = Point.null
+        var wheelRadius = 0.25
+        var wheelMass = 0.5
+        var bodySize = Point(0.3, 0.4, 0.8)
+        var bodyMass = 4.0
+        /// The torque of each wheel motor in newton meters.
+        var maxTorque = 8.0
+        /// The lean of the body at the start, in radians towards the x axis.
+        var initialTilt = 0.1
+        /// The gains of the controller, which turns the tilt left to the target tilt into an angular acceleration
+        /// of the wheels in radians per second squared.
+        var balance = PIDController(proportionalGain: 120, integralGain: 20, derivativeGain: 15, integralLimit: 1)
+        /// The bot leans back towards where it started by this many radians per meter it is away from there,
+        /// and against its speed by this many radians per meter per second, up to the largest lean.
+        var positionGain = 0.1
+        var speedGain = 0.1
+        var maxLean = 0.15
+    }
+    
+    /// A body on two wheels balancing upright like an inverted pendulum. Its controller reads the tilt of the body,
+    /// as a gyroscope would, and the hinge angles of the wheels, as encoders would, and accelerates the wheels
+    /// under the body through their motors. Driven by a stage of the world's pipeline.
+    final class BalanceBot {
+        struct Report {
+            var hasFallen: Bool
+            var finalTilt: Double
+            /// How far the bot is from where it started along the x axis.
+            var finalDistance: Double
+            /// The largest torque any wheel motor applied, which must not exceed the maximum.
+            var peakTorque: Double
+            var maxTorque: Double
+            /// Steps in which any wheel motor applied its maximum torque.
+            var saturatedSteps: Int
+            /// Whether stepping directly and through a fixed time step driver with irregular frames gave the same states.
+            var isDeterministic: Bool
+        }
+        
+        let body: RigidHandle
+        let wheels: [RigidHandle]
+        /// The hinges of both wheels, with the body as their first rigid.
+        let joints: [Joint]
+        private(set) var controller: PIDController
+        /// The tilt of the body towards the x axis as measured in the last step, in radians.
+        private(set) var tilt: Double
+        /// How far the wheels rolled since the start, measured by their hinge angles.
+        private(set) var distance = 0.0
+        private(set) var peakTorque = 0.0
+        private(set) var saturatedSteps = 0
+        /// The mean hinge angle of the wheels, summed over the steps so that it isn't confined to -π ... π.
+        private var hingeAngle: Double
+        private var hingeAngles: [Double]
+        private let config: BalanceBotConfig
+        
+        fileprivate init(body: RigidHandle, wheels: [RigidHandle], joints: [Joint], config: BalanceBotConfig) {
+            self.body = body
+            self.wheels = wheels
+            self.joints = joints
+            self.config = config
+            controller = config.balance
+            tilt = config.initialTilt
+            hingeAngles = joints.map { $0.angle }
+            hingeAngle = hingeAngles.reduce(0, +) / Double(hingeAngles.count)
+        }
+        
+        var hasFallen: Bool {
+            abs(tilt) > 1
+        }
+        
+        /// Measures tilt and distance, and sets the speeds of the motors such that the wheels accelerate as the controller says.
+        fileprivate func update(in world: World, dt: Double) {
+            guard dt > 0 else {
+                return
+            }
+            for joint in joints {
+                peakTorque = max(peakTorque, abs(joint.motorTorque))
+            }
+            if joints.contains(where: { $0.isMotorSaturated }) {
+                saturatedSteps += 1
+            }
+            
+            let frame = world.rigid(body).frame
+            let up = frame.quaternion.act(on: .ez)
+            let forward = frame.quaternion.act(on: .ey).cross(.ez).normalize
+            let tilt = atan2(up.dot(forward), up.ez)
+            let tiltRate = (tilt - self.tilt) / dt
+            self.tilt = tilt
+            
+            // A wheel turns relative to the ground by the tilt of the body and its hinge angle together.
+            for (index, joint) in joints.enumerated() {
+                var delta = joint.angle - hingeAngles[index]
+                delta -= 2 * .pi * (delta / (2 * .pi)).rounded()
+                hingeAngles[index] = joint.angle
+                hingeAngle += delta / Double(joints.count)
+            }
+            let previousDistance = distance
+            distance = config.wheelRadius * (hingeAngle + tilt)
+            let speed = (distance - previousDistance) / dt
+            
+            if hasFallen {
+                for joint in joints {
+                    joint.motor?.targetSpeed = 0
+                }
+                return
+            }
+            let targetTilt = (-config.positionGain * distance - config.speedGain * speed).clamped(to: -config.maxLean ... config.maxLean)
+            let acceleration = controller.update(error: tilt - targetTilt, dt: dt)
+            // The motors turn the wheels relative to the body, which turns itself.
+            let wheelSpeed = speed / config.wheelRadius + acceleration * dt
+            for joint in joints {
+                joint.motor?.targetSpeed = wheelSpeed - tiltRate
+            }
+        }
+    }
+    
+    /// Adds a balancing bot leaning slightly forward at the start, see `BalanceBot`.
+    @discardableResult
+    static func balanceBot(_ config: BalanceBotConfig = BalanceBotConfig(), in world: World) -> BalanceBot {
+        let axle = config.position + config.wheelRadius * Point.ez
+        let body = world.addConvex(.box(halfExtents: 0.5 * config.bodySize), at: axle + 0.5 * config.bodySize.ez * Point.ez,
+                                   mass: config.bodyMass, color: Color(0.2, 0.5, 0.8))
+        
+        let joints = [-1.0, 1.0].map { side -> Joint in
+            let center = axle + side * (0.5 * config.bodySize.ey + config.wheelRadius + 0.02) * Point.ey
+            let wheel = world.addConvex(.sphere(radius: config.wheelRadius), at: center, mass: config.wheelMass, color: Color(0.3))
+            let joint = Joint(.hinge(axes: (.ey, .ey)), between: body, and: wheel, at: center)
+            joint.motor = JointMotor(targetSpeed: 0, maxTorque: config.maxTorque)
+            return world.addJoint(joint)
+        }
+        
+        // The body is tilted about the axle after the hinges were created upright, so that the wheels start out
+        // turned against the body by the tilt, just as if they hadn't rolled.
+        let rotation = Quaternion(by: config.initialTilt, around: .ey)
+        body.frame = Frame(position: axle + rotation.act(on: axle.to(body.frame.position)), quaternion: rotation)
+        body.pastFrame = body.frame
+        
+        let bot = BalanceBot(body: world.handle(of: body)!, wheels: joints.map { world.handle(of: $0.rigids.1)! }, joints: joints, config: config)
+        world.pipeline.insert(StepStage(name: "Balance") { world, dt in
+            bot.update(in: world, dt: dt)
+        }, before: World.applyForces.name)
+        return bot
+    }
+    
+    /// Balances a bot headlessly, once by stepping the world directly and once through a fixed time step driver
+    /// fed with frames of random durations, and reports how it went.
+    static func balanceCheck(_ config: BalanceBotConfig = BalanceBotConfig(), duration: Double = 10, dt: Double = 1.0 / 60) -> BalanceBot.Report {
+        let stepCount = Int((duration / dt).rounded())
+        
+        let world = World()
+        let bot = Scenarios.balanceBot(config, in: world)
+        for _ in 0 ..< stepCount {
+            world.integrate(dt: dt)
+        }
+        
+        // Frames never exceed a step, so that the driver stops exactly at the same step count.
+        let drivenWorld = World()
+        let drivenBot = Scenarios.balanceBot(config, in: drivenWorld)
+        let driver = FixedTimeStepDriver(world: drivenWorld, config: SolverConfig(fixedTimeStep: dt))
+        var generator = SeededGenerator(seed: 1)
+        while drivenWorld.stepCount < stepCount {
+            driver.advance(by: .random(in: 0 ... dt, using: &generator))
+        }
+        
+        let states = { (world: World, bot: BalanceBot) in
+            try? JSONEncoder().encode(([bot.body] + bot.wheels).map { world.rigid($0).state })
+        }
+        return BalanceBot.Report(hasFallen: bot.hasFallen,
+                                 finalTilt: bot.tilt,
+                                 finalDistance: bot.distance,
+                                 peakTorque: bot.peakTorque,
+                                 maxTorque: config.maxTorque,
+                                 saturatedSteps: bot.saturatedSteps,
+                                 isDeterministic: states(world, bot) != nil && states(world, bot) == states(drivenWorld, drivenBot))
+    }
+    
+    /// Adds a static rigid, which can be made a sensor or kinematic afterwards.
+    private static func addStatic(_ collider: ConvexCollider, at position: Point, in world: World) -> Rigid {
+        var prefab = Prefab()
+        prefab.addBody(.convex(collider), mass: nil, at: Frame())
+        return world.instantiate(prefab, at: Frame(position: position))[0]
+    }
+}
+
+
+extension Scenarios.BalanceBot.Report: CustomStringConvertible {
+    var description: String {
+        [
+            "fallen:            \(hasFallen ? "yes" : "no")",
+            String(format: "final tilt:        %.4f rad", finalTilt),
+            String(format: "final distance:    %.3f m", finalDistance),
+            String(format: "peak torque:       %.2f of %.2f N m", peakTorque, maxTorque),
+            "saturated steps:   \(saturatedSteps)",
+            "deterministic:     \(isDeterministic ? "yes" : "no")"
+        ].joined(separator: "\n")
+    }
+}
+
+
+extension Scenarios.AngularMomentumReport: CustomStringConvertible {
+    var description: String {
+        [
+            String(format: "max drift:         %.3f %%", 100 * maxDrift),
+            String(format: "final drift:       %.3f %%", 100 * finalDrift),
+            String(format: "energy drift:      %.3f %%", 100 * energyDrift)
+        ].joined(separator: "\n")
+    }
+}
@@ -0,0 +1,402 @@
+//
+//  Scene.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Everything needed to rebuild a world in exactly the mechanical state it was saved in, including joints
+/// and bodies in mid-swing. Remote bodies are driven from elsewhere and are therefore not part of a scene.
+struct Scene: Codable {
+    struct RigidDescription: Codable {
+        var collider: Collider
+        var inverseMass: Double
+        var inverseInertia: Point
+        var externalForce: Point
+        var state: RigidState
+        var isKinematic: Bool
+        var material: Material?
+        var groups: UInt32
+        var anisotropicFriction: AnisotropicFriction?
+        var maxAngularSpeed: Double?
+        var spinDamping: SpinDamping?
+        var timeScale: Double?
+        var restitution: Double?
+        var isSensor: Bool?
+        var surfaceVelocity: Point?
+        var principalFrame: Frame?
+        var softContact: SoftContact?
+        var userTag: Int?
+    }
+    
+    struct JointDescription: Codable {
+        var kind: JointKind
+        /// Indices of the two rigids, where -1 stands for the world.
+        var rigids: [Int]
+        var anchors: [Point]
+        var compliance: Double
+        /// Lower and upper bound of the hinge angle.
+        var angleLimits: [Double]?
+        var motor: JointMotor?
+        var referenceAngle: Double?
+        var breakingForce: Double?
+        /// Whether the joint keeps its rigids from colliding, which scenes saved before only recorded in the disabled pairs.
+        var disablesCollision: Bool?
+        
+        fileprivate enum CodingKeys: String, CodingKey {
+            case kind, rigids, anchors, compliance, angleLimits, motor, referenceAngle, breakingForce, disablesCollision
+        }
+    }
+    
+    struct SpringDescription: Codable {
+        /// Indices of the two rigids, where -1 stands for the world.
+        var rigids: [Int]
+        var anchors: [Point]
+        var restLength: Double
+        var stiffness: Double
+        var damping: Double
+        var model: SpringModel
+    }
+    
+    struct SuspensionDescription: Codable {
+        /// Indices of the chassis and the wheel.
+        var rigids: [Int]
+        var anchors: [Point]
+        var axis: Point
+        /// Lower and upper bound of the travel along the axis.
+        var travel: [Double]
+        var restLength: Double
+        var stiffness: Double
+        var damping: Double
+    }
+    
+    /// The coefficients of a pair of materials, see `MaterialTable`.
+    struct MaterialPairDescription: Codable {
+        /// The names of both materials, or a single one for contacts of a material with itself.
+        var materials: [String]
+        var coefficients: MaterialTable.Override
+    }
+    
+    enum ValidationError: Error {
+        /// The rigid at the index has an inverse mass or inverse moments of inertia which are negative or not finite,
+        /// i.e. a mass which is neither positive nor that of a static rigid.
+        case invalidMass(rigid: Int)
+        /// The joint at the index doesn't refer to two rigids of the scene or the world, or lacks their anchors.
+        case invalidJoint(Int)
+        /// The spring at the index doesn't refer to two rigids of the scene or the world, or lacks their anchors.
+        case invalidSpring(Int)
+        /// The suspension at the index doesn't refer to two rigids of the scene, lacks their anchors, or has invalid travel.
+        case invalidSuspension(Int)
+        /// The disabled pair at the index doesn't refer to two rigids of the scene.
+        case invalidDisabledPair(Int)
+        /// The material pair at the index doesn't name one or two materials.
+        case invalidMaterialPair(Int)
+    }
+    
+    var rigids: [RigidDescription]
+    /// The joints other than those solving springs and suspensions, which are rebuilt with them.
+    var joints: [JointDescription]
+    var springs: [SpringDescription]?
+    var suspensions: [SuspensionDescription]?
+    /// Index pairs of rigids which don't collide besides those kept apart by joints, which scenes saved before
+    /// joints recorded it themselves also include.
+    var disabledPairs: [[Int]]
+    var gravity: Point
+    var time: Double
+    /// Zero for scenes saved before steps were counted.
+    var stepCount: Int
+    /// The friction of the solver, which is the default one for scenes saved before it was saved.
+    var friction: Friction?
+    var materialPairs: [MaterialPairDescription]?
+    
+    private enum CodingKeys: String, CodingKey {
+        case rigids, joints, springs, suspensions, disabledPairs, gravity, time, stepCount, friction, materialPairs
+    }
+    
+    func encoded() throws -> Data {
+        let encoder = JSONEncoder()
+        encoder.outputFormatting = [.prettyPrinted, .sortedKeys]
+        return try encoder.encode(self)
+    }
+    
+    init(data: Data) throws {
+        self = try JSONDecoder().decode(Scene.self, from: data)
+    }
+    
+    init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        rigids = try container.decode([RigidDescription].self, forKey: .rigids)
+        joints = try container.decode([JointDescription].self, forKey: .joints)
+        springs = try container.decodeIfPresent([SpringDescription].self, forKey: .springs)
+        suspensions = try container.decodeIfPresent([SuspensionDescription].self, forKey: .suspensions)
+        disabledPairs = try container.decode([[Int]].self, forKey: .disabledPairs)
+        gravity = try container.decode(Point.self, forKey: .gravity)
+        time = try container.decode(Double.self, forKey: .time)
+        stepCount = try container.decodeIfPresent(Int.self, forKey: .stepCount) ?? 0
+        friction = try container.decodeIfPresent(Friction.self, forKey: .friction)
+        materialPairs = try container.decodeIfPresent([MaterialPairDescription].self, forKey: .materialPairs)
+    }
+    
+    init(rigids: [RigidDescription], joints: [JointDescription], springs: [SpringDescription]? = nil,
+         suspensions: [SuspensionDescription]? = nil, disabledPairs: [[Int]], gravity: Point, time: Double, stepCount: Int, friction: Friction? = nil, materialPairs: [MaterialPairDescription]? = nil) {
+        self.rigids = rigids
+        self.joints = joints
+        self.springs = springs
+        self.suspensions = suspensions
+        self.disabledPairs = disabledPairs
+        self.gravity = gravity
+        self.time = time
+        self.stepCount = stepCount
+        self.friction = friction
+        self.materialPairs = materialPairs
+    }
+    
+    /// Checks that everything refers to rigids which exist, so that a scene edited by hand fails to load
+    /// rather than crashing.
+    func validate() throws {
+        func isRigid(_ index: Int) -> Bool {
+            rigids.indices.contains(index)
+        }
+        // -1 stands for the world.
+        func isConnection(rigids: [Int], anchors: [Point]) -> Bool {
+            rigids.count == 2 && anchors.count == 2 && rigids.allSatisfy { isRigid($0) || $0 == -1 }
+        }
+        // Static rigids have zero inverse mass and inertia.
+        func isInverseMass(_ value: Double) -> Bool {
+            value.isFinite && value >= 0
+        }
+        for (index, rigid) in rigids.enumerated() {
+            let inertia = rigid.inverseInertia
+            if !isInverseMass(rigid.inverseMass) || ![inertia.ex, inertia.ey, inertia.ez].allSatisfy(isInverseMass) {
+                throw ValidationError.invalidMass(rigid: index)
+            }
+        }
+        for (index, joint) in joints.enumerated() where !isConnection(rigids: joint.rigids, anchors: joint.anchors) {
+            throw ValidationError.invalidJoint(index)
+        }
+        for (index, spring) in (springs ?? []).enumerated() where !isConnection(rigids: spring.rigids, anchors: spring.anchors) {
+            throw ValidationError.invalidSpring(index)
+        }
+        for (index, suspension) in (suspensions ?? []).enumerated() {
+            let travel = suspension.travel
+            if suspension.rigids.count != 2 || suspension.anchors.count != 2 || !suspension.rigids.allSatisfy(isRigid)
+                || travel.count != 2 || !(travel[0] <= travel[1]) {
+                throw ValidationError.invalidSuspension(index)
+            }
+        }
+        for (index, pair) in disabledPairs.enumerated() where pair.count != 2 || !pair.allSatisfy(isRigid) {
+            throw ValidationError.invalidDisabledPair(index)
+        }
+        for (index, pair) in (materialPairs ?? []).enumerated() where !(1 ... 2).contains(pair.materials.count) {
+            throw ValidationError.invalidMaterialPair(index)
+        }
+    }
+}
+
+
+extension Collider: Codable {
+    private enum CodingKeys: String, CodingKey {
+        case type, normal, offset, margin, vertices, triangles, sidedness, correctsInternalEdges, thickness, pieces, shapes
+        case radius, halfExtents, halfHeight
+    }
+    
+    private struct PieceDescription: Codable {
+        let vertices: [Point]
+        let faces: [[Int]]
+    }
+    
+    private struct ShapeDescription: Codable {
+        let collider: Collider
+        let frame: Frame
+        let density: Double
+    }
+    
+    func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case let .plane(plane):
+            try container.encode("plane", forKey: .type)
+            try container.encode(plane.normal, forKey: .normal)
+            try container.encode(plane.offset, forKey: .offset)
+        case let .box(box):
+            try container.encode("box", forKey: .type)
+            try container.encode(box.margin, forKey: .margin)
+        case let .mesh(mesh):
+            try container.encode("mesh", forKey: .type)
+            try container.encode(mesh.vertices, forKey: .vertices)
+            try container.encode(mesh.triangles.map { [$0.corners.0, $0.corners.1, $0.corners.2] }, forKey: .triangles)
+            try container.encode(mesh.sidedness, forKey: .sidedness)
+            try container.encode(mesh.correctsInternalEdges, forKey: .correctsInternalEdges)
+            try container.encode(mesh.thickness, forKey: .thickness)
+        case let .compound(compound):
+            try container.encode("compound", forKey: .type)
+            try container.encode(compound.shapes.map { shape in
+                ShapeDescription(collider: .convex(shape.collider), frame: shape.frame, density: shape.density)
+            }, forKey: .shapes)
+        case let .convex(.sphere(radius)):
+            try container.encode("sphere", forKey: .type)
+            try container.encode(radius, forKey: .radius)
+        case let .convex(.box(halfExtents)):
+            try container.encode("cuboid", forKey: .type)
+            try container.encode(halfExtents, forKey: .halfExtents)
+        case let .convex(.capsule(radius, halfHeight)):
+            try container.encode("capsule", forKey: .type)
+            try container.encode(radius, forKey: .radius)
+            try container.encode(halfHeight, forKey: .halfHeight)
+        case let .convex(.hull(piece)):
+            try container.encode("hull", forKey: .type)
+            try container.encode(piece.vertices, forKey: .vertices)
+            try container.encode(piece.faces.map { [$0.0, $0.1, $0.2] }, forKey: .triangles)
+        }
+    }
+    
+    init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let type = try container.decode(String.self, forKey: .type)
+        
+        // Indices out of range would only crash later, in the colliders built from them.
+        func triangles(_ indices: [[Int]], of vertices: [Point], key: CodingKeys = .triangles) throws -> [(Int, Int, Int)] {
+            try indices.map { triangle in
+                guard triangle.count == 3 else {
+                    throw DecodingError.dataCorruptedError(forKey: key, in: container, debugDescription: "Triangles need three corners")
+                }
+                guard triangle.allSatisfy(vertices.indices.contains) else {
+                    throw DecodingError.dataCorruptedError(forKey: key, in: container,
+                                                           debugDescription: "Triangle \(triangle) refers to corners beyond the \(vertices.count) vertices")
+                }
+                return (triangle[0], triangle[1], triangle[2])
+            }
+        }
+        
+        switch type {
+        case "plane":
+            self = .plane(Plane(normal: try container.decode(Point.self, forKey: .normal), offset: try container.decode(Double.self, forKey: .offset)))
+        case "box":
+            self = .box(BoxCollider(margin: try container.decode(Double.self, forKey: .margin)))
+        case "mesh":
+            let vertices = try container.decode([Point].self, forKey: .vertices)
+            self = .mesh(TriangleMeshCollider(
+                vertices: vertices,
+                triangles: try triangles(container.decode([[Int]].self, forKey: .triangles), of: vertices),
+                sidedness: try container.decode(TriangleSidedness.self, forKey: .sidedness),
+                correctsInternalEdges: try container.decode(Bool.self, forKey: .correctsInternalEdges),
+                thickness: try container.decode(Double.self, forKey: .thickness)))
+        case "compound":
+            // Older scenes list the pieces of a decomposition instead of placed shapes.
+            if let pieces = try container.decodeIfPresent([PieceDescription].self, forKey: .pieces) {
+                self = .compound(CompoundCollider(pieces: try pieces.map { piece in
+                    guard !piece.vertices.isEmpty && !piece.faces.isEmpty else {
+                        throw DecodingError.dataCorruptedError(forKey: .pieces, in: container, debugDescription: "Pieces need vertices and faces")
+                    }
+                    return ConvexPiece(vertices: piece.vertices, faces: try triangles(piece.faces, of: piece.vertices, key: .pieces))
+                }))
+                break
+            }
+            let shapes = try container.decode([ShapeDescription].self, forKey: .shapes)
+            self = .compound(CompoundCollider(shapes: try shapes.map { shape in
+                guard case let .convex(collider) = shape.collider else {
+                    throw DecodingError.dataCorruptedError(forKey: .shapes, in: container, debugDescription: "Compound shapes must be convex")
+                }
+                return CompoundShape(collider: collider, frame: shape.frame, density: shape.density)
+            }))
+        case "sphere":
+            self = .convex(.sphere(radius: try container.decode(Double.self, forKey: .radius)))
+        case "cuboid":
+            self = .convex(.box(halfExtents: try container.decode(Point.self, forKey: .halfExtents)))
+        case "capsule":
+            self = .convex(.capsule(radius: try container.decode(Double.self, forKey: .radius),
+                                    halfHeight: try container.decode(Double.self, forKey: .halfHeight)))
+        case "hull":
+            let vertices = try container.decode([Point].self, forKey: .vertices)
+            let faces = try triangles(container.decode([[Int]].self, forKey: .triangles), of: vertices)
+            guard !faces.isEmpty else {
+                throw DecodingError.dataCorruptedError(forKey: .triangles, in: container, debugDescription: "Hulls need faces")
+            }
+            self = .convex(.hull(ConvexPiece(vertices: vertices, faces: faces)))
+        default:
+            throw DecodingError.dataCorruptedError(forKey: .type, in: container, debugDescription: "Unknown collider type \(type)")
+        }
+    }
+}
+
+
+extension Scene.JointDescription {
+    init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        kind = try container.decode(JointKind.self, forKey: .kind)
+        rigids = try container.decode([Int].self, forKey: .rigids)
+        anchors = try container.decode([Point].self, forKey: .anchors)
+        compliance = try container.decode(Double.self, forKey: .compliance)
+        angleLimits = try container.decodeIfPresent([Double].self, forKey: .angleLimits)
+        if let limits = angleLimits, limits.count != 2 || !(limits[0] <= limits[1]) {
+            throw DecodingError.dataCorruptedError(forKey: .angleLimits, in: container, debugDescription: "Angle limits need a lower and an upper bound")
+        }
+        motor = try container.decodeIfPresent(JointMotor.self, forKey: .motor)
+        referenceAngle = try container.decodeIfPresent(Double.self, forKey: .referenceAngle)
+        breakingForce = try container.decodeIfPresent(Double.self, forKey: .breakingForce)
+        disablesCollision = try container.decodeIfPresent(Bool.self, forKey: .disablesCollision)
+    }
+}
+
+
+extension JointKind: Codable {
+    private enum CodingKeys: String, CodingKey {
+        case type, limits, axes, relativeOrientation, axis
+    }
+    
+    func encode(to encoder: Encoder) throws {
+        var container = encoder.container(keyedBy: CodingKeys.self)
+        switch self {
+        case .ball:
+            try container.encode("ball", forKey: .type)
+        case let .distance(limits):
+            try container.encode("distance", forKey: .type)
+            try container.encode([limits.lowerBound, limits.upperBound], forKey: .limits)
+        case let .hinge(axes):
+            try container.encode("hinge", forKey: .type)
+            try container.encode([axes.0, axes.1], forKey: .axes)
+        case let .fixed(relativeOrientation):
+            try container.encode("fixed", forKey: .type)
+            try container.encode(relativeOrientation, forKey: .relativeOrientation)
+        case let .line(axis, travel):
+            try container.encode("line", forKey: .type)
+            try container.encode(axis, forKey: .axis)
+            try container.encode([travel.lowerBound, travel.upperBound], forKey: .limits)
+        }
+    }
+    
+    init(from decoder: Decoder) throws {
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let type = try container.decode(String.self, forKey: .type)
+        
+        switch type {
+        case "ball":
+            self = .ball
+        case "distance":
+            let limits = try container.decode([Double].self, forKey: .limits)
+            guard limits.count == 2, limits[0] <= limits[1] else {
+                throw DecodingError.dataCorruptedError(forKey: .limits, in: container, debugDescription: "Limits need a lower and an upper bound")
+            }
+            self = .distance(limits[0] ... limits[1])
+        case "hinge":
+            let axes = try container.decode([Point].self, forKey: .axes)
+            guard axes.count == 2 else {
+                throw DecodingError.dataCorruptedError(forKey: .axes, in: container, debugDescription: "Hinges need an axis for each rigid")
+            }
+            self = .hinge(axes: (axes[0], axes[1]))
+        case "fixed":
+            self = .fixed(relativeOrientation: try container.decode(Quaternion.self, forKey: .relativeOrientation))
+        case "line":
+            let travel = try container.decode([Double].self, forKey: .limits)
+            guard travel.count == 2, travel[0] <= travel[1] else {
+                throw DecodingError.dataCorruptedError(forKey: .limits, in: container, debugDescription: "Travel needs a lower and an upper bound")
+            }
+            self = .line(axis: try container.decode(Point.self, forKey: .axis), travel: travel[0] ... travel[1])
+        default:
+            throw DecodingError.dataCorruptedError(forKey: .type, in: container, debugDescription: "Unknown joint type \(type)")
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//
+//  SceneFile.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A world written by hand in JSON, which only needs to state what differs from the defaults:
+///
+///     {
+///         "gravity": { "ex": 0, "ey": 0, "ez": -9.81 },
+///         "bodies": [
+///             { "collider": { "type": "cuboid", "halfExtents": { "ex": 0.5, "ey": 0.5, "ez": 0.5 } },
+///               "mass": 2, "position": { "ex": 0, "ey": 0, "ez": 3 }, "velocity": { "ex": 1, "ey": 0, "ez": 0 } }
+///         ],
+///         "joints": [
+///             { "kind": { "type": "ball" }, "bodies": [0, -1], "anchor": { "ex": 0, "ey": 0, "ez": 4 } }
+///         ]
+///     }
+///
+/// Bodies without a mass are static. Joints refer to bodies by their index, where -1 stands for the world,
+/// and join them at a global anchor. The ground is always there. Unlike a `Scene`, which is saved from a running
+/// world, a scene file describes the initial state only.
+struct SceneFile: Codable {
+    struct Body: Codable {
+        var collider: Collider
+        var mass: Double?
+        var position: Point?
+        var orientation: Quaternion?
+        var velocity: Point?
+        var angularVelocity: Point?
+        var material: Material?
+        var restitution: Double?
+        var isSensor: Bool?
+        var surfaceVelocity: Point?
+    }
+    
+    struct JointDefinition: Codable {
+        var kind: JointKind
+        var bodies: [Int]
+        var anchor: Point
+        var compliance: Double?
+        var breakingForce: Double?
+    }
+    
+    enum ValidationError: Error {
+        /// The body at the index has a mass which is not a positive number.
+        case invalidMass(body: Int)
+        /// The joint at the index doesn't refer to two bodies of the file or the world.
+        case invalidBodies(joint: Int)
+    }
+    
+    var gravity: Point?
+    var bodies: [Body]
+    var joints: [JointDefinition]?
+    
+    init(data: Data) throws {
+        self = try JSONDecoder().decode(SceneFile.self, from: data)
+        for (index, body) in bodies.enumerated() {
+            if let mass = body.mass, !(mass.isFinite && mass > 0) {
+                throw ValidationError.invalidMass(body: index)
+            }
+        }
+        for (index, joint) in (joints ?? []).enumerated() {
+            if joint.bodies.count != 2 || !joint.bodies.allSatisfy({ (-1 ..< bodies.count).contains($0) }) {
+                throw ValidationError.invalidBodies(joint: index)
+            }
+        }
+    }
+}
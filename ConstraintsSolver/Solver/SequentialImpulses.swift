@@ -0,0 +1,419 @@
+//
+//  SequentialImpulses.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum SolverBackend {
+    /// Extended position based dynamics, correcting positions directly in every sub-step.
+    case positionBased
+    /// Classic velocity-level sequential impulses, iterated in every sub-step, with the remaining penetration
+    /// resolved by a Baumgarte bias. Joints hold their rigids with a velocity row along every direction they constrain,
+    /// which the bias also pulls back together once they drifted apart. Joint compliance, hinge limits, iteration budgets and mass ratio mitigation
+    /// are supported, while the simulation level of detail is ignored, so every rigid moves in every sub-step.
+    /// Mouse joints, soft contacts, rolling friction, continuous collision and fracture are left to the position based backend.
+    case sequentialImpulses(iterations: Int)
+}
+
+
+/// A contact or joint direction solved on velocities.
+fileprivate struct VelocityConstraint {
+    let rigids: (Rigid, Rigid)
+    let points: (Point, Point)
+    /// The direction the first rigid is pushed into, and the second one against.
+    let normal: Point
+    let depth: Double
+    let tangent: Point
+    let friction: Double
+    /// The separating speed the contact bounces off with, which replaces the bias if faster.
+    let bounceSpeed: Double
+    /// Joints pull as well as push, while contacts only push.
+    let joint: Joint?
+    /// Added to the effective inverse mass along the normal, which lets a compliant joint give way.
+    let softness: Double
+    var normalImpulse = 0.0
+    var tangentImpulse = 0.0
+    
+    init(_ row: JointRow, joint: Joint, softness: Double) {
+        rigids = joint.rigids
+        points = row.points
+        normal = row.direction
+        depth = row.error
+        tangent = .null
+        friction = 0
+        bounceSpeed = 0
+        self.joint = joint
+        self.softness = softness
+    }
+    
+    init(_ constraint: PositionalConstraint, friction: Double, restitution: Double = 0, restingSpeed: Double = 0, joint: Joint?, softness: Double = 0) {
+        rigids = constraint.rigids
+        points = constraint.contacts
+        normal = constraint.normal ?? constraint.direction
+        depth = constraint.measure - constraint.targetMeasure
+        self.friction = friction
+        self.joint = joint
+        self.softness = softness
+        
+        let relativeVelocity = rigids.1.pointVelocity(at: points.1) - rigids.0.pointVelocity(at: points.0)
+        let tangentialVelocity = relativeVelocity - relativeVelocity.project(onto: normal)
+        tangent = tangentialVelocity.length > 1e-9 ? tangentialVelocity.normalize : .null
+        
+        let approachSpeed = relativeVelocity.dot(normal)
+        bounceSpeed = approachSpeed > restingSpeed ? restitution * approachSpeed : 0
+    }
+    
+    /// Starts from the impulses a contact at the same features ended the last sub-step with, so that the iterations
+    /// only need to correct them. Stacks then settle within far fewer iterations.
+    mutating func warmStart(from contact: ContactManifold.Contact) {
+        normalImpulse = contact.normalImpulse
+        let bound = friction * normalImpulse
+        tangentImpulse = tangent.length > 0 ? contact.tangentImpulse.dot(tangent).clamped(to: -bound ... bound) : 0
+        apply(normalImpulse * normal + tangentImpulse * tangent)
+    }
+    
+    func effectiveInverseMass(along direction: Point) -> Double {
+        let arms = (points.0 - rigids.0.frame.position, points.1 - rigids.1.frame.position)
+        return rigids.0.inverseMass + rigids.1.inverseMass +
+            rigids.0.applyInverseInertia(arms.0.cross(direction)).cross(arms.0).dot(direction) +
+            rigids.1.applyInverseInertia(arms.1.cross(direction)).cross(arms.1).dot(direction)
+    }
+    
+    func relativeVelocity(along direction: Point) -> Double {
+        (rigids.1.pointVelocity(at: points.1) - rigids.0.pointVelocity(at: points.0)).dot(direction)
+    }
+    
+    func apply(_ impulse: Point) {
+        rigids.0.applyImpulse(impulse, at: points.0)
+        rigids.1.applyImpulse(-impulse, at: points.1)
+    }
+    
+    mutating func solve(bias: Double) {
+        let inverseMass = effectiveInverseMass(along: normal)
+        if inverseMass < 1e-12 {
+            return
+        }
+        
+        var impulse = (relativeVelocity(along: normal) + max(bounceSpeed, bias * depth)) / (inverseMass + softness)
+        if joint == nil {
+            // Clamp the accumulated impulse rather than the increment, so that earlier overshoots can be taken back.
+            impulse = max(normalImpulse + impulse, 0) - normalImpulse
+        }
+        normalImpulse += impulse
+        apply(impulse * normal)
+        joint?.accumulatedImpulse += abs(impulse)
+        
+        if tangent.length == 0 || friction == 0 {
+            return
+        }
+        let tangentInverseMass = effectiveInverseMass(along: tangent)
+        let bound = friction * normalImpulse
+        var tangentialImpulse = relativeVelocity(along: tangent) / tangentInverseMass
+        tangentialImpulse = (tangentImpulse + tangentialImpulse).clamped(to: -bound ... bound) - tangentImpulse
+        tangentImpulse += tangentialImpulse
+        apply(tangentialImpulse * tangent)
+    }
+}
+
+
+/// A direction along which a joint holds the anchors of its rigids together, at the points it acts on.
+fileprivate struct JointRow {
+    let points: (Point, Point)
+    let direction: Point
+    /// How far the anchor of the second rigid is ahead of the one of the first rigid along the direction.
+    let error: Double
+}
+
+
+/// An axis about which a joint keeps its rigids from turning relative to each other.
+fileprivate struct AngularJointRow {
+    let axis: Point
+    /// How far the second rigid is turned ahead of the first one about the axis, in radians.
+    let error: Double
+}
+
+
+fileprivate extension Joint {
+    /// The directions along which the anchors are held together on velocities. Unlike the positional constraints,
+    /// these also exist while the joint is satisfied, so that the rigids are kept from drifting apart in the first place.
+    func linearRows() -> [JointRow] {
+        let contacts = (rigids.0.frame.act(anchors.0), rigids.1.frame.act(anchors.1))
+        let difference = contacts.0.to(contacts.1)
+        switch kind {
+        case .ball, .hinge(_), .fixed(_):
+            return [Point.ex, .ey, .ez].map { JointRow(points: contacts, direction: $0, error: difference.dot($0)) }
+        
+        case .distance(_):
+            // Ropes and rods only act beyond their limits.
+            return constraints().map { JointRow(points: $0.contacts, direction: $0.direction, error: $0.measure - $0.targetMeasure) }
+        
+        case let .line(axis, travel):
+            let direction = rigids.0.frame.quaternion.act(on: axis.normalize)
+            let offset = difference.dot(direction)
+            // The point of the line closest to the second anchor moves along with the first rigid.
+            let points = (contacts.0 + offset * direction, contacts.1)
+            let tangents = direction.tangents
+            var rows = [tangents.0, tangents.1].map { JointRow(points: points, direction: $0, error: difference.dot($0)) }
+            if !travel.contains(offset) {
+                rows.append(JointRow(points: points, direction: direction, error: offset - offset.clamped(to: travel)))
+            }
+            return rows
+        }
+    }
+    
+    /// The axes about which hinges and fixed joints keep their rigids from turning relative to each other.
+    func angularRows() -> [AngularJointRow] {
+        switch kind {
+        case let .hinge(axes):
+            let hingeAxes = (rigids.0.frame.quaternion.act(on: axes.0.normalize), rigids.1.frame.quaternion.act(on: axes.1.normalize))
+            // Turning the axis of the first rigid into the one of the second rigid, by the small angles sub-steps leave.
+            let error = hingeAxes.0.cross(hingeAxes.1)
+            let tangents = hingeAxes.0.tangents
+            return [tangents.0, tangents.1].map { AngularJointRow(axis: $0, error: error.dot($0)) }
+        
+        case let .fixed(relativeOrientation):
+            // The first rigid is turned by the relative orientation from the second one when the joint is satisfied.
+            let target = rigids.0.frame.quaternion * relativeOrientation.inverse
+            let error = rigids.1.frame.quaternion.derive(by: 1, target)
+            return [Point.ex, .ey, .ez].map { AngularJointRow(axis: $0, error: error.dot($0)) }
+        
+        case .ball, .distance(_), .line(_, _):
+            return []
+        }
+    }
+}
+
+
+/// A hinge angle limit, which only ever pushes the angle back into the limits, or an axis about which a joint
+/// holds its rigids in both directions, solved on angular velocities.
+fileprivate struct AngularVelocityConstraint {
+    let rigids: (Rigid, Rigid)
+    let axis: Point
+    /// Positive beyond the upper limit and negative below the lower one.
+    let depth: Double
+    let joint: Joint
+    let isLimit: Bool
+    /// Added to the effective inverse inertia about the axis, which lets a compliant joint give way.
+    let softness: Double
+    var impulse = 0.0
+    
+    init(_ constraint: AngularConstraint, joint: Joint) {
+        rigids = constraint.rigids
+        axis = constraint.axis
+        depth = constraint.measure - constraint.targetMeasure
+        self.joint = joint
+        isLimit = true
+        softness = 0
+    }
+    
+    init(_ row: AngularJointRow, joint: Joint, softness: Double) {
+        rigids = joint.rigids
+        axis = row.axis
+        depth = row.error
+        self.joint = joint
+        isLimit = false
+        self.softness = softness
+    }
+    
+    mutating func solve(bias: Double) {
+        let inverseInertia = rigids.0.applyInverseInertia(axis).dot(axis) + rigids.1.applyInverseInertia(axis).dot(axis)
+        if inverseInertia < 1e-12 {
+            return
+        }
+        
+        let speed = (rigids.1.angularVelocity - rigids.0.angularVelocity).dot(axis)
+        var increment = (speed + bias * depth) / (inverseInertia + softness)
+        if isLimit {
+            // The accumulated impulse keeps the sign of the violated limit.
+            increment = (depth > 0 ? max(impulse + increment, 0) : min(impulse + increment, 0)) - impulse
+        }
+        impulse += increment
+        rigids.0.applyTorqueImpulse(increment * axis)
+        rigids.1.applyTorqueImpulse(-increment * axis)
+        joint.accumulatedImpulse += abs(increment)
+    }
+}
+
+
+extension Rigid {
+    /// Applies the global inverse inertia tensor to a global vector.
+    func applyInverseInertia(_ v: Point) -> Point {
+        frame.quaternion.act(on: inverseInertia .* frame.quaternion.inverse.act(on: v))
+    }
+    
+    /// The global velocity of a global point attached to this rigid.
+    func pointVelocity(at point: Point) -> Point {
+        velocity + angularVelocity.cross(point - frame.position)
+    }
+    
+    /// Applies an impulse at a global point, changing the linear and angular velocity.
+    func applyImpulse(_ impulse: Point, at point: Point) {
+        wake()
+        velocity = velocity + inverseMass * impulse
+        angularVelocity = angularVelocity + applyInverseInertia((point - frame.position).cross(impulse))
+    }
+    
+    /// Applies a global angular impulse, changing the angular velocity only.
+    func applyTorqueImpulse(_ impulse: Point) {
+        wake()
+        angularVelocity = angularVelocity + applyInverseInertia(impulse)
+    }
+}
+
+
+extension Solver {
+    /// Advances all rigids by one sub-step using sequential impulses, returning the pairs in contact,
+    /// the pairs overlapping sensors, the contact manifolds, the number of contacts and the largest penetration.
+    /// Impulses are exchanged in world time, while forces and motion are integrated over the sub-step scaled by the
+    /// time scale of each rigid. Only the partners found by the broad phase are checked for contacts.
+    /// Contacts found at the same features as in the last sub-step are warm started with the impulses they ended with.
+    /// The contacts of rigids given more than one iteration in `rigidIterations` by an iteration budget get the ones
+    /// beyond the first on top of the iterations of the backend. The residuals are the deepest penetration of each rigid.
+    func stepSequentialImpulses(_ rigids: [Rigid], joints: [Joint], by dt: Double, timeScales: [Double], iterations: Int, rigidIterations: [Int], partners: [[Int]], candidatePairs: Set<IndexPair>? = nil) -> (pairs: [(Int, Int)], sensorPairs: [(Int, Int)], manifolds: [RigidPair: ContactManifold], contactCount: Int, maxPenetration: Double, residuals: [Double]) {
+        for (rigid, timeScale) in zip(rigids, timeScales) {
+            rigid.velocity = rigid.velocity + dt * timeScale * rigid.inverseMass * (rigid.externalForce + rigid.generatedForce)
+            let torque = isGyroscopic ? rigid.generatedTorque + rigid.gyroscopicTorque : rigid.generatedTorque
+            rigid.angularVelocity = rigid.angularVelocity + dt * timeScale * rigid.applyInverseInertia(torque)
+        }
+        
+        var constraints: [VelocityConstraint] = []
+        var pairs: [(Int, Int)] = []
+        var sensorPairs: [(Int, Int)] = []
+        // The constraints of each pair follow each other, in the order of the contacts of its manifold.
+        var pairContacts: [(pair: RigidPair, constraints: Range<Int>, manifold: ContactManifold)] = []
+        var contactRanges: [IndexPair: Range<Int>] = [:]
+        // The iterations each constraint gets on top of the ones of the backend.
+        var extraIterations: [Int] = []
+        var maxPenetration = 0.0
+        var residuals = [Double](repeating: 0, count: rigids.count)
+        
+        for i in rigids.indices {
+            for j in partners[i] where rigids[i].inverseMass > 0 || rigids[j].inverseMass > 0 {
+                let (rigid, other) = (rigids[i], rigids[j])
+                if candidatePairs?.contains(IndexPair(i, j)) == false {
+                    continue
+                }
+                if !disabledPairs.isEmpty && disabledPairs.contains(RigidPair(rigid, other)) {
+                    continue
+                }
+                let contacts = generateConstraints(for: rigid, and: other).compactMap { $0 as? PositionalConstraint }
+                if contacts.isEmpty {
+                    continue
+                }
+                if rigid.isSensor || other.isSensor {
+                    sensorPairs.append((i, j))
+                    continue
+                }
+                pairs.append((i, j))
+                let coefficient = friction.isEnabled ? pairFriction(for: rigid, and: other).coefficient : 0
+                let restitution = isRestitutionEnabled ? rigid.restitution(against: other) : 0
+                let restingSpeed = max(rigid.restingSpeed(for: dt), other.restingSpeed(for: dt))
+                let touching = contacts.filter { $0.measure > 1e-12 }
+                let pair = RigidPair(rigid, other)
+                guard let manifold = ContactManifold(touching, previous: manifolds[pair]) else {
+                    continue
+                }
+                let start = constraints.count
+                for contact in touching {
+                    let depth = contact.measure - contact.targetMeasure
+                    maxPenetration = max(maxPenetration, depth)
+                    for k in [i, j] where rigids[k].inverseMass > 0 {
+                        residuals[k] = max(residuals[k], depth)
+                    }
+                    constraints.append(VelocityConstraint(contact, friction: coefficient, restitution: restitution, restingSpeed: restingSpeed, joint: nil))
+                    extraIterations.append(max(rigidIterations[i], rigidIterations[j], 1) - 1)
+                }
+                pairContacts.append((pair, start ..< constraints.count, manifold))
+                contactRanges[IndexPair(i, j)] = start ..< constraints.count
+            }
+        }
+        let contactCount = constraints.count
+        
+        // Only once all contacts know their approach speeds, since warm starting changes the velocities.
+        if isWarmStarting {
+            for (pair, range, manifold) in pairContacts {
+                for (index, contact) in zip(range, manifold.contacts) {
+                    if let feature = contact.feature, let previous = manifolds[pair]?.contact(at: feature) {
+                        constraints[index].warmStart(from: previous)
+                    }
+                }
+            }
+        }
+        
+        var angularConstraints: [AngularVelocityConstraint] = []
+        for joint in joints {
+            // The compliance is in meters per newton, and turns into an inverse mass over the sub-step like in XPBD.
+            let softness = joint.compliance / dt.sq
+            for row in joint.linearRows() {
+                constraints.append(VelocityConstraint(row, joint: joint, softness: softness))
+                extraIterations.append(0)
+            }
+            angularConstraints += joint.angularRows().map { AngularVelocityConstraint($0, joint: joint, softness: softness) }
+            angularConstraints += joint.angularConstraints().map { AngularVelocityConstraint($0, joint: joint) }
+        }
+        
+        // Resolve a fraction of the penetration per sub-step, as resolving all at once adds energy.
+        let bias = 0.2 / dt
+        for iteration in 0 ..< iterations + (extraIterations.max() ?? 0) {
+            for index in constraints.indices where iteration < iterations + extraIterations[index] {
+                constraints[index].solve(bias: bias)
+            }
+            for index in angularConstraints.indices where iteration < iterations {
+                angularConstraints[index].solve(bias: bias)
+            }
+        }
+        
+        // The contacts of light rigids with their heaviest partners are solved last, as in the position based backend.
+        if let mitigation = massRatioMitigation {
+            let scheduled = mitigation.schedule(pairs, of: rigids)
+            for _ in 0 ..< (scheduled.isEmpty ? 0 : mitigation.extraIterations) {
+                for (i, j) in scheduled {
+                    guard let range = contactRanges[IndexPair(i, j)] else {
+                        continue
+                    }
+                    for index in range {
+                        constraints[index].solve(bias: bias)
+                    }
+                }
+            }
+        }
+        
+        // The force of a joint in the sub-step follows from the impulse its linear rows accumulated together,
+        // and from the impulses of its limits.
+        var jointImpulses: [ObjectIdentifier: (joint: Joint, linear: Point, limits: Double)] = [:]
+        for constraint in constraints[contactCount...] {
+            if let joint = constraint.joint {
+                let impulse = jointImpulses[ObjectIdentifier(joint), default: (joint, .null, 0)]
+                jointImpulses[ObjectIdentifier(joint)] = (joint, impulse.linear + constraint.normalImpulse * constraint.normal, impulse.limits)
+            }
+        }
+        for limit in angularConstraints where limit.isLimit {
+            jointImpulses[ObjectIdentifier(limit.joint), default: (limit.joint, .null, 0)].limits += abs(limit.impulse)
+        }
+        for (joint, linear, limits) in jointImpulses.values {
+            joint.force = max(joint.force, (linear.length + limits) / dt)
+        }
+        
+        var solvedManifolds: [RigidPair: ContactManifold] = [:]
+        for (pair, range, manifold) in pairContacts {
+            var manifold = manifold
+            for (index, constraint) in zip(manifold.contacts.indices, constraints[range]) {
+                manifold.contacts[index].normalImpulse = constraint.normalImpulse
+                manifold.contacts[index].tangentImpulse = constraint.tangentImpulse * constraint.tangent
+            }
+            solvedManifolds[pair] = manifold
+        }
+        
+        for (rigid, timeScale) in zip(rigids, timeScales) where timeScale > 0 {
+            rigid.limitSpin(for: dt * timeScale)
+            rigid.pastFrame = rigid.frame
+            rigid.frame = rigid.frame.integrate(by: dt * timeScale, linearVelocity: rigid.velocity, angularVelocity: rigid.angularVelocity)
+        }
+        
+        return (pairs, sensorPairs, solvedManifolds, contactCount, maxPenetration, residuals)
+    }
+}
@@ -0,0 +1,103 @@
+//
+//  Simulation.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// The quickest way to get something falling: a world with the ground, earth gravity, a fixed time step,
+/// and sub-steps adapting to the fastest body, so that nothing needs to be configured up front.
+///
+///     let simulation = Simulation()
+///     try simulation.addBox(halfExtents: Point(0.5), at: Point(0, 0, 1))
+///     let ball = try simulation.addSphere(radius: 0.3, at: Point(0.2, 0, 3))
+///     simulation.step(count: 120)
+///     print(simulation.state(of: ball).frame.position)
+///
+/// Nothing but the solver is needed, so it runs in tests or on a server as well, by linking the ConstraintsSolverKit
+/// framework. The basics are public there, whereas everything beyond them is available through the world
+/// to code built together with the solver, such as the viewer.
+public class Simulation {
+    public struct Hit {
+        public let body: RigidHandle
+        public let distance: Double
+        public let point: Point
+        /// The surface normal at the hit point, facing the ray.
+        public let normal: Point
+    }
+    
+    let world: World
+    /// Simulated seconds per step.
+    public var timeStep: Double
+    
+    public convenience init(timeStep: Double = 1.0 / 60, gravity: Point = -9.81 * Point.ez) {
+        self.init(timeStep: timeStep, gravity: gravity, renderer: nil)
+    }
+    
+    init(timeStep: Double, gravity: Point, renderer: MeshRegistry?) {
+        self.timeStep = timeStep
+        world = World(renderer: renderer)
+        world.gravity = gravity
+        world.adaptiveSubSteps = AdaptiveSubSteps()
+    }
+    
+    /// Adds a dynamic box subject to gravity. Like all bodies, it throws an `InertiaError` if its mass or size
+    /// gives it unusable moments of inertia, such as a zero mass or a flat box.
+    @discardableResult
+    public func addBox(halfExtents: Point, at position: Point, mass: Double = 1) throws -> RigidHandle {
+        try addBody(.box(halfExtents: halfExtents), at: Frame(position: position), mass: mass)
+    }
+    
+    /// Adds a dynamic sphere subject to gravity.
+    @discardableResult
+    public func addSphere(radius: Double, at position: Point, mass: Double = 1) throws -> RigidHandle {
+        try addBody(.sphere(radius: radius), at: Frame(position: position), mass: mass)
+    }
+    
+    /// Adds a dynamic body of any convex shape subject to gravity.
+    @discardableResult
+    public func addBody(_ collider: ConvexCollider, at frame: Frame, mass: Double = 1) throws -> RigidHandle {
+        world.handle(of: try world.addDynamic(.convex(collider), at: frame, mass: mass))!
+    }
+    
+    /// Advances the simulation by the given number of time steps.
+    public func step(count: Int = 1) {
+        for _ in 0 ..< count {
+            step(by: timeStep)
+        }
+    }
+    
+    /// Advances the simulation by a step of any duration, for callers keeping their own clock.
+    public func step(by dt: Double) {
+        world.integrate(dt: dt)
+    }
+    
+    /// The frame and velocities of a body after the last step.
+    public func state(of handle: RigidHandle) -> RigidState {
+        world.rigid(handle).state
+    }
+    
+    /// The body hit first by a ray starting at the origin, for picking or for gameplay queries like line of sight.
+    /// The ground is a body as well, and the direction needn't be normalized.
+    public func raycast(from origin: Point, along direction: Point, maxDistance: Double = .infinity, filter: QueryFilter = .none) -> Hit? {
+        guard let hit = world.closestHit(along: Ray(origin: origin, direction: direction, maxDistance: maxDistance), filter: filter),
+              let handle = world.handle(of: hit.rigid) else {
+            return nil
+        }
+        return Hit(body: handle, distance: hit.distance, point: hit.point, normal: hit.normal)
+    }
+    
+    /// The poses of all moving bodies after the last step.
+    public var poses: [Pose] {
+        world.poses()
+    }
+    
+    /// A checksum of the state of all bodies, which two runs of the same steps agree on, e.g. to check
+    /// that the peers of a lockstep game are in sync.
+    public var stateHash: UInt64 {
+        world.stateHash
+    }
+}
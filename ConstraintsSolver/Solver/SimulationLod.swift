@@ -0,0 +1,37 @@
+//
+//  SimulationLod.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Level of detail of the simulation depending on the distance of a body to a focus point, e.g. the player.
+/// Far bodies are integrated only every few sub-steps, very far ones are frozen in place, keeping their velocities.
+/// Bodies wake up again as soon as the focus approaches or an active body touches them.
+struct SimulationLod {
+    var focus: Point = .null
+    /// Bodies further away are integrated with `reducedStride` times the sub-step duration.
+    var reducedDistance = 20.0
+    /// Bodies further away are frozen.
+    var frozenDistance = 40.0
+    var reducedStride = 5
+    
+    /// Every how many sub-steps the rigid is integrated, where zero means frozen.
+    func stride(for rigid: Rigid) -> Int {
+        if rigid.inverseMass == 0 {
+            return 1
+        }
+        
+        let distance = rigid.frame.position.distance(to: focus)
+        if distance > frozenDistance {
+            return 0
+        }
+        if distance > reducedDistance {
+            return reducedStride
+        }
+        return 1
+    }
+}
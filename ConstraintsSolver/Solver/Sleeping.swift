@@ -0,0 +1,49 @@
+//
+//  Sleeping.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Puts islands of rigids to sleep which stayed slow for a while, so that resting piles stop costing anything.
+/// Sleeping rigids are frozen like the far ones of the level of detail, but lose their velocities. They wake up when
+/// an awake rigid touches them or an impulse is applied to them. Ignored by the sequential impulses backend.
+struct Sleeping {
+    /// Rigids slower than both thresholds are resting.
+    var linearThreshold = 0.05
+    var angularThreshold = 0.05
+    /// Seconds all rigids of an island need to rest before it falls asleep. Rigids pass through zero velocity
+    /// at the top of a bounce or a swing, which must not put them to sleep.
+    var delay = 0.5
+    
+    /// Advances the resting times of the awake dynamic rigids by a step, and puts the islands to sleep
+    /// whose rigids have all rested long enough. An island resting on a frozen rigid falls asleep as well.
+    func update(_ rigids: [Rigid], islands: [Int?], dt: Double) {
+        var restlessIslands = Set<Int>()
+        for (rigid, island) in zip(rigids, islands) where !rigid.isFrozen {
+            guard let island = island else {
+                continue
+            }
+            if rigid.velocity.length < linearThreshold && rigid.angularVelocity.length < angularThreshold {
+                rigid.restingTime += dt
+            }
+            else {
+                rigid.restingTime = 0
+            }
+            if rigid.restingTime < delay {
+                restlessIslands.insert(island)
+            }
+        }
+        
+        for (rigid, island) in zip(rigids, islands) where !rigid.isFrozen {
+            if let island = island, !restlessIslands.contains(island) {
+                rigid.isSleeping = true
+                rigid.velocity = .null
+                rigid.angularVelocity = .null
+            }
+        }
+    }
+}
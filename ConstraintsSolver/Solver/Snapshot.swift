@@ -0,0 +1,72 @@
+//
+//  Snapshot.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 17.04.21.
+//
+
+import Foundation
+
+
+/// The dynamic state of a rigid, sufficient to resume its simulation from this point.
+public struct RigidState: Codable {
+    public let frame: Frame
+    public let pastFrame: Frame
+    public let velocity: Point
+    public let angularVelocity: Point
+}
+
+
+extension Rigid {
+    var state: RigidState {
+        RigidState(frame: frame, pastFrame: pastFrame, velocity: velocity, angularVelocity: angularVelocity)
+    }
+    
+    func restore(_ state: RigidState) {
+        frame = state.frame
+        pastFrame = state.pastFrame
+        velocity = state.velocity
+        angularVelocity = state.angularVelocity
+    }
+}
+
+
+/// A copy of the dynamic state of every rigid of a world at some point in time.
+struct Snapshot {
+    fileprivate let states: [(Rigid, RigidState)]
+    
+    init(of rigids: [Rigid]) {
+        states = rigids.map { ($0, $0.state) }
+    }
+    
+    /// Resets all captured rigids to their captured state.
+    /// Rigids which were created after the snapshot was taken are left untouched.
+    func restore() {
+        for (rigid, state) in states {
+            rigid.restore(state)
+        }
+    }
+}
+
+
+/// A checksum of the exact bits of the frames and velocities of rigids in the given order, for checking that two runs,
+/// or the peers of a lockstep game, agree. Unlike `Hasher`, which is seeded anew by every process, it is 64-bit FNV-1a,
+/// so checksums can be compared across processes and machines.
+func checksum(of rigids: [Rigid]) -> UInt64 {
+    var hash: UInt64 = 0xcbf2_9ce4_8422_2325
+    for rigid in rigids {
+        let (position, quaternion) = (rigid.frame.position, rigid.frame.quaternion)
+        let values = [position.ex, position.ey, position.ez,
+                      quaternion.scalar, quaternion.bivector.ex, quaternion.bivector.ey, quaternion.bivector.ez,
+                      rigid.velocity.ex, rigid.velocity.ey, rigid.velocity.ez,
+                      rigid.angularVelocity.ex, rigid.angularVelocity.ey, rigid.angularVelocity.ez]
+        for value in values {
+            var bits = value.bitPattern
+            for _ in 0 ..< 8 {
+                hash = (hash ^ (bits & 0xff)) &* 0x100_0000_01b3
+                bits >>= 8
+            }
+        }
+    }
+    return hash
+}
@@ -0,0 +1,42 @@
+//
+//  SoftContact.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Lets the contacts of a rigid give like a spring and a damper, which makes for soft landings on a compliant ground
+/// and tames violent impacts. A rigid resting on it sinks in by its weight divided by the stiffness.
+/// Only the position based backend applies it.
+struct SoftContact: Codable {
+    /// Force in newtons per meter of penetration, which must be positive.
+    var stiffness: Double
+    /// Force in newtons per meter per second of penetration speed.
+    var damping = 0.0
+    
+    /// The softness of a contact between two rigids, whose springs and dampers act in series.
+    static func combined(_ a: SoftContact?, _ b: SoftContact?) -> SoftContact? {
+        guard let a = a else {
+            return b
+        }
+        guard let b = b else {
+            return a
+        }
+        let damping = a.damping + b.damping > 0 ? a.damping * b.damping / (a.damping + b.damping) : 0
+        return SoftContact(stiffness: a.stiffness * b.stiffness / (a.stiffness + b.stiffness), damping: damping)
+    }
+    
+    /// The Lagrange multiplier correcting a contact during a sub-step, following the damped constraints of extended
+    /// position based dynamics. The damper resists the penetration gained during the sub-step, and the result never
+    /// pulls the rigids together. The compliance of all contacts is added to the one of the spring.
+    func lagrangeFactor(for contact: PositionalConstraint, depth: Double, compliance: Double, dt: Double) -> Double {
+        let springCompliance = 1 / (stiffness * dt.sq)
+        let dampingRatio = damping / (stiffness * dt)
+        let direction = contact.direction
+        let gained = (contact.rigids.1.delta(global: contact.contacts.1) - contact.rigids.0.delta(global: contact.contacts.0)).dot(direction)
+        return max((depth + dampingRatio * gained) / ((1 + dampingRatio) * contact.inverseResistance + compliance + springCompliance), 0)
+    }
+}
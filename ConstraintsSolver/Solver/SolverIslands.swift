@@ -0,0 +1,73 @@
+//
+//  SolverIslands.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// The rigids which may touch each other or are jointed during a step, grouped into islands which the position based
+/// backend solves one after the other, or concurrently. Nothing a rigid does can reach the rigids of another island
+/// before the next step, so each island converges on its own, and islands whose rigids are all asleep or frozen are
+/// skipped as a whole.
+/// Static rigids belong to no island, but are visited by every island they may touch, for its own rigids only.
+struct SolverIslands {
+    /// The indices of the rigids of each island in ascending order, including the static rigids it may touch.
+    let members: [[Int]]
+    /// The island of each dynamic rigid, or `nil` for static ones.
+    let islandOfRigid: [Int?]
+    
+    /// Builds the islands from the partners found by the broad phase, and the pairs of rigids held by joints.
+    /// Islands are numbered in the order of their first dynamic rigid, so they are the same for the same scene.
+    init(of rigids: [Rigid], partners: [[Int]], jointPairs: [(Int, Int)]) {
+        let pairs = partners.enumerated().flatMap { i, row in row.map { (i, $0) } } + jointPairs
+        let roots = islands(of: rigids, linkedBy: pairs)
+        
+        var numbers: [Int: Int] = [:]
+        var members: [[Int]] = []
+        var islandOfRigid = [Int?](repeating: nil, count: rigids.count)
+        for (i, root) in roots.enumerated() {
+            guard let root = root else {
+                continue
+            }
+            let island = numbers[root] ?? members.count
+            if island == members.count {
+                numbers[root] = island
+                members.append([])
+            }
+            members[island].append(i)
+            islandOfRigid[i] = island
+        }
+        
+        for (i, row) in partners.enumerated() where roots[i] == nil {
+            for island in Set(row.compactMap { islandOfRigid[$0] }) {
+                members[island].append(i)
+            }
+        }
+        self.members = members.map { $0.sorted() }
+        self.islandOfRigid = islandOfRigid
+    }
+}
+
+
+/// What solving an island during a sub-step found. Islands solved concurrently each fill their own outcome, and the
+/// outcomes are merged in the order of the islands, so that they end up in the same order as on a single thread.
+struct IslandOutcome {
+    var contactCount = 0
+    var contactPairs: [(Int, Int)] = []
+    var sensorPairs: [(Int, Int)] = []
+    var manifolds: [(RigidPair, ContactManifold?)] = []
+    var maxPenetration = 0.0
+    var bouncingContacts: [RestitutionContact] = []
+    var rollingContacts: [RollingContact] = []
+    /// The largest contact impulse of each rigid touched, by its index.
+    var peakImpulses: [Int: Double] = [:]
+    var contactImpulses: [IndexPair: ContactImpulse] = [:]
+    /// The static and kinematic rigids which were touched. They are shared with other islands, so they are only
+    /// woken up once all islands are solved.
+    var touchedStatics: [Int] = []
+    var narrowPhaseDuration = 0.0
+    var iterationCount = 0
+}
@@ -0,0 +1,38 @@
+//
+//  SpinLimit.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Damps the part of the angular speed of a rigid exceeding a threshold, which calms down thin bodies
+/// which were hit at a corner without affecting ordinary tumbling.
+struct SpinDamping: Codable {
+    /// Angular speed in radians per second above which damping starts.
+    var threshold = 20.0
+    /// The fraction of the excess speed lost per second is `1 - exp(-rate)`.
+    var rate = 5.0
+}
+
+
+extension Rigid {
+    /// Applies the spin damping and the maximum angular speed of this rigid after a step of the given duration.
+    func limitSpin(for dt: Double) {
+        var speed = angularVelocity.length
+        if maxAngularSpeed == nil && spinDamping == nil || speed < 1e-12 {
+            return
+        }
+        let axis = (1 / speed) * angularVelocity
+        
+        if let damping = spinDamping, speed > damping.threshold {
+            speed = damping.threshold + (speed - damping.threshold) * exp(-damping.rate * dt)
+        }
+        if let maxAngularSpeed = maxAngularSpeed {
+            speed = min(speed, maxAngularSpeed)
+        }
+        angularVelocity = speed * axis
+    }
+}
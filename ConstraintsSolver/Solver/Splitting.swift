@@ -0,0 +1,157 @@
+//
+//  Splitting.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+extension BoxCollider {
+    /// The unit box as a convex piece, without its margin.
+    var piece: ConvexPiece {
+        // The corners are numbered by their coordinates as bits, x being the lowest.
+        ConvexPiece(vertices: points, faces: [
+            (0, 2, 1), (1, 2, 3), (4, 5, 6), (5, 7, 6),
+            (0, 1, 4), (1, 5, 4), (2, 6, 3), (3, 6, 7),
+            (0, 4, 2), (2, 4, 6), (1, 3, 5), (3, 7, 5)
+        ])
+    }
+}
+
+
+/// Volume, center and second moments of the volume about the center, per axis, of solids given by convex pieces.
+/// Multiplying the moments by the density gives the diagonal of the inertia tensor as `(yy + zz, xx + zz, xx + yy)`.
+struct VolumeProperties {
+    let volume: Double
+    let center: Point
+    let secondMoments: Point
+    /// The mixed second moments `(yz, xz, xy)` about the center, which are the negated entries off the diagonal
+    /// of the inertia tensor when multiplied by the density.
+    let products: Point
+    
+    init(of pieces: [ConvexPiece]) {
+        var volume = 0.0
+        var firstMoments = Point.null
+        var secondMoments = Point.null
+        var products = Point.null
+        
+        // Sum tetrahedra spanned by every face and the center of its piece, which lies inside since pieces are convex.
+        for piece in pieces {
+            let o = piece.center
+            for (a, b, c) in piece.faces {
+                let corners = [o, piece.vertices[a], piece.vertices[b], piece.vertices[c]]
+                let tetrahedron = abs(o.to(corners[1]).dot(o.to(corners[2]).cross(o.to(corners[3])))) / 6
+                let sum = corners.reduce(Point.null, +)
+                volume += tetrahedron
+                firstMoments = firstMoments + (tetrahedron / 4) * sum
+                secondMoments = secondMoments + (tetrahedron / 20) * (corners.reduce(Point.null) { $0 + $1 .* $1 } + sum .* sum)
+                products = products + (tetrahedron / 20) * (corners.reduce(Point.null) { $0 + $1.mixed } + sum.mixed)
+            }
+        }
+        
+        self.volume = volume
+        center = volume > 0 ? (1 / volume) * firstMoments : .null
+        // Move the moments from the origin to the center.
+        self.secondMoments = secondMoments - volume * (center .* center)
+        self.products = products - volume * center.mixed
+    }
+    
+    /// The diagonal of the inertia tensor about the center for the given mass.
+    func inertia(mass: Double) -> Point {
+        let m = secondMoments
+        return (mass / volume) * Point(m.ey + m.ez, m.ex + m.ez, m.ex + m.ey)
+    }
+    
+    /// The full inertia tensor about the center for the given mass.
+    func tensor(mass: Double) -> InertiaTensor {
+        InertiaTensor(diagonal: inertia(mass: mass), offDiagonal: -(mass / volume) * products)
+    }
+}
+
+
+private extension Point {
+    /// The products of pairs of coordinates, `(yz, xz, xy)`.
+    var mixed: Point {
+        Point(ey * ez, ex * ez, ex * ey)
+    }
+}
+
+
+extension ConvexPiece {
+    /// The part of the piece behind a plane given in its local frame, or `nil` if nothing remains.
+    func clipped(behind plane: Plane) -> ConvexPiece? {
+        var vertices: [Point] = []
+        var faces: [(Int, Int, Int)] = []
+        var cut: [Point] = []
+        
+        // Welds points shared by several faces, so that each corner yields a single contact.
+        func index(of point: Point) -> Int {
+            if let existing = vertices.firstIndex(where: { $0.distance(to: point) < 1e-9 }) {
+                return existing
+            }
+            vertices.append(point)
+            return vertices.count - 1
+        }
+        
+        func add(_ polygon: [Point]) {
+            guard polygon.count >= 3 else {
+                return
+            }
+            for i in 1 ..< polygon.count - 1 {
+                let (a, b, c) = (polygon[0], polygon[i], polygon[i + 1])
+                if a.to(b).cross(a.to(c)).length < 1e-12 {
+                    continue
+                }
+                faces.append((index(of: a), index(of: b), index(of: c)))
+            }
+        }
+        
+        for (a, b, c) in self.faces {
+            // Sutherland-Hodgman clipping of the triangle, collecting the points where edges cross the plane.
+            let corners = [self.vertices[a], self.vertices[b], self.vertices[c]]
+            var polygon: [Point] = []
+            for (i, corner) in corners.enumerated() {
+                let next = corners[(i + 1) % 3]
+                let heights = (corner.dot(plane.normal) - plane.offset, next.dot(plane.normal) - plane.offset)
+                if heights.0 <= 0 {
+                    polygon.append(corner)
+                }
+                if (heights.0 < 0) != (heights.1 < 0) && heights.0 != heights.1 {
+                    let crossing = corner + (heights.0 / (heights.0 - heights.1)) * corner.to(next)
+                    polygon.append(crossing)
+                    cut.append(crossing)
+                }
+            }
+            add(polygon)
+        }
+        
+        // Close the cut by the convex polygon of the crossings, sorted by their angle about their center.
+        var unique: [Point] = []
+        for point in cut where !unique.contains(where: { $0.distance(to: point) < 1e-9 }) {
+            unique.append(point)
+        }
+        if unique.count >= 3 {
+            let center = unique.center
+            let u = center.to(unique[0]).normalize
+            let v = plane.normal.cross(u)
+            add(unique.sorted {
+                atan2(center.to($0).dot(v), center.to($0).dot(u)) < atan2(center.to($1).dot(v), center.to($1).dot(u))
+            })
+        }
+        
+        if faces.count < 4 {
+            return nil
+        }
+        return ConvexPiece(vertices: vertices, faces: faces)
+    }
+    
+    func translated(by offset: Point) -> ConvexPiece {
+        ConvexPiece(vertices: vertices.map { $0 + offset }, faces: faces)
+    }
+    
+    func transformed(by frame: Frame) -> ConvexPiece {
+        ConvexPiece(vertices: vertices.map(frame.act), faces: faces)
+    }
+}
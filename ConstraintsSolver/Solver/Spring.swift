@@ -0,0 +1,93 @@
+//
+//  Spring.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum SpringModel: String, Codable {
+    /// Applies the spring force once per step, which is cheap but becomes unstable for stiff springs.
+    case force
+    /// Solves the spring as a compliant distance joint in every sub-step, which is stable for any stiffness.
+    /// The damping is still applied as a force.
+    case compliantConstraint
+}
+
+
+/// A spring and a damper in parallel, connecting two rigids at anchors given in their local frames.
+class Spring {
+    let rigids: (Rigid, Rigid)
+    let anchors: (Point, Point)
+    let restLength: Double
+    let model: SpringModel
+    /// Force in newtons per meter of extension.
+    var stiffness: Double {
+        didSet {
+            joint?.compliance = 1 / stiffness
+        }
+    }
+    /// Force in newtons per meter per second of extension speed.
+    var damping: Double
+    /// The joint which solves the spring in the compliant constraint model.
+    let joint: Joint?
+    
+    init(between a: Rigid, at anchorA: Point, and b: Rigid, at anchorB: Point, restLength: Double, stiffness: Double, damping: Double, model: SpringModel = .compliantConstraint) {
+        rigids = (a, b)
+        anchors = (anchorA, anchorB)
+        self.restLength = restLength
+        self.stiffness = stiffness
+        self.damping = damping
+        self.model = model
+        
+        switch model {
+        case .force:
+            joint = nil
+        case .compliantConstraint:
+            let joint = Joint(.distance(restLength ... restLength), between: a, at: anchorA, and: b, at: anchorB)
+            joint.compliance = 1 / stiffness
+            self.joint = joint
+        }
+    }
+    
+    var endpoints: (Point, Point) {
+        (rigids.0.frame.act(anchors.0), rigids.1.frame.act(anchors.1))
+    }
+    
+    /// The force on the second rigid at its anchor, which the first rigid receives oppositely.
+    /// Only the damping is included if the spring is solved as a constraint.
+    var force: Point {
+        let (a, b) = endpoints
+        let offset = a.to(b)
+        let length = offset.length
+        if length < 1e-9 {
+            return .null
+        }
+        let direction = (1 / length) * offset
+        let speed = (rigids.1.pointVelocity(at: b) - rigids.0.pointVelocity(at: a)).dot(direction)
+        
+        var magnitude = damping * speed
+        if model == .force {
+            magnitude += stiffness * (length - restLength)
+        }
+        return -magnitude * direction
+    }
+    
+    func applyForce() {
+        let force = self.force
+        let (a, b) = endpoints
+        rigids.0.applyGeneratedForce(-force, at: a)
+        rigids.1.applyGeneratedForce(force, at: b)
+    }
+}
+
+
+extension Rigid {
+    /// Adds a force acting at a global point during the current step, which also results in a torque.
+    func applyGeneratedForce(_ force: Point, at point: Point) {
+        generatedForce = generatedForce + force
+        generatedTorque = generatedTorque + (point - frame.position).cross(force)
+    }
+}
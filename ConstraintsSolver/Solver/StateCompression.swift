@@ -0,0 +1,228 @@
+//
+//  StateCompression.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 21.04.21.
+//
+
+import Foundation
+
+
+/// The state of a rigid reduced to integers, as sent over the network.
+/// Positions and velocities are fixed-point, the orientation is packed using the smallest-three scheme.
+struct QuantizedState: Equatable {
+    var position: SIMD3<Int64>
+    var orientation: UInt32
+    var velocity: SIMD3<Int64>
+    var angularVelocity: SIMD3<Int64>
+}
+
+
+enum StateCodingError: Error {
+    case truncated
+    case malformed
+    case baselineMismatch
+}
+
+
+/// Quantizes rigid states and encodes them into compact byte buffers, optionally as deltas to a baseline
+/// which the receiver is known to have, e.g. the last acknowledged one.
+struct StateCodec {
+    /// Smallest representable position difference in meters.
+    var positionResolution = 1.0 / 1024
+    /// Smallest representable difference of linear and angular velocities.
+    var velocityResolution = 1.0 / 256
+    
+    func quantize(_ rigid: Rigid) -> QuantizedState {
+        QuantizedState(
+            position: quantize(rigid.frame.position, resolution: positionResolution),
+            orientation: packSmallestThree(rigid.frame.quaternion),
+            velocity: quantize(rigid.velocity, resolution: velocityResolution),
+            angularVelocity: quantize(rigid.angularVelocity, resolution: velocityResolution))
+    }
+    
+    /// Sets the state of a rigid, which is treated as having been there during the previous step too.
+    func apply(_ state: QuantizedState, to rigid: Rigid) {
+        rigid.frame = Frame(position: dequantize(state.position, resolution: positionResolution),
+                            quaternion: unpackSmallestThree(state.orientation))
+        rigid.pastFrame = rigid.frame
+        rigid.velocity = dequantize(state.velocity, resolution: velocityResolution)
+        rigid.angularVelocity = dequantize(state.angularVelocity, resolution: velocityResolution)
+    }
+    
+    /// Encodes states, relative to a baseline of the same length if given.
+    /// States equal to their baseline take a single byte.
+    func encode(_ states: [QuantizedState], relativeTo baseline: [QuantizedState]? = nil) -> Data {
+        precondition(baseline == nil || baseline!.count == states.count, "Baseline must contain as many states")
+        var data = Data()
+        writeVarint(UInt64(states.count), to: &data)
+        
+        for (index, state) in states.enumerated() {
+            let reference = baseline?[index]
+            if state == reference {
+                data.append(0)
+                continue
+            }
+            data.append(1)
+            
+            let zero = QuantizedState(position: .zero, orientation: 0, velocity: .zero, angularVelocity: .zero)
+            let base = reference ?? zero
+            for (value, baseValue) in [(state.position, base.position), (state.velocity, base.velocity), (state.angularVelocity, base.angularVelocity)] {
+                for i in 0 ..< 3 {
+                    writeVarint(zigzag(value[i] &- baseValue[i]), to: &data)
+                }
+            }
+            withUnsafeBytes(of: state.orientation.littleEndian) { data.append(contentsOf: $0) }
+        }
+        
+        return data
+    }
+    
+    func decode(_ data: Data, relativeTo baseline: [QuantizedState]? = nil) throws -> [QuantizedState] {
+        var offset = data.startIndex
+        guard let count = Int(exactly: try readVarint(from: data, at: &offset)) else {
+            throw StateCodingError.malformed
+        }
+        if let baseline = baseline, baseline.count != count {
+            throw StateCodingError.baselineMismatch
+        }
+        
+        var states: [QuantizedState] = []
+        for index in 0 ..< count {
+            guard offset < data.endIndex else {
+                throw StateCodingError.truncated
+            }
+            let flag = data[offset]
+            offset += 1
+            
+            guard flag <= 1 else {
+                throw StateCodingError.malformed
+            }
+            let reference = baseline?[index]
+            if flag == 0 {
+                guard let reference = reference else {
+                    throw StateCodingError.malformed
+                }
+                states.append(reference)
+                continue
+            }
+            
+            var vectors: [SIMD3<Int64>] = []
+            let base = reference.map { [$0.position, $0.velocity, $0.angularVelocity] } ?? [.zero, .zero, .zero]
+            for baseValue in base {
+                var value = SIMD3<Int64>()
+                for i in 0 ..< 3 {
+                    value[i] = baseValue[i] &+ unzigzag(try readVarint(from: data, at: &offset))
+                }
+                vectors.append(value)
+            }
+            
+            guard data.endIndex - offset >= 4 else {
+                throw StateCodingError.truncated
+            }
+            var orientation: UInt32 = 0
+            for byte in 0 ..< 4 {
+                orientation |= UInt32(data[offset + byte]) << (8 * byte)
+            }
+            offset += 4
+            
+            states.append(QuantizedState(position: vectors[0], orientation: orientation, velocity: vectors[1], angularVelocity: vectors[2]))
+        }
+        
+        return states
+    }
+    
+    private func quantize(_ point: Point, resolution: Double) -> SIMD3<Int64> {
+        SIMD3(quantize(point.ex, resolution: resolution), quantize(point.ey, resolution: resolution), quantize(point.ez, resolution: resolution))
+    }
+    
+    /// Values beyond the range of the integers saturate, and NaNs, which only a diverged rigid has, become zero,
+    /// since converting either of them would trap.
+    private func quantize(_ value: Double, resolution: Double) -> Int64 {
+        let steps = (value / resolution).rounded()
+        if steps.isNaN {
+            return 0
+        }
+        // Leaves room for the differences to baselines.
+        let limit = Double(Int64(1) << 62)
+        return Int64(steps.clamped(to: -limit ... limit))
+    }
+    
+    private func dequantize(_ value: SIMD3<Int64>, resolution: Double) -> Point {
+        Point(Double(value.x) * resolution, Double(value.y) * resolution, Double(value.z) * resolution)
+    }
+}
+
+
+/// Packs a unit quaternion into 32 bits: the index of the largest component in 2 bits,
+/// and the other three in 10 bits each. The largest component is restored from the unit length,
+/// and can be taken as positive since q and -q represent the same rotation.
+func packSmallestThree(_ quaternion: Quaternion) -> UInt32 {
+    var components = [quaternion.bivector.ex, quaternion.bivector.ey, quaternion.bivector.ez, quaternion.scalar]
+    let largest = components.indices.max { abs(components[$0]) < abs(components[$1]) }!
+    if components[largest] < 0 {
+        components = components.map { -$0 }
+    }
+    
+    var packed = UInt32(largest) << 30
+    var shift: UInt32 = 20
+    for (index, component) in components.enumerated() where index != largest {
+        // The smaller components lie within ±1/√2.
+        let normalized = (component * 2.0.squareRoot() * 0.5 + 0.5).clamped(to: 0 ... 1)
+        packed |= UInt32((normalized * 1023).rounded()) << shift
+        shift -= 10
+    }
+    return packed
+}
+
+
+func unpackSmallestThree(_ packed: UInt32) -> Quaternion {
+    let largest = Int(packed >> 30)
+    var components = [Double](repeating: 0, count: 4)
+    var shift: UInt32 = 20
+    for index in 0 ..< 4 where index != largest {
+        let normalized = Double((packed >> shift) & 1023) / 1023
+        components[index] = (normalized - 0.5) * 2 / 2.0.squareRoot()
+        shift -= 10
+    }
+    components[largest] = max(0, 1 - components.reduce(0) { $0 + $1.sq }).squareRoot()
+    return Quaternion(scalar: components[3], bivector: Point(components[0], components[1], components[2]))
+}
+
+
+private func zigzag(_ value: Int64) -> UInt64 {
+    UInt64(bitPattern: (value << 1) ^ (value >> 63))
+}
+
+
+private func unzigzag(_ value: UInt64) -> Int64 {
+    Int64(bitPattern: value >> 1) ^ -Int64(bitPattern: value & 1)
+}
+
+
+func writeVarint(_ value: UInt64, to data: inout Data) {
+    var value = value
+    while value >= 0x80 {
+        data.append(UInt8(value & 0x7F) | 0x80)
+        value >>= 7
+    }
+    data.append(UInt8(value))
+}
+
+
+func readVarint(from data: Data, at offset: inout Data.Index) throws -> UInt64 {
+    var value: UInt64 = 0
+    var shift: UInt64 = 0
+    while true {
+        guard offset < data.endIndex, shift < 64 else {
+            throw StateCodingError.truncated
+        }
+        let byte = data[offset]
+        offset += 1
+        value |= UInt64(byte & 0x7F) << shift
+        if byte < 0x80 {
+            return value
+        }
+        shift += 7
+    }
+}
@@ -0,0 +1,47 @@
+//
+//  StepStage.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A named part of a step of a world, given the world and the time step.
+struct StepStage {
+    let name: String
+    let run: (World, Double) -> Void
+    
+    init(name: String, run: @escaping (World, Double) -> Void) {
+        self.name = name
+        self.run = run
+    }
+}
+
+
+extension Array where Element == StepStage {
+    func firstIndex(named name: String) -> Int? {
+        firstIndex { $0.name == name }
+    }
+    
+    /// Inserts a stage right before the stage with the given name, or at the end if there is none.
+    mutating func insert(_ stage: StepStage, before name: String) {
+        insert(stage, at: firstIndex(named: name) ?? endIndex)
+    }
+    
+    /// Inserts a stage right after the stage with the given name, or at the end if there is none.
+    mutating func insert(_ stage: StepStage, after name: String) {
+        insert(stage, at: firstIndex(named: name).map { $0 + 1 } ?? endIndex)
+    }
+    
+    /// Replaces the stage with the given name, returning whether there was one.
+    @discardableResult
+    mutating func replace(_ name: String, with stage: StepStage) -> Bool {
+        guard let index = firstIndex(named: name) else {
+            return false
+        }
+        self[index] = stage
+        return true
+    }
+}
@@ -0,0 +1,92 @@
+//
+//  StepTrace.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// Records what the world does during its steps in the trace event format of Chrome, which chrome://tracing and
+/// Perfetto show as a timeline without custom tooling: the stages of the pipeline, the broad phase, every sub-step,
+/// the islands on the threads solving them, and the number of contacts as a counter.
+///
+///     let trace = StepTrace()
+///     world.trace = trace
+///     world.integrate(dt: 1.0 / 60)
+///     try trace.write(to: URL(fileURLWithPath: "step.json"))
+///
+/// Events pile up until the trace is cleared, so a trace of many steps takes a lot of memory.
+final class StepTrace {
+    /// An event laid out as the format expects, with times in microseconds.
+    struct Event: Encodable {
+        let name: String
+        let cat: String
+        /// "X" for a span, and "C" for a counter.
+        let ph: String
+        let ts: Double
+        let dur: Double?
+        let pid: Int
+        let tid: UInt64
+        let args: [String: Double]?
+    }
+    
+    /// The time the timeline starts at.
+    private let origin = DispatchTime.now()
+    /// Islands solved concurrently add their spans from several threads.
+    private let lock = NSLock()
+    private var events: [Event] = []
+    
+    var eventCount: Int {
+        lock.lock()
+        defer { lock.unlock() }
+        return events.count
+    }
+    
+    /// Adds a span from the given time until now, on the timeline of the calling thread.
+    func span(_ name: String, category: String, from start: DispatchTime, args: [String: Double]? = nil) {
+        let end = DispatchTime.now()
+        add(Event(name: name, cat: category, ph: "X", ts: microseconds(at: start), dur: microseconds(at: end) - microseconds(at: start),
+                  pid: 1, tid: currentThread, args: args))
+    }
+    
+    /// Adds the current values of a counter, each of which is drawn as a series of its own.
+    func counter(_ name: String, _ values: [String: Double]) {
+        add(Event(name: name, cat: "counter", ph: "C", ts: microseconds(at: .now()), dur: nil, pid: 1, tid: currentThread, args: values))
+    }
+    
+    func clear() {
+        lock.lock()
+        defer { lock.unlock() }
+        events.removeAll()
+    }
+    
+    /// Encodes the events as a JSON trace file.
+    func encoded() throws -> Data {
+        lock.lock()
+        defer { lock.unlock() }
+        return try JSONEncoder().encode(["traceEvents": events])
+    }
+    
+    func write(to url: URL) throws {
+        try encoded().write(to: url)
+    }
+    
+    private func add(_ event: Event) {
+        lock.lock()
+        defer { lock.unlock() }
+        events.append(event)
+    }
+    
+    private func microseconds(at time: DispatchTime) -> Double {
+        (Double(time.uptimeNanoseconds) - Double(origin.uptimeNanoseconds)) * 1e-3
+    }
+    
+    /// Identifies the calling thread, which gets a timeline of its own.
+    private var currentThread: UInt64 {
+        var id: UInt64 = 0
+        pthread_threadid_np(nil, &id)
+        return id
+    }
+}
@@ -0,0 +1,80 @@
+//
+//  Suspension.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A wheel suspension, which keeps a wheel on a strut of the chassis within its travel, and pushes it outwards
+/// with a spring and a damper acting along the strut only. Unlike a spring next to a separate joint, the spring
+/// can't pull the wheel sideways, and the wheel stops firmly at the ends of the travel.
+class Suspension {
+    let chassis: Rigid
+    let wheel: Rigid
+    /// The joint keeping the wheel on the strut, which also disables collisions between chassis and wheel.
+    let joint: Joint
+    /// The direction of the strut in the local frame of the chassis.
+    let axis: Point
+    /// How far the wheel can move out along the strut.
+    let travel: ClosedRange<Double>
+    /// The offset along the strut at which the spring is relaxed.
+    var restLength: Double
+    /// Force in newtons per meter of compression.
+    var stiffness: Double
+    /// Force in newtons per meter per second of compression speed.
+    var damping: Double
+    
+    /// Creates a suspension whose strut starts at an anchor in the local frame of the chassis and runs along an axis
+    /// in the same frame, usually pointing down. The wheel is held at its center unless another anchor is given.
+    init(chassis: Rigid, at anchor: Point, axis: Point, wheel: Rigid, at wheelAnchor: Point = .null,
+         travel: ClosedRange<Double>, restLength: Double, stiffness: Double, damping: Double) {
+        self.chassis = chassis
+        self.wheel = wheel
+        self.restLength = restLength
+        self.stiffness = stiffness
+        self.damping = damping
+        self.axis = axis
+        self.travel = travel
+        joint = Joint(.line(axis: axis, travel: travel), between: chassis, at: anchor, and: wheel, at: wheelAnchor)
+    }
+    
+    /// The global direction of the strut.
+    var direction: Point {
+        chassis.frame.quaternion.act(on: axis.normalize)
+    }
+    
+    /// The anchors of the chassis and the wheel in global coordinates.
+    var endpoints: (Point, Point) {
+        (chassis.frame.act(joint.anchors.0), wheel.frame.act(joint.anchors.1))
+    }
+    
+    /// How far the wheel is out along the strut.
+    var offset: Double {
+        let (a, b) = endpoints
+        return a.to(b).dot(direction)
+    }
+    
+    /// Whether the wheel is at either end of its travel, which usually means the spring is too soft.
+    var isAtLimit: Bool {
+        offset <= travel.lowerBound + 1e-3 || offset >= travel.upperBound - 1e-3
+    }
+    
+    /// The force on the wheel at its anchor, which the chassis receives oppositely.
+    var force: Point {
+        let (a, b) = endpoints
+        let direction = self.direction
+        let speed = (wheel.pointVelocity(at: b) - chassis.pointVelocity(at: a)).dot(direction)
+        return (stiffness * (restLength - offset) - damping * speed) * direction
+    }
+    
+    func applyForce() {
+        let force = self.force
+        // The chassis is pushed at the wheel anchor, which lies on its strut.
+        let (_, b) = endpoints
+        chassis.applyGeneratedForce(-force, at: b)
+        wheel.applyGeneratedForce(force, at: b)
+    }
+}
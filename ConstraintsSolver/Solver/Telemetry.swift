@@ -0,0 +1,74 @@
+//
+//  Telemetry.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 16.04.21.
+//
+
+import Foundation
+
+
+/// Statistics gathered by the solver during its last integration step.
+struct Telemetry {
+    /// Wall-clock time spent in the last integration step, in seconds.
+    var stepDuration: Double = 0
+    /// Wall-clock time spent finding the pairs of rigids which may touch, in seconds.
+    var broadPhaseDuration: Double = 0
+    /// Time spent generating the contacts of the pairs passed on by the broad phase, in seconds. Islands solved
+    /// concurrently add up their times, so this can exceed the step. Only the position based backend measures it.
+    var narrowPhaseDuration: Double = 0
+    /// Wall-clock time spent in the sub-steps, including the narrow phase, in seconds.
+    var solverDuration: Double = 0
+    var bodyCount = 0
+    var subStepCount = 0
+    /// Number of contact constraints generated in the last sub-step.
+    var contactCount = 0
+    /// Number of groups of dynamic bodies which are connected by contacts.
+    var islandCount = 0
+    /// Number of rigids frozen by the simulation level of detail or sleeping.
+    var frozenCount = 0
+    /// Number of passes over the contacts of a rigid in all sub-steps, which grows with the iteration budget.
+    /// Only the position based backend counts them.
+    var iterationCount = 0
+    /// Total kinetic energy of all rigids after the last integration step.
+    var kineticEnergy: Double = 0
+    /// Potential energy of all dynamic rigids in the gravity of the world, relative to the origin. While nothing
+    /// touches, it should add up with the kinetic energy to about the same total from step to step.
+    var potentialEnergy: Double = 0
+    /// Largest constraint violation encountered before correction during the last integration step.
+    var maxPenetration: Double = 0
+}
+
+
+/// Wall-clock seconds elapsed since the given time.
+func seconds(since start: DispatchTime) -> Double {
+    Double(DispatchTime.now().uptimeNanoseconds - start.uptimeNanoseconds) * 1e-9
+}
+
+
+/// Counts the connected components of dynamic rigids, where two rigids are connected if they share a contact.
+/// Static rigids do not propagate connectivity, otherwise everything lying on the ground would form one island.
+func countIslands(of rigids: [Rigid], linkedBy pairs: [(Int, Int)]) -> Int {
+    Set(islands(of: rigids, linkedBy: pairs).compactMap { $0 }).count
+}
+
+
+/// The island of each rigid, identified by the index of one of its members, or `nil` for static rigids.
+func islands(of rigids: [Rigid], linkedBy pairs: [(Int, Int)]) -> [Int?] {
+    var parents = Array(rigids.indices)
+    
+    func root(_ i: Int) -> Int {
+        var i = i
+        while parents[i] != i {
+            parents[i] = parents[parents[i]]
+            i = parents[i]
+        }
+        return i
+    }
+    
+    for (a, b) in pairs where rigids[a].inverseMass > 0 && rigids[b].inverseMass > 0 {
+        parents[root(a)] = root(b)
+    }
+    
+    return rigids.indices.map { rigids[$0].inverseMass > 0 ? root($0) : nil }
+}
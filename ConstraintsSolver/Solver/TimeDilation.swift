@@ -0,0 +1,37 @@
+//
+//  TimeDilation.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// A sphere inside which time runs slower for rigids, or stands still if the scale is zero, like a bullet-time bubble.
+struct TimeDilationVolume {
+    var center: Point
+    var radius: Double
+    /// How fast time runs inside relative to the world, where 1 is normal speed.
+    var timeScale: Double
+    
+    func contains(_ point: Point) -> Bool {
+        center.distance(to: point) <= radius
+    }
+}
+
+
+extension Solver {
+    /// How fast time runs for a rigid, which is its own time scale times the smallest scale of the volumes
+    /// containing its center. Static rigids are not affected.
+    func timeScale(for rigid: Rigid) -> Double {
+        if rigid.inverseMass == 0 {
+            return 1
+        }
+        let volumeScale = timeDilationVolumes
+            .filter { $0.contains(rigid.frame.position) }
+            .map { max($0.timeScale, 0) }
+            .min() ?? 1
+        return max(rigid.timeScale, 0) * volumeScale
+    }
+}
@@ -0,0 +1,262 @@
+//
+//  TriangleMeshCollider.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+enum TriangleSidedness: String, Codable {
+    /// Triangles only collide from the side their counter-clockwise winding faces.
+    case oneSided
+    /// Triangles collide from both sides. Points are pushed back to the side they came from.
+    case doubleSided
+}
+
+
+/// A static collider made of triangles, such as terrain or level geometry.
+struct TriangleMeshCollider {
+    struct Triangle {
+        let corners: (Int, Int, Int)
+        let normal: Point
+        /// Whether the edges from the first to the second, the second to the third, and the third to the first corner
+        /// are shared with another triangle.
+        let internalEdges: (Bool, Bool, Bool)
+    }
+    
+    fileprivate enum Feature {
+        case face
+        /// The edge starting at the corner with this index.
+        case edge(Int)
+        case vertex(Int)
+    }
+    
+    let vertices: [Point]
+    let triangles: [Triangle]
+    var sidedness: TriangleSidedness
+    /// Pushes points near internal edges and vertices along the face normal instead of away from the edge,
+    /// so that bodies sliding or rolling over the mesh don't catch on the seams between triangles.
+    var correctsInternalEdges: Bool
+    /// Points at most this far behind a triangle are considered to penetrate it.
+    var thickness: Double
+    
+    /// Creates a mesh from indexed triangles. Vertices at the same position are welded, so that edges of triangles
+    /// which don't share indices are still recognized as internal.
+    init(vertices: [Point], triangles indices: [(Int, Int, Int)], sidedness: TriangleSidedness = .oneSided, correctsInternalEdges: Bool = true, thickness: Double = 0.25) {
+        self.vertices = vertices
+        self.sidedness = sidedness
+        self.correctsInternalEdges = correctsInternalEdges
+        self.thickness = thickness
+        
+        let welded = vertices.map { vertex in
+            SIMD3<Int64>(Int64((1e6 * vertex.ex).rounded()), Int64((1e6 * vertex.ey).rounded()), Int64((1e6 * vertex.ez).rounded()))
+        }
+        func edge(_ a: Int, _ b: Int) -> Set<SIMD3<Int64>> {
+            [welded[a], welded[b]]
+        }
+        
+        var edgeCounts: [Set<SIMD3<Int64>>: Int] = [:]
+        for (a, b, c) in indices {
+            for key in [edge(a, b), edge(b, c), edge(c, a)] {
+                edgeCounts[key, default: 0] += 1
+            }
+        }
+        
+        triangles = indices.map { a, b, c in
+            Triangle(
+                corners: (a, b, c),
+                normal: vertices[a].to(vertices[b]).cross(vertices[a].to(vertices[c])).normalize,
+                internalEdges: (edgeCounts[edge(a, b)]! > 1, edgeCounts[edge(b, c)]! > 1, edgeCounts[edge(c, a)]! > 1)
+            )
+        }
+    }
+    
+    fileprivate func corners(of triangle: Triangle) -> [Point] {
+        [vertices[triangle.corners.0], vertices[triangle.corners.1], vertices[triangle.corners.2]]
+    }
+    
+    /// The closest point on a triangle to a point, and the feature of the triangle it lies on.
+    fileprivate func closestPoint(to p: Point, on triangle: Triangle) -> (point: Point, feature: Feature) {
+        let corners = self.corners(of: triangle)
+        let (a, b, c) = (corners[0], corners[1], corners[2])
+        let ab = a.to(b)
+        let ac = a.to(c)
+        let ap = a.to(p)
+        
+        let d1 = ab.dot(ap)
+        let d2 = ac.dot(ap)
+        if d1 <= 0 && d2 <= 0 {
+            return (a, .vertex(0))
+        }
+        
+        let bp = b.to(p)
+        let d3 = ab.dot(bp)
+        let d4 = ac.dot(bp)
+        if d3 >= 0 && d4 <= d3 {
+            return (b, .vertex(1))
+        }
+        
+        let vc = d1 * d4 - d3 * d2
+        if vc <= 0 && d1 >= 0 && d3 <= 0 {
+            return (a + d1 / (d1 - d3) * ab, .edge(0))
+        }
+        
+        let cp = c.to(p)
+        let d5 = ab.dot(cp)
+        let d6 = ac.dot(cp)
+        if d6 >= 0 && d5 <= d6 {
+            return (c, .vertex(2))
+        }
+        
+        let vb = d5 * d2 - d1 * d6
+        if vb <= 0 && d2 >= 0 && d6 <= 0 {
+            return (a + d2 / (d2 - d6) * ac, .edge(2))
+        }
+        
+        let va = d3 * d6 - d5 * d4
+        if va <= 0 && d4 - d3 >= 0 && d5 - d6 >= 0 {
+            return (b + (d4 - d3) / ((d4 - d3) + (d5 - d6)) * b.to(c), .edge(1))
+        }
+        
+        let denominator = 1 / (va + vb + vc)
+        return (a + vb * denominator * ab + vc * denominator * ac, .face)
+    }
+    
+    fileprivate func isInternal(_ feature: Feature, of triangle: Triangle) -> Bool {
+        let edges = [triangle.internalEdges.0, triangle.internalEdges.1, triangle.internalEdges.2]
+        switch feature {
+        case .face:
+            return true
+        case let .edge(index):
+            return edges[index]
+        case let .vertex(index):
+            // A vertex is internal if both edges meeting there are.
+            return edges[index] && edges[(index + 2) % 3]
+        }
+    }
+    
+    /// Where a point given in the local frame of the mesh has to move to resolve its penetration, together with the
+    /// contact normal. The previous position decides which side of double-sided triangles the point belongs to.
+    func contact(for point: Point, previously previous: Point, margin: Double = 0) -> (target: Point, normal: Point)? {
+        var best: (target: Point, normal: Point, distance: Double)?
+        
+        for triangle in triangles {
+            let origin = vertices[triangle.corners.0]
+            var normal = triangle.normal
+            if sidedness == .doubleSided && origin.to(previous).dot(normal) < 0 {
+                normal = -normal
+            }
+            
+            let height = origin.to(point).dot(normal) - margin
+            if height >= 0 || height < -thickness {
+                continue
+            }
+            
+            let closest = closestPoint(to: point, on: triangle)
+            let target: Point
+            switch closest.feature {
+            case .face:
+                target = point - height * normal
+            case _ where !isInternal(closest.feature, of: triangle):
+                // Points beyond the boundary of the mesh are free to pass.
+                continue
+            case _ where correctsInternalEdges:
+                if closest.point.distance(to: point) > thickness {
+                    continue
+                }
+                target = point - height * normal
+            default:
+                // Without correction, the point is pushed away from the edge, which makes bodies catch on it.
+                target = closest.point + margin * normal
+                normal = point.to(closest.point).normalize
+            }
+            
+            let distance = point.distance(to: target)
+            if distance < best?.distance ?? .infinity {
+                best = (target, normal, distance)
+            }
+        }
+        
+        return best.map { ($0.target, $0.normal) }
+    }
+    
+    /// Intersects a ray given in the local frame of the mesh with its triangles, each pushed outwards by `offset`.
+    func intersect(_ ray: Ray, offset: Double = 0) -> (distance: Double, normal: Point)? {
+        var best: (distance: Double, normal: Point)?
+        
+        for triangle in triangles {
+            let alignment = triangle.normal.dot(ray.direction)
+            if abs(alignment) < 1e-12 || (sidedness == .oneSided && alignment > 0) {
+                continue
+            }
+            
+            // Faces hit from behind are pushed outwards along the flipped normal.
+            let normal = alignment < 0 ? triangle.normal : -triangle.normal
+            let corners = self.corners(of: triangle).map { $0 + offset * normal }
+            let distance = corners[0].to(ray.origin).dot(normal) / -normal.dot(ray.direction)
+            if distance < 0 || distance > min(ray.maxDistance, best?.distance ?? .infinity) {
+                continue
+            }
+            
+            let hit = ray.at(distance)
+            let isInside = (0 ..< 3).allSatisfy { index in
+                let edge = corners[index].to(corners[(index + 1) % 3])
+                return edge.cross(corners[index].to(hit)).dot(triangle.normal) >= 0
+            }
+            if isInside {
+                best = (distance, normal)
+            }
+        }
+        
+        return best
+    }
+    
+    /// Distance of a point given in the local frame of the mesh to its closest triangle.
+    func distance(to point: Point) -> Double {
+        triangles.map { closestPoint(to: point, on: $0).point.distance(to: point) }.min() ?? .infinity
+    }
+}
+
+
+extension BoxCollider {
+    func intersect(attachedTo rigid: Rigid, with mesh: TriangleMeshCollider, attachedTo other: Rigid, friction: Friction) -> [Constraint] {
+        intersectPoints(points, margin: margin, attachedTo: rigid, with: mesh, attachedTo: other, friction: friction)
+    }
+}
+
+
+/// Pushes points given in the local frame of a rigid out of a triangle mesh attached to another rigid.
+/// Contacts are told apart across sub-steps by the index of the point they stem from, even as it crosses triangles.
+func intersectPoints(_ points: [Point], margin: Double, attachedTo rigid: Rigid, with mesh: TriangleMeshCollider, attachedTo other: Rigid, friction: Friction) -> [Constraint] {
+    var constraints: [Constraint] = []
+    let inverse = other.frame.inverse
+    
+    for (index, point) in points.enumerated() {
+        let position = rigid.frame.act(point)
+        let previous = rigid.pastFrame.act(point)
+        guard let contact = mesh.contact(for: inverse.act(position), previously: inverse.act(previous), margin: margin) else {
+            continue
+        }
+        
+        let targetPosition = other.frame.act(contact.target)
+        let normal = other.frame.quaternion.act(on: contact.normal)
+        let correction = position.to(targetPosition)
+        
+        let deltaPosition = rigid.delta(global: position)
+        let deltaTangentialPosition = deltaPosition - deltaPosition.project(onto: correction)
+        let surfaceDisplacement = friction.surfaceDisplacement - friction.surfaceDisplacement.project(onto: normal)
+        let tangentialCorrection = friction.limit(surfaceDisplacement - deltaTangentialPosition, normal: normal, depth: correction.length, of: rigid)
+        
+        constraints.append(PositionalConstraint(
+            rigids: (rigid, other),
+            contacts: (position, targetPosition + tangentialCorrection),
+            distance: 0,
+            normal: normal,
+            feature: ContactFeature(isReferenceFirst: true, referenceFace: -1, incidentFace: -1, point: index)
+        ))
+    }
+    
+    return constraints
+}
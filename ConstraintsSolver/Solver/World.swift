@@ -6,44 +6,1337 @@
 //
 
 import Foundation
+import simd
+
+/// Identifies a rigid of a world by its index. Welding two rigids removes the second one, which moves the handles
+/// of all rigids after it down by one, so handles held across a `weld(_:_:)` have to be looked up again with
+/// `handle(of:)`. Splitting and fracture append their fragments, so other handles stay valid, but the handle
+/// of the broken rigid then refers to its first fragment.
+public struct RigidHandle: Hashable {
+    public let index: Int
+}
+
+
+public struct Pose {
+    public let handle: RigidHandle
+    public let position: Point
+    public let quaternion: Quaternion
+}
+
+
+/// Displays the meshes of a world. The world only knows renderers through this, so that the solver doesn't depend
+/// on the GPU or the window.
+protocol MeshRegistry: AnyObject {
+    func registerMesh(_ mesh: Mesh)
+    func unregisterMesh(_ mesh: Mesh)
+}
+
 
 class World {
     private let integrator = Solver(subStepCount: 50)
-    private let cubeMesh1: Mesh
-//    private let cubeMesh2: Mesh
-    private let cube1: Rigid
-//    private let cube2: Rigid
-    private let ground: Rigid
-    
-    init(renderer: Renderer) {
-        cubeMesh1 = Mesh.makeCube(name: "Cube", color: .white)
-        cubeMesh1.map { $0 - simd_float3(0.5, 0.5, 0.5) }
-        renderer.registerMesh(cubeMesh1)
-        
-//        cubeMesh2 = Mesh.makeCube(name: "Cube", color: .white)
-//        cubeMesh2.map { $0 - simd_float3(0.5, 0.5, 0.5) }
-//        renderer.registerMesh(cubeMesh2)
-        
-        cube1 = Rigid(collider: .box(BoxCollider()), mass: 1)
-        cube1.frame.quaternion = Quaternion(by: .pi / 8, around: .ey + 0.5 * .ex)
-        cube1.frame.position = Point(0, -2, 4)
-        cube1.externalForce = -8 * .ez
-        cube1.angularVelocity = Point(4, 4, 6)
-        cube1.velocity = 3 * .ey
-        
-//        cube2 = Rigid(collider: .box(BoxCollider()), mass: 2)
-//        cube2.frame.position = Point(1, 1, 3)
-//        cube2.frame.quaternion = Quaternion(by: .pi / 8, around: .ey + 0.25 * .ex)
-//        cube2.externalForce = -9.81 * .ez
-//        cube2.angularVelocity = Point(1, 2, 0.1)
-//        cube2.velocity = 2 * .ey
-        
-        ground = Rigid(collider: .plane(Plane(direction: .ez, offset: 0)), mass: nil)
+    /// Meshes are only created when there is a renderer, so that worlds can also be simulated headlessly.
+    private let renderer: MeshRegistry?
+    private(set) var rigids: [Rigid] = []
+    private(set) var remoteBodies: [RemoteBody] = []
+    private(set) var joints: [Joint] = []
+    /// The joints dragging rigids around, e.g. with the mouse.
+    private(set) var mouseJoints: [MouseJoint] = []
+    private(set) var springs: [Spring] = []
+    private(set) var suspensions: [Suspension] = []
+    /// The joints which keep their rigids from colliding, and how many of them do so for each pair.
+    private var exemptingJoints = Set<ObjectIdentifier>()
+    private var jointExemptions: [RigidPair: Int] = [:]
+    /// Pairs kept from colliding regardless of joints, by `disableCollision` or because they were split from one rigid.
+    private var explicitExemptions = Set<RigidPair>()
+    private var eventChannels: [EventChannel] = []
+    private var soundChannels: [ContactSoundChannel] = []
+    private var reportedPairs = Set<IndexPair>()
+    private var reportedFrozen: [Bool] = []
+    private var reportedMotions: [IndexPair: ContactMotion] = [:]
+    /// Tells how persistent contacts move for contact events and sounds.
+    var contactMotionClassifier = ContactMotionClassifier()
+    /// Events which are not derived from the state of the world, to be sent at the end of the step.
+    private var pendingEvents: [PhysicsEvent] = []
+    /// The recording shown instead of simulating, see `play(_:)`.
+    private(set) var playback: Playback?
+    /// The stages every step runs through in order. Custom stages can be inserted and built-in ones replaced,
+    /// which allows experimenting with variants of the solver without forking it.
+    var pipeline = World.defaultPipeline
+    /// Computes forces at the beginning of every step, such as magnets.
+    var forceGenerators: [ForceGenerator] = []
+    private var meshes: [(Rigid, Mesh)] = []
+    var gravity = -8 * Point.ez
+    /// Simulated time in seconds.
+    private(set) var time = 0.0
+    /// Number of steps taken, which is more convenient than the time to correlate events and logs with.
+    private(set) var stepCount = 0
+    /// Checks the moments of inertia of the dynamic rigids added from now on. Rigids restored from saved scenes
+    /// are not checked again.
+    var inertiaBounds: InertiaBounds
+    
+    /// Creates a world containing nothing but the ground.
+    init(renderer: MeshRegistry? = nil, inertiaBounds: InertiaBounds = InertiaBounds()) {
+        self.renderer = renderer
+        self.inertiaBounds = inertiaBounds
+        rigids.append(Rigid(collider: .plane(Plane(direction: .ez, offset: 0)), mass: nil))
+    }
+    
+    /// The plane every world starts with, which can be given a material like any other rigid,
+    /// or made compliant by giving it a `SoftContact`.
+    var ground: Rigid {
+        rigids[0]
+    }
+    
+    /// Rebuilds a world from a scene, in exactly the mechanical state the scene was saved in.
+    /// Throws a `Scene.ValidationError` if the scene refers to rigids it doesn't contain.
+    init(scene: Scene, renderer: MeshRegistry? = nil, inertiaBounds: InertiaBounds = InertiaBounds()) throws {
+        try scene.validate()
+        self.renderer = renderer
+        self.inertiaBounds = inertiaBounds
+        gravity = scene.gravity
+        time = scene.time
+        stepCount = scene.stepCount
+        
+        for description in scene.rigids {
+            let rigid = Rigid(collider: description.collider, inverseMass: description.inverseMass, inverseInertia: description.inverseInertia)
+            rigid.principalFrame = description.principalFrame ?? .identity
+            rigid.externalForce = description.externalForce
+            rigid.isKinematic = description.isKinematic
+            rigid.material = description.material
+            rigid.groups = CollisionGroups(rawValue: description.groups)
+            rigid.anisotropicFriction = description.anisotropicFriction
+            rigid.maxAngularSpeed = description.maxAngularSpeed
+            rigid.spinDamping = description.spinDamping
+            rigid.timeScale = description.timeScale ?? 1
+            rigid.restitution = description.restitution ?? 0
+            rigid.isSensor = description.isSensor ?? false
+            rigid.surfaceVelocity = description.surfaceVelocity
+            rigid.softContact = description.softContact
+            rigid.userTag = description.userTag
+            rigid.restore(description.state)
+            rigids.append(rigid)
+            addMesh(following: rigid)
+        }
+        
+        func rigid(at index: Int) -> Rigid {
+            index == -1 ? Joint.world : rigids[index]
+        }
+        for description in scene.joints {
+            let joint = Joint(description.kind,
+                              between: rigid(at: description.rigids[0]), at: description.anchors[0],
+                              and: rigid(at: description.rigids[1]), at: description.anchors[1])
+            joint.compliance = description.compliance
+            joint.angleLimits = description.angleLimits.map { $0[0] ... $0[1] }
+            joint.motor = description.motor
+            joint.referenceAngle = description.referenceAngle ?? 0
+            joint.breakingForce = description.breakingForce
+            addJoint(joint, disablesCollision: description.disablesCollision ?? false)
+        }
+        for description in scene.springs ?? [] {
+            addSpring(Spring(between: rigid(at: description.rigids[0]), at: description.anchors[0],
+                             and: rigid(at: description.rigids[1]), at: description.anchors[1],
+                             restLength: description.restLength, stiffness: description.stiffness, damping: description.damping,
+                             model: description.model))
+        }
+        for description in scene.suspensions ?? [] {
+            addSuspension(Suspension(chassis: rigids[description.rigids[0]], at: description.anchors[0], axis: description.axis,
+                                     wheel: rigids[description.rigids[1]], at: description.anchors[1],
+                                     travel: description.travel[0] ... description.travel[1], restLength: description.restLength,
+                                     stiffness: description.stiffness, damping: description.damping))
+        }
+        for pair in scene.disabledPairs {
+            exemptCollision(of: RigidPair(rigids[pair[0]], rigids[pair[1]]))
+        }
+        integrator.friction = scene.friction ?? Friction()
+        for pair in scene.materialPairs ?? [] {
+            integrator.materials.setFriction(pair.coefficients.friction, staticFriction: pair.coefficients.staticFriction,
+                                             between: pair.materials[0], and: pair.materials.last!)
+        }
+    }
+    
+    /// Loads a world from a JSON file, which is either a scene saved by `save(to:)` or a scene file written by hand.
+    /// Errors are those of the kind of file it is, or an `InertiaError` for bodies of a scene file whose mass or shape
+    /// gives them unusable moments of inertia.
+    convenience init(contentsOf url: URL, renderer: MeshRegistry? = nil, inertiaBounds: InertiaBounds = InertiaBounds()) throws {
+        let data = try Data(contentsOf: url)
+        // Saved scenes list rigids, whereas scene files list bodies.
+        if let object = try JSONSerialization.jsonObject(with: data) as? [String: Any], object["rigids"] != nil {
+            try self.init(scene: try Scene(data: data), renderer: renderer, inertiaBounds: inertiaBounds)
+            return
+        }
+        let file = try SceneFile(data: data)
+        self.init(renderer: renderer, inertiaBounds: inertiaBounds)
+        try add(file)
+    }
+    
+    /// Saves the complete state of this world, which `init(contentsOf:)` restores exactly.
+    func save(to url: URL) throws {
+        try scene().encoded().write(to: url)
+    }
+    
+    /// Adds the bodies and joints of a scene file, and takes over its gravity.
+    private func add(_ file: SceneFile) throws {
+        gravity = file.gravity ?? gravity
+        var prefab = Prefab()
+        for body in file.bodies {
+            let index = prefab.addBody(body.collider, mass: body.mass,
+                                       at: Frame(position: body.position ?? .null, quaternion: body.orientation ?? .identity))
+            prefab.bodies[index].material = body.material
+        }
+        let instances = add(try makeRigids(of: prefab, at: Frame()), of: prefab)
+        for (rigid, body) in zip(instances, file.bodies) {
+            rigid.velocity = body.velocity ?? .null
+            rigid.angularVelocity = body.angularVelocity ?? .null
+            rigid.restitution = body.restitution ?? 0
+            rigid.isSensor = body.isSensor ?? false
+            rigid.surfaceVelocity = body.surfaceVelocity
+        }
+        
+        for definition in file.joints ?? [] {
+            let rigids = definition.bodies.map { $0 < 0 ? Joint.world : instances[$0] }
+            let joint = Joint(definition.kind, between: rigids[0], and: rigids[1], at: definition.anchor)
+            joint.compliance = definition.compliance ?? 0
+            joint.breakingForce = definition.breakingForce
+            addJoint(joint)
+        }
+    }
+    
+    /// Captures this world for saving, such that `init(scene:)` restores it exactly.
+    func scene() -> Scene {
+        let indices = Dictionary(uniqueKeysWithValues: rigids.enumerated().map { (ObjectIdentifier($1), $0) })
+        // The world itself is saved as index -1.
+        func index(of rigid: Rigid) -> Int {
+            rigid === Joint.world ? -1 : indices[ObjectIdentifier(rigid)]!
+        }
+        // Joints solving springs and holding suspensions are rebuilt together with them.
+        let ownedJoints = Set(springs.compactMap { $0.joint.map(ObjectIdentifier.init) } + suspensions.map { ObjectIdentifier($0.joint) })
+        var disabledPairs: [[Int]] = []
+        if !explicitExemptions.isEmpty {
+            for i in rigids.indices {
+                for j in i + 1 ..< rigids.count where explicitExemptions.contains(RigidPair(rigids[i], rigids[j])) {
+                    disabledPairs.append([i, j])
+                }
+            }
+        }
+        
+        return Scene(
+            rigids: rigids.map { rigid in
+                Scene.RigidDescription(collider: rigid.collider,
+                                       inverseMass: rigid.inverseMass,
+                                       inverseInertia: rigid.inverseInertia,
+                                       externalForce: rigid.externalForce,
+                                       state: rigid.state,
+                                       isKinematic: rigid.isKinematic,
+                                       material: rigid.material,
+                                       groups: rigid.groups.rawValue,
+                                       anisotropicFriction: rigid.anisotropicFriction,
+                                       maxAngularSpeed: rigid.maxAngularSpeed,
+                                       spinDamping: rigid.spinDamping,
+                                       timeScale: rigid.timeScale,
+                                       restitution: rigid.restitution,
+                                       isSensor: rigid.isSensor,
+                                       surfaceVelocity: rigid.surfaceVelocity,
+                                       principalFrame: rigid.principalFrame,
+                                       softContact: rigid.softContact,
+                                       userTag: rigid.userTag)
+            },
+            joints: joints.filter { !ownedJoints.contains(ObjectIdentifier($0)) }.map { joint in
+                Scene.JointDescription(kind: joint.kind,
+                                       rigids: [index(of: joint.rigids.0), index(of: joint.rigids.1)],
+                                       anchors: [joint.anchors.0, joint.anchors.1],
+                                       compliance: joint.compliance,
+                                       angleLimits: joint.angleLimits.map { [$0.lowerBound, $0.upperBound] },
+                                       motor: joint.motor,
+                                       referenceAngle: joint.referenceAngle,
+                                       breakingForce: joint.breakingForce,
+                                       disablesCollision: exemptingJoints.contains(ObjectIdentifier(joint)))
+            },
+            springs: springs.map { spring in
+                Scene.SpringDescription(rigids: [index(of: spring.rigids.0), index(of: spring.rigids.1)],
+                                        anchors: [spring.anchors.0, spring.anchors.1],
+                                        restLength: spring.restLength,
+                                        stiffness: spring.stiffness,
+                                        damping: spring.damping,
+                                        model: spring.model)
+            },
+            suspensions: suspensions.map { suspension in
+                Scene.SuspensionDescription(rigids: [index(of: suspension.chassis), index(of: suspension.wheel)],
+                                            anchors: [suspension.joint.anchors.0, suspension.joint.anchors.1],
+                                            axis: suspension.axis,
+                                            travel: [suspension.travel.lowerBound, suspension.travel.upperBound],
+                                            restLength: suspension.restLength,
+                                            stiffness: suspension.stiffness,
+                                            damping: suspension.damping)
+            },
+            disabledPairs: disabledPairs,
+            gravity: gravity,
+            time: time,
+            stepCount: stepCount,
+            friction: integrator.friction,
+            materialPairs: integrator.materials.pairs.map { Scene.MaterialPairDescription(materials: $0.materials, coefficients: $0.coefficients) })
+    }
+    
+    /// Adds a spinning cube thrown sideways, which is the scene shown on launch.
+    func addDemoScene() {
+        let cube = addCube(at: Point(0, -2, 4))
+        cube.frame.quaternion = Quaternion(by: .pi / 8, around: .ey + 0.5 * .ex)
+        cube.angularVelocity = Point(4, 4, 6)
+        cube.velocity = 3 * .ey
+    }
+    
+    /// Adds a unit cube subject to gravity, together with a mesh following it.
+    @discardableResult
+    func addCube(at position: Point, mass: Double = 1) -> Rigid {
+        let cube = makeRigid(.box(BoxCollider()), mass: mass)
+        cube.frame.position = position
+        cube.externalForce = mass * gravity
+        rigids.append(cube)
+        addCubeMesh(following: cube, color: .white)
+        return cube
+    }
+    
+    /// Adds a kinematic unit cube whose poses are received from elsewhere.
+    func addRemoteCube() -> RemoteBody {
+        let cube = Rigid(collider: .box(BoxCollider()), mass: nil)
+        rigids.append(cube)
+        addCubeMesh(following: cube, color: .cyan)
+        
+        let body = RemoteBody(rigid: cube)
+        remoteBodies.append(body)
+        return body
+    }
+    
+    private func addCubeMesh(following rigid: Rigid, color: Color) {
+        if let renderer = renderer {
+            let mesh = Mesh.makeCube(name: "Cube", color: color)
+            mesh.map { $0 - simd_float3(0.5, 0.5, 0.5) }
+            renderer.registerMesh(mesh)
+            meshes.append((rigid, mesh))
+        }
+        updateMeshes()
+    }
+    
+    /// Encloses the area within `extent` of the origin by four static walls.
+    /// The walls are half-spaces facing inwards, so they hold back bodies even above their visual height.
+    @discardableResult
+    func addArena(extent: Double, wallHeight: Double) -> [Rigid] {
+        let normals = [Point.ex, Point.ey, -Point.ex, -Point.ey]
+        
+        return normals.map { normal in
+            let wall = Rigid(collider: .plane(Plane(direction: normal, offset: -extent)), mass: nil)
+            rigids.append(wall)
+            
+            guard let renderer = renderer else {
+                return wall
+            }
+            let center = -extent * normal
+            let tangent = extent * normal.cross(.ez)
+            let mesh = Mesh.makeWall(name: "Wall",
+                                     from: simd_float3(center - tangent),
+                                     to: simd_float3(center + tangent),
+                                     height: Float(wallHeight),
+                                     color: Color(0.4))
+            renderer.registerMesh(mesh)
+            meshes.append((wall, mesh))
+            return wall
+        }
+    }
+    
+    /// Adds static level geometry made of triangles, such as terrain.
+    @discardableResult
+    func addStaticMesh(_ collider: TriangleMeshCollider, color: Color = Color(0.5)) -> Rigid {
+        let rigid = Rigid(collider: .mesh(collider), mass: nil)
+        rigids.append(rigid)
+        addTriangleMesh(following: rigid, collider: collider, color: color)
+        return rigid
+    }
+    
+    private func addTriangleMesh(following rigid: Rigid, collider: TriangleMeshCollider, color: Color) {
+        if let renderer = renderer {
+            let mesh = Mesh.makeTriangles(name: "Static Mesh",
+                                          vertices: collider.vertices.map { simd_float3($0) },
+                                          triangles: collider.triangles.map { $0.corners },
+                                          isDoubleSided: collider.sidedness == .doubleSided,
+                                          color: color)
+            renderer.registerMesh(mesh)
+            meshes.append((rigid, mesh))
+        }
+        updateMeshes()
+    }
+    
+    /// Adds a rigid made of convex shapes placed relative to the given position, e.g. primitives or the pieces of
+    /// a convex decomposition, together with a mesh following it. The position is the one of the body frame,
+    /// which may differ from the center of mass.
+    @discardableResult
+    func addCompound(_ collider: CompoundCollider, at position: Point, mass: Double = 1, color: Color = .white) -> Rigid {
+        let rigid = makeRigid(.compound(collider), mass: mass)
+        rigid.bodyFrame = Frame(position: position)
+        rigid.pastFrame = rigid.frame
+        rigid.externalForce = mass * gravity
+        rigids.append(rigid)
+        if case let .compound(principal) = rigid.collider {
+            addCompoundMesh(following: rigid, collider: principal, color: color)
+        }
+        return rigid
+    }
+    
+    private func addCompoundMesh(following rigid: Rigid, collider: CompoundCollider, color: Color) {
+        if let renderer = renderer {
+            var vertices: [simd_float3] = []
+            var triangles: [(Int, Int, Int)] = []
+            for shape in collider.shapes {
+                let base = vertices.count
+                if let piece = shape.collider.piece?.transformed(by: shape.frame) {
+                    vertices += piece.vertices.map { simd_float3($0) }
+                    triangles += piece.outwardFaces.map { a, b, c in (base + a, base + b, base + c) }
+                }
+                else if let tessellation = shape.collider.tessellation() {
+                    vertices += tessellation.vertices.map { simd_float3(shape.frame.act($0)) }
+                    triangles += tessellation.triangles.map { a, b, c in (base + a, base + b, base + c) }
+                }
+            }
+            let mesh = Mesh.makeTriangles(name: "Compound", vertices: vertices, triangles: triangles, isDoubleSided: false, color: color)
+            renderer.registerMesh(mesh)
+            meshes.append((rigid, mesh))
+        }
+        updateMeshes()
+    }
+    
+    /// Adds a sphere, box, capsule or hull subject to gravity, together with a mesh following it.
+    @discardableResult
+    func addConvex(_ collider: ConvexCollider, at position: Point, mass: Double = 1, color: Color = .white) -> Rigid {
+        let rigid = makeRigid(.convex(collider), mass: mass)
+        rigid.frame.position = position
+        rigid.externalForce = mass * gravity
+        rigids.append(rigid)
+        addConvexMesh(following: rigid, collider: collider, color: color)
+        return rigid
+    }
+    
+    private func addConvexMesh(following rigid: Rigid, collider: ConvexCollider, color: Color) {
+        if let piece = collider.piece {
+            addCompoundMesh(following: rigid, collider: CompoundCollider(pieces: [piece]), color: color)
+            return
+        }
+        if let renderer = renderer, let tessellation = collider.tessellation() {
+            let mesh = Mesh.makeTriangles(name: "Convex",
+                                          vertices: tessellation.vertices.map { simd_float3($0) },
+                                          triangles: tessellation.triangles,
+                                          isDoubleSided: false,
+                                          color: color)
+            renderer.registerMesh(mesh)
+            meshes.append((rigid, mesh))
+        }
+        updateMeshes()
+    }
+    
+    /// Adds a dynamic rigid of any collider subject to gravity, together with the default mesh for its collider.
+    /// Throws an `InertiaError` instead if its mass or shape gives it unusable moments of inertia.
+    func addDynamic(_ collider: Collider, at frame: Frame, mass: Double) throws -> Rigid {
+        let rigid = try Rigid(collider: collider, mass: mass, bounds: inertiaBounds)
+        rigid.bodyFrame = frame
+        rigid.pastFrame = rigid.frame
+        rigid.externalForce = mass * gravity
+        rigids.append(rigid)
+        addMesh(following: rigid)
+        return rigid
+    }
+    
+    /// Creates a dynamic rigid whose moments of inertia are checked against `inertiaBounds`. Unusable ones are
+    /// programming errors here, whereas scene files and `addDynamic(_:at:mass:)` throw them.
+    private func makeRigid(_ collider: Collider, mass: Double) -> Rigid {
+        do {
+            return try Rigid(collider: collider, mass: mass, bounds: inertiaBounds)
+        }
+        catch {
+            preconditionFailure(error.localizedDescription)
+        }
+    }
+    
+    /// Adds the default mesh for the collider of a rigid, if it has one.
+    private func addMesh(following rigid: Rigid) {
+        switch rigid.collider {
+        case .plane(_):
+            break
+        case .box(_):
+            addCubeMesh(following: rigid, color: .white)
+        case let .mesh(collider):
+            addTriangleMesh(following: rigid, collider: collider, color: Color(0.5))
+        case let .compound(collider):
+            addCompoundMesh(following: rigid, collider: collider, color: .white)
+        case let .convex(collider):
+            addConvexMesh(following: rigid, collider: collider, color: .white)
+        }
+    }
+    
+    /// Adds a copy of the rigids and joints of a prefab, placed relative to the given frame.
+    @discardableResult
+    func instantiate(_ prefab: Prefab, at frame: Frame) -> [Rigid] {
+        let instances: [Rigid]
+        do {
+            instances = try makeRigids(of: prefab, at: frame)
+        }
+        catch {
+            preconditionFailure(error.localizedDescription)
+        }
+        return add(instances, of: prefab)
+    }
+    
+    /// Creates the rigids of a prefab placed relative to the given frame, without adding them yet, so that
+    /// a body with unusable moments of inertia leaves the world as it was.
+    private func makeRigids(of prefab: Prefab, at frame: Frame) throws -> [Rigid] {
+        try prefab.bodies.map { body in
+            let rigid: Rigid
+            if let mass = body.mass {
+                rigid = try Rigid(collider: body.collider, mass: mass, bounds: inertiaBounds)
+            }
+            else {
+                rigid = Rigid(collider: body.collider, mass: nil)
+            }
+            rigid.bodyFrame = frame.act(body.frame)
+            rigid.pastFrame = rigid.frame
+            rigid.externalForce = (body.mass ?? 0) * gravity
+            rigid.material = body.material
+            rigid.groups = CollisionGroups(rawValue: body.groups)
+            return rigid
+        }
+    }
+    
+    /// Adds the rigids made for a prefab, and copies of its joints between them.
+    private func add(_ instances: [Rigid], of prefab: Prefab) -> [Rigid] {
+        for rigid in instances {
+            rigids.append(rigid)
+            addMesh(following: rigid)
+        }
+        
+        for description in prefab.joints {
+            let joint = Joint(description.kind,
+                              between: instances[description.rigids[0]], at: description.anchors[0],
+                              and: instances[description.rigids[1]], at: description.anchors[1])
+            joint.compliance = description.compliance
+            addJoint(joint, disablesCollision: description.disablesCollision)
+        }
+        
+        return instances
+    }
+    
+    /// All rigids hit by the ray which pass the filter, ordered by distance.
+    func intersections(with ray: Ray, filter: QueryFilter = .none) -> [RayHit] {
+        var hits: [RayHit] = []
+        forEachHit(ray, filter: filter) { hit in
+            hits.append(hit)
+            return true
+        }
+        return hits.sorted { $0.distance < $1.distance }
+    }
+    
+    /// The rigid hit first by the ray among those passing the filter.
+    func closestHit(along ray: Ray, filter: QueryFilter = .none) -> RayHit? {
+        var closest: RayHit?
+        forEachHit(ray, filter: filter) { hit in
+            if hit.distance < closest?.distance ?? .infinity {
+                closest = hit
+            }
+            return true
+        }
+        return closest
+    }
+    
+    /// Calls `body` for every rigid hit by the ray, in no particular order, until it returns false.
+    /// Allocates nothing, which suits queries in hot code.
+    func forEachHit(_ ray: Ray, filter: QueryFilter = .none, _ body: (RayHit) -> Bool) {
+        for rigid in rigids where filter.accepts(rigid) {
+            if let hit = rigid.intersect(ray), !body(hit) {
+                return
+            }
+        }
+    }
+    
+    /// Cuts a dynamic box or polyhedral compound rigid into two along a plane given globally, for simple destruction effects.
+    /// The parts share the mass and the external force of the rigid by their volume, and move on like they did
+    /// as part of it. The rigid is replaced by the part behind the plane, which takes over its handle,
+    /// and the part in front is appended, so handles of other rigids stay valid. Joints attached to the rigid are removed.
+    /// The parts don't collide with each other, since they touch along the cut and would push each other apart.
+    /// Returns the handles of both parts, or `nil` if the plane misses the rigid.
+    @discardableResult
+    func split(_ handle: RigidHandle, along plane: Plane) -> (RigidHandle, RigidHandle)? {
+        let rigid = self.rigid(handle)
+        guard let pieces = rigid.collider.convexPieces, rigid.inverseMass > 0 else {
+            return nil
+        }
+        
+        let localNormal = rigid.frame.quaternion.inverse.act(on: plane.normal)
+        let localPlane = Plane(normal: localNormal, offset: rigid.frame.inverse.act(plane.support).dot(localNormal))
+        let sides = [
+            pieces.compactMap { $0.clipped(behind: localPlane) },
+            pieces.compactMap { $0.clipped(behind: localPlane.flip) }
+        ]
+        guard let handles = replace(handle, withFragments: sides) else {
+            return nil
+        }
+        return (handles[0], handles[1])
+    }
+    
+    /// Replaces a rigid by fragments made of convex pieces given in its local frame, see `split(_:along:)`.
+    /// The first fragment takes over the handle of the rigid. Fragments don't collide with each other.
+    private func replace(_ handle: RigidHandle, withFragments fragments: [[ConvexPiece]]) -> [RigidHandle]? {
+        let rigid = self.rigid(handle)
+        let properties = fragments.map { VolumeProperties(of: $0) }
+        guard fragments.count >= 2, properties.allSatisfy({ $0.volume > 1e-12 }) else {
+            return nil
+        }
+        let totalVolume = properties.reduce(0) { $0 + $1.volume }
+        
+        let parts: [Rigid] = zip(fragments, properties).map { pieces, properties in
+            let share = properties.volume / totalVolume
+            let mass = share / rigid.inverseMass
+            // The parts have their origin at their center of mass and their axes along the principal ones, like every rigid,
+            // and keep the body frame of the rigid.
+            let principal = properties.tensor(mass: mass).principalAxes
+            let principalFrame = Frame(position: properties.center, quaternion: principal.rotation)
+            let inertia = principal.moments
+            let collider = CompoundCollider(pieces: pieces.map { $0.transformed(by: principalFrame.inverse) })
+            let part = Rigid(collider: .compound(collider), inverseMass: 1 / mass, inverseInertia: Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez))
+            
+            part.principalFrame = rigid.principalFrame.act(principalFrame)
+            part.frame = rigid.frame.act(principalFrame)
+            part.pastFrame = rigid.pastFrame.act(principalFrame)
+            part.velocity = rigid.pointVelocity(at: part.frame.position)
+            part.angularVelocity = rigid.angularVelocity
+            part.externalForce = share * rigid.externalForce
+            part.material = rigid.material
+            part.userTag = rigid.userTag
+            part.groups = rigid.groups
+            part.anisotropicFriction = rigid.anisotropicFriction
+            part.maxAngularSpeed = rigid.maxAngularSpeed
+            part.spinDamping = rigid.spinDamping
+            part.timeScale = rigid.timeScale
+            part.restitution = rigid.restitution
+            return part
+        }
+        
+        detach(rigid)
+        rigids[handle.index] = parts[0]
+        rigids += parts.dropFirst()
+        for (index, part) in parts.enumerated() {
+            for other in parts[index + 1 ..< parts.count] {
+                exemptCollision(of: RigidPair(part, other))
+            }
+            addMesh(following: part)
+        }
+        return [handle] + (rigids.count - parts.count + 1 ..< rigids.count).map(RigidHandle.init)
+    }
+    
+    /// Removes the joints whose force during the last step exceeded their breaking force.
+    private func breakOverloadedJoints() {
+        for joint in joints {
+            if let breakingForce = joint.breakingForce, joint.force > breakingForce {
+                removeJoint(joint)
+                pendingEvents.append(.jointBroke(handle(of: joint.rigids.0), handle(of: joint.rigids.1)))
+            }
+        }
+    }
+    
+    /// Breaks rigids whose contacts during the last step exceeded their fracture threshold into their fragments.
+    /// The first fragment takes over the handle of the rigid and the others are appended, see `PhysicsEvent.fractured`.
+    private func fractureOverloadedRigids() {
+        let impulses = integrator.peakContactImpulses
+        for (index, rigid) in rigids.enumerated() where index < impulses.count {
+            guard let fracture = rigid.fracture, impulses[index] > fracture.impulseThreshold else {
+                continue
+            }
+            if let fragments = replace(RigidHandle(index: index), withFragments: fracture.fragments) {
+                pendingEvents.append(.fractured(RigidHandle(index: index), fragments: fragments))
+            }
+        }
+    }
+    
+    enum Weld {
+        /// The rigids were merged into a single compound rigid.
+        case merged(RigidHandle)
+        /// The rigids were connected by a fixed joint.
+        case joined(Joint)
+    }
+    
+    /// Attaches two rigids to each other as they are, e.g. a sticky projectile to its target.
+    /// Dynamic boxes and polyhedral compounds are merged into one compound with their combined mass properties,
+    /// which takes over the handle of `a`, while `b` is removed and the handles of the rigids after it move down by one,
+    /// which invalidates handles held for them.
+    /// Joints attached to either rigid are removed. Other rigids are connected by a fixed joint instead.
+    @discardableResult
+    func weld(_ a: RigidHandle, _ b: RigidHandle) -> Weld {
+        precondition(a != b, "Cannot weld a rigid to itself")
+        let rigids = (rigid(a), rigid(b))
+        guard rigids.0.inverseMass > 0, rigids.1.inverseMass > 0,
+              let pieces = rigids.0.collider.convexPieces, let otherPieces = rigids.1.collider.convexPieces else {
+            let anchor = 0.5 * (rigids.0.frame.position + rigids.1.frame.position)
+            let joint = Joint(.fixed(relativeOrientation: rigids.1.frame.quaternion.inverse * rigids.0.frame.quaternion),
+                              between: rigids.0, and: rigids.1, at: anchor)
+            return .joined(addJoint(joint))
+        }
+        
+        let masses = (1 / rigids.0.inverseMass, 1 / rigids.1.inverseMass)
+        let mass = masses.0 + masses.1
+        let center = (masses.0 / mass) * rigids.0.frame.position + (masses.1 / mass) * rigids.1.frame.position
+        
+        // Rotate the tensors of both rigids into the global frame and move them to the combined center.
+        // The merged rigid turns onto the principal axes of the sum, and keeps the body frame of the first one.
+        var tensor = InertiaTensor.zero
+        for (rigid, rigidMass) in [(rigids.0, masses.0), (rigids.1, masses.1)] {
+            tensor = tensor + InertiaTensor(diagonal: rigid.inertia).rotated(by: rigid.frame.quaternion) +
+                InertiaTensor(pointMass: rigidMass, at: rigid.frame.position - center)
+        }
+        let principal = tensor.principalAxes
+        let frame = Frame(position: center, quaternion: principal.rotation)
+        let inertia = principal.moments
+        
+        let toMerged = (frame.inverse.act(rigids.0.frame), frame.inverse.act(rigids.1.frame))
+        let collider = CompoundCollider(pieces: pieces.map { $0.transformed(by: toMerged.0) } + otherPieces.map { $0.transformed(by: toMerged.1) })
+        let merged = Rigid(collider: .compound(collider), inverseMass: 1 / mass, inverseInertia: Point(1 / inertia.ex, 1 / inertia.ey, 1 / inertia.ez))
+        merged.principalFrame = rigids.0.bodyFrame.inverse.act(frame)
+        merged.frame = frame
+        merged.pastFrame = frame
+        // Conserve the linear momentum, and the angular momentum about the merged center, which includes
+        // the orbits of both centers around it besides their spins.
+        merged.velocity = (masses.0 / mass) * rigids.0.velocity + (masses.1 / mass) * rigids.1.velocity
+        var angularMomentum = Point.null
+        for (rigid, rigidMass) in [(rigids.0, masses.0), (rigids.1, masses.1)] {
+            angularMomentum = angularMomentum + rigid.angularMomentum +
+                rigidMass * (rigid.frame.position - center).cross(rigid.velocity - merged.velocity)
+        }
+        merged.angularVelocity = merged.applyInverseInertia(angularMomentum)
+        merged.externalForce = rigids.0.externalForce + rigids.1.externalForce
+        merged.material = rigids.0.material
+        merged.userTag = rigids.0.userTag
+        merged.groups = rigids.0.groups
+        merged.anisotropicFriction = rigids.0.anisotropicFriction
+        merged.maxAngularSpeed = rigids.0.maxAngularSpeed
+        merged.spinDamping = rigids.0.spinDamping
+        merged.timeScale = rigids.0.timeScale
+        merged.restitution = max(rigids.0.restitution, rigids.1.restitution)
+        
+        detach(rigids.0)
+        detach(rigids.1)
+        self.rigids[a.index] = merged
+        addMesh(following: merged)
+        removeRigid(at: b.index)
+        return .merged(RigidHandle(index: b.index < a.index ? a.index - 1 : a.index))
+    }
+    
+    /// Unregisters all meshes of this world from the renderer, which is needed before replacing the world by another one.
+    func removeMeshes() {
+        for (_, mesh) in meshes {
+            renderer?.unregisterMesh(mesh)
+        }
+        meshes.removeAll()
+    }
+    
+    /// Removes the joints, collision exceptions and mesh of a rigid which is about to be replaced.
+    private func detach(_ rigid: Rigid) {
+        // Springs and suspensions go first, since their joints would otherwise be removed without them.
+        for spring in springs where spring.rigids.0 === rigid || spring.rigids.1 === rigid {
+            removeSpring(spring)
+        }
+        for suspension in suspensions where suspension.chassis === rigid || suspension.wheel === rigid {
+            removeSuspension(suspension)
+        }
+        for joint in joints where joint.rigids.0 === rigid || joint.rigids.1 === rigid {
+            removeJoint(joint)
+        }
+        mouseJoints.removeAll { $0.rigid === rigid }
+        explicitExemptions = explicitExemptions.filter { !$0.contains(rigid) }
+        integrator.disabledPairs = integrator.disabledPairs.filter { !$0.contains(rigid) }
+        if let index = meshes.firstIndex(where: { $0.0 === rigid }) {
+            renderer?.unregisterMesh(meshes[index].1)
+            meshes.remove(at: index)
+        }
+    }
+    
+    /// Removes a detached rigid, moving the handles of the following rigids down by one.
+    private func removeRigid(at index: Int) {
+        rigids.remove(at: index)
+        integrator.removeState(ofRigidAt: index)
+        // Keep the reported state in line with the new indices, so that no spurious events are sent.
+        func shifted(_ i: Int) -> Int {
+            i > index ? i - 1 : i
+        }
+        reportedPairs = Set(reportedPairs
+            .filter { $0.first != index && $0.second != index }
+            .map { IndexPair(shifted($0.first), shifted($0.second)) })
+        if index < reportedFrozen.count {
+            reportedFrozen.remove(at: index)
+        }
+        reportedMotions = Dictionary(uniqueKeysWithValues: reportedMotions
+            .filter { $0.key.first != index && $0.key.second != index }
+            .map { (IndexPair(shifted($0.key.first), shifted($0.key.second)), $0.value) })
+    }
+    
+    func handle(of rigid: Rigid) -> RigidHandle? {
+        rigids.firstIndex { $0 === rigid }.map(RigidHandle.init)
+    }
+    
+    func rigid(_ handle: RigidHandle) -> Rigid {
+        rigids[handle.index]
+    }
+    
+    /// The rigids overlapping a sensor, or touching any other rigid, during the last sub-step, in the order of their indices.
+    /// These are the pairs reported by contact events, without having to subscribe to them.
+    func rigids(touching handle: RigidHandle) -> [RigidHandle] {
+        integrator.touchingPairs.sorted().compactMap { pair in
+            pair.first == handle.index ? RigidHandle(index: pair.second) : pair.second == handle.index ? RigidHandle(index: pair.first) : nil
+        }
+    }
+    
+    /// Adds a joint between two rigids of this world. Jointed rigids usually overlap near the joint,
+    /// so by default they stop colliding with each other, which would otherwise fight the joint.
+    @discardableResult
+    func addJoint(_ joint: Joint, disablesCollision: Bool = true) -> Joint {
+        joints.append(joint)
+        if disablesCollision {
+            let pair = RigidPair(joint.rigids.0, joint.rigids.1)
+            exemptingJoints.insert(ObjectIdentifier(joint))
+            jointExemptions[pair, default: 0] += 1
+            updateCollision(of: pair)
+        }
+        return joint
+    }
+    
+    /// Removes a joint. If it kept its rigids from colliding, they collide again unless another joint
+    /// or `disableCollision` still keeps them apart.
+    func removeJoint(_ joint: Joint) {
+        joints.removeAll { $0 === joint }
+        if exemptingJoints.remove(ObjectIdentifier(joint)) != nil {
+            let pair = RigidPair(joint.rigids.0, joint.rigids.1)
+            jointExemptions[pair]! -= 1
+            if jointExemptions[pair] == 0 {
+                jointExemptions[pair] = nil
+            }
+            updateCollision(of: pair)
+        }
+    }
+    
+    /// Starts dragging a rigid of this world at a global point, waking it up. Move the target of the returned joint
+    /// to drag the rigid along, and remove the joint to let go.
+    @discardableResult
+    func addMouseJoint(grabbing rigid: Rigid, at point: Point) -> MouseJoint {
+        rigid.wake()
+        let joint = MouseJoint(grabbing: rigid, at: point)
+        mouseJoints.append(joint)
+        return joint
+    }
+    
+    func removeMouseJoint(_ joint: MouseJoint) {
+        mouseJoints.removeAll { $0 === joint }
+    }
+    
+    /// Adds a spring between two rigids of this world, which keep colliding with each other.
+    @discardableResult
+    func addSpring(_ spring: Spring) -> Spring {
+        springs.append(spring)
+        if let joint = spring.joint {
+            addJoint(joint, disablesCollision: false)
+        }
+        return spring
+    }
+    
+    func removeSpring(_ spring: Spring) {
+        springs.removeAll { $0 === spring }
+        if let joint = spring.joint {
+            removeJoint(joint)
+        }
+    }
+    
+    /// Adds a suspension between a chassis and a wheel of this world, which stop colliding with each other.
+    @discardableResult
+    func addSuspension(_ suspension: Suspension) -> Suspension {
+        suspensions.append(suspension)
+        addJoint(suspension.joint)
+        return suspension
+    }
+    
+    func removeSuspension(_ suspension: Suspension) {
+        suspensions.removeAll { $0 === suspension }
+        removeJoint(suspension.joint)
+    }
+    
+    /// Stops two rigids from colliding with each other, e.g. because a joint already keeps them in place.
+    func disableCollision(between a: RigidHandle, and b: RigidHandle) {
+        exemptCollision(of: RigidPair(rigid(a), rigid(b)))
+    }
+    
+    /// Undoes `disableCollision`. Rigids connected by a joint which disables their collision still don't collide.
+    func enableCollision(between a: RigidHandle, and b: RigidHandle) {
+        let pair = RigidPair(rigid(a), rigid(b))
+        explicitExemptions.remove(pair)
+        updateCollision(of: pair)
+    }
+    
+    func isCollisionEnabled(between a: RigidHandle, and b: RigidHandle) -> Bool {
+        !integrator.disabledPairs.contains(RigidPair(rigid(a), rigid(b)))
+    }
+    
+    private func exemptCollision(of pair: RigidPair) {
+        explicitExemptions.insert(pair)
+        updateCollision(of: pair)
+    }
+    
+    /// Lets the solver skip a pair as long as anything keeps it from colliding.
+    private func updateCollision(of pair: RigidPair) {
+        if explicitExemptions.contains(pair) || jointExemptions[pair] != nil {
+            integrator.disabledPairs.insert(pair)
+        }
+        else {
+            integrator.disabledPairs.remove(pair)
+        }
+    }
+    
+    /// The poses of all moving rigids which are not frozen, in one contiguous array.
+    /// Lets renderers and other consumers sync without visiting every rigid.
+    func poses() -> [Pose] {
+        var poses: [Pose] = []
+        poses.reserveCapacity(rigids.count)
+        for (index, rigid) in rigids.enumerated() where (rigid.inverseMass > 0 || rigid.isKinematic) && !rigid.isFrozen {
+            poses.append(Pose(handle: RigidHandle(index: index), position: rigid.frame.position, quaternion: rigid.frame.quaternion))
+        }
+        return poses
+    }
+    
+    /// Quantizes the states of all rigids for transmission to a remote copy of this world.
+    func quantizedStates(using codec: StateCodec) -> [QuantizedState] {
+        rigids.map(codec.quantize)
+    }
+    
+    /// Applies states received from a remote copy of this world, which must contain the same rigids in the same order.
+    func apply(_ states: [QuantizedState], using codec: StateCodec) {
+        precondition(states.count == rigids.count, "State count does not match rigid count")
+        for (state, rigid) in zip(states, rigids) {
+            codec.apply(state, to: rigid)
+        }
+        updateMeshes()
+    }
+    
+    /// Copies the current geometry into a view which other threads can query during the next step.
+    func view() -> WorldView {
+        WorldView(bodies: rigids.enumerated().map { index, rigid in
+            WorldView.Body(handle: RigidHandle(index: index), collider: rigid.collider, frame: rigid.frame, groups: rigid.groups)
+        }, time: time)
+    }
+    
+    /// Plays back a recording instead of simulating, starting at its first step. Each step of the world then shows
+    /// the next recorded one, and `seek(toStep:)` scrubs through them. The world should be built from the scene of
+    /// the recording, and throws a `Recording.PlaybackError` if it has another number of rigids. Playback ends
+    /// before the first step in which the number of rigids changed, e.g. by welding or fracture.
+    func play(_ recording: Recording) throws {
+        if let first = recording.steps.first, first.states.count != rigids.count {
+            throw Recording.PlaybackError.rigidCountMismatch(recorded: first.states.count, world: rigids.count)
+        }
+        let stepCount = recording.steps.prefix { $0.states.count == rigids.count }.count
+        playback = Playback(recording: recording, stepCount: stepCount)
+        seek(toStep: 0)
+    }
+    
+    /// Shows a step of the recording being played back, clamped to the ones which can be played back.
+    func seek(toStep index: Int) {
+        guard let playback = playback, playback.stepCount > 0 else {
+            return
+        }
+        let recording = playback.recording
+        let index = index.clamped(to: 0 ... playback.stepCount - 1)
+        let step = recording.steps[index]
+        for (state, rigid) in zip(step.states, rigids) {
+            recording.codec.apply(state, to: rigid)
+        }
+        playback?.step = index
+        time = step.time
+        updateMeshes()
+    }
+    
+    /// Simulates again, starting from the step shown.
+    func stopPlayback() {
+        playback = nil
+    }
+    
+    func snapshot() -> Snapshot {
+        Snapshot(of: rigids)
+    }
+    
+    func restore(_ snapshot: Snapshot) {
+        snapshot.restore()
+        updateMeshes()
+    }
+    
+    var friction: Friction {
+        get { integrator.friction }
+        set { integrator.friction = newValue }
+    }
+    
+    /// Whether contacts bounce at all, see `Solver.isRestitutionEnabled`.
+    var isRestitutionEnabled: Bool {
+        get { integrator.isRestitutionEnabled }
+        set { integrator.isRestitutionEnabled = newValue }
+    }
+    
+    /// Whether persistent contacts are warm started, see `Solver.isWarmStarting`.
+    var isWarmStarting: Bool {
+        get { integrator.isWarmStarting }
+        set { integrator.isWarmStarting = newValue }
+    }
+    
+    /// Whether contacts and joints are solved on positions or on velocities, which allows comparing both on the same scene.
+    var backend: SolverBackend {
+        get { integrator.backend }
+        set { integrator.backend = newValue }
+    }
+    
+    /// Whether contacts are searched for in every sub-step or among the pairs found at the beginning of a step only.
+    var collisionDetection: CollisionDetection {
+        get { integrator.collisionDetection }
+        set { integrator.collisionDetection = newValue }
+    }
+    
+    /// How pairs of rigids far apart are skipped before searching them for contacts.
+    var broadPhase: BroadPhase {
+        get { integrator.broadPhase }
+        set { integrator.broadPhase = newValue }
+    }
+    
+    /// The number of sub-steps per step, unless adaptive sub-steps are enabled.
+    var subStepCount: Int {
+        get { integrator.subStepCount }
+        set { integrator.subStepCount = newValue }
+    }
+    
+    var contactCompliance: Double {
+        get { integrator.contactCompliance }
+        set { integrator.contactCompliance = newValue }
+    }
+    
+    /// Sweeps fast rigids to keep them from tunneling through thin ones if set, see `ContinuousCollision`.
+    var continuousCollision: ContinuousCollision? {
+        get { integrator.continuousCollision }
+        set { integrator.continuousCollision = newValue }
+    }
+    
+    /// Whether spinning rigids feel their gyroscopic torque, see `Solver.isGyroscopic`.
+    var isGyroscopic: Bool {
+        get { integrator.isGyroscopic }
+        set { integrator.isGyroscopic = newValue }
+    }
+    
+    /// Whether bounces are resolved per contact or passed on through touching rigids.
+    var restitutionPropagation: RestitutionPropagation {
+        get { integrator.restitutionPropagation }
+        set { integrator.restitutionPropagation = newValue }
+    }
+    
+    /// Scales the number of sub-steps with the activity of the scene, or uses a fixed number if `nil`.
+    var adaptiveSubSteps: AdaptiveSubSteps? {
+        get { integrator.adaptiveSubSteps }
+        set { integrator.adaptiveSubSteps = newValue }
+    }
+    
+    /// Distributes extra iterations among islands which did not converge, disabled if `nil`.
+    var iterationBudget: IslandIterationBudget? {
+        get { integrator.iterationBudget }
+        set { integrator.iterationBudget = newValue }
+    }
+    
+    /// Whether the pairs found once per step are searched on several threads, which never changes the results.
+    var isBroadphaseConcurrent: Bool {
+        get { integrator.isBroadphaseConcurrent }
+        set { integrator.isBroadphaseConcurrent = newValue }
+    }
+    
+    /// Whether the islands of the position based backend are solved on several threads, which never changes the results.
+    var isIslandSolvingConcurrent: Bool {
+        get { integrator.isIslandSolvingConcurrent }
+        set { integrator.isIslandSolvingConcurrent = newValue }
+    }
+    
+    /// Keeps heavy rigids from squashing light ones, disabled if `nil`.
+    var massRatioMitigation: MassRatioMitigation? {
+        get { integrator.massRatioMitigation }
+        set { integrator.massRatioMitigation = newValue }
+    }
+    
+    /// Volumes in which time runs slower or stands still for the rigids inside, like bullet-time bubbles.
+    var timeDilationVolumes: [TimeDilationVolume] {
+        get { integrator.timeDilationVolumes }
+        set { integrator.timeDilationVolumes = newValue }
+    }
+    
+    /// Puts resting islands of rigids to sleep, disabled if `nil`.
+    var sleeping: Sleeping? {
+        get { integrator.sleeping }
+        set { integrator.sleeping = newValue }
+    }
+    
+    /// Reduces the simulation effort for bodies far from a focus point, disabled if `nil`.
+    var lod: SimulationLod? {
+        get { integrator.lod }
+        set { integrator.lod = newValue }
+    }
+    
+    /// Friction coefficients for specific pairs of materials.
+    var materials: MaterialTable {
+        integrator.materials
+    }
+    
+    /// Records the stages of every step, and the phases of the solver within them, if set.
+    var trace: StepTrace? {
+        get { integrator.trace }
+        set { integrator.trace = newValue }
+    }
+    
+    /// Records the iterations of GJK and EPA for the convex pairs checked for contacts, if set.
+    var collisionTracer: CollisionTracer? {
+        get { integrator.collisionTracer }
+        set { integrator.collisionTracer = newValue }
+    }
+    
+    var telemetry: Telemetry {
+        var telemetry = integrator.telemetry
+        // Only the world knows the gravity, which the solver merely sees as part of the external forces.
+        telemetry.potentialEnergy = rigids.filter { $0.inverseMass > 0 }.reduce(0) { $0 - gravity.dot($1.frame.position) / $1.inverseMass }
+        return telemetry
+    }
+    
+    /// Draws the requested overlays for the last step, as the solver sees it.
+    func drawDebugOverlays(_ overlays: DebugOverlays, into canvas: DebugCanvas) {
+        integrator.draw(overlays, rigids: rigids, joints: joints, into: canvas)
     }
     
     func integrate(dt: Double) {
-        integrator.integrate([cube1, ground], by: dt)
-        cubeMesh1.transform = cube1.frame.matrix
-//        cubeMesh2.transform = cube2.frame.matrix
+        // A recording being played back advances by a step instead.
+        if let playback = playback {
+            seek(toStep: playback.step + 1)
+            return
+        }
+        for stage in pipeline {
+            let start = DispatchTime.now()
+            stage.run(self, dt)
+            trace?.span(stage.name, category: "stage", from: start)
+        }
+    }
+    
+    /// Simulates until all dynamic rigids stayed slower than `tolerance` for ten steps in a row, and then
+    /// stops them, so that levels start with stable stacks instead of visibly settling after loading.
+    /// The level of detail is disabled meanwhile, since frozen rigids would seem to be at rest.
+    /// Neither time nor the step count advance, and no events are sent. Returns whether everything came to rest
+    /// within `maxSteps`, and leaves the rigids in motion otherwise.
+    @discardableResult
+    func settle(maxSteps: Int, tolerance: Double, dt: Double = 1.0 / 60) -> Bool {
+        let lod = integrator.lod
+        integrator.lod = nil
+        defer {
+            integrator.lod = lod
+        }
+        
+        let dynamicRigids = rigids.filter { $0.inverseMass > 0 }
+        var restingSteps = 0
+        for _ in 0 ..< maxSteps {
+            integrator.integrate(rigids, joints: joints, by: dt)
+            let maxSpeed = dynamicRigids.map { max($0.velocity.length, $0.angularVelocity.length) }.max() ?? 0
+            restingSteps = maxSpeed < tolerance ? restingSteps + 1 : 0
+            
+            if restingSteps == 10 {
+                for rigid in dynamicRigids {
+                    rigid.velocity = .null
+                    rigid.angularVelocity = .null
+                    rigid.pastFrame = rigid.frame
+                }
+                updateMeshes()
+                return true
+            }
+        }
+        
+        updateMeshes()
+        return false
+    }
+    
+    /// Moves remote bodies towards their received poses.
+    static let driveRemoteBodies = StepStage(name: "Drive Remote Bodies") { world, dt in
+        for body in world.remoteBodies {
+            body.drive(towardsTime: world.time + dt, dt: dt)
+        }
+    }
+    
+    /// Replaces the generated forces of the last step by the ones of the force generators, springs, suspensions
+    /// and joint motors.
+    static let applyForces = StepStage(name: "Apply Forces") { world, dt in
+        for rigid in world.rigids {
+            rigid.generatedForce = .null
+            rigid.generatedTorque = .null
+        }
+        for generator in world.forceGenerators {
+            generator.apply(to: world.rigids)
+        }
+        for spring in world.springs {
+            spring.applyForce()
+        }
+        for suspension in world.suspensions {
+            suspension.applyForce()
+        }
+        for joint in world.joints {
+            joint.applyMotorTorque(for: dt)
+        }
+    }
+    
+    /// Detects collisions, solves contacts and joints, and integrates the rigids in all sub-steps.
+    /// These are interleaved per rigid, so they form a single stage.
+    static let solve = StepStage(name: "Solve") { world, dt in
+        world.integrator.integrate(world.rigids, joints: world.joints, mouseJoints: world.mouseJoints, by: dt)
+    }
+    
+    /// Removes joints which were pulled too hard during the step.
+    static let breakJoints = StepStage(name: "Break Joints") { world, _ in
+        world.breakOverloadedJoints()
+    }
+    
+    /// Breaks fracturable rigids which were hit too hard during the step.
+    static let fracture = StepStage(name: "Fracture") { world, _ in
+        world.fractureOverloadedRigids()
+    }
+    
+    static let advanceTime = StepStage(name: "Advance Time") { world, dt in
+        world.time += dt
+        world.stepCount += 1
+        world.updateMeshes()
+    }
+    
+    static let sendEvents = StepStage(name: "Send Events") { world, _ in
+        world.sendEvents()
+        world.sendContactSounds()
+    }
+    
+    static let defaultPipeline = [driveRemoteBodies, applyForces, solve, breakJoints, fracture, advanceTime, sendEvents]
+    
+    /// Registers a handler which receives the events of every step on the given queue, stamped with the step they occurred in.
+    @discardableResult
+    func subscribe(on queue: DispatchQueue, _ handler: @escaping ([PhysicsEvent], StepStamp) -> Void) -> EventChannel {
+        let channel = EventChannel(queue: queue, handler: handler)
+        eventChannels.append(channel)
+        return channel
+    }
+    
+    func unsubscribe(_ channel: EventChannel) {
+        eventChannels.removeAll { $0 === channel }
+    }
+    
+    /// Registers a handler which receives the contacts of every step hard enough to be heard, for impact and scrape
+    /// sounds. Each pair of rigids is reported at most once per interval. Only the position based backend reports them.
+    @discardableResult
+    func subscribeToContactSounds(on queue: DispatchQueue, minimumImpulse: Double = 0.1, minimumInterval: Double = 0.1,
+                                  _ handler: @escaping ([ContactSound], StepStamp) -> Void) -> ContactSoundChannel {
+        let channel = ContactSoundChannel(queue: queue, minimumImpulse: minimumImpulse, minimumInterval: minimumInterval, handler: handler)
+        soundChannels.append(channel)
+        integrator.tracksContactImpulses = true
+        return channel
+    }
+    
+    func unsubscribe(_ channel: ContactSoundChannel) {
+        soundChannels.removeAll { $0 === channel }
+        integrator.tracksContactImpulses = !soundChannels.isEmpty
+    }
+    
+    /// A checksum of the frames and velocities of all rigids, see `checksum(of:)`. Runs of the same build with the same
+    /// inputs end up with the same checksum, whether or not anything is solved concurrently. State rounding makes
+    /// checksums agree across machines more often.
+    var stateHash: UInt64 {
+        checksum(of: rigids)
+    }
+    
+    /// Sends the changes since the events were last sent.
+    private func sendEvents() {
+        let previousPairs = reportedPairs
+        let wasFrozen = reportedFrozen
+        let previousMotions = reportedMotions
+        reportedPairs = integrator.touchingPairs
+        reportedFrozen = rigids.map { $0.isFrozen }
+        reportedMotions = eventChannels.isEmpty && soundChannels.isEmpty ? [:] : classifyContacts()
+        var events = pendingEvents
+        pendingEvents = []
+        if eventChannels.isEmpty {
+            return
+        }
+        
+        // Sets are iterated in an order which differs from process to process, so the pairs are sorted.
+        let pairs = integrator.touchingPairs
+        for pair in pairs.subtracting(previousPairs).sorted() {
+            events.append(.contactBegan(RigidHandle(index: pair.first), RigidHandle(index: pair.second), contactInfo(of: pair)))
+        }
+        for pair in previousPairs.subtracting(pairs).sorted() {
+            events.append(.contactEnded(RigidHandle(index: pair.first), RigidHandle(index: pair.second)))
+        }
+        for (pair, motion) in reportedMotions.sorted(by: { $0.key < $1.key }) where previousMotions[pair] != motion {
+            events.append(.contactMotionChanged(RigidHandle(index: pair.first), RigidHandle(index: pair.second), motion))
+        }
+        // Rigids added since the events were last sent were not frozen before.
+        for (index, rigid) in rigids.enumerated() where rigid.isFrozen != (index < wasFrozen.count && wasFrozen[index]) {
+            events.append(rigid.isFrozen ? .froze(RigidHandle(index: index)) : .woke(RigidHandle(index: index)))
+        }
+        
+        if !events.isEmpty {
+            // Time has already been advanced, so the step the events belong to is the previous one.
+            let stamp = StepStamp(step: stepCount - 1, time: time)
+            for channel in eventChannels {
+                channel.send(events, stamp: stamp)
+            }
+        }
+    }
+    
+    /// The surfaces of a pair of rigids which began touching, with the deepest contact of the last sub-step.
+    private func contactInfo(of pair: IndexPair) -> ContactInfo {
+        let (a, b) = (rigids[pair.first], rigids[pair.second])
+        let manifold = integrator.manifolds[RigidPair(a, b)]
+        let deepest = manifold?.contacts.max { $0.positions.0.distance(to: $0.positions.1) < $1.positions.0.distance(to: $1.positions.1) }
+        let isFlipped = manifold?.rigids.0 !== a
+        return ContactInfo(surfaces: (ContactSurface(of: a), ContactSurface(of: b)),
+                           point: deepest.map { 0.5 * ($0.positions.0 + $0.positions.1) },
+                           normal: deepest.map { isFlipped ? -$0.normal : $0.normal })
+    }
+    
+    /// Tells how the rigids of every persistent contact of the last sub-step moved against each other, by their indices.
+    private func classifyContacts() -> [IndexPair: ContactMotion] {
+        let indices = Dictionary(uniqueKeysWithValues: rigids.enumerated().map { (ObjectIdentifier($1), $0) })
+        var motions: [IndexPair: ContactMotion] = [:]
+        for manifold in integrator.manifolds.values {
+            if let a = indices[ObjectIdentifier(manifold.rigids.0)], let b = indices[ObjectIdentifier(manifold.rigids.1)],
+               let motion = contactMotionClassifier.classify(manifold) {
+                motions[IndexPair(a, b)] = motion
+            }
+        }
+        return motions
+    }
+    
+    /// Sends the contacts of the step to the channels, which pick the ones to report.
+    private func sendContactSounds() {
+        if soundChannels.isEmpty {
+            return
+        }
+        let pairs = integrator.contactImpulses.keys.filter { $0.second < rigids.count }.sorted()
+        let contacts = pairs.map { pair -> (pair: RigidPair, sound: ContactSound) in
+            let (a, b) = (rigids[pair.first], rigids[pair.second])
+            let contact = integrator.contactImpulses[pair]!
+            let sound = ContactSound(rigids: (RigidHandle(index: pair.first), RigidHandle(index: pair.second)),
+                                     materials: (a.material, b.material),
+                                     userTags: (a.userTag, b.userTag),
+                                     impulse: contact.impulse,
+                                     relativeVelocity: contact.relativeVelocity,
+                                     normal: contact.normal,
+                                     motion: reportedMotions[pair])
+            return (RigidPair(a, b), sound)
+        }
+        // Time has already been advanced, so the contacts belong to the previous step.
+        let stamp = StepStamp(step: stepCount - 1, time: time)
+        for channel in soundChannels {
+            channel.send(contacts, stamp: stamp)
+        }
+    }
+    
+    private func updateMeshes() {
+        for (rigid, mesh) in meshes {
+            mesh.transform = rigid.frame.matrix
+        }
     }
 }
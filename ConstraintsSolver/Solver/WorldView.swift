@@ -0,0 +1,93 @@
+//
+//  WorldView.swift
+//  ConstraintsSolver
+//
+//  Created by Jim on 22.04.21.
+//
+
+import Foundation
+
+
+/// An immutable copy of the geometry of a world, which can be queried from any thread
+/// while the world itself is busy with its next step.
+struct WorldView {
+    struct Body {
+        let handle: RigidHandle
+        let collider: Collider
+        let frame: Frame
+        let groups: CollisionGroups
+    }
+    
+    struct Hit {
+        let handle: RigidHandle
+        let distance: Double
+        let point: Point
+        let normal: Point
+    }
+    
+    /// Like `QueryFilter`, but refers to rigids by handle, since rigids themselves must not be touched from other threads.
+    struct Filter {
+        var groups = CollisionGroups.all
+        var excluded: Set<RigidHandle> = []
+        
+        static let none = Filter()
+        
+        func accepts(_ body: Body) -> Bool {
+            !body.groups.isDisjoint(with: groups) && !excluded.contains(body.handle)
+        }
+    }
+    
+    let bodies: [Body]
+    /// Simulated time at which the view was taken.
+    let time: Double
+    
+    /// Calls `body` for every hit in no particular order, until it returns false.
+    func forEachHit(_ ray: Ray, filter: Filter = .none, _ visit: (Hit) -> Bool) {
+        for body in bodies where filter.accepts(body) {
+            guard let hit = body.collider.intersect(ray.localized(to: body.frame)) else {
+                continue
+            }
+            let worldHit = Hit(handle: body.handle, distance: hit.distance, point: ray.at(hit.distance), normal: body.frame.quaternion.act(on: hit.normal))
+            if !visit(worldHit) {
+                return
+            }
+        }
+    }
+    
+    /// All hits ordered by distance.
+    func intersections(with ray: Ray, filter: Filter = .none) -> [Hit] {
+        var hits: [Hit] = []
+        forEachHit(ray, filter: filter) { hit in
+            hits.append(hit)
+            return true
+        }
+        return hits.sorted { $0.distance < $1.distance }
+    }
+    
+    /// The handles of all bodies overlapping a sphere.
+    func overlaps(sphereAt center: Point, radius: Double, filter: Filter = .none) -> [RigidHandle] {
+        bodies.filter { body in
+            filter.accepts(body) && body.collider.distance(to: body.frame.inverse.act(center)) < radius
+        }.map { $0.handle }
+    }
+}
+
+
+extension Collider {
+    /// Distance of a point given in the local frame of the collider to its surface, or zero if inside.
+    func distance(to point: Point) -> Double {
+        switch self {
+        case let .plane(plane):
+            return max(point.dot(plane.normal) - plane.offset, 0)
+        case .box:
+            let outside = Point(max(abs(point.ex) - 0.5, 0), max(abs(point.ey) - 0.5, 0), max(abs(point.ez) - 0.5, 0))
+            return outside.length
+        case let .mesh(mesh):
+            return mesh.distance(to: point)
+        case let .compound(compound):
+            return compound.distance(to: point)
+        case let .convex(convex):
+            return convex.distance(to: point)
+        }
+    }
+}